@@ -8,7 +8,7 @@ use axum::{
     Json, Router,
 };
 use axum_extra::extract::CookieJar;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use tower_http::services::ServeDir;
 
 #[tokio::main]
@@ -57,8 +57,10 @@ async fn protected(jar: CookieJar) -> impl IntoResponse {
 
     let api_client = reqwest::Client::builder().build().unwrap();
 
+    // Only accept tokens the auth service issued for this service
     let verify_token_body = serde_json::json!({
         "token": &jwt_cookie.value(),
+        "audience": "app-service",
     });
 
     let auth_hostname = env::var("AUTH_SERVICE_HOST_NAME").unwrap_or("0.0.0.0".to_owned());
@@ -73,14 +75,37 @@ async fn protected(jar: CookieJar) -> impl IntoResponse {
 
     match response.status() {
         reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::BAD_REQUEST => {
-            StatusCode::UNAUTHORIZED.into_response()
+            return StatusCode::UNAUTHORIZED.into_response();
+        }
+        reqwest::StatusCode::OK => {}
+        _ => {
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    }
+
+    let grants = match response.json::<VerifyTokenResponse>().await {
+        Ok(grants) => grants,
+        Err(_) => {
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    // Any logged in user may see the page unless a permission is required for it
+    if let Ok(permission) = env::var("PROTECTED_PERMISSION") {
+        if !permission.is_empty() && !grants.permissions.contains(&permission) {
+            return StatusCode::FORBIDDEN.into_response();
         }
-        reqwest::StatusCode::OK => Json(ProtectedRouteResponse {
-            img_url: "https://i.ibb.co/YP90j68/Light-Live-Bootcamp-Certificate.png".to_owned(),
-        })
-        .into_response(),
-        _ => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
     }
+
+    Json(ProtectedRouteResponse {
+        img_url: "https://i.ibb.co/YP90j68/Light-Live-Bootcamp-Certificate.png".to_owned(),
+    })
+    .into_response()
+}
+
+#[derive(Deserialize)]
+struct VerifyTokenResponse {
+    permissions: Vec<String>,
 }
 
 #[derive(Serialize)]
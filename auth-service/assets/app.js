@@ -1,6 +1,21 @@
 const loginSection = document.getElementById("login-section");
 const twoFASection = document.getElementById("2fa-section");
 const signupSection = document.getElementById("signup-section");
+const consentSection = document.getElementById("consent-section");
+
+// Set when an OAuth client sent the user here to log in, or to ask for their consent
+const pageParams = new URLSearchParams(window.location.search);
+const authorizeQuery = pageParams.get("authorize");
+const consentClient = pageParams.get("client");
+
+// Goes back to the authorization request once the user is logged in
+function continueAuthorization() {
+    if (authorizeQuery === null) {
+        return false;
+    }
+    window.location.href = "/oauth/authorize?" + authorizeQuery;
+    return true;
+}
 
 const signupLink = document.getElementById("signup-link");
 const twoFALoginLink = document.getElementById("2fa-login-link");
@@ -66,7 +81,9 @@ loginButton.addEventListener("click", (e) => {
             loginForm.email.value = "";
             loginForm.password.value = "";
             loginErrAlter.style.display = "none";
-            alert("You have successfully logged in.");
+            if (!continueAuthorization()) {
+                alert("You have successfully logged in.");
+            }
         } else {
             response.json().then(data => {
                 let error_msg = data.error;
@@ -145,6 +162,9 @@ TwoFAButton.addEventListener("click", (e) => {
             TwoFAForm.email_code.value = "";
             TwoFAForm.login_attempt_id.value = "";
             TwoFAErrAlter.style.display = "none";
+            if (continueAuthorization()) {
+                return;
+            }
             alert("You have successfully logged in.");
             loginSection.style.display = "block";
             twoFASection.style.display = "none";
@@ -161,4 +181,62 @@ TwoFAButton.addEventListener("click", (e) => {
             });
         }
     });
-});
\ No newline at end of file
+});
+
+// -----------------------------------------------------
+
+const consentErrAlter = document.getElementById("consent-err-alert");
+
+if (authorizeQuery !== null && consentClient !== null) {
+    document.getElementById("consent-client").textContent = consentClient;
+    document.getElementById("consent-client-name").textContent = consentClient;
+
+    const scope = new URLSearchParams(authorizeQuery).get("scope") || "";
+    const scopeList = document.getElementById("consent-scopes");
+    scope.split(" ").filter(token => token !== "").forEach(token => {
+        const item = document.createElement("li");
+        item.textContent = token;
+        scopeList.appendChild(item);
+    });
+
+    loginSection.style.display = "none";
+    twoFASection.style.display = "none";
+    signupSection.style.display = "none";
+    consentSection.style.display = "block";
+}
+
+function answerConsent(approve) {
+    fetch('/oauth/authorize?' + authorizeQuery, {
+        method: 'POST',
+        headers: {
+            'Content-Type': 'application/json',
+        },
+        body: JSON.stringify({ approve }),
+    }).then(response => {
+        if (response.ok) {
+            response.json().then(data => {
+                window.location.href = data.redirectTo;
+            });
+        } else {
+            response.json().then(data => {
+                let error_msg = data.error;
+                if (error_msg !== undefined && error_msg !== null && error_msg !== "") {
+                    consentErrAlter.innerHTML = `<span><strong>Error: </strong>${error_msg}</span>`;
+                    consentErrAlter.style.display = "block";
+                } else {
+                    consentErrAlter.style.display = "none";
+                }
+            });
+        }
+    });
+}
+
+document.getElementById("consent-approve").addEventListener("click", (e) => {
+    e.preventDefault();
+    answerConsent(true);
+});
+
+document.getElementById("consent-deny").addEventListener("click", (e) => {
+    e.preventDefault();
+    answerConsent(false);
+});
@@ -0,0 +1,3 @@
+-- Accounts created before verification existed are treated as verified
+ALTER TABLE users ADD COLUMN verified BOOLEAN NOT NULL DEFAULT TRUE;
+ALTER TABLE users ALTER COLUMN verified SET DEFAULT FALSE;
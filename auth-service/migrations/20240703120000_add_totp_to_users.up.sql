@@ -0,0 +1,5 @@
+-- The TOTP secret is stored encrypted, as base64 of the AES-GCM nonce followed by the ciphertext
+ALTER TABLE users ADD COLUMN totp_secret TEXT;
+ALTER TABLE users ADD COLUMN totp_enabled BOOLEAN NOT NULL DEFAULT FALSE;
+-- The time step of the last accepted code, as each code may only be used once
+ALTER TABLE users ADD COLUMN totp_last_step BIGINT;
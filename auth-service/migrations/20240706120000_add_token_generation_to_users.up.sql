@@ -0,0 +1,2 @@
+-- Every auth token carries the generation it was issued under, and bumping it revokes them all
+ALTER TABLE users ADD COLUMN token_generation BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,18 @@
+ALTER TABLE refresh_tokens DROP CONSTRAINT refresh_tokens_email_fkey;
+ALTER TABLE webauthn_credentials DROP CONSTRAINT webauthn_credentials_email_fkey;
+ALTER TABLE recovery_codes DROP CONSTRAINT recovery_codes_email_fkey;
+ALTER TABLE sessions DROP CONSTRAINT sessions_email_fkey;
+
+ALTER TABLE users DROP CONSTRAINT users_pkey;
+ALTER TABLE users DROP CONSTRAINT users_email_key;
+ALTER TABLE users ADD PRIMARY KEY (email);
+ALTER TABLE users DROP COLUMN id;
+
+ALTER TABLE refresh_tokens ADD CONSTRAINT refresh_tokens_email_fkey
+   FOREIGN KEY (email) REFERENCES users(email) ON DELETE CASCADE ON UPDATE CASCADE;
+ALTER TABLE webauthn_credentials ADD CONSTRAINT webauthn_credentials_email_fkey
+   FOREIGN KEY (email) REFERENCES users(email) ON DELETE CASCADE ON UPDATE CASCADE;
+ALTER TABLE recovery_codes ADD CONSTRAINT recovery_codes_email_fkey
+   FOREIGN KEY (email) REFERENCES users(email) ON DELETE CASCADE ON UPDATE CASCADE;
+ALTER TABLE sessions ADD CONSTRAINT sessions_email_fkey
+   FOREIGN KEY (email) REFERENCES users(email) ON DELETE CASCADE ON UPDATE CASCADE;
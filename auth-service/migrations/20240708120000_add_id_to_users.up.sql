@@ -0,0 +1,23 @@
+-- Users are identified by a UUID rather than their email, which is personal data and can change.
+-- Existing users are given one as the column is added.
+ALTER TABLE users ADD COLUMN id UUID NOT NULL DEFAULT gen_random_uuid();
+
+-- Tables referring to users still do so by email, which stays unique
+ALTER TABLE users ADD CONSTRAINT users_email_key UNIQUE (email);
+
+ALTER TABLE refresh_tokens DROP CONSTRAINT refresh_tokens_email_fkey;
+ALTER TABLE webauthn_credentials DROP CONSTRAINT webauthn_credentials_email_fkey;
+ALTER TABLE recovery_codes DROP CONSTRAINT recovery_codes_email_fkey;
+ALTER TABLE sessions DROP CONSTRAINT sessions_email_fkey;
+
+ALTER TABLE users DROP CONSTRAINT users_pkey;
+ALTER TABLE users ADD PRIMARY KEY (id);
+
+ALTER TABLE refresh_tokens ADD CONSTRAINT refresh_tokens_email_fkey
+   FOREIGN KEY (email) REFERENCES users(email) ON DELETE CASCADE ON UPDATE CASCADE;
+ALTER TABLE webauthn_credentials ADD CONSTRAINT webauthn_credentials_email_fkey
+   FOREIGN KEY (email) REFERENCES users(email) ON DELETE CASCADE ON UPDATE CASCADE;
+ALTER TABLE recovery_codes ADD CONSTRAINT recovery_codes_email_fkey
+   FOREIGN KEY (email) REFERENCES users(email) ON DELETE CASCADE ON UPDATE CASCADE;
+ALTER TABLE sessions ADD CONSTRAINT sessions_email_fkey
+   FOREIGN KEY (email) REFERENCES users(email) ON DELETE CASCADE ON UPDATE CASCADE;
@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS user_roles(
+   user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+   role TEXT NOT NULL,
+   PRIMARY KEY (user_id, role)
+);
+
+CREATE TABLE IF NOT EXISTS user_permissions(
+   user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+   permission TEXT NOT NULL,
+   PRIMARY KEY (user_id, permission)
+);
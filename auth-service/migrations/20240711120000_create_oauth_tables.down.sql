@@ -0,0 +1,4 @@
+ALTER TABLE sessions DROP COLUMN IF EXISTS oauth_scope;
+ALTER TABLE sessions DROP COLUMN IF EXISTS oauth_client_id;
+DROP TABLE IF EXISTS oauth_consents;
+DROP TABLE IF EXISTS oauth_clients;
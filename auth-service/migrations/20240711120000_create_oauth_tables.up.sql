@@ -0,0 +1,18 @@
+CREATE TABLE IF NOT EXISTS oauth_clients(
+   id UUID NOT NULL PRIMARY KEY,
+   name TEXT NOT NULL,
+   redirect_uris TEXT[] NOT NULL,
+   created_at TIMESTAMPTZ NOT NULL
+);
+
+-- The scope each user consented to grant each client, space separated
+CREATE TABLE IF NOT EXISTS oauth_consents(
+   user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+   client_id UUID NOT NULL REFERENCES oauth_clients(id) ON DELETE CASCADE,
+   scope TEXT NOT NULL,
+   PRIMARY KEY (user_id, client_id)
+);
+
+-- Sessions started by an OAuth client only issue tokens to that client, within the granted scope
+ALTER TABLE sessions ADD COLUMN IF NOT EXISTS oauth_client_id UUID REFERENCES oauth_clients(id);
+ALTER TABLE sessions ADD COLUMN IF NOT EXISTS oauth_scope TEXT;
@@ -0,0 +1,7 @@
+-- When and how the user logged in. Sessions from before this was recorded count as logged in when created.
+ALTER TABLE sessions ADD COLUMN IF NOT EXISTS auth_time TIMESTAMPTZ;
+UPDATE sessions SET auth_time = created_at WHERE auth_time IS NULL;
+ALTER TABLE sessions ALTER COLUMN auth_time SET NOT NULL;
+
+-- Authentication Method Reference values (RFC 8176), such as pwd and otp
+ALTER TABLE sessions ADD COLUMN IF NOT EXISTS auth_methods TEXT[] NOT NULL DEFAULT '{}';
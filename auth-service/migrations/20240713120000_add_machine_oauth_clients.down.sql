@@ -0,0 +1,5 @@
+ALTER TABLE oauth_clients DROP COLUMN IF EXISTS secret_created_at;
+ALTER TABLE oauth_clients DROP COLUMN IF EXISTS secret_hash;
+ALTER TABLE oauth_clients DROP COLUMN IF EXISTS secret_id;
+ALTER TABLE oauth_clients DROP COLUMN IF EXISTS scope;
+ALTER TABLE oauth_clients DROP COLUMN IF EXISTS kind;
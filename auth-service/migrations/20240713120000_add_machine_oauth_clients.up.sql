@@ -0,0 +1,8 @@
+-- Machine clients get tokens for themselves with their secret, within the scope they're allowed
+ALTER TABLE oauth_clients ADD COLUMN IF NOT EXISTS kind TEXT NOT NULL DEFAULT 'public';
+ALTER TABLE oauth_clients ADD COLUMN IF NOT EXISTS scope TEXT NOT NULL DEFAULT '';
+
+-- Only the SHA-256 digest of a machine client's current secret is kept, all NULL once it's revoked
+ALTER TABLE oauth_clients ADD COLUMN IF NOT EXISTS secret_id UUID;
+ALTER TABLE oauth_clients ADD COLUMN IF NOT EXISTS secret_hash TEXT;
+ALTER TABLE oauth_clients ADD COLUMN IF NOT EXISTS secret_created_at TIMESTAMPTZ;
@@ -0,0 +1,13 @@
+-- Personal API keys, kept only as a SHA-256 digest along with a hint of how the key starts
+CREATE TABLE IF NOT EXISTS api_keys(
+   id UUID NOT NULL PRIMARY KEY,
+   user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+   name TEXT NOT NULL,
+   scope TEXT NOT NULL,
+   hint TEXT NOT NULL,
+   key_hash TEXT NOT NULL UNIQUE,
+   created_at TIMESTAMPTZ NOT NULL,
+   expires_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS api_keys_user_id_idx ON api_keys(user_id);
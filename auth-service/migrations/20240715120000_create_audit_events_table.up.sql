@@ -0,0 +1,9 @@
+-- Security relevant events on users' accounts, which go with the account when it is deleted
+CREATE TABLE IF NOT EXISTS audit_events(
+   id BIGSERIAL PRIMARY KEY,
+   user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+   kind TEXT NOT NULL,
+   created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS audit_events_user_id_idx ON audit_events(user_id);
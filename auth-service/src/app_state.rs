@@ -1,11 +1,33 @@
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
-use crate::domain::{BannedTokenStore, EmailClient, TwoFACodeStore, UserStore};
+use crate::{
+    domain::{
+        ApiKeyStore, AuditEventStore, AuthorizationCodeStore, BannedTokenStore, EmailClient,
+        FailedAttemptStore, OAuthClientStore, OAuthConsentStore, PasswordResetTokenStore,
+        RateLimitStore, RecoveryCodeStore, RefreshTokenStore, RoleStore, SessionStore,
+        TwoFACodeStore, UserStore, WebAuthnChallengeStore, WebAuthnCredentialStore,
+    },
+    utils::rate_limit::RateLimiter,
+};
 
 pub type UserStoreType = Arc<RwLock<dyn UserStore + Send + Sync>>;
 pub type BannedTokenStoreType = Arc<RwLock<dyn BannedTokenStore + Send + Sync>>;
 pub type TwoFACodeStoreType = Arc<RwLock<dyn TwoFACodeStore + Send + Sync>>;
+pub type RefreshTokenStoreType = Arc<RwLock<dyn RefreshTokenStore + Send + Sync>>;
+pub type SessionStoreType = Arc<RwLock<dyn SessionStore + Send + Sync>>;
+pub type PasswordResetTokenStoreType = Arc<RwLock<dyn PasswordResetTokenStore + Send + Sync>>;
+pub type RecoveryCodeStoreType = Arc<RwLock<dyn RecoveryCodeStore + Send + Sync>>;
+pub type FailedAttemptStoreType = Arc<RwLock<dyn FailedAttemptStore + Send + Sync>>;
+pub type WebAuthnCredentialStoreType = Arc<RwLock<dyn WebAuthnCredentialStore + Send + Sync>>;
+pub type WebAuthnChallengeStoreType = Arc<RwLock<dyn WebAuthnChallengeStore + Send + Sync>>;
+pub type RoleStoreType = Arc<RwLock<dyn RoleStore + Send + Sync>>;
+pub type OAuthClientStoreType = Arc<RwLock<dyn OAuthClientStore + Send + Sync>>;
+pub type OAuthConsentStoreType = Arc<RwLock<dyn OAuthConsentStore + Send + Sync>>;
+pub type AuthorizationCodeStoreType = Arc<RwLock<dyn AuthorizationCodeStore + Send + Sync>>;
+pub type ApiKeyStoreType = Arc<RwLock<dyn ApiKeyStore + Send + Sync>>;
+pub type AuditEventStoreType = Arc<RwLock<dyn AuditEventStore + Send + Sync>>;
+pub type RateLimitStoreType = Arc<RwLock<dyn RateLimitStore + Send + Sync>>;
 pub type EmailClientType = Arc<dyn EmailClient + Send + Sync>;
 
 #[derive(Clone)]
@@ -13,21 +35,67 @@ pub struct AppState {
     pub user_store: UserStoreType,
     pub banned_token_store: BannedTokenStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
+    pub refresh_token_store: RefreshTokenStoreType,
+    pub session_store: SessionStoreType,
+    pub password_reset_token_store: PasswordResetTokenStoreType,
+    pub recovery_code_store: RecoveryCodeStoreType,
+    pub failed_attempt_store: FailedAttemptStoreType,
+    pub webauthn_credential_store: WebAuthnCredentialStoreType,
+    pub webauthn_challenge_store: WebAuthnChallengeStoreType,
+    pub role_store: RoleStoreType,
+    pub oauth_client_store: OAuthClientStoreType,
+    pub oauth_consent_store: OAuthConsentStoreType,
+    pub authorization_code_store: AuthorizationCodeStoreType,
+    pub api_key_store: ApiKeyStoreType,
+    pub audit_event_store: AuditEventStoreType,
+    pub rate_limiter: RateLimiter,
     pub email_client: EmailClientType,
+    pub require_email_verification: bool,
 }
 
 impl AppState {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         user_store: UserStoreType,
         banned_token_store: BannedTokenStoreType,
         two_fa_code_store: TwoFACodeStoreType,
+        refresh_token_store: RefreshTokenStoreType,
+        session_store: SessionStoreType,
+        password_reset_token_store: PasswordResetTokenStoreType,
+        recovery_code_store: RecoveryCodeStoreType,
+        failed_attempt_store: FailedAttemptStoreType,
+        webauthn_credential_store: WebAuthnCredentialStoreType,
+        webauthn_challenge_store: WebAuthnChallengeStoreType,
+        role_store: RoleStoreType,
+        oauth_client_store: OAuthClientStoreType,
+        oauth_consent_store: OAuthConsentStoreType,
+        authorization_code_store: AuthorizationCodeStoreType,
+        api_key_store: ApiKeyStoreType,
+        audit_event_store: AuditEventStoreType,
+        rate_limiter: RateLimiter,
         email_client: EmailClientType,
+        require_email_verification: bool,
     ) -> Self {
         Self {
             user_store,
             banned_token_store,
             two_fa_code_store,
+            refresh_token_store,
+            session_store,
+            password_reset_token_store,
+            recovery_code_store,
+            failed_attempt_store,
+            webauthn_credential_store,
+            webauthn_challenge_store,
+            role_store,
+            oauth_client_store,
+            oauth_consent_store,
+            authorization_code_store,
+            api_key_store,
+            audit_event_store,
+            rate_limiter,
             email_client,
+            require_email_verification,
         }
     }
 }
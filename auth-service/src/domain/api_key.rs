@@ -0,0 +1,161 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Context, Result};
+use uuid::Uuid;
+
+use super::{ApiKeySecret, OAuthScope, UserId};
+
+/// Identifies an API key. It's safe to show, unlike the key itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ApiKeyId(Uuid);
+
+impl ApiKeyId {
+    pub fn parse(id: String) -> Result<Self> {
+        let parsed_id = Uuid::parse_str(&id).wrap_err("Invalid API key ID")?;
+        Ok(Self(parsed_id))
+    }
+}
+
+impl Default for ApiKeyId {
+    fn default() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl From<Uuid> for ApiKeyId {
+    fn from(id: Uuid) -> Self {
+        Self(id)
+    }
+}
+
+impl AsRef<Uuid> for ApiKeyId {
+    fn as_ref(&self) -> &Uuid {
+        &self.0
+    }
+}
+
+impl fmt::Display for ApiKeyId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// A long-lived key users create for their scripts, acting for them within its scope. What's kept of
+/// it; the key itself is only shown once, when it's created.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiKey {
+    pub id: ApiKeyId,
+    pub user_id: UserId,
+    pub name: String,
+    /// What relying services may let the key do. It grants nothing by itself.
+    pub scope: OAuthScope,
+    /// The start of the key, for users to tell their keys apart
+    pub hint: String,
+    /// SHA-256 digest of the key
+    pub hash: String,
+    pub created_at: DateTime<Utc>,
+    /// Keys without an expiry last until they're revoked
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl ApiKey {
+    pub fn new(
+        user_id: UserId,
+        name: String,
+        scope: OAuthScope,
+        expires_at: Option<DateTime<Utc>>,
+        secret: &ApiKeySecret,
+    ) -> Result<Self> {
+        let name = check_key_name(name)?;
+
+        let created_at = Utc::now();
+        if expires_at.is_some_and(|expires_at| expires_at <= created_at) {
+            return Err(eyre!("API keys must expire in the future"));
+        }
+
+        Ok(Self {
+            id: ApiKeyId::default(),
+            user_id,
+            name,
+            scope,
+            hint: secret.hint(),
+            hash: secret.hash(),
+            created_at,
+            expires_at,
+        })
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now())
+    }
+}
+
+const MAX_KEY_NAME_LENGTH: usize = 100;
+
+/// Trims the name, which must not be left empty.
+pub fn check_key_name(name: String) -> Result<String> {
+    let name = name.trim().to_owned();
+    if name.is_empty() || name.len() > MAX_KEY_NAME_LENGTH {
+        return Err(eyre!(
+            "API key names must be 1 to {} characters",
+            MAX_KEY_NAME_LENGTH
+        ));
+    }
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    fn new_key(expires_at: Option<DateTime<Utc>>) -> Result<ApiKey> {
+        ApiKey::new(
+            UserId::default(),
+            " Deploy script ".to_owned(),
+            OAuthScope::parse("reports:read").unwrap(),
+            expires_at,
+            &ApiKeySecret::default(),
+        )
+    }
+
+    #[test]
+    fn keys_keep_only_the_hash_and_hint_of_the_secret() {
+        let secret = ApiKeySecret::default();
+        let key = ApiKey::new(
+            UserId::default(),
+            "Deploy script".to_owned(),
+            OAuthScope::default(),
+            None,
+            &secret,
+        )
+        .unwrap();
+
+        assert_eq!(key.hash, secret.hash());
+        assert_eq!(key.hint, secret.hint());
+        assert!(!key.is_expired());
+    }
+
+    #[test]
+    fn key_names_are_trimmed_and_must_not_be_empty() {
+        assert_eq!(new_key(None).unwrap().name, "Deploy script");
+
+        for name in ["", "   ", &"a".repeat(MAX_KEY_NAME_LENGTH + 1)] {
+            assert!(check_key_name(name.to_owned()).is_err());
+        }
+    }
+
+    #[test]
+    fn keys_must_expire_in_the_future() {
+        assert!(new_key(Some(Utc::now() - Duration::seconds(1))).is_err());
+
+        let mut key = new_key(Some(Utc::now() + Duration::days(30))).unwrap();
+        assert!(!key.is_expired());
+
+        key.expires_at = Some(Utc::now() - Duration::seconds(1));
+        assert!(key.is_expired());
+    }
+}
@@ -0,0 +1,89 @@
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Result};
+
+use super::UserId;
+
+/// Something security relevant that happened to a user's account, kept so the user can review it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEvent {
+    pub user_id: UserId,
+    pub kind: AuditEventKind,
+    pub created_at: DateTime<Utc>,
+}
+
+impl AuditEvent {
+    pub fn new(user_id: UserId, kind: AuditEventKind) -> Self {
+        Self {
+            user_id,
+            kind,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditEventKind {
+    Login,
+    Logout,
+    LogoutAll,
+    PasswordChanged,
+    PasswordReset,
+    EmailChanged,
+    TotpEnabled,
+    PasskeyAdded,
+    RecoveryCodesGenerated,
+}
+
+impl AuditEventKind {
+    pub fn parse(kind: &str) -> Result<Self> {
+        match kind {
+            "login" => Ok(Self::Login),
+            "logout" => Ok(Self::Logout),
+            "logout_all" => Ok(Self::LogoutAll),
+            "password_changed" => Ok(Self::PasswordChanged),
+            "password_reset" => Ok(Self::PasswordReset),
+            "email_changed" => Ok(Self::EmailChanged),
+            "totp_enabled" => Ok(Self::TotpEnabled),
+            "passkey_added" => Ok(Self::PasskeyAdded),
+            "recovery_codes_generated" => Ok(Self::RecoveryCodesGenerated),
+            _ => Err(eyre!("Unknown audit event: {}", kind)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Login => "login",
+            Self::Logout => "logout",
+            Self::LogoutAll => "logout_all",
+            Self::PasswordChanged => "password_changed",
+            Self::PasswordReset => "password_reset",
+            Self::EmailChanged => "email_changed",
+            Self::TotpEnabled => "totp_enabled",
+            Self::PasskeyAdded => "passkey_added",
+            Self::RecoveryCodesGenerated => "recovery_codes_generated",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audit_event_kinds_round_trip_through_strings() {
+        for kind in [
+            AuditEventKind::Login,
+            AuditEventKind::Logout,
+            AuditEventKind::LogoutAll,
+            AuditEventKind::PasswordChanged,
+            AuditEventKind::PasswordReset,
+            AuditEventKind::EmailChanged,
+            AuditEventKind::TotpEnabled,
+            AuditEventKind::PasskeyAdded,
+            AuditEventKind::RecoveryCodesGenerated,
+        ] {
+            assert_eq!(AuditEventKind::parse(kind.as_str()).unwrap(), kind);
+        }
+        assert!(AuditEventKind::parse("account_hacked").is_err());
+    }
+}
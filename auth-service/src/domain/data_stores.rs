@@ -1,16 +1,72 @@
 use color_eyre::eyre::{eyre, Context, Report, Result};
-use rand::Rng;
+use rand::{distributions::Alphanumeric, Rng};
 use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
 use thiserror::Error;
 
-use super::{Email, Password, User};
+use super::{
+    ApiKey, ApiKeyId, AuditEvent, AuthorizationCodeGrant, Email, OAuthClient, OAuthClientId,
+    OAuthClientSecret, OAuthScope, Password, Permission, Role, Session, SessionClient, TotpSecret,
+    User, UserId, WebAuthnCeremony, WebAuthnChallenge, WebAuthnCredential, WebAuthnCredentialId,
+};
 
 #[async_trait::async_trait]
 pub trait UserStore {
     async fn add_user(&mut self, user: User) -> Result<(), UserStoreError>;
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError>;
+    async fn get_user_by_id(&self, id: &UserId) -> Result<User, UserStoreError>;
     async fn validate_user(&self, email: &Email, password: &Password)
         -> Result<(), UserStoreError>;
+    async fn update_password(
+        &mut self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError>;
+    async fn mark_verified(&mut self, email: &Email) -> Result<(), UserStoreError>;
+    /// Moves the user over to `new_email`, which counts as verified, and on to a new token generation,
+    /// revoking every auth token issued so far. Fails with `InvalidCredentials` unless the user is still
+    /// on `token_generation`, so a change approved before any later revocation cannot go through.
+    /// Whatever else is stored under the old email must be moved or cleared by the caller.
+    async fn update_email(
+        &mut self,
+        email: &Email,
+        new_email: &Email,
+        token_generation: i64,
+    ) -> Result<(), UserStoreError>;
+    /// Removes the user for good. Whatever else is stored under their email must be cleared by the caller.
+    async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError>;
+    /// Stores a TOTP secret that is pending confirmation. TOTP stays disabled until `enable_totp`.
+    async fn set_totp_secret(
+        &mut self,
+        email: &Email,
+        secret: TotpSecret,
+    ) -> Result<(), UserStoreError>;
+    async fn get_totp_secret(&self, email: &Email) -> Result<Option<TotpSecret>, UserStoreError>;
+    /// Turns on TOTP, and with it 2FA, for a user whose secret has been confirmed.
+    async fn enable_totp(&mut self, email: &Email) -> Result<(), UserStoreError>;
+    /// Records that a TOTP code for time `step` was accepted. Returns false, recording nothing, if a
+    /// code for that step or a later one already was, as no code may be accepted twice (RFC 6238 §5.2).
+    async fn record_totp_step(&mut self, email: &Email, step: u64) -> Result<bool, UserStoreError>;
+    /// Every auth token carries the generation it was issued under and is only valid while it's current.
+    async fn get_token_generation(&self, email: &Email) -> Result<i64, UserStoreError>;
+    /// Moves the user on to a new token generation, revoking every auth token issued so far.
+    async fn increment_token_generation(&mut self, email: &Email) -> Result<i64, UserStoreError>;
+    /// Lists a page of users ordered by email, keeping only those whose email contains `email_search`
+    /// (ignoring case) if given.
+    async fn list_users(
+        &self,
+        email_search: Option<&str>,
+        offset: u32,
+        limit: u32,
+    ) -> Result<Vec<User>, UserStoreError>;
+    /// Counts the users `list_users` pages through for the same `email_search`.
+    async fn count_users(&self, email_search: Option<&str>) -> Result<usize, UserStoreError>;
+    async fn set_disabled(&mut self, email: &Email, disabled: bool) -> Result<(), UserStoreError>;
+    async fn set_requires_2fa(
+        &mut self,
+        email: &Email,
+        requires_2fa: bool,
+    ) -> Result<(), UserStoreError>;
 }
 
 #[derive(Debug, Error)]
@@ -37,13 +93,234 @@ impl PartialEq for UserStoreError {
     }
 }
 
+/// Roles and permissions granted to users. Granting something twice, or revoking something that
+/// wasn't granted, does nothing.
+#[async_trait::async_trait]
+pub trait RoleStore {
+    async fn grant_role(&mut self, user_id: &UserId, role: &Role) -> Result<(), RoleStoreError>;
+    async fn revoke_role(&mut self, user_id: &UserId, role: &Role) -> Result<(), RoleStoreError>;
+    async fn get_roles(&self, user_id: &UserId) -> Result<Vec<Role>, RoleStoreError>;
+    async fn grant_permission(
+        &mut self,
+        user_id: &UserId,
+        permission: &Permission,
+    ) -> Result<(), RoleStoreError>;
+    async fn revoke_permission(
+        &mut self,
+        user_id: &UserId,
+        permission: &Permission,
+    ) -> Result<(), RoleStoreError>;
+    async fn get_permissions(&self, user_id: &UserId) -> Result<Vec<Permission>, RoleStoreError>;
+    /// Revokes everything granted to the user.
+    async fn revoke_all(&mut self, user_id: &UserId) -> Result<(), RoleStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum RoleStoreError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for RoleStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait OAuthClientStore {
+    async fn add_client(&mut self, client: OAuthClient) -> Result<(), OAuthClientStoreError>;
+    async fn get_client(&self, id: &OAuthClientId) -> Result<OAuthClient, OAuthClientStoreError>;
+    /// Lists every client, oldest first.
+    async fn list_clients(&self) -> Result<Vec<OAuthClient>, OAuthClientStoreError>;
+    /// Replaces the client's secret, revoking it when `secret` is `None`.
+    async fn set_client_secret(
+        &mut self,
+        id: &OAuthClientId,
+        secret: Option<OAuthClientSecret>,
+    ) -> Result<(), OAuthClientStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum OAuthClientStoreError {
+    #[error("Client not found")]
+    ClientNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for OAuthClientStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::ClientNotFound, Self::ClientNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+/// What users have consented to let OAuth clients do on their behalf, so they're only asked once.
+#[async_trait::async_trait]
+pub trait OAuthConsentStore {
+    /// Returns the scope the user consented to grant the client, or `None` if they never approved it.
+    async fn get_consent(
+        &self,
+        user_id: &UserId,
+        client_id: &OAuthClientId,
+    ) -> Result<Option<OAuthScope>, OAuthConsentStoreError>;
+    /// Returns every client the user approved, along with the scope they consented to grant it.
+    async fn get_user_consents(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<(OAuthClientId, OAuthScope)>, OAuthConsentStoreError>;
+    /// Replaces the scope the user consents to grant the client.
+    async fn set_consent(
+        &mut self,
+        user_id: &UserId,
+        client_id: &OAuthClientId,
+        scope: &OAuthScope,
+    ) -> Result<(), OAuthConsentStoreError>;
+    async fn remove_user_consents(
+        &mut self,
+        user_id: &UserId,
+    ) -> Result<(), OAuthConsentStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum OAuthConsentStoreError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for OAuthConsentStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+/// Users' personal API keys. Keys are only ever looked up by their hash, and only changed or removed by
+/// their owner, so someone else's key looks just like one that doesn't exist.
+#[async_trait::async_trait]
+pub trait ApiKeyStore {
+    async fn add_key(&mut self, key: ApiKey) -> Result<(), ApiKeyStoreError>;
+    /// Finds the key whatever its expiry, which is for the caller to check.
+    async fn get_key(&self, secret: &ApiKeySecret) -> Result<ApiKey, ApiKeyStoreError>;
+    /// Lists the user's keys, oldest first.
+    async fn get_user_keys(&self, user_id: &UserId) -> Result<Vec<ApiKey>, ApiKeyStoreError>;
+    async fn rename_key(
+        &mut self,
+        user_id: &UserId,
+        id: &ApiKeyId,
+        name: &str,
+    ) -> Result<(), ApiKeyStoreError>;
+    async fn remove_key(&mut self, user_id: &UserId, id: &ApiKeyId)
+        -> Result<(), ApiKeyStoreError>;
+    async fn remove_user_keys(&mut self, user_id: &UserId) -> Result<(), ApiKeyStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum ApiKeyStoreError {
+    #[error("API key not found")]
+    KeyNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for ApiKeyStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::KeyNotFound, Self::KeyNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+/// The security relevant events on users' accounts, which users can review in their data export.
+#[async_trait::async_trait]
+pub trait AuditEventStore {
+    async fn add_event(&mut self, event: AuditEvent) -> Result<(), AuditEventStoreError>;
+    /// Lists the user's events, most recent first.
+    async fn get_user_events(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<AuditEvent>, AuditEventStoreError>;
+    async fn remove_user_events(&mut self, user_id: &UserId) -> Result<(), AuditEventStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum AuditEventStoreError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for AuditEventStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait AuthorizationCodeStore {
+    async fn add_code(
+        &mut self,
+        code: AuthorizationCode,
+        grant: AuthorizationCodeGrant,
+    ) -> Result<(), AuthorizationCodeStoreError>;
+    /// Returns what the code was issued for and removes it, so it can only be exchanged once.
+    async fn take_code(
+        &mut self,
+        code: &AuthorizationCode,
+    ) -> Result<AuthorizationCodeGrant, AuthorizationCodeStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum AuthorizationCodeStoreError {
+    #[error("Authorization code not found")]
+    CodeNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for AuthorizationCodeStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::CodeNotFound, Self::CodeNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
 #[async_trait::async_trait]
 pub trait BannedTokenStore {
+    /// Bans the token with this `jti`, or every token of the session with this `sid`, until `expires_at`
+    /// (in seconds), when they would be rejected as expired anyway.
     async fn add_banned_token(
         &mut self,
-        token: Secret<String>,
+        id: String,
+        expires_at: i64,
+    ) -> Result<(), BannedTokenStoreError>;
+    async fn is_banned(&self, id: &str) -> Result<bool, BannedTokenStoreError>;
+    /// Revokes every token for `subject` that was issued before `timestamp` (in seconds).
+    async fn revoke_tokens_issued_before(
+        &mut self,
+        subject: Secret<String>,
+        timestamp: i64,
     ) -> Result<(), BannedTokenStoreError>;
-    async fn is_banned(&self, token: &Secret<String>) -> Result<bool, BannedTokenStoreError>;
+    async fn get_revocation_timestamp(
+        &self,
+        subject: &Secret<String>,
+    ) -> Result<Option<i64>, BannedTokenStoreError>;
 }
 
 #[derive(Debug, Error)]
@@ -84,6 +361,294 @@ impl PartialEq for TwoFACodeStoreError {
     }
 }
 
+#[async_trait::async_trait]
+pub trait RefreshTokenStore {
+    async fn add_token(
+        &mut self,
+        token: RefreshToken,
+        email: Email,
+        family_id: RefreshTokenFamilyId,
+    ) -> Result<(), RefreshTokenStoreError>;
+    async fn consume_token(
+        &mut self,
+        token: &RefreshToken,
+    ) -> Result<(Email, RefreshTokenFamilyId), RefreshTokenStoreError>;
+    /// Looks up the family of a token without using it up, whether or not it has been used already
+    async fn get_family(
+        &self,
+        token: &RefreshToken,
+    ) -> Result<RefreshTokenFamilyId, RefreshTokenStoreError>;
+    async fn revoke_family(
+        &mut self,
+        family_id: &RefreshTokenFamilyId,
+    ) -> Result<(), RefreshTokenStoreError>;
+    async fn revoke_user_tokens(&mut self, email: &Email) -> Result<(), RefreshTokenStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum RefreshTokenStoreError {
+    #[error("Refresh token not found")]
+    TokenNotFound,
+    #[error("Refresh token reused")]
+    TokenReused(RefreshTokenFamilyId),
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for RefreshTokenStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::TokenNotFound, Self::TokenNotFound)
+                | (Self::TokenReused(_), Self::TokenReused(_))
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait SessionStore {
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError>;
+    /// Records that the session was just used from `client`.
+    async fn touch_session(
+        &mut self,
+        id: &RefreshTokenFamilyId,
+        client: SessionClient,
+    ) -> Result<(), SessionStoreError>;
+    async fn get_session(&self, id: &RefreshTokenFamilyId) -> Result<Session, SessionStoreError>;
+    /// Returns the user's sessions, most recently seen first.
+    async fn get_user_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError>;
+    async fn remove_session(&mut self, id: &RefreshTokenFamilyId) -> Result<(), SessionStoreError>;
+    async fn remove_user_sessions(&mut self, email: &Email) -> Result<(), SessionStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum SessionStoreError {
+    #[error("Session not found")]
+    SessionNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for SessionStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::SessionNotFound, Self::SessionNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait PasswordResetTokenStore {
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: PasswordResetToken,
+    ) -> Result<(), PasswordResetTokenStoreError>;
+    /// Returns the email the token was issued for and removes the token, so it can only be used once.
+    async fn take_token(
+        &mut self,
+        token: &PasswordResetToken,
+    ) -> Result<Email, PasswordResetTokenStoreError>;
+    /// Removes every token issued for the email, so none of them can reset the password any more.
+    async fn remove_user_tokens(
+        &mut self,
+        email: &Email,
+    ) -> Result<(), PasswordResetTokenStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum PasswordResetTokenStoreError {
+    #[error("Password reset token not found")]
+    TokenNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for PasswordResetTokenStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::TokenNotFound, Self::TokenNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+/// Counts failed authentication attempts per key (an email, a client IP...) and tracks lockouts.
+#[async_trait::async_trait]
+pub trait FailedAttemptStore {
+    /// Increments the failure count and returns it. Counts expire `window_seconds` after the last failure.
+    async fn record_failure(
+        &mut self,
+        key: &str,
+        window_seconds: u64,
+    ) -> Result<u32, FailedAttemptStoreError>;
+    /// Clears the failure count, leaving any active lockout in place.
+    async fn reset(&mut self, key: &str) -> Result<(), FailedAttemptStoreError>;
+    async fn lock(&mut self, key: &str, seconds: u64) -> Result<(), FailedAttemptStoreError>;
+    /// Returns how many seconds are left on the key's lockout, if any.
+    async fn get_lockout(&self, key: &str) -> Result<Option<u64>, FailedAttemptStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum FailedAttemptStoreError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for FailedAttemptStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait RateLimitStore {
+    /// Refills the key's token bucket for the time since it was last used, then tries to take a token from it.
+    /// A full bucket holds `capacity` tokens and refills completely over `refill_seconds`.
+    async fn take_token(
+        &mut self,
+        key: &str,
+        capacity: u32,
+        refill_seconds: u64,
+    ) -> Result<TokenBucket, RateLimitStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum RateLimitStoreError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for RateLimitStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait RecoveryCodeStore {
+    /// Replaces any codes the user already has, so only the latest set works.
+    async fn set_codes(
+        &mut self,
+        email: &Email,
+        codes: Vec<RecoveryCode>,
+    ) -> Result<(), RecoveryCodeStoreError>;
+    /// Burns the code and returns how many the user has left.
+    async fn use_code(
+        &mut self,
+        email: &Email,
+        code: &RecoveryCode,
+    ) -> Result<usize, RecoveryCodeStoreError>;
+    async fn count_codes(&self, email: &Email) -> Result<usize, RecoveryCodeStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum RecoveryCodeStoreError {
+    #[error("Recovery code not found")]
+    RecoveryCodeNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for RecoveryCodeStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::RecoveryCodeNotFound, Self::RecoveryCodeNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait WebAuthnCredentialStore {
+    async fn add_credential(
+        &mut self,
+        credential: WebAuthnCredential,
+    ) -> Result<(), WebAuthnCredentialStoreError>;
+    async fn get_credential(
+        &self,
+        credential_id: &WebAuthnCredentialId,
+    ) -> Result<WebAuthnCredential, WebAuthnCredentialStoreError>;
+    async fn get_user_credentials(
+        &self,
+        email: &Email,
+    ) -> Result<Vec<WebAuthnCredential>, WebAuthnCredentialStoreError>;
+    async fn update_sign_count(
+        &mut self,
+        credential_id: &WebAuthnCredentialId,
+        sign_count: u32,
+    ) -> Result<(), WebAuthnCredentialStoreError>;
+    async fn remove_user_credentials(
+        &mut self,
+        email: &Email,
+    ) -> Result<(), WebAuthnCredentialStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum WebAuthnCredentialStoreError {
+    #[error("Credential already exists")]
+    CredentialAlreadyExists,
+    #[error("Credential not found")]
+    CredentialNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for WebAuthnCredentialStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::CredentialAlreadyExists, Self::CredentialAlreadyExists)
+                | (Self::CredentialNotFound, Self::CredentialNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait WebAuthnChallengeStore {
+    async fn add_challenge(
+        &mut self,
+        challenge: WebAuthnChallenge,
+        email: Email,
+        ceremony: WebAuthnCeremony,
+    ) -> Result<(), WebAuthnChallengeStoreError>;
+    /// Returns who the challenge was issued to and for which ceremony, and removes it so it can only be answered once.
+    async fn take_challenge(
+        &mut self,
+        challenge: &WebAuthnChallenge,
+    ) -> Result<(Email, WebAuthnCeremony), WebAuthnChallengeStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum WebAuthnChallengeStoreError {
+    #[error("Challenge not found")]
+    ChallengeNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for WebAuthnChallengeStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::ChallengeNotFound, Self::ChallengeNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct LoginAttemptId(Secret<String>);
 
@@ -126,13 +691,10 @@ impl PartialEq for TwoFACode {
 
 impl TwoFACode {
     pub fn parse(code: Secret<String>) -> Result<Self> {
-        // Ensure `code` is a 6-digit number
-        let code_as_u32 = code
-            .expose_secret()
-            .parse::<u32>()
-            .wrap_err("Invalid 2FA code")?;
+        // Ensure `code` is exactly 6 digits. Leading zeros are allowed, as TOTP codes can have them.
+        let digits = code.expose_secret();
 
-        if (100_000..=999_999).contains(&code_as_u32) {
+        if digits.len() == 6 && digits.bytes().all(|b| b.is_ascii_digit()) {
             Ok(Self(code))
         } else {
             Err(eyre!("Invalid 2FA code"))
@@ -154,3 +716,407 @@ impl AsRef<Secret<String>> for TwoFACode {
         &self.0
     }
 }
+
+#[derive(Debug, Clone)]
+pub struct RefreshToken(Secret<String>);
+
+impl PartialEq for RefreshToken {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl RefreshToken {
+    pub fn parse(token: Secret<String>) -> Result<Self> {
+        if is_opaque_token(&token) {
+            Ok(Self(token))
+        } else {
+            Err(eyre!("Invalid refresh token"))
+        }
+    }
+
+    /// SHA-256 digest of the token. Stores only ever persist this value, so a leaked
+    /// database cannot be used to mint new access tokens.
+    pub fn hash(&self) -> String {
+        hash_opaque_token(&self.0)
+    }
+}
+
+impl Default for RefreshToken {
+    fn default() -> Self {
+        Self(generate_opaque_token())
+    }
+}
+
+impl AsRef<Secret<String>> for RefreshToken {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PasswordResetToken(Secret<String>);
+
+impl PartialEq for PasswordResetToken {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl PasswordResetToken {
+    pub fn parse(token: Secret<String>) -> Result<Self> {
+        if is_opaque_token(&token) {
+            Ok(Self(token))
+        } else {
+            Err(eyre!("Invalid password reset token"))
+        }
+    }
+
+    /// SHA-256 digest of the token, which is what stores persist.
+    pub fn hash(&self) -> String {
+        hash_opaque_token(&self.0)
+    }
+}
+
+impl Default for PasswordResetToken {
+    fn default() -> Self {
+        Self(generate_opaque_token())
+    }
+}
+
+impl AsRef<Secret<String>> for PasswordResetToken {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AuthorizationCode(Secret<String>);
+
+impl PartialEq for AuthorizationCode {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl AuthorizationCode {
+    pub fn parse(code: Secret<String>) -> Result<Self> {
+        if is_opaque_token(&code) {
+            Ok(Self(code))
+        } else {
+            Err(eyre!("Invalid authorization code"))
+        }
+    }
+
+    /// SHA-256 digest of the code, which is what stores persist.
+    pub fn hash(&self) -> String {
+        hash_opaque_token(&self.0)
+    }
+}
+
+impl Default for AuthorizationCode {
+    fn default() -> Self {
+        Self(generate_opaque_token())
+    }
+}
+
+impl AsRef<Secret<String>> for AuthorizationCode {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+/// The secret a machine client authenticates with.
+#[derive(Debug, Clone)]
+pub struct ClientSecret(Secret<String>);
+
+impl PartialEq for ClientSecret {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl ClientSecret {
+    pub fn parse(secret: Secret<String>) -> Result<Self> {
+        if is_opaque_token(&secret) {
+            Ok(Self(secret))
+        } else {
+            Err(eyre!("Invalid client secret"))
+        }
+    }
+
+    /// SHA-256 digest of the secret, which is what stores persist.
+    pub fn hash(&self) -> String {
+        hash_opaque_token(&self.0)
+    }
+}
+
+impl Default for ClientSecret {
+    fn default() -> Self {
+        Self(generate_opaque_token())
+    }
+}
+
+impl AsRef<Secret<String>> for ClientSecret {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+/// A personal API key. Its prefix lets it be told apart from other tokens at a glance, by people and
+/// by secret scanners alike.
+#[derive(Debug, Clone)]
+pub struct ApiKeySecret(Secret<String>);
+
+impl PartialEq for ApiKeySecret {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl ApiKeySecret {
+    pub const PREFIX: &'static str = "apikey_";
+
+    pub fn parse(key: Secret<String>) -> Result<Self> {
+        let is_api_key = key
+            .expose_secret()
+            .strip_prefix(Self::PREFIX)
+            .is_some_and(|token| is_opaque_token(&Secret::new(token.to_owned())));
+
+        if is_api_key {
+            Ok(Self(key))
+        } else {
+            Err(eyre!("Invalid API key"))
+        }
+    }
+
+    /// SHA-256 digest of the key, which is what stores persist.
+    pub fn hash(&self) -> String {
+        hash_opaque_token(&self.0)
+    }
+
+    /// The prefix and first few characters of the key, which are safe to show to tell keys apart.
+    pub fn hint(&self) -> String {
+        self.0.expose_secret()[..Self::PREFIX.len() + API_KEY_HINT_LENGTH].to_owned()
+    }
+}
+
+impl Default for ApiKeySecret {
+    fn default() -> Self {
+        let token = generate_opaque_token();
+        Self(Secret::new(format!(
+            "{}{}",
+            Self::PREFIX,
+            token.expose_secret()
+        )))
+    }
+}
+
+impl AsRef<Secret<String>> for ApiKeySecret {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+const API_KEY_HINT_LENGTH: usize = 4;
+
+/// A token bucket as left by a request that tried to take a token from it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TokenBucket {
+    pub allowed: bool,
+    pub tokens: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct RecoveryCode(Secret<String>);
+
+impl PartialEq for RecoveryCode {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl RecoveryCode {
+    /// Accepts codes as users tend to type them back: any case, with or without the dash.
+    pub fn parse(code: Secret<String>) -> Result<Self> {
+        let chars: String = code
+            .expose_secret()
+            .chars()
+            .filter(|c| *c != '-')
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+
+        if chars.len() == RECOVERY_CODE_LENGTH
+            && chars.bytes().all(|b| RECOVERY_CODE_ALPHABET.contains(&b))
+        {
+            Ok(Self(Secret::new(format_recovery_code(&chars))))
+        } else {
+            Err(eyre!("Invalid recovery code"))
+        }
+    }
+}
+
+impl Default for RecoveryCode {
+    fn default() -> Self {
+        let mut rng = rand::thread_rng();
+        let chars: String = (0..RECOVERY_CODE_LENGTH)
+            .map(|_| RECOVERY_CODE_ALPHABET[rng.gen_range(0..RECOVERY_CODE_ALPHABET.len())] as char)
+            .collect();
+        Self(Secret::new(format_recovery_code(&chars)))
+    }
+}
+
+impl AsRef<Secret<String>> for RecoveryCode {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+// Lowercase letters and digits minus the easily confused 0, o, 1, l and i
+const RECOVERY_CODE_ALPHABET: &[u8] = b"23456789abcdefghjkmnpqrstuvwxyz";
+const RECOVERY_CODE_LENGTH: usize = 10;
+
+// Split in two halves, e.g. "k7m2x-9qhtp", to make the codes easier to copy down
+fn format_recovery_code(chars: &str) -> String {
+    let (first, second) = chars.split_at(RECOVERY_CODE_LENGTH / 2);
+    format!("{}-{}", first, second)
+}
+
+// Opaque tokens are always 64 random alphanumeric characters
+const OPAQUE_TOKEN_LENGTH: usize = 64;
+
+fn generate_opaque_token() -> Secret<String> {
+    let token = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(OPAQUE_TOKEN_LENGTH)
+        .map(char::from)
+        .collect();
+    Secret::new(token)
+}
+
+fn is_opaque_token(token: &Secret<String>) -> bool {
+    let token = token.expose_secret();
+    token.len() == OPAQUE_TOKEN_LENGTH && token.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+fn hash_opaque_token(token: &Secret<String>) -> String {
+    format!("{:x}", Sha256::digest(token.expose_secret().as_bytes()))
+}
+
+/// Identifies a chain of rotated refresh tokens that all descend from the same login.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RefreshTokenFamilyId(String);
+
+impl RefreshTokenFamilyId {
+    pub fn parse(id: String) -> Result<Self> {
+        let parsed_id = uuid::Uuid::parse_str(&id).wrap_err("Invalid refresh token family ID")?;
+        Ok(Self(parsed_id.to_string()))
+    }
+}
+
+impl Default for RefreshTokenFamilyId {
+    fn default() -> Self {
+        Self(uuid::Uuid::new_v4().to_string())
+    }
+}
+
+impl AsRef<str> for RefreshTokenFamilyId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn two_fa_codes_with_leading_zeros_are_accepted() {
+        assert!(TwoFACode::parse(Secret::new("012345".to_owned())).is_ok());
+    }
+
+    #[test]
+    fn malformed_two_fa_codes_are_rejected() {
+        for code in ["", "12345", "1234567", "12345a", "+12345", " 12345"] {
+            assert!(TwoFACode::parse(Secret::new(code.to_owned())).is_err());
+        }
+    }
+
+    #[test]
+    fn default_refresh_tokens_are_parsed_successfully() {
+        let token = RefreshToken::default();
+        assert!(RefreshToken::parse(token.as_ref().clone()).is_ok());
+    }
+
+    #[test]
+    fn malformed_refresh_tokens_are_rejected() {
+        for token in ["", "short", &"!".repeat(OPAQUE_TOKEN_LENGTH)] {
+            assert!(RefreshToken::parse(Secret::new(token.to_owned())).is_err());
+        }
+    }
+
+    #[test]
+    fn refresh_token_hash_is_stable_and_hides_the_token() {
+        let token = RefreshToken::default();
+        assert_eq!(token.hash(), token.hash());
+        assert_ne!(&token.hash(), token.as_ref().expose_secret());
+        assert_ne!(token.hash(), RefreshToken::default().hash());
+    }
+
+    #[test]
+    fn default_password_reset_tokens_are_parsed_successfully() {
+        let token = PasswordResetToken::default();
+        assert!(PasswordResetToken::parse(token.as_ref().clone()).is_ok());
+    }
+
+    #[test]
+    fn malformed_password_reset_tokens_are_rejected() {
+        for token in ["", "short", &"!".repeat(OPAQUE_TOKEN_LENGTH)] {
+            assert!(PasswordResetToken::parse(Secret::new(token.to_owned())).is_err());
+        }
+    }
+
+    #[test]
+    fn default_api_keys_are_parsed_successfully() {
+        let key = ApiKeySecret::default();
+        assert!(key.as_ref().expose_secret().starts_with("apikey_"));
+        assert_eq!(ApiKeySecret::parse(key.as_ref().clone()).unwrap(), key);
+        assert_eq!(key.hint().len(), "apikey_".len() + 4);
+        assert!(key.as_ref().expose_secret().starts_with(&key.hint()));
+    }
+
+    #[test]
+    fn malformed_api_keys_are_rejected() {
+        let token = RefreshToken::default();
+        let unprefixed = token.as_ref().expose_secret().as_str();
+        for key in [
+            "",
+            "apikey_",
+            "apikey_short",
+            unprefixed,
+            &format!("apikey_{}", "!".repeat(OPAQUE_TOKEN_LENGTH)),
+            &format!("sk_{}", unprefixed),
+        ] {
+            assert!(ApiKeySecret::parse(Secret::new(key.to_owned())).is_err());
+        }
+    }
+
+    #[test]
+    fn default_recovery_codes_are_parsed_successfully() {
+        let code = RecoveryCode::default();
+        assert_eq!(RecoveryCode::parse(code.as_ref().clone()).unwrap(), code);
+    }
+
+    #[test]
+    fn recovery_codes_are_normalised() {
+        let code = RecoveryCode::parse(Secret::new("K7M2X9QHTP".to_owned())).unwrap();
+        assert_eq!(code.as_ref().expose_secret(), "k7m2x-9qhtp");
+    }
+
+    #[test]
+    fn malformed_recovery_codes_are_rejected() {
+        for code in ["", "123456", "k7m2x-9qht", "k7m2x-9qhtpp", "k7m2x-9qht0"] {
+            assert!(RecoveryCode::parse(Secret::new(code.to_owned())).is_err());
+        }
+    }
+}
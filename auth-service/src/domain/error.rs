@@ -9,10 +9,78 @@ pub enum AuthAPIError {
     InvalidCredentials,
     #[error("Incorrect credentials")]
     IncorrectCredentials,
+    #[error("Email not verified")]
+    EmailNotVerified,
+    #[error("Account disabled")]
+    AccountDisabled,
+    #[error("TOTP already enabled")]
+    TotpAlreadyEnabled,
+    #[error("Passkey already registered")]
+    PasskeyAlreadyRegistered,
+    #[error("Session not found")]
+    SessionNotFound,
+    #[error("User not found")]
+    UserNotFound,
+    #[error("Client not found")]
+    ClientNotFound,
+    #[error("API key not found")]
+    ApiKeyNotFound,
+    /// Carries the number of seconds until the client may try again.
+    #[error("Too many attempts")]
+    TooManyAttempts(u64),
     #[error("Invalid token")]
     InvalidToken,
     #[error("Missing token")]
     MissingToken,
+    #[error("Forbidden")]
+    Forbidden,
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
+
+/// Errors of the OAuth endpoints, which clients tell apart by the codes of RFC 6749, section 5.2.
+#[derive(Debug, Error)]
+pub enum OAuthError {
+    /// Carries a description of what's wrong with the request.
+    #[error("Invalid request: {0}")]
+    InvalidRequest(&'static str),
+    #[error("Invalid client")]
+    InvalidClient,
+    #[error("Invalid grant")]
+    InvalidGrant,
+    #[error("Unauthorized client")]
+    UnauthorizedClient,
+    #[error("Unsupported grant type")]
+    UnsupportedGrantType,
+    #[error("Unsupported response type")]
+    UnsupportedResponseType,
+    #[error("Invalid scope")]
+    InvalidScope,
+    #[error("Access denied")]
+    AccessDenied,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl OAuthError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            OAuthError::InvalidRequest(_) => "invalid_request",
+            OAuthError::InvalidClient => "invalid_client",
+            OAuthError::InvalidGrant => "invalid_grant",
+            OAuthError::UnauthorizedClient => "unauthorized_client",
+            OAuthError::UnsupportedGrantType => "unsupported_grant_type",
+            OAuthError::UnsupportedResponseType => "unsupported_response_type",
+            OAuthError::InvalidScope => "invalid_scope",
+            OAuthError::AccessDenied => "access_denied",
+            OAuthError::UnexpectedError(_) => "server_error",
+        }
+    }
+
+    pub fn description(&self) -> Option<&'static str> {
+        match self {
+            OAuthError::InvalidRequest(description) => Some(description),
+            _ => None,
+        }
+    }
+}
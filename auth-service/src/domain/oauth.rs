@@ -0,0 +1,475 @@
+use std::{collections::BTreeSet, fmt};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Context, Result};
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use url::Url;
+use uuid::Uuid;
+
+use super::{Authentication, ClientSecret, User, UserId};
+
+/// Identifies a registered OAuth client. It's public, as clients send it along with every request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OAuthClientId(Uuid);
+
+impl OAuthClientId {
+    pub fn parse(id: String) -> Result<Self> {
+        let parsed_id = Uuid::parse_str(&id).wrap_err("Invalid OAuth client ID")?;
+        Ok(Self(parsed_id))
+    }
+}
+
+impl Default for OAuthClientId {
+    fn default() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl From<Uuid> for OAuthClientId {
+    fn from(id: Uuid) -> Self {
+        Self(id)
+    }
+}
+
+impl AsRef<Uuid> for OAuthClientId {
+    fn as_ref(&self) -> &Uuid {
+        &self.0
+    }
+}
+
+impl fmt::Display for OAuthClientId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// An application that gets tokens from this service. Public clients, such as single page apps or
+/// CLIs, are ones users log in to. They have no secret, so every authorization must be protected with
+/// PKCE. Machine clients are backend services, which authenticate with a secret to get tokens for
+/// themselves.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OAuthClient {
+    pub id: OAuthClientId,
+    /// Shown to users when they're asked to consent
+    pub name: String,
+    pub kind: OAuthClientKind,
+    /// Where authorization codes may be sent. A requested redirect URI must match one of them exactly.
+    /// Machine clients have none.
+    pub redirect_uris: Vec<String>,
+    /// The most a machine client may ask for. Public clients are limited by what users consent to instead.
+    pub scope: OAuthScope,
+    /// The machine client's current secret. Public clients never have one, and machine clients go
+    /// without once it's revoked.
+    pub secret: Option<OAuthClientSecret>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl OAuthClient {
+    pub fn new(name: String, redirect_uris: Vec<String>) -> Result<Self> {
+        let name = check_client_name(name)?;
+
+        if redirect_uris.is_empty() {
+            return Err(eyre!("Clients need at least one redirect URI"));
+        }
+        for redirect_uri in &redirect_uris {
+            check_redirect_uri(redirect_uri)?;
+        }
+
+        Ok(Self {
+            id: OAuthClientId::default(),
+            name,
+            kind: OAuthClientKind::Public,
+            redirect_uris,
+            scope: OAuthScope::default(),
+            secret: None,
+            created_at: Utc::now(),
+        })
+    }
+
+    /// A machine client allowed tokens within `scope`. It has no secret until one is issued.
+    pub fn new_machine(name: String, scope: OAuthScope) -> Result<Self> {
+        let name = check_client_name(name)?;
+
+        Ok(Self {
+            id: OAuthClientId::default(),
+            name,
+            kind: OAuthClientKind::Machine,
+            redirect_uris: Vec::new(),
+            scope,
+            secret: None,
+            created_at: Utc::now(),
+        })
+    }
+
+    /// Whether `secret` is the machine client's current secret.
+    pub fn verify_secret(&self, secret: &ClientSecret) -> bool {
+        self.kind == OAuthClientKind::Machine
+            && self
+                .secret
+                .as_ref()
+                .is_some_and(|current| current.hash == secret.hash())
+    }
+
+    /// Picks where to send the user back to. Requests may only leave it out if there's no choice.
+    pub fn redirect_uri(&self, requested: Option<&str>) -> Option<&str> {
+        match requested {
+            Some(requested) => self
+                .redirect_uris
+                .iter()
+                .find(|redirect_uri| *redirect_uri == requested)
+                .map(String::as_str),
+            None if self.redirect_uris.len() == 1 => Some(&self.redirect_uris[0]),
+            None => None,
+        }
+    }
+}
+
+const MAX_CLIENT_NAME_LENGTH: usize = 100;
+
+fn check_client_name(name: String) -> Result<String> {
+    let name = name.trim().to_owned();
+    if name.is_empty() || name.len() > MAX_CLIENT_NAME_LENGTH {
+        return Err(eyre!(
+            "Client names must be 1 to {} characters",
+            MAX_CLIENT_NAME_LENGTH
+        ));
+    }
+    Ok(name)
+}
+
+// Codes must only ever travel over TLS, except to a CLI listening on the user's own machine
+fn check_redirect_uri(redirect_uri: &str) -> Result<()> {
+    let url = Url::parse(redirect_uri).wrap_err("Invalid redirect URI")?;
+
+    if url.fragment().is_some() {
+        return Err(eyre!("Redirect URIs must not have a fragment"));
+    }
+
+    let loopback = matches!(url.host_str(), Some("localhost" | "127.0.0.1" | "[::1]"));
+    match url.scheme() {
+        "https" => Ok(()),
+        "http" if loopback => Ok(()),
+        _ => Err(eyre!(
+            "Redirect URIs must use https, or http on a loopback address"
+        )),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OAuthClientKind {
+    /// Users log in to it with the authorization code grant
+    Public,
+    /// It acts on its own behalf with the client credentials grant
+    Machine,
+}
+
+impl OAuthClientKind {
+    pub fn parse(kind: &str) -> Result<Self> {
+        match kind {
+            "public" => Ok(Self::Public),
+            "machine" => Ok(Self::Machine),
+            _ => Err(eyre!("Invalid OAuth client type")),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Public => "public",
+            Self::Machine => "machine",
+        }
+    }
+}
+
+/// What's kept of a machine client's secret. The secret itself is only shown once, when it's issued.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OAuthClientSecret {
+    /// Tokens name the secret they were issued with, so they stop working once it's rotated or revoked
+    pub id: Uuid,
+    /// SHA-256 digest of the secret
+    pub hash: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl OAuthClientSecret {
+    pub fn new(secret: &ClientSecret) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            hash: secret.hash(),
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// Who a token was issued to, which its `sub` claim identifies.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SubjectType {
+    /// A user, whether the token is their own or a client's acting for them
+    #[default]
+    User,
+    /// A machine client acting on its own behalf
+    Client,
+}
+
+impl SubjectType {
+    pub fn is_user(&self) -> bool {
+        *self == Self::User
+    }
+}
+
+/// The access a client asks for, as a set of scope tokens that travel space separated.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OAuthScope(BTreeSet<String>);
+
+impl OAuthScope {
+    /// Makes the client an OpenID Connect relying party, which is issued ID tokens
+    pub const OPENID: &'static str = "openid";
+    /// Grants the user's email and whether it's verified
+    pub const EMAIL: &'static str = "email";
+    /// Grants the user's profile. Users have no name, so that's only their username, which is their email.
+    pub const PROFILE: &'static str = "profile";
+
+    pub fn parse(scope: &str) -> Result<Self> {
+        let tokens: BTreeSet<String> = scope
+            .split(' ')
+            .filter(|token| !token.is_empty())
+            .map(str::to_owned)
+            .collect();
+
+        // Scope tokens are printable ASCII, minus space, `"` and `\` (RFC 6749, section 3.3)
+        let valid = |token: &String| {
+            token
+                .bytes()
+                .all(|b| matches!(b, 0x21 | 0x23..=0x5b | 0x5d..=0x7e))
+        };
+        if !tokens.iter().all(valid) {
+            return Err(eyre!("Invalid scope"));
+        }
+
+        Ok(Self(tokens))
+    }
+
+    /// Whether this scope grants at least as much as `other`.
+    pub fn includes(&self, other: &OAuthScope) -> bool {
+        self.0.is_superset(&other.0)
+    }
+
+    pub fn contains(&self, token: &str) -> bool {
+        self.0.contains(token)
+    }
+
+    pub fn union(&self, other: &OAuthScope) -> Self {
+        Self(self.0.union(&other.0).cloned().collect())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(String::as_str)
+    }
+}
+
+impl fmt::Display for OAuthScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tokens: Vec<&str> = self.iter().collect();
+        f.write_str(&tokens.join(" "))
+    }
+}
+
+/// The OAuth client a session was started for, and the scope of the tokens issued to it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OAuthGrant {
+    pub client_id: OAuthClientId,
+    pub scope: OAuthScope,
+}
+
+/// What a client granted `scope` may know about the user, as OpenID Connect claims.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserInfo {
+    pub sub: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email_verified: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preferred_username: Option<String>,
+}
+
+impl UserInfo {
+    pub fn new(user: &User, scope: &OAuthScope) -> Self {
+        let email = user.email.as_ref().expose_secret().to_owned();
+        let email_scope = scope.contains(OAuthScope::EMAIL);
+        let profile_scope = scope.contains(OAuthScope::PROFILE);
+
+        Self {
+            sub: user.id.to_string(),
+            email: email_scope.then(|| email.clone()),
+            email_verified: email_scope.then_some(user.verified),
+            preferred_username: profile_scope.then_some(email),
+        }
+    }
+}
+
+/// What an authorization code can be exchanged for, and what the exchange must prove.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthorizationCodeGrant {
+    pub client_id: OAuthClientId,
+    pub user_id: UserId,
+    pub redirect_uri: String,
+    pub scope: OAuthScope,
+    /// The PKCE S256 challenge the code verifier must match
+    pub code_challenge: String,
+    /// Passed on in the ID token, so the client can tell it was issued for its request
+    pub nonce: Option<String>,
+    /// How the user logged in before authorizing the client
+    pub authentication: Authentication,
+}
+
+impl AuthorizationCodeGrant {
+    /// Checks the PKCE code verifier against the challenge the code was issued for (RFC 7636).
+    pub fn verify(&self, code_verifier: &str) -> bool {
+        is_pkce_value(code_verifier) && pkce_challenge(code_verifier) == self.code_challenge
+    }
+}
+
+/// Whether `value` can be a PKCE code verifier, or an S256 code challenge, which has the same syntax.
+pub fn is_pkce_value(value: &str) -> bool {
+    (43..=128).contains(&value.len())
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~'))
+}
+
+fn pkce_challenge(code_verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use crate::domain::{Email, Password};
+
+    use super::*;
+
+    #[test]
+    fn redirect_uris_must_be_safe() {
+        for redirect_uri in [
+            "https://app.example.com/callback",
+            "https://app.example.com/callback?tab=1",
+            "http://127.0.0.1:8765/callback",
+            "http://localhost/callback",
+        ] {
+            let client = OAuthClient::new("App".to_owned(), vec![redirect_uri.to_owned()]);
+            assert!(client.is_ok(), "{} should be accepted", redirect_uri);
+        }
+
+        for redirect_uri in [
+            "http://app.example.com/callback",
+            "https://app.example.com/callback#fragment",
+            "javascript:alert(1)",
+            "/callback",
+        ] {
+            let client = OAuthClient::new("App".to_owned(), vec![redirect_uri.to_owned()]);
+            assert!(client.is_err(), "{} should be rejected", redirect_uri);
+        }
+
+        assert!(OAuthClient::new("App".to_owned(), Vec::new()).is_err());
+        assert!(
+            OAuthClient::new(" ".to_owned(), vec!["https://a.example.com".to_owned()]).is_err()
+        );
+    }
+
+    #[test]
+    fn redirect_uri_must_match_exactly() {
+        let client = OAuthClient::new(
+            "App".to_owned(),
+            vec!["https://app.example.com/callback".to_owned()],
+        )
+        .unwrap();
+
+        assert_eq!(
+            client.redirect_uri(None),
+            Some("https://app.example.com/callback")
+        );
+        assert_eq!(
+            client.redirect_uri(Some("https://app.example.com/callback")),
+            Some("https://app.example.com/callback")
+        );
+        assert_eq!(
+            client.redirect_uri(Some("https://app.example.com/callback/")),
+            None
+        );
+    }
+
+    #[test]
+    fn only_a_machine_clients_current_secret_verifies() {
+        let mut client =
+            OAuthClient::new_machine("Jobs".to_owned(), OAuthScope::parse("read").unwrap())
+                .unwrap();
+        let secret = ClientSecret::default();
+        assert!(!client.verify_secret(&secret));
+
+        client.secret = Some(OAuthClientSecret::new(&secret));
+        assert!(client.verify_secret(&secret));
+        assert!(!client.verify_secret(&ClientSecret::default()));
+
+        // Public clients can't be given a secret to authenticate with
+        client.kind = OAuthClientKind::Public;
+        assert!(!client.verify_secret(&secret));
+    }
+
+    #[test]
+    fn scopes_are_sets_of_tokens() {
+        let scope = OAuthScope::parse("read  write read").unwrap();
+        assert_eq!(scope.to_string(), "read write");
+
+        let read = OAuthScope::parse("read").unwrap();
+        assert!(scope.includes(&read));
+        assert!(!read.includes(&scope));
+        assert!(read.includes(&OAuthScope::default()));
+        assert!(scope.contains("write"));
+        assert!(!read.contains("write"));
+        assert_eq!(read.union(&OAuthScope::parse("write").unwrap()), scope);
+
+        assert!(OAuthScope::parse("quote\"d").is_err());
+    }
+
+    #[test]
+    fn user_info_only_has_claims_the_scope_grants() {
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let password = Password::parse(Secret::new("password123".to_owned())).unwrap();
+        let mut user = User::new(email, password, false);
+        user.verified = true;
+
+        let openid = UserInfo::new(&user, &OAuthScope::parse("openid").unwrap());
+        assert_eq!(openid.sub, user.id.to_string());
+        assert_eq!(openid.email, None);
+        assert_eq!(openid.email_verified, None);
+        assert_eq!(openid.preferred_username, None);
+
+        let all = UserInfo::new(&user, &OAuthScope::parse("openid email profile").unwrap());
+        assert_eq!(all.email.as_deref(), Some("test@example.com"));
+        assert_eq!(all.email_verified, Some(true));
+        assert_eq!(all.preferred_username.as_deref(), Some("test@example.com"));
+    }
+
+    #[test]
+    fn pkce_verifier_must_match_challenge() {
+        // The example from RFC 7636, appendix B
+        let grant = AuthorizationCodeGrant {
+            client_id: OAuthClientId::default(),
+            user_id: UserId::default(),
+            redirect_uri: "https://app.example.com/callback".to_owned(),
+            scope: OAuthScope::default(),
+            code_challenge: "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM".to_owned(),
+            nonce: None,
+            authentication: Authentication::new(Vec::new()),
+        };
+
+        assert!(grant.verify("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"));
+        assert!(!grant.verify("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXX"));
+        assert!(!grant.verify("too-short"));
+    }
+}
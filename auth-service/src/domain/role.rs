@@ -0,0 +1,84 @@
+use color_eyre::eyre::{eyre, Result};
+
+// Grants are named like `admin` or `reports:read`, so they read well in tokens and URLs
+fn is_valid_grant_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name.starts_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+        && name.chars().all(|c| {
+            c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '_' | '-' | '.' | ':')
+        })
+}
+
+/// A role granted to a user, carried in their auth tokens.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Role(String);
+
+impl Role {
+    /// Lets the user manage other users through the admin API
+    pub const ADMIN: &'static str = "admin";
+
+    pub fn parse(role: String) -> Result<Self> {
+        if is_valid_grant_name(&role) {
+            Ok(Self(role))
+        } else {
+            Err(eyre!("{} is not a valid role", role))
+        }
+    }
+
+    pub fn admin() -> Self {
+        Self(Self::ADMIN.to_owned())
+    }
+}
+
+impl AsRef<str> for Role {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/// A permission granted to a user, carried in their auth tokens for relying services to check.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Permission(String);
+
+impl Permission {
+    pub fn parse(permission: String) -> Result<Self> {
+        if is_valid_grant_name(&permission) {
+            Ok(Self(permission))
+        } else {
+            Err(eyre!("{} is not a valid permission", permission))
+        }
+    }
+}
+
+impl AsRef<str> for Permission {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_names_are_accepted() {
+        for name in [
+            "admin",
+            "reports:read",
+            "app-service.protected",
+            "2fa_admin",
+        ] {
+            assert!(Role::parse(name.to_owned()).is_ok());
+            assert!(Permission::parse(name.to_owned()).is_ok());
+        }
+    }
+
+    #[test]
+    fn invalid_names_are_rejected() {
+        for name in ["", "Admin", ":read", "has space", "a/b", &"a".repeat(65)] {
+            assert!(Role::parse(name.to_owned()).is_err());
+            assert!(Permission::parse(name.to_owned()).is_err());
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use std::net::IpAddr;
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Result};
+
+use super::{Email, OAuthGrant, RefreshTokenFamilyId};
+
+// Longer user agents are cut short rather than stored in full
+const MAX_USER_AGENT_LENGTH: usize = 512;
+
+/// A login on one device. It lasts as long as its refresh token family, so it goes by the family's ID.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    pub id: RefreshTokenFamilyId,
+    pub email: Email,
+    pub client: SessionClient,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    /// Set for logins to an OAuth client, whose refresh tokens can only be redeemed by that client
+    pub oauth: Option<OAuthGrant>,
+    pub authentication: Authentication,
+}
+
+impl Session {
+    pub fn new(id: RefreshTokenFamilyId, email: Email, client: SessionClient) -> Self {
+        let now = Utc::now();
+        Self {
+            id,
+            email,
+            client,
+            created_at: now,
+            last_seen_at: now,
+            oauth: None,
+            authentication: Authentication::new(Vec::new()),
+        }
+    }
+
+    /// A session the user just logged in to with `methods`.
+    pub fn for_login(
+        id: RefreshTokenFamilyId,
+        email: Email,
+        client: SessionClient,
+        methods: Vec<AuthMethod>,
+    ) -> Self {
+        Self {
+            authentication: Authentication::new(methods),
+            ..Self::new(id, email, client)
+        }
+    }
+
+    /// A session for an OAuth client, which inherits how the user logged in from the session they authorized it with.
+    pub fn for_oauth_client(
+        id: RefreshTokenFamilyId,
+        email: Email,
+        client: SessionClient,
+        grant: OAuthGrant,
+        authentication: Authentication,
+    ) -> Self {
+        Self {
+            oauth: Some(grant),
+            authentication,
+            ..Self::new(id, email, client)
+        }
+    }
+}
+
+/// When and how the user logged in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Authentication {
+    pub time: DateTime<Utc>,
+    /// Empty for sessions from before login methods were recorded
+    pub methods: Vec<AuthMethod>,
+}
+
+impl Authentication {
+    pub fn new(methods: Vec<AuthMethod>) -> Self {
+        Self {
+            time: Utc::now(),
+            methods,
+        }
+    }
+
+    /// The Authentication Method Reference values (RFC 8176) for the login.
+    pub fn amr(&self) -> Vec<&'static str> {
+        let mut amr: Vec<&'static str> = self.methods.iter().map(AuthMethod::as_str).collect();
+
+        // Passkeys are always used with user verification, so they are two factors in one
+        if self.methods.len() > 1 || self.methods.contains(&AuthMethod::HardwareKey) {
+            amr.push("mfa");
+        }
+
+        amr
+    }
+}
+
+/// A way the user proved who they are when logging in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthMethod {
+    Password,
+    /// An emailed or authenticator app 2FA code, or a recovery code
+    OneTimePassword,
+    /// A passkey
+    HardwareKey,
+}
+
+impl AuthMethod {
+    pub fn parse(method: &str) -> Result<Self> {
+        match method {
+            "pwd" => Ok(Self::Password),
+            "otp" => Ok(Self::OneTimePassword),
+            "hwk" => Ok(Self::HardwareKey),
+            _ => Err(eyre!("Unknown authentication method: {}", method)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Password => "pwd",
+            Self::OneTimePassword => "otp",
+            Self::HardwareKey => "hwk",
+        }
+    }
+}
+
+/// The device a session was last seen on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionClient {
+    pub user_agent: Option<String>,
+    pub ip_address: IpAddr,
+}
+
+impl SessionClient {
+    pub fn new(user_agent: Option<&str>, ip_address: IpAddr) -> Self {
+        let user_agent = user_agent.map(|user_agent| {
+            user_agent
+                .chars()
+                .take(MAX_USER_AGENT_LENGTH)
+                .collect::<String>()
+        });
+
+        Self {
+            user_agent,
+            ip_address,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    #[test]
+    fn test_long_user_agents_are_truncated() {
+        let user_agent = "a".repeat(MAX_USER_AGENT_LENGTH * 2);
+        let client = SessionClient::new(Some(&user_agent), IpAddr::V4(Ipv4Addr::LOCALHOST));
+        assert_eq!(
+            client.user_agent.map(|user_agent| user_agent.len()),
+            Some(MAX_USER_AGENT_LENGTH)
+        );
+    }
+
+    #[test]
+    fn test_amr_reports_multiple_factors() {
+        let password = Authentication::new(vec![AuthMethod::Password]);
+        assert_eq!(password.amr(), ["pwd"]);
+
+        let two_factor =
+            Authentication::new(vec![AuthMethod::Password, AuthMethod::OneTimePassword]);
+        assert_eq!(two_factor.amr(), ["pwd", "otp", "mfa"]);
+
+        let passkey = Authentication::new(vec![AuthMethod::HardwareKey]);
+        assert_eq!(passkey.amr(), ["hwk", "mfa"]);
+
+        assert!(Authentication::new(Vec::new()).amr().is_empty());
+    }
+
+    #[test]
+    fn test_auth_methods_round_trip_through_strings() {
+        for method in [
+            AuthMethod::Password,
+            AuthMethod::OneTimePassword,
+            AuthMethod::HardwareKey,
+        ] {
+            assert_eq!(AuthMethod::parse(method.as_str()).unwrap(), method);
+        }
+        assert!(AuthMethod::parse("sms").is_err());
+    }
+}
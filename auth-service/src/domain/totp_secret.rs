@@ -0,0 +1,86 @@
+use color_eyre::eyre::{eyre, Result};
+use rand::RngCore;
+use secrecy::{ExposeSecret, Secret};
+
+// 160 bits, the length recommended by RFC 4226 for HMAC-SHA1
+const TOTP_SECRET_BYTES: usize = 20;
+
+// RFC 4226 requires at least 128 bits
+const MIN_TOTP_SECRET_BYTES: usize = 16;
+
+/// Shared TOTP secret, held as the unpadded base32 string authenticator apps expect.
+#[derive(Debug, Clone)]
+pub struct TotpSecret(Secret<String>);
+
+impl PartialEq for TotpSecret {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl TotpSecret {
+    pub fn parse(s: Secret<String>) -> Result<Self> {
+        let bytes = totp_rs::Secret::Encoded(s.expose_secret().to_owned())
+            .to_bytes()
+            .map_err(|_| eyre!("TOTP secret is not valid base32"))?;
+
+        if bytes.len() < MIN_TOTP_SECRET_BYTES {
+            return Err(eyre!("TOTP secret is too short"));
+        }
+
+        Ok(Self(s))
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        totp_rs::Secret::Encoded(self.0.expose_secret().to_owned())
+            .to_bytes()
+            .map_err(|_| eyre!("TOTP secret is not valid base32"))
+    }
+}
+
+impl Default for TotpSecret {
+    fn default() -> Self {
+        let mut bytes = [0u8; TOTP_SECRET_BYTES];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let encoded = totp_rs::Secret::Raw(bytes.to_vec())
+            .to_encoded()
+            .to_string();
+        Self(Secret::new(encoded))
+    }
+}
+
+impl AsRef<Secret<String>> for TotpSecret {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_secret_is_valid() {
+        let secret = TotpSecret::default();
+        assert_eq!(secret.to_bytes().unwrap().len(), TOTP_SECRET_BYTES);
+        assert!(TotpSecret::parse(secret.as_ref().clone()).is_ok());
+    }
+
+    #[test]
+    fn default_secrets_are_unique() {
+        assert_ne!(TotpSecret::default(), TotpSecret::default());
+    }
+
+    #[test]
+    fn invalid_base32_is_rejected() {
+        let secret = Secret::new("not base32!".to_owned());
+        assert!(TotpSecret::parse(secret).is_err());
+    }
+
+    #[test]
+    fn short_secret_is_rejected() {
+        // 10 bytes of base32
+        let secret = Secret::new("GEZDGNBVGY3TQOJQ".to_owned());
+        assert!(TotpSecret::parse(secret).is_err());
+    }
+}
@@ -1,18 +1,85 @@
+use std::fmt;
+
+use color_eyre::eyre::{Context, Result};
+use uuid::Uuid;
+
 use super::{Email, Password};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct User {
+    pub id: UserId,
     pub email: Email,
     pub password: Password,
     pub requires_2fa: bool,
+    pub verified: bool,
+    pub totp_enabled: bool,
+    /// Disabled users can't log in, and tokens issued to them are rejected.
+    pub disabled: bool,
 }
 
 impl User {
     pub fn new(email: Email, password: Password, requires_2fa: bool) -> Self {
         Self {
+            id: UserId::default(),
             email,
             password,
             requires_2fa,
+            verified: false,
+            totp_enabled: false,
+            disabled: false,
+        }
+    }
+}
+
+/// Identifies a user for good. Unlike their email it never changes and is safe to hand out, e.g. as a token subject.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UserId(Uuid);
+
+impl UserId {
+    pub fn parse(id: String) -> Result<Self> {
+        let parsed_id = Uuid::parse_str(&id).wrap_err("Invalid user ID")?;
+        Ok(Self(parsed_id))
+    }
+}
+
+impl Default for UserId {
+    fn default() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl From<Uuid> for UserId {
+    fn from(id: Uuid) -> Self {
+        Self(id)
+    }
+}
+
+impl AsRef<Uuid> for UserId {
+    fn as_ref(&self) -> &Uuid {
+        &self.0
+    }
+}
+
+impl fmt::Display for UserId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_ids_round_trip_through_strings() {
+        let id = UserId::default();
+        assert_eq!(UserId::parse(id.to_string()).unwrap(), id);
+    }
+
+    #[test]
+    fn malformed_user_ids_are_rejected() {
+        for id in ["", "test@example.com", "not-a-uuid"] {
+            assert!(UserId::parse(id.to_owned()).is_err());
         }
     }
 }
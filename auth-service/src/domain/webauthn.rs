@@ -0,0 +1,136 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD as BASE64_URL, Engine};
+use color_eyre::eyre::{eyre, Context, Result};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::Email;
+
+// The spec asks for at least 16 random bytes
+const CHALLENGE_BYTES: usize = 32;
+
+// Authenticators may not create credential IDs longer than this
+const MAX_CREDENTIAL_ID_BYTES: usize = 1023;
+
+/// A registered passkey. The public key is an uncompressed SEC1 encoded P-256 point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebAuthnCredential {
+    pub credential_id: WebAuthnCredentialId,
+    pub email: Email,
+    pub public_key: Vec<u8>,
+    pub sign_count: u32,
+}
+
+/// Credential ID chosen by the authenticator, held base64url encoded as browsers send it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WebAuthnCredentialId(String);
+
+impl WebAuthnCredentialId {
+    pub fn parse(id: String) -> Result<Self> {
+        let bytes = BASE64_URL
+            .decode(&id)
+            .wrap_err("Credential ID is not valid base64url")?;
+
+        if bytes.is_empty() || bytes.len() > MAX_CREDENTIAL_ID_BYTES {
+            return Err(eyre!("Invalid credential ID length"));
+        }
+
+        Ok(Self(id))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Self::parse(BASE64_URL.encode(bytes))
+    }
+}
+
+impl AsRef<str> for WebAuthnCredentialId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Random challenge an authenticator has to sign, held base64url encoded as it appears in clientDataJSON.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebAuthnChallenge(String);
+
+impl WebAuthnChallenge {
+    pub fn parse(challenge: String) -> Result<Self> {
+        let bytes = BASE64_URL
+            .decode(&challenge)
+            .wrap_err("Challenge is not valid base64url")?;
+
+        if bytes.len() != CHALLENGE_BYTES {
+            return Err(eyre!("Invalid challenge length"));
+        }
+
+        Ok(Self(challenge))
+    }
+
+    pub fn hash(&self) -> String {
+        format!("{:x}", Sha256::digest(self.0.as_bytes()))
+    }
+}
+
+impl Default for WebAuthnChallenge {
+    fn default() -> Self {
+        let mut bytes = [0u8; CHALLENGE_BYTES];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        Self(BASE64_URL.encode(bytes))
+    }
+}
+
+impl AsRef<str> for WebAuthnChallenge {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Which ceremony a challenge was issued for, so a registration challenge can't be used to log in.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum WebAuthnCeremony {
+    Registration,
+    Authentication,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_challenges_are_parsed_successfully() {
+        let challenge = WebAuthnChallenge::default();
+        assert!(WebAuthnChallenge::parse(challenge.as_ref().to_owned()).is_ok());
+    }
+
+    #[test]
+    fn default_challenges_are_unique() {
+        assert_ne!(WebAuthnChallenge::default(), WebAuthnChallenge::default());
+    }
+
+    #[test]
+    fn malformed_challenges_are_rejected() {
+        for challenge in ["", "not base64!", &BASE64_URL.encode([0u8; 16])] {
+            assert!(WebAuthnChallenge::parse(challenge.to_owned()).is_err());
+        }
+    }
+
+    #[test]
+    fn credential_ids_round_trip_through_bytes() {
+        let id = WebAuthnCredentialId::from_bytes(&[1, 2, 3]).unwrap();
+        assert_eq!(
+            WebAuthnCredentialId::parse(id.as_ref().to_owned()).unwrap(),
+            id
+        );
+    }
+
+    #[test]
+    fn malformed_credential_ids_are_rejected() {
+        for id in [
+            "".to_owned(),
+            "not base64!".to_owned(),
+            BASE64_URL.encode([0u8; 1024]),
+        ] {
+            assert!(WebAuthnCredentialId::parse(id).is_err());
+        }
+    }
+}
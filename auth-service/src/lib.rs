@@ -1,79 +1,220 @@
 use app_state::AppState;
 use axum::{
-    http::{Method, StatusCode},
+    extract::{connect_info::IntoMakeServiceWithConnectInfo, ConnectInfo},
+    http::{
+        header::{AUTHORIZATION, CONTENT_TYPE, RETRY_AFTER},
+        Method, StatusCode,
+    },
+    middleware::{self, AddExtension},
     response::{IntoResponse, Response},
-    routing::post,
+    routing::{delete, get, patch, post, put},
     serve::Serve,
     Json, Router,
 };
-use domain::AuthAPIError;
+use domain::{AuthAPIError, OAuthError};
 use redis::{Client, RedisResult};
-use routes::{login, logout, signup, verify_2fa, verify_token};
+use routes::{
+    admin_create_oauth_client, admin_delete_user, admin_disable_user, admin_enable_user,
+    admin_force_password_reset, admin_get_oauth_client, admin_get_user, admin_grant_permission,
+    admin_grant_role, admin_list_oauth_clients, admin_list_users, admin_revoke_oauth_client_secret,
+    admin_revoke_permission, admin_revoke_role, admin_rotate_oauth_client_secret,
+    admin_set_requires_2fa, change_email, change_password, confirm_email_change,
+    confirm_password_reset, confirm_totp, create_api_key, delete_account, delete_api_key,
+    delete_session, enroll_totp, export_account, generate_recovery_codes, introspect, jwks,
+    list_api_keys, list_sessions, login, logout, logout_all, oauth_authorize, oauth_consent,
+    oauth_token, openid_configuration, refresh, rename_api_key, request_password_reset,
+    resend_verification, revoke, signup, userinfo, verify_2fa, verify_api_key, verify_email,
+    verify_token, webauthn_login_finish, webauthn_login_start, webauthn_register_finish,
+    webauthn_register_start,
+};
 use secrecy::{ExposeSecret, Secret};
 // use routes::{login, signup, verify_2fa, verify_token};
 use serde::{Deserialize, Serialize};
 use sqlx::{postgres::PgPoolOptions, PgPool};
-use std::error::Error;
-use tower_http::{cors::CorsLayer, services::ServeDir, trace::TraceLayer};
+use std::{error::Error, net::SocketAddr};
+use tower_http::{
+    cors::{Any, CorsLayer},
+    services::ServeDir,
+    trace::TraceLayer,
+};
 use utils::tracing::{make_span_with_request_id, on_request, on_response};
+use utils::{auth::require_admin, rate_limit::rate_limit};
 
 pub mod app_state;
 pub mod domain {
+    pub mod api_key;
+    pub mod audit_event;
     pub mod data_stores;
     pub mod email;
     pub mod email_client;
     pub mod error;
+    pub mod oauth;
     pub mod password;
+    pub mod role;
+    pub mod session;
+    pub mod totp_secret;
     pub mod user;
+    pub mod webauthn;
     // re-export the modules
+    pub use api_key::*;
+    pub use audit_event::*;
     pub use data_stores::*;
     pub use email::*;
     pub use email_client::*;
     pub use error::*;
+    pub use oauth::*;
     pub use password::*;
+    pub use role::*;
+    pub use session::*;
+    pub use totp_secret::*;
     pub use user::*;
+    pub use webauthn::*;
 }
 pub mod routes {
+    pub mod account;
+    pub mod admin;
+    pub mod api_keys;
+    pub mod change_email;
+    pub mod change_password;
+    pub mod introspect;
+    pub mod jwks;
     pub mod login;
     pub mod logout;
+    pub mod oauth;
+    pub mod oauth_clients;
+    pub mod oidc;
+    pub mod password_reset;
+    pub mod recovery_codes;
+    pub mod refresh;
+    pub mod revoke;
+    pub mod sessions;
     pub mod signup;
+    pub mod totp;
     pub mod verify_2fa;
+    pub mod verify_email;
     pub mod verify_token;
+    pub mod webauthn;
     // re-export the modules
+    pub use account::*;
+    pub use admin::*;
+    pub use api_keys::*;
+    pub use change_email::*;
+    pub use change_password::*;
+    pub use introspect::*;
+    pub use jwks::*;
     pub use login::*;
     pub use logout::*;
+    pub use oauth::*;
+    pub use oauth_clients::*;
+    pub use oidc::*;
+    pub use password_reset::*;
+    pub use recovery_codes::*;
+    pub use refresh::*;
+    pub use revoke::*;
+    pub use sessions::*;
     pub use signup::*;
+    pub use totp::*;
     pub use verify_2fa::*;
+    pub use verify_email::*;
     pub use verify_token::*;
+    pub use webauthn::*;
 }
 pub mod services {
     pub mod data_stores {
+        pub mod hashmap_api_key_store;
+        pub mod hashmap_audit_event_store;
+        pub mod hashmap_authorization_code_store;
+        pub mod hashmap_failed_attempt_store;
+        pub mod hashmap_oauth_client_store;
+        pub mod hashmap_oauth_consent_store;
+        pub mod hashmap_password_reset_token_store;
+        pub mod hashmap_rate_limit_store;
+        pub mod hashmap_recovery_code_store;
+        pub mod hashmap_refresh_token_store;
+        pub mod hashmap_role_store;
+        pub mod hashmap_session_store;
         pub mod hashmap_two_fa_code_store;
         pub mod hashmap_user_store;
+        pub mod hashmap_webauthn_challenge_store;
+        pub mod hashmap_webauthn_credential_store;
         pub mod hashset_banned_token_store;
+        pub mod postgres_api_key_store;
+        pub mod postgres_audit_event_store;
+        pub mod postgres_oauth_client_store;
+        pub mod postgres_oauth_consent_store;
+        pub mod postgres_recovery_code_store;
+        pub mod postgres_refresh_token_store;
+        pub mod postgres_role_store;
+        pub mod postgres_session_store;
         pub mod postgres_user_store;
+        pub mod postgres_webauthn_credential_store;
+        pub mod redis_authorization_code_store;
         pub mod redis_banned_token_store;
+        pub mod redis_failed_attempt_store;
+        pub mod redis_password_reset_token_store;
+        pub mod redis_rate_limit_store;
+        pub mod redis_refresh_token_store;
         pub mod redis_two_fa_code_store;
+        pub mod redis_webauthn_challenge_store;
         // re-export the modules
+        pub use hashmap_api_key_store::*;
+        pub use hashmap_audit_event_store::*;
+        pub use hashmap_authorization_code_store::*;
+        pub use hashmap_failed_attempt_store::*;
+        pub use hashmap_oauth_client_store::*;
+        pub use hashmap_oauth_consent_store::*;
+        pub use hashmap_password_reset_token_store::*;
+        pub use hashmap_rate_limit_store::*;
+        pub use hashmap_recovery_code_store::*;
+        pub use hashmap_refresh_token_store::*;
+        pub use hashmap_role_store::*;
+        pub use hashmap_session_store::*;
         pub use hashmap_two_fa_code_store::*;
         pub use hashmap_user_store::*;
+        pub use hashmap_webauthn_challenge_store::*;
+        pub use hashmap_webauthn_credential_store::*;
         pub use hashset_banned_token_store::*;
+        pub use postgres_api_key_store::*;
+        pub use postgres_audit_event_store::*;
+        pub use postgres_oauth_client_store::*;
+        pub use postgres_oauth_consent_store::*;
+        pub use postgres_recovery_code_store::*;
+        pub use postgres_refresh_token_store::*;
+        pub use postgres_role_store::*;
+        pub use postgres_session_store::*;
         pub use postgres_user_store::*;
+        pub use postgres_webauthn_credential_store::*;
+        pub use redis_authorization_code_store::*;
         pub use redis_banned_token_store::*;
+        pub use redis_failed_attempt_store::*;
+        pub use redis_password_reset_token_store::*;
+        pub use redis_rate_limit_store::*;
+        pub use redis_refresh_token_store::*;
         pub use redis_two_fa_code_store::*;
+        pub use redis_webauthn_challenge_store::*;
     }
     pub mod mock_email_client;
     pub mod postmark_email_client;
 }
 
 pub mod utils {
+    pub mod audit;
     pub mod auth;
+    pub mod brute_force;
+    pub mod client_ip;
     pub mod constants;
+    pub mod jwt_keys;
+    pub mod rate_limit;
+    pub mod totp;
     pub mod tracing;
+    pub mod webauthn;
 }
 
 pub struct Application {
-    server: Serve<Router, Router>,
+    server: Serve<
+        IntoMakeServiceWithConnectInfo<Router, SocketAddr>,
+        AddExtension<Router, ConnectInfo<SocketAddr>>,
+    >,
     pub address: String,
 }
 
@@ -86,19 +227,119 @@ impl Application {
         ];
 
         let cors = CorsLayer::new()
-            .allow_methods([Method::GET, Method::POST])
+            .allow_methods([
+                Method::GET,
+                Method::POST,
+                Method::PUT,
+                Method::PATCH,
+                Method::DELETE,
+            ])
             .allow_credentials(true)
             .allow_origin(allowed_origins);
 
+        // Every admin route requires the auth token of an admin
+        let admin_router = Router::new()
+            .route("/users", get(admin_list_users))
+            .route("/users/:id", get(admin_get_user).delete(admin_delete_user))
+            .route("/users/:id/disable", post(admin_disable_user))
+            .route("/users/:id/enable", post(admin_enable_user))
+            .route("/users/:id/requires-2fa", put(admin_set_requires_2fa))
+            .route(
+                "/users/:id/password-reset",
+                post(admin_force_password_reset),
+            )
+            .route("/users/:id/roles", post(admin_grant_role))
+            .route("/users/:id/roles/:role", delete(admin_revoke_role))
+            .route("/users/:id/permissions", post(admin_grant_permission))
+            .route(
+                "/users/:id/permissions/:permission",
+                delete(admin_revoke_permission),
+            )
+            .route(
+                "/oauth/clients",
+                get(admin_list_oauth_clients).post(admin_create_oauth_client),
+            )
+            .route("/oauth/clients/:id", get(admin_get_oauth_client))
+            .route(
+                "/oauth/clients/:id/secret",
+                post(admin_rotate_oauth_client_secret).delete(admin_revoke_oauth_client_secret),
+            )
+            .route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                require_admin,
+            ));
+
+        // OAuth clients call these from their own origins, and never with cookies
+        let client_cors = CorsLayer::new()
+            .allow_methods([
+                Method::GET,
+                Method::POST,
+                Method::PUT,
+                Method::PATCH,
+                Method::DELETE,
+            ])
+            .allow_headers([AUTHORIZATION, CONTENT_TYPE])
+            .allow_origin(Any);
+
+        let client_router = Router::new()
+            .route("/oauth/token", post(oauth_token))
+            .route("/oauth/introspect", post(introspect))
+            .route("/oauth/revoke", post(revoke))
+            .route("/userinfo", get(userinfo).post(userinfo))
+            .route(
+                "/.well-known/openid-configuration",
+                get(openid_configuration),
+            )
+            .layer(middleware::from_fn_with_state(
+                app_state.rate_limiter.clone(),
+                rate_limit,
+            ))
+            .with_state(app_state.clone())
+            .layer(client_cors);
+
         let router = Router::new()
             .nest_service("/", ServeDir::new("assets"))
             .route("/signup", post(signup))
+            .route("/verify-email", get(verify_email))
+            .route("/resend-verification", post(resend_verification))
             .route("/login", post(login))
             .route("/verify-2fa", post(verify_2fa))
+            .route("/totp/enroll", post(enroll_totp))
+            .route("/totp/confirm", post(confirm_totp))
+            .route("/recovery-codes", post(generate_recovery_codes))
+            .route("/webauthn/register/start", post(webauthn_register_start))
+            .route("/webauthn/register/finish", post(webauthn_register_finish))
+            .route("/webauthn/login/start", post(webauthn_login_start))
+            .route("/webauthn/login/finish", post(webauthn_login_finish))
             .route("/logout", post(logout))
+            .route("/logout-all", post(logout_all))
+            .route("/sessions", get(list_sessions))
+            .route("/sessions/:id", delete(delete_session))
+            .route("/refresh", post(refresh))
+            .route("/password-reset/request", post(request_password_reset))
+            .route("/password-reset/confirm", post(confirm_password_reset))
+            .route("/change-password", post(change_password))
+            .route("/change-email", post(change_email))
+            .route("/change-email/confirm", get(confirm_email_change))
+            .route("/account", delete(delete_account))
+            .route("/account/export", get(export_account))
             .route("/verify-token", post(verify_token))
-            .with_state(app_state)
+            .route("/api-keys", get(list_api_keys).post(create_api_key))
+            .route(
+                "/api-keys/:id",
+                patch(rename_api_key).delete(delete_api_key),
+            )
+            .route("/verify-api-key", post(verify_api_key))
+            .route("/.well-known/jwks.json", get(jwks))
+            .route("/oauth/authorize", get(oauth_authorize).post(oauth_consent))
+            .nest("/admin", admin_router)
+            .layer(middleware::from_fn_with_state(
+                app_state.rate_limiter.clone(),
+                rate_limit,
+            ))
+            .with_state(app_state.clone())
             .layer(cors)
+            .merge(client_router)
             .layer(
                 TraceLayer::new_for_http()
                     .make_span_with(make_span_with_request_id)
@@ -108,7 +349,11 @@ impl Application {
 
         let listener = tokio::net::TcpListener::bind(address).await?;
         let address = listener.local_addr()?.to_string();
-        let server = axum::serve(listener, router);
+        // Handlers need the peer address to count failed attempts per client IP
+        let server = axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<SocketAddr>(),
+        );
 
         // Create a new Application instance and return it
         Ok(Application { server, address })
@@ -129,14 +374,34 @@ pub struct ErrorResponse {
 impl IntoResponse for AuthAPIError {
     fn into_response(self) -> Response {
         log_error_chain(&self);
+
+        let retry_after = match self {
+            AuthAPIError::TooManyAttempts(seconds) => Some(seconds),
+            _ => None,
+        };
+
         let (status, error_message) = match self {
             AuthAPIError::UserAlreadyExists => (StatusCode::CONFLICT, "User already exists"),
             AuthAPIError::InvalidCredentials => (StatusCode::BAD_REQUEST, "Invalid credentials"),
             AuthAPIError::IncorrectCredentials => {
                 (StatusCode::UNAUTHORIZED, "Incorrect credentials")
             }
+            AuthAPIError::EmailNotVerified => (StatusCode::FORBIDDEN, "Email not verified"),
+            AuthAPIError::AccountDisabled => (StatusCode::FORBIDDEN, "Account disabled"),
+            AuthAPIError::TotpAlreadyEnabled => (StatusCode::CONFLICT, "TOTP already enabled"),
+            AuthAPIError::PasskeyAlreadyRegistered => {
+                (StatusCode::CONFLICT, "Passkey already registered")
+            }
+            AuthAPIError::SessionNotFound => (StatusCode::NOT_FOUND, "Session not found"),
+            AuthAPIError::UserNotFound => (StatusCode::NOT_FOUND, "User not found"),
+            AuthAPIError::ClientNotFound => (StatusCode::NOT_FOUND, "Client not found"),
+            AuthAPIError::ApiKeyNotFound => (StatusCode::NOT_FOUND, "API key not found"),
+            AuthAPIError::TooManyAttempts(_) => {
+                (StatusCode::TOO_MANY_REQUESTS, "Too many attempts")
+            }
             AuthAPIError::MissingToken => (StatusCode::BAD_REQUEST, "Missing auth token"),
             AuthAPIError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid auth token"),
+            AuthAPIError::Forbidden => (StatusCode::FORBIDDEN, "Forbidden"),
             AuthAPIError::UnexpectedError(_) => {
                 (StatusCode::INTERNAL_SERVER_ERROR, "Unexpected error")
             }
@@ -145,6 +410,36 @@ impl IntoResponse for AuthAPIError {
         let body = Json(ErrorResponse {
             error: error_message.to_string(),
         });
+
+        match retry_after {
+            Some(seconds) => (status, [(RETRY_AFTER, seconds.to_string())], body).into_response(),
+            None => (status, body).into_response(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct OAuthErrorResponse {
+    pub error: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_description: Option<String>,
+}
+
+impl IntoResponse for OAuthError {
+    fn into_response(self) -> Response {
+        log_error_chain(&self);
+
+        let status = match self {
+            OAuthError::InvalidClient => StatusCode::UNAUTHORIZED,
+            OAuthError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        };
+
+        let body = Json(OAuthErrorResponse {
+            error: self.code().to_owned(),
+            error_description: self.description().map(str::to_owned),
+        });
+
         (status, body).into_response()
     }
 }
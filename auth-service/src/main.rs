@@ -1,19 +1,35 @@
+use chrono::Utc;
+use color_eyre::eyre::{eyre, Result};
 use reqwest::Client;
-use secrecy::Secret;
+use secrecy::{ExposeSecret, Secret};
 use sqlx::PgPool;
-use std::sync::Arc;
+use std::{path::Path, sync::Arc};
 use tokio::sync::RwLock;
 
 use auth_service::{
     app_state::AppState,
-    domain::Email,
+    domain::{Email, Role, RoleStore, UserStore},
     get_postgres_pool, get_redis_client,
     services::{
-        data_stores::{PostgresUserStore, RedisBannedTokenStore, RedisTwoFACodeStore},
+        data_stores::{
+            PostgresApiKeyStore, PostgresAuditEventStore, PostgresOAuthClientStore,
+            PostgresOAuthConsentStore, PostgresRecoveryCodeStore, PostgresRefreshTokenStore,
+            PostgresRoleStore, PostgresSessionStore, PostgresUserStore,
+            PostgresWebAuthnCredentialStore, RedisAuthorizationCodeStore, RedisBannedTokenStore,
+            RedisFailedAttemptStore, RedisPasswordResetTokenStore, RedisRateLimitStore,
+            RedisTwoFACodeStore, RedisWebAuthnChallengeStore,
+        },
         postmark_email_client::PostmarkEmailClient,
     },
     utils::{
-        constants::{prod, DATABASE_URL, POSTMARK_AUTH_TOKEN, REDIS_HOST_NAME},
+        auth::revoke_user_sessions,
+        constants::{
+            check_prod_env, prod, DATABASE_URL, DEFAULT_JWT_KEY_ACTIVATION_DELAY_SECONDS,
+            JWT_KEYRING, JWT_KEYRING_RELOAD_INTERVAL, JWT_KEYS_DIR, JWT_KEY_RETENTION_SECONDS,
+            POSTMARK_AUTH_TOKEN, RATE_LIMITS, REDIS_HOST_NAME, REQUIRE_EMAIL_VERIFICATION,
+        },
+        jwt_keys::{generate_key_file, prune_key_files, reload_keyring},
+        rate_limit::{RateLimitConfig, RateLimiter},
         tracing::init_tracing,
     },
     Application,
@@ -22,23 +38,90 @@ use auth_service::{
 #[tokio::main]
 async fn main() {
     color_eyre::install().expect("Failed to install color_eyre");
+
+    // Commands such as `auth-service keys rotate` run an admin task instead of starting the server
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        if let Err(e) = run_command(&args).await {
+            eprintln!("{:?}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    check_prod_env();
     init_tracing().expect("Failed to initialize tracing");
     let pg_pool = configure_postgresql().await;
     let redis_connection = Arc::new(RwLock::new(configure_redis()));
 
-    let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
+    let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool.clone())));
     let banned_token_store = Arc::new(RwLock::new(RedisBannedTokenStore::new(
         redis_connection.clone(),
     )));
-    let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_connection)));
+    let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(
+        redis_connection.clone(),
+    )));
+    let password_reset_token_store = Arc::new(RwLock::new(RedisPasswordResetTokenStore::new(
+        redis_connection.clone(),
+    )));
+    let failed_attempt_store = Arc::new(RwLock::new(RedisFailedAttemptStore::new(
+        redis_connection.clone(),
+    )));
+    let webauthn_challenge_store = Arc::new(RwLock::new(RedisWebAuthnChallengeStore::new(
+        redis_connection.clone(),
+    )));
+    let authorization_code_store = Arc::new(RwLock::new(RedisAuthorizationCodeStore::new(
+        redis_connection.clone(),
+    )));
+    // Redis backed, so every replica draws from the same buckets
+    let rate_limit_store = Arc::new(RwLock::new(RedisRateLimitStore::new(redis_connection)));
+    let rate_limits = RateLimitConfig::parse(&RATE_LIMITS).expect("Invalid RATE_LIMITS");
+    let rate_limiter = RateLimiter::new(rate_limit_store, rate_limits);
+    let recovery_code_store =
+        Arc::new(RwLock::new(PostgresRecoveryCodeStore::new(pg_pool.clone())));
+    let webauthn_credential_store = Arc::new(RwLock::new(PostgresWebAuthnCredentialStore::new(
+        pg_pool.clone(),
+    )));
+    let role_store = Arc::new(RwLock::new(PostgresRoleStore::new(pg_pool.clone())));
+    let oauth_client_store = Arc::new(RwLock::new(PostgresOAuthClientStore::new(pg_pool.clone())));
+    let oauth_consent_store =
+        Arc::new(RwLock::new(PostgresOAuthConsentStore::new(pg_pool.clone())));
+    let api_key_store = Arc::new(RwLock::new(PostgresApiKeyStore::new(pg_pool.clone())));
+    let audit_event_store = Arc::new(RwLock::new(PostgresAuditEventStore::new(pg_pool.clone())));
+    let session_store = Arc::new(RwLock::new(PostgresSessionStore::new(pg_pool.clone())));
+    let refresh_token_store = Arc::new(RwLock::new(PostgresRefreshTokenStore::new(pg_pool)));
     let email_client = Arc::new(configure_postmark_email_client());
     let app_state = AppState::new(
         user_store,
         banned_token_store,
         two_fa_code_store,
+        refresh_token_store,
+        session_store,
+        password_reset_token_store,
+        recovery_code_store,
+        failed_attempt_store,
+        webauthn_credential_store,
+        webauthn_challenge_store,
+        role_store,
+        oauth_client_store,
+        oauth_consent_store,
+        authorization_code_store,
+        api_key_store,
+        audit_event_store,
+        rate_limiter,
         email_client,
+        *REQUIRE_EMAIL_VERIFICATION,
     );
 
+    if let Some(dir) = JWT_KEYS_DIR.as_ref() {
+        tokio::spawn(reload_keyring(
+            &JWT_KEYRING,
+            dir.into(),
+            *JWT_KEY_RETENTION_SECONDS,
+            JWT_KEYRING_RELOAD_INTERVAL,
+        ));
+    }
+
     let app = Application::build(app_state, prod::APP_ADDRESS)
         .await
         .expect("Failed to build app");
@@ -46,6 +129,121 @@ async fn main() {
     app.run().await.expect("Failed to run app");
 }
 
+const USAGE: &str = "Usage:
+    auth-service keys rotate [--activate-in <seconds>]
+        Adds a new signing key to JWT_KEYS_DIR, taking over after the given delay.
+        The first key needs `--activate-in 0`, as the server won't start without an active key
+    auth-service keys prune
+        Deletes the retired keys in JWT_KEYS_DIR whose tokens have all expired
+    auth-service users logout-all <email>
+        Revokes every auth and refresh token of the user, logging them out on all devices
+    auth-service users grant-role <email> <role>
+        Grants the role to the user, which their auth tokens carry from their next login or refresh.
+        The `admin` role gives access to the admin API
+    auth-service users revoke-role <email> <role>
+        Revokes the role from the user, logging them out on all devices";
+
+async fn run_command(args: &[String]) -> Result<()> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        ["keys", "rotate"] => rotate_keys(DEFAULT_JWT_KEY_ACTIVATION_DELAY_SECONDS),
+        ["keys", "rotate", "--activate-in", seconds] => rotate_keys(
+            seconds
+                .parse()
+                .map_err(|_| eyre!("{} is not a number of seconds", seconds))?,
+        ),
+        ["keys", "prune"] => prune_keys(),
+        ["users", "logout-all", email] => logout_all(email).await,
+        ["users", "grant-role", email, role] => grant_role(email, role).await,
+        ["users", "revoke-role", email, role] => revoke_role(email, role).await,
+        _ => Err(eyre!(USAGE)),
+    }
+}
+
+fn keys_dir() -> Result<&'static Path> {
+    JWT_KEYS_DIR
+        .as_ref()
+        .map(Path::new)
+        .ok_or_else(|| eyre!("JWT_KEYS_DIR must be set to manage keys"))
+}
+
+fn rotate_keys(activation_delay: i64) -> Result<()> {
+    let path = generate_key_file(keys_dir()?, Utc::now().timestamp() + activation_delay)?;
+    println!("Added {}", path.display());
+    Ok(())
+}
+
+fn prune_keys() -> Result<()> {
+    let now = Utc::now().timestamp();
+    for path in prune_key_files(keys_dir()?, now, *JWT_KEY_RETENTION_SECONDS)? {
+        println!("Deleted {}", path.display());
+    }
+    Ok(())
+}
+
+async fn logout_all(email: &str) -> Result<()> {
+    let email = Email::parse(Secret::new(email.to_owned()))?;
+    let pg_pool = configure_postgresql().await;
+    let redis_connection = Arc::new(RwLock::new(configure_redis()));
+
+    revoke_user_sessions(
+        &email,
+        Arc::new(RwLock::new(PostgresUserStore::new(pg_pool.clone()))),
+        Arc::new(RwLock::new(RedisBannedTokenStore::new(redis_connection))),
+        Arc::new(RwLock::new(PostgresRefreshTokenStore::new(pg_pool.clone()))),
+        Arc::new(RwLock::new(PostgresSessionStore::new(pg_pool))),
+    )
+    .await?;
+
+    println!(
+        "Logged out {} on all devices",
+        email.as_ref().expose_secret()
+    );
+    Ok(())
+}
+
+async fn grant_role(email: &str, role: &str) -> Result<()> {
+    let email = Email::parse(Secret::new(email.to_owned()))?;
+    let role = Role::parse(role.to_owned())?;
+    let pg_pool = configure_postgresql().await;
+
+    let user = PostgresUserStore::new(pg_pool.clone())
+        .get_user(&email)
+        .await?;
+    PostgresRoleStore::new(pg_pool)
+        .grant_role(&user.id, &role)
+        .await?;
+
+    println!(
+        "Granted {} to {}",
+        role.as_ref(),
+        email.as_ref().expose_secret()
+    );
+    Ok(())
+}
+
+async fn revoke_role(email: &str, role: &str) -> Result<()> {
+    let email = Email::parse(Secret::new(email.to_owned()))?;
+    let role = Role::parse(role.to_owned())?;
+    let pg_pool = configure_postgresql().await;
+
+    let mut user_store = PostgresUserStore::new(pg_pool.clone());
+    let user = user_store.get_user(&email).await?;
+    PostgresRoleStore::new(pg_pool)
+        .revoke_role(&user.id, &role)
+        .await?;
+    // Tokens issued so far still carry the role
+    user_store.increment_token_generation(&email).await?;
+
+    println!(
+        "Revoked {} from {}",
+        role.as_ref(),
+        email.as_ref().expose_secret()
+    );
+    Ok(())
+}
+
 async fn configure_postgresql() -> PgPool {
     // Create a new database connection pool
     let pg_pool = get_postgres_pool(&DATABASE_URL)
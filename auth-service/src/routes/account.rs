@@ -0,0 +1,362 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum_extra::extract::{cookie, CookieJar};
+use chrono::SecondsFormat;
+use color_eyre::eyre::Result;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email, OAuthClientStoreError, Password, TwoFACodeStoreError},
+    routes::{
+        api_keys::ApiKeyResponse, change_password::check_current_password,
+        sessions::SessionResponse,
+    },
+    utils::{
+        auth::{authenticate, authenticate_claims, revoke_user_sessions},
+        constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+    },
+};
+
+#[tracing::instrument(name = "Delete account", skip_all)]
+pub async fn delete_account(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<DeleteAccountRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let email = match authenticate(
+        &jar,
+        state.banned_token_store.clone(),
+        state.user_store.clone(),
+    )
+    .await
+    {
+        Ok(email) => email,
+        Err(e) => return (jar, Err(e)),
+    };
+
+    let Ok(password) = Password::parse(request.password) else {
+        return (jar, Err(AuthAPIError::InvalidCredentials));
+    };
+
+    // There's no undoing this, so a stolen auth token alone must not be enough
+    if let Err(e) = check_current_password(&email, &password, &state).await {
+        return (jar, Err(e));
+    }
+
+    if let Err(e) = erase_user(&email, &state).await {
+        return (jar, Err(e));
+    }
+
+    if let Err(e) = send_account_deleted_email(&email, &state).await {
+        tracing::error!("Failed to send account deleted email: {:?}", e);
+    }
+
+    let jar = jar
+        .remove(cookie::Cookie::from(JWT_COOKIE_NAME))
+        .remove(cookie::Cookie::from(REFRESH_TOKEN_COOKIE_NAME));
+
+    (jar, Ok(StatusCode::NO_CONTENT))
+}
+
+#[tracing::instrument(name = "Export account", skip_all)]
+pub async fn export_account(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (claims, email) = authenticate_claims(
+        &jar,
+        state.banned_token_store.clone(),
+        state.user_store.clone(),
+    )
+    .await?;
+
+    let user = state
+        .user_store
+        .read()
+        .await
+        .get_user(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let passkeys = state
+        .webauthn_credential_store
+        .read()
+        .await
+        .get_user_credentials(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let recovery_codes_remaining = state
+        .recovery_code_store
+        .read()
+        .await
+        .count_codes(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let sessions = state
+        .session_store
+        .read()
+        .await
+        .get_user_sessions(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let api_keys = state
+        .api_key_store
+        .read()
+        .await
+        .get_user_keys(&user.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let consents = state
+        .oauth_consent_store
+        .read()
+        .await
+        .get_user_consents(&user.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let mut oauth_consents = Vec::with_capacity(consents.len());
+    let client_store = state.oauth_client_store.read().await;
+    for (client_id, scope) in consents {
+        let client = match client_store.get_client(&client_id).await {
+            Ok(client) => client,
+            // A consent whose client has since been deleted no longer grants anything
+            Err(OAuthClientStoreError::ClientNotFound) => continue,
+            Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+        };
+        oauth_consents.push(OAuthConsentExport {
+            client_id: client_id.to_string(),
+            client_name: client.name,
+            scope: scope.to_string(),
+        });
+    }
+    drop(client_store);
+
+    let audit_events = state
+        .audit_event_store
+        .read()
+        .await
+        .get_user_events(&user.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let role_store = state.role_store.read().await;
+    let roles = role_store
+        .get_roles(&user.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let permissions = role_store
+        .get_permissions(&user.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    // Secrets such as the password hash and TOTP secret are left out on purpose
+    let response = Json(AccountExportResponse {
+        profile: ProfileExport {
+            id: user.id.to_string(),
+            email: email.as_ref().expose_secret().to_owned(),
+            verified: user.verified,
+            roles: roles.iter().map(|role| role.as_ref().to_owned()).collect(),
+            permissions: permissions
+                .iter()
+                .map(|permission| permission.as_ref().to_owned())
+                .collect(),
+        },
+        two_factor: TwoFactorExport {
+            requires_2fa: user.requires_2fa,
+            totp_enabled: user.totp_enabled,
+            passkeys: passkeys
+                .iter()
+                .map(|passkey| passkey.credential_id.as_ref().to_owned())
+                .collect(),
+            recovery_codes_remaining,
+        },
+        sessions: sessions
+            .iter()
+            .map(|session| SessionResponse::new(session, claims.sid()))
+            .collect(),
+        api_keys: api_keys.iter().map(ApiKeyResponse::from).collect(),
+        oauth_consents,
+        audit_events: audit_events
+            .iter()
+            .map(|event| AuditEventExport {
+                event: event.kind.as_str().to_owned(),
+                time: event.created_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+            })
+            .collect(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+/// Removes the user along with everything stored about them, logging them out everywhere.
+pub(crate) async fn erase_user(email: &Email, state: &AppState) -> Result<(), AuthAPIError> {
+    let user = state
+        .user_store
+        .read()
+        .await
+        .get_user(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    // Banning the sessions' tokens keeps them dead should the email be registered again
+    revoke_user_sessions(
+        email,
+        state.user_store.clone(),
+        state.banned_token_store.clone(),
+        state.refresh_token_store.clone(),
+        state.session_store.clone(),
+    )
+    .await
+    .map_err(AuthAPIError::UnexpectedError)?;
+
+    match state
+        .two_fa_code_store
+        .write()
+        .await
+        .remove_code(email)
+        .await
+    {
+        Ok(()) | Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => {}
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    state
+        .recovery_code_store
+        .write()
+        .await
+        .set_codes(email, Vec::new())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    state
+        .webauthn_credential_store
+        .write()
+        .await
+        .remove_user_credentials(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    state
+        .role_store
+        .write()
+        .await
+        .revoke_all(&user.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    state
+        .oauth_consent_store
+        .write()
+        .await
+        .remove_user_consents(&user.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    state
+        .api_key_store
+        .write()
+        .await
+        .remove_user_keys(&user.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    state
+        .audit_event_store
+        .write()
+        .await
+        .remove_user_events(&user.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    // A reset link still in the user's inbox would otherwise work on whoever registers the email next
+    state
+        .password_reset_token_store
+        .write()
+        .await
+        .remove_user_tokens(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    state
+        .user_store
+        .write()
+        .await
+        .delete_user(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
+
+#[tracing::instrument(name = "Send account deleted email", skip_all)]
+async fn send_account_deleted_email(email: &Email, state: &AppState) -> Result<()> {
+    state
+        .email_client
+        .send_email(
+            email,
+            "Your account was deleted",
+            "Your account and everything stored with it have been deleted.",
+        )
+        .await
+}
+
+#[derive(Deserialize)]
+pub struct DeleteAccountRequest {
+    pub password: Secret<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccountExportResponse {
+    pub profile: ProfileExport,
+    #[serde(rename = "twoFactor")]
+    pub two_factor: TwoFactorExport,
+    pub sessions: Vec<SessionResponse>,
+    #[serde(rename = "apiKeys")]
+    pub api_keys: Vec<ApiKeyResponse>,
+    /// OAuth clients the user approved, and the scope each may act with
+    #[serde(rename = "oauthConsents")]
+    pub oauth_consents: Vec<OAuthConsentExport>,
+    /// Security relevant events on the account, most recent first
+    #[serde(rename = "auditEvents")]
+    pub audit_events: Vec<AuditEventExport>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProfileExport {
+    pub id: String,
+    pub email: String,
+    pub verified: bool,
+    pub roles: Vec<String>,
+    pub permissions: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TwoFactorExport {
+    #[serde(rename = "requires2FA")]
+    pub requires_2fa: bool,
+    #[serde(rename = "totpEnabled")]
+    pub totp_enabled: bool,
+    /// IDs of the user's passkeys
+    pub passkeys: Vec<String>,
+    #[serde(rename = "recoveryCodesRemaining")]
+    pub recovery_codes_remaining: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OAuthConsentExport {
+    #[serde(rename = "clientId")]
+    pub client_id: String,
+    #[serde(rename = "clientName")]
+    pub client_name: String,
+    pub scope: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuditEventExport {
+    /// What happened, e.g. `login` or `password_changed`
+    pub event: String,
+    pub time: String,
+}
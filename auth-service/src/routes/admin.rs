@@ -0,0 +1,408 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use rand::{distributions::Alphanumeric, Rng};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuthAPIError, Password, Permission, Role, TwoFACodeStoreError, User, UserId, UserStoreError,
+    },
+    routes::{account::erase_user, password_reset::send_password_reset_email},
+    utils::auth::revoke_user_sessions,
+};
+
+const DEFAULT_PAGE_SIZE: u32 = 50;
+const MAX_PAGE_SIZE: u32 = 100;
+
+#[tracing::instrument(name = "Admin list users", skip_all)]
+pub async fn admin_list_users(
+    State(state): State<AppState>,
+    Query(query): Query<ListUsersQuery>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let offset = query.offset.unwrap_or_default();
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let email_search = query.email.as_deref().filter(|search| !search.is_empty());
+
+    let user_store = state.user_store.read().await;
+    let users = user_store
+        .list_users(email_search, offset, limit)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let total = user_store
+        .count_users(email_search)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    drop(user_store);
+
+    let mut responses = Vec::with_capacity(users.len());
+    for user in &users {
+        responses.push(user_response(user, &state).await?);
+    }
+
+    let response = Json(AdminUsersResponse {
+        users: responses,
+        total,
+        offset,
+        limit,
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Admin get user", skip_all)]
+pub async fn admin_get_user(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let user = find_user(id, &state).await?;
+
+    Ok((StatusCode::OK, Json(user_response(&user, &state).await?)))
+}
+
+#[tracing::instrument(name = "Admin disable user", skip_all)]
+pub async fn admin_disable_user(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let user = find_user(id, &state).await?;
+
+    state
+        .user_store
+        .write()
+        .await
+        .set_disabled(&user.email, true)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    // Tokens of disabled users are rejected anyway, but their sessions shouldn't come back to
+    // life if the account is enabled again
+    revoke_user_sessions(
+        &user.email,
+        state.user_store.clone(),
+        state.banned_token_store.clone(),
+        state.refresh_token_store.clone(),
+        state.session_store.clone(),
+    )
+    .await
+    .map_err(AuthAPIError::UnexpectedError)?;
+
+    match state
+        .two_fa_code_store
+        .write()
+        .await
+        .remove_code(&user.email)
+        .await
+    {
+        Ok(()) | Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => {}
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    let user = reload_user(&user.id, &state).await?;
+
+    Ok((StatusCode::OK, Json(user_response(&user, &state).await?)))
+}
+
+#[tracing::instrument(name = "Admin enable user", skip_all)]
+pub async fn admin_enable_user(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let user = find_user(id, &state).await?;
+
+    state
+        .user_store
+        .write()
+        .await
+        .set_disabled(&user.email, false)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let user = reload_user(&user.id, &state).await?;
+
+    Ok((StatusCode::OK, Json(user_response(&user, &state).await?)))
+}
+
+#[tracing::instrument(name = "Admin set requires 2FA", skip_all)]
+pub async fn admin_set_requires_2fa(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(request): Json<SetRequires2FARequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let user = find_user(id, &state).await?;
+
+    state
+        .user_store
+        .write()
+        .await
+        .set_requires_2fa(&user.email, request.requires_2fa)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let user = reload_user(&user.id, &state).await?;
+
+    Ok((StatusCode::OK, Json(user_response(&user, &state).await?)))
+}
+
+#[tracing::instrument(name = "Admin grant role", skip_all)]
+pub async fn admin_grant_role(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(request): Json<GrantRoleRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let role = Role::parse(request.role).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let user = find_user(id, &state).await?;
+
+    state
+        .role_store
+        .write()
+        .await
+        .grant_role(&user.id, &role)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok((StatusCode::OK, Json(user_response(&user, &state).await?)))
+}
+
+#[tracing::instrument(name = "Admin revoke role", skip_all)]
+pub async fn admin_revoke_role(
+    State(state): State<AppState>,
+    Path((id, role)): Path<(String, String)>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let role = Role::parse(role).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let user = find_user(id, &state).await?;
+
+    state
+        .role_store
+        .write()
+        .await
+        .revoke_role(&user.id, &role)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    revoke_issued_grants(&user, &state).await?;
+
+    Ok((StatusCode::OK, Json(user_response(&user, &state).await?)))
+}
+
+#[tracing::instrument(name = "Admin grant permission", skip_all)]
+pub async fn admin_grant_permission(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(request): Json<GrantPermissionRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let permission =
+        Permission::parse(request.permission).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let user = find_user(id, &state).await?;
+
+    state
+        .role_store
+        .write()
+        .await
+        .grant_permission(&user.id, &permission)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok((StatusCode::OK, Json(user_response(&user, &state).await?)))
+}
+
+#[tracing::instrument(name = "Admin revoke permission", skip_all)]
+pub async fn admin_revoke_permission(
+    State(state): State<AppState>,
+    Path((id, permission)): Path<(String, String)>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let permission = Permission::parse(permission).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let user = find_user(id, &state).await?;
+
+    state
+        .role_store
+        .write()
+        .await
+        .revoke_permission(&user.id, &permission)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    revoke_issued_grants(&user, &state).await?;
+
+    Ok((StatusCode::OK, Json(user_response(&user, &state).await?)))
+}
+
+#[tracing::instrument(name = "Admin force password reset", skip_all)]
+pub async fn admin_force_password_reset(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let user = find_user(id, &state).await?;
+
+    // Swap the password for one nobody knows, so the emailed link is the only way back in
+    let password: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect();
+    let password = Password::parse(Secret::new(password)).map_err(AuthAPIError::UnexpectedError)?;
+
+    state
+        .user_store
+        .write()
+        .await
+        .update_password(&user.email, password)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    revoke_user_sessions(
+        &user.email,
+        state.user_store.clone(),
+        state.banned_token_store.clone(),
+        state.refresh_token_store.clone(),
+        state.session_store.clone(),
+    )
+    .await
+    .map_err(AuthAPIError::UnexpectedError)?;
+
+    // Whoever may have compromised the account could have minted API keys, which outlive sessions
+    state
+        .api_key_store
+        .write()
+        .await
+        .remove_user_keys(&user.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    send_password_reset_email(&user.email, &state)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    let response = Json(AdminMessageResponse {
+        message: "The user has been logged out and sent a password reset link".to_owned(),
+    });
+
+    Ok((StatusCode::ACCEPTED, response))
+}
+
+#[tracing::instrument(name = "Admin delete user", skip_all)]
+pub async fn admin_delete_user(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let user = find_user(id, &state).await?;
+
+    erase_user(&user.email, &state).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Auth tokens carry what was granted when they were issued, so the ones issued so far are revoked.
+// The user stays logged in, as refreshing issues a token with what they have left.
+async fn revoke_issued_grants(user: &User, state: &AppState) -> Result<(), AuthAPIError> {
+    state
+        .user_store
+        .write()
+        .await
+        .increment_token_generation(&user.email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(())
+}
+
+async fn user_response(user: &User, state: &AppState) -> Result<AdminUserResponse, AuthAPIError> {
+    let role_store = state.role_store.read().await;
+    let roles = role_store
+        .get_roles(&user.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let permissions = role_store
+        .get_permissions(&user.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(AdminUserResponse {
+        id: user.id.to_string(),
+        email: user.email.as_ref().expose_secret().to_owned(),
+        verified: user.verified,
+        requires_2fa: user.requires_2fa,
+        totp_enabled: user.totp_enabled,
+        disabled: user.disabled,
+        roles: roles.iter().map(|role| role.as_ref().to_owned()).collect(),
+        permissions: permissions
+            .iter()
+            .map(|permission| permission.as_ref().to_owned())
+            .collect(),
+    })
+}
+
+// Malformed IDs are as unknown as IDs nobody has
+async fn find_user(id: String, state: &AppState) -> Result<User, AuthAPIError> {
+    let id = UserId::parse(id).map_err(|_| AuthAPIError::UserNotFound)?;
+    reload_user(&id, state).await
+}
+
+async fn reload_user(id: &UserId, state: &AppState) -> Result<User, AuthAPIError> {
+    match state.user_store.read().await.get_user_by_id(id).await {
+        Ok(user) => Ok(user),
+        Err(UserStoreError::UserNotFound) => Err(AuthAPIError::UserNotFound),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ListUsersQuery {
+    /// Only list users whose email contains this
+    pub email: Option<String>,
+    pub offset: Option<u32>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Deserialize)]
+pub struct SetRequires2FARequest {
+    #[serde(rename = "requires2FA")]
+    pub requires_2fa: bool,
+}
+
+#[derive(Deserialize)]
+pub struct GrantRoleRequest {
+    pub role: String,
+}
+
+#[derive(Deserialize)]
+pub struct GrantPermissionRequest {
+    pub permission: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AdminUsersResponse {
+    pub users: Vec<AdminUserResponse>,
+    /// How many users match, across all pages
+    pub total: usize,
+    pub offset: u32,
+    pub limit: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AdminUserResponse {
+    pub id: String,
+    pub email: String,
+    pub verified: bool,
+    #[serde(rename = "requires2FA")]
+    pub requires_2fa: bool,
+    #[serde(rename = "totpEnabled")]
+    pub totp_enabled: bool,
+    pub disabled: bool,
+    pub roles: Vec<String>,
+    pub permissions: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct AdminMessageResponse {
+    pub message: String,
+}
@@ -0,0 +1,248 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::CookieJar;
+use chrono::{Duration, SecondsFormat, Utc};
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        check_key_name, ApiKey, ApiKeyId, ApiKeySecret, ApiKeyStoreError, AuthAPIError, OAuthScope,
+        UserId, UserStoreError,
+    },
+    utils::auth::{authenticate, bearer_token},
+};
+
+// Keys can go without an expiry, but those that have one can't be set to last longer than this
+const MAX_API_KEY_LIFETIME_DAYS: i64 = 365;
+
+/// Creates an API key for the logged in user. The key itself is only ever shown here.
+#[tracing::instrument(name = "Create API key", skip_all)]
+pub async fn create_api_key(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<CreateApiKeyRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let user_id = current_user_id(&jar, &state).await?;
+
+    let scope = OAuthScope::parse(request.scope.as_deref().unwrap_or_default())
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let expires_at = match request.expires_in_days {
+        Some(days) if (1..=MAX_API_KEY_LIFETIME_DAYS).contains(&days) => {
+            Some(Utc::now() + Duration::days(days))
+        }
+        Some(_) => return Err(AuthAPIError::InvalidCredentials),
+        None => None,
+    };
+
+    let secret = ApiKeySecret::default();
+    let key = ApiKey::new(user_id, request.name, scope, expires_at, &secret)
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    state
+        .api_key_store
+        .write()
+        .await
+        .add_key(key.clone())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let mut response = ApiKeyResponse::from(&key);
+    response.api_key = Some(secret.as_ref().expose_secret().to_owned());
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+#[tracing::instrument(name = "List API keys", skip_all)]
+pub async fn list_api_keys(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let user_id = current_user_id(&jar, &state).await?;
+
+    let keys = state
+        .api_key_store
+        .read()
+        .await
+        .get_user_keys(&user_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response = Json(ApiKeysResponse {
+        api_keys: keys.iter().map(ApiKeyResponse::from).collect(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Rename API key", skip_all)]
+pub async fn rename_api_key(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(id): Path<String>,
+    Json(request): Json<RenameApiKeyRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let user_id = current_user_id(&jar, &state).await?;
+    let id = ApiKeyId::parse(id).map_err(|_| AuthAPIError::ApiKeyNotFound)?;
+    let name = check_key_name(request.name).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let mut api_key_store = state.api_key_store.write().await;
+    match api_key_store.rename_key(&user_id, &id, &name).await {
+        Ok(()) => {}
+        Err(ApiKeyStoreError::KeyNotFound) => return Err(AuthAPIError::ApiKeyNotFound),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    let key = api_key_store
+        .get_user_keys(&user_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?
+        .into_iter()
+        .find(|key| key.id == id)
+        .ok_or(AuthAPIError::ApiKeyNotFound)?;
+
+    Ok((StatusCode::OK, Json(ApiKeyResponse::from(&key))))
+}
+
+/// Revokes the key for good. Scripts using it are turned away from then on.
+#[tracing::instrument(name = "Revoke API key", skip_all)]
+pub async fn delete_api_key(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let user_id = current_user_id(&jar, &state).await?;
+    let id = ApiKeyId::parse(id).map_err(|_| AuthAPIError::ApiKeyNotFound)?;
+
+    match state
+        .api_key_store
+        .write()
+        .await
+        .remove_key(&user_id, &id)
+        .await
+    {
+        Ok(()) => Ok(StatusCode::NO_CONTENT),
+        Err(ApiKeyStoreError::KeyNotFound) => Err(AuthAPIError::ApiKeyNotFound),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+/// Tells a relying service which user an `Authorization: Bearer` API key acts for, and within what
+/// scope. Keys that are unknown, expired or whose user is disabled are all just invalid.
+#[tracing::instrument(name = "Verify API key", skip_all)]
+pub async fn verify_api_key(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let token = bearer_token(&headers).ok_or(AuthAPIError::MissingToken)?;
+    let secret = ApiKeySecret::parse(token).map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let key = match state.api_key_store.read().await.get_key(&secret).await {
+        Ok(key) if !key.is_expired() => key,
+        Ok(_) | Err(ApiKeyStoreError::KeyNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    match state
+        .user_store
+        .read()
+        .await
+        .get_user_by_id(&key.user_id)
+        .await
+    {
+        Ok(user) if !user.disabled => {}
+        Ok(_) | Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    let response = Json(VerifyApiKeyResponse {
+        user_id: key.user_id.to_string(),
+        key_id: key.id.to_string(),
+        scope: key.scope.to_string(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+// Keys are managed with the user's own auth cookie, never with another key
+async fn current_user_id(jar: &CookieJar, state: &AppState) -> Result<UserId, AuthAPIError> {
+    let email = authenticate(
+        jar,
+        state.banned_token_store.clone(),
+        state.user_store.clone(),
+    )
+    .await?;
+
+    match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => Ok(user.id),
+        Err(UserStoreError::UserNotFound) => Err(AuthAPIError::InvalidToken),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    /// Space-delimited, for relying services to check. Empty if left out.
+    pub scope: Option<String>,
+    /// Left out, the key lasts until it's revoked
+    #[serde(rename = "expiresInDays")]
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RenameApiKeyRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApiKeysResponse {
+    #[serde(rename = "apiKeys")]
+    pub api_keys: Vec<ApiKeyResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApiKeyResponse {
+    pub id: String,
+    pub name: String,
+    pub scope: String,
+    /// How the key starts, to tell it apart from the user's other keys
+    pub hint: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "expiresAt", default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+    /// Only set when the key is created, as it's never shown again
+    #[serde(rename = "apiKey", default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+}
+
+impl From<&ApiKey> for ApiKeyResponse {
+    fn from(key: &ApiKey) -> Self {
+        Self {
+            id: key.id.to_string(),
+            name: key.name.clone(),
+            scope: key.scope.to_string(),
+            hint: key.hint.clone(),
+            created_at: key.created_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+            expires_at: key
+                .expires_at
+                .map(|expires_at| expires_at.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            api_key: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct VerifyApiKeyResponse {
+    #[serde(rename = "userId")]
+    pub user_id: String,
+    #[serde(rename = "keyId")]
+    pub key_id: String,
+    pub scope: String,
+}
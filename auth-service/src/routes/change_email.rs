@@ -0,0 +1,207 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::CookieJar;
+use color_eyre::eyre::Result;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuditEventKind, AuthAPIError, Email, Password, TwoFACodeStoreError, UserStoreError},
+    routes::change_password::check_current_password,
+    utils::{
+        audit::record_audit_event,
+        auth::{
+            authenticate, generate_email_change_token, revoke_user_sessions,
+            validate_email_change_token, EMAIL_CHANGE_TOKEN_TTL_SECONDS,
+        },
+        constants::AUTH_SERVICE_URL,
+    },
+};
+
+#[tracing::instrument(name = "Change email", skip_all)]
+pub async fn change_email(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<ChangeEmailRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = authenticate(
+        &jar,
+        state.banned_token_store.clone(),
+        state.user_store.clone(),
+    )
+    .await?;
+
+    let new_email =
+        Email::parse(request.new_email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let password =
+        Password::parse(request.password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    if new_email == email {
+        return Err(AuthAPIError::InvalidCredentials);
+    }
+
+    check_current_password(&email, &password, &state).await?;
+
+    match state.user_store.read().await.get_user(&new_email).await {
+        Ok(_) => return Err(AuthAPIError::UserAlreadyExists),
+        Err(UserStoreError::UserNotFound) => {}
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    // The link stops working once the user moves on to another token generation, which the change
+    // itself does. Logging out everywhere or changing the password also voids it.
+    let generation = state
+        .user_store
+        .read()
+        .await
+        .get_token_generation(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    // Nothing changes until the link sent to the new address is followed
+    send_email_change_confirmation(&email, &new_email, generation, &state)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    if let Err(e) = send_email_change_notice(&email, &new_email, &state).await {
+        tracing::error!("Failed to send email change notice: {:?}", e);
+    }
+
+    let response = Json(ChangeEmailResponse {
+        message: "A confirmation link has been sent to the new email".to_owned(),
+    });
+
+    Ok((StatusCode::ACCEPTED, response))
+}
+
+#[tracing::instrument(name = "Confirm email change", skip_all)]
+pub async fn confirm_email_change(
+    State(state): State<AppState>,
+    Query(query): Query<ConfirmEmailChangeQuery>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (email, new_email, generation) =
+        validate_email_change_token(&query.token).map_err(|_| AuthAPIError::InvalidToken)?;
+
+    // Moving on to a new token generation along with the email revokes every auth token, which
+    // name the user's old email, and makes this link single use
+    match state
+        .user_store
+        .write()
+        .await
+        .update_email(&email, &new_email, generation)
+        .await
+    {
+        Ok(()) => {}
+        Err(UserStoreError::UserNotFound | UserStoreError::InvalidCredentials) => {
+            return Err(AuthAPIError::InvalidToken)
+        }
+        // The new email may have been registered since the link was sent
+        Err(UserStoreError::UserAlreadyExists) => return Err(AuthAPIError::UserAlreadyExists),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    record_audit_event(&new_email, AuditEventKind::EmailChanged, &state).await;
+
+    // Sessions and refresh tokens have moved to the new email with the user
+    revoke_user_sessions(
+        &new_email,
+        state.user_store.clone(),
+        state.banned_token_store.clone(),
+        state.refresh_token_store.clone(),
+        state.session_store.clone(),
+    )
+    .await
+    .map_err(AuthAPIError::UnexpectedError)?;
+
+    // A login waiting on its 2FA code can't be finished under the old email
+    match state
+        .two_fa_code_store
+        .write()
+        .await
+        .remove_code(&email)
+        .await
+    {
+        Ok(()) | Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => {}
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    // ...nor may a reset link sent to it, which would otherwise work on whoever registers it next
+    state
+        .password_reset_token_store
+        .write()
+        .await
+        .remove_user_tokens(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response = Json(ChangeEmailResponse {
+        message: "Email changed successfully".to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Send email change confirmation", skip_all)]
+async fn send_email_change_confirmation(
+    email: &Email,
+    new_email: &Email,
+    generation: i64,
+    state: &AppState,
+) -> Result<()> {
+    let token = generate_email_change_token(email, new_email, generation)?;
+
+    let link = format!(
+        "{}/change-email/confirm?token={}",
+        AUTH_SERVICE_URL.as_str(),
+        token.expose_secret()
+    );
+    let content = format!(
+        "Use the following link to confirm this as the new email of your account. It expires in {} minutes: {}",
+        EMAIL_CHANGE_TOKEN_TTL_SECONDS / 60,
+        link
+    );
+
+    state
+        .email_client
+        .send_email(new_email, "Confirm your new email", &content)
+        .await
+}
+
+#[tracing::instrument(name = "Send email change notice", skip_all)]
+async fn send_email_change_notice(
+    email: &Email,
+    new_email: &Email,
+    state: &AppState,
+) -> Result<()> {
+    let content = format!(
+        "A change of your account's email to {} was requested. It takes effect once confirmed from that address. If this wasn't you, change your password right away.",
+        new_email.as_ref().expose_secret()
+    );
+
+    state
+        .email_client
+        .send_email(email, "Your email is being changed", &content)
+        .await
+}
+
+#[derive(Deserialize)]
+pub struct ChangeEmailRequest {
+    #[serde(rename = "newEmail")]
+    pub new_email: Secret<String>,
+    pub password: Secret<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ConfirmEmailChangeQuery {
+    pub token: Secret<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ChangeEmailResponse {
+    pub message: String,
+}
@@ -0,0 +1,165 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum_extra::extract::CookieJar;
+use color_eyre::eyre::Result;
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuditEventKind, AuthAPIError, AuthMethod, Email, Password, SessionClient},
+    routes::login::start_session,
+    utils::{
+        audit::record_audit_event,
+        auth::{authenticate, revoke_user_sessions},
+        brute_force::{check_lockout, email_key, record_failed_attempt},
+        constants::MAX_FAILED_LOGIN_ATTEMPTS,
+    },
+};
+
+#[tracing::instrument(name = "Change password", skip_all)]
+pub async fn change_password(
+    State(state): State<AppState>,
+    client: SessionClient,
+    jar: CookieJar,
+    Json(request): Json<ChangePasswordRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let email = match authenticate(
+        &jar,
+        state.banned_token_store.clone(),
+        state.user_store.clone(),
+    )
+    .await
+    {
+        Ok(email) => email,
+        Err(e) => return (jar, Err(e)),
+    };
+
+    let (current_password, new_password) = match (
+        Password::parse(request.current_password),
+        Password::parse(request.new_password),
+    ) {
+        (Ok(current_password), Ok(new_password)) => (current_password, new_password),
+        _ => return (jar, Err(AuthAPIError::InvalidCredentials)),
+    };
+
+    if let Err(e) = check_current_password(&email, &current_password, &state).await {
+        return (jar, Err(e));
+    }
+
+    if let Err(e) = state
+        .user_store
+        .write()
+        .await
+        .update_password(&email, new_password)
+        .await
+    {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+
+    record_audit_event(&email, AuditEventKind::PasswordChanged, &state).await;
+
+    let jar = if request.revoke_other_sessions {
+        // Log out everywhere, then log this device straight back in with a new session
+        if let Err(e) = revoke_user_sessions(
+            &email,
+            state.user_store.clone(),
+            state.banned_token_store.clone(),
+            state.refresh_token_store.clone(),
+            state.session_store.clone(),
+        )
+        .await
+        {
+            return (jar, Err(AuthAPIError::UnexpectedError(e)));
+        }
+
+        // The user has only just proven they know the password, whatever they logged in with before
+        match start_session(&email, vec![AuthMethod::Password], client, &state).await {
+            Ok((auth_cookie, refresh_cookie)) => jar.add(auth_cookie).add(refresh_cookie),
+            Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+        }
+    } else {
+        jar
+    };
+
+    // The change has gone through either way, so a failed notice is only logged
+    if let Err(e) = send_password_changed_email(&email, &state).await {
+        tracing::error!("Failed to send password changed email: {:?}", e);
+    }
+
+    let response = Json(ChangePasswordResponse {
+        message: "Password changed successfully".to_owned(),
+    });
+
+    (jar, Ok((StatusCode::OK, response)))
+}
+
+/// Makes sure a logged in user knows their password before a sensitive change to their account.
+#[tracing::instrument(name = "Check current password", skip_all)]
+pub(crate) async fn check_current_password(
+    email: &Email,
+    password: &Password,
+    state: &AppState,
+) -> Result<(), AuthAPIError> {
+    // A stolen auth token must not be enough to guess the password, so failures count
+    // towards the same lockout as logins
+    let email_key = email_key(email);
+    check_lockout(
+        &state.failed_attempt_store,
+        std::slice::from_ref(&email_key),
+    )
+    .await?;
+
+    let is_valid = state
+        .user_store
+        .read()
+        .await
+        .validate_user(email, password)
+        .await
+        .is_ok();
+
+    if !is_valid {
+        record_failed_attempt(
+            &state.failed_attempt_store,
+            &email_key,
+            *MAX_FAILED_LOGIN_ATTEMPTS,
+        )
+        .await?;
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    Ok(())
+}
+
+#[tracing::instrument(name = "Send password changed email", skip_all)]
+async fn send_password_changed_email(email: &Email, state: &AppState) -> Result<()> {
+    state
+        .email_client
+        .send_email(
+            email,
+            "Your password was changed",
+            "The password of your account was just changed. If this wasn't you, reset your password right away.",
+        )
+        .await
+}
+
+fn default_revoke_other_sessions() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+pub struct ChangePasswordRequest {
+    #[serde(rename = "currentPassword")]
+    pub current_password: Secret<String>,
+    #[serde(rename = "newPassword")]
+    pub new_password: Secret<String>,
+    #[serde(
+        rename = "revokeOtherSessions",
+        default = "default_revoke_other_sessions"
+    )]
+    pub revoke_other_sessions: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ChangePasswordResponse {
+    pub message: String,
+}
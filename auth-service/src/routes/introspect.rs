@@ -0,0 +1,89 @@
+use axum::{
+    extract::State,
+    http::{
+        header::{CACHE_CONTROL, PRAGMA},
+        HeaderMap, StatusCode,
+    },
+    response::IntoResponse,
+    Form, Json,
+};
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::OAuthError,
+    routes::oauth::authenticate_machine_client,
+    utils::{
+        auth::{validate_any_token, Claims},
+        constants::JWT_AUDIENCE,
+    },
+};
+
+/// Tells a resource server whether an access token is active, and what it was issued for (RFC 7662).
+/// Only machine clients may ask, so tokens can't be probed by anyone who finds one.
+#[tracing::instrument(name = "Introspect token", skip_all)]
+pub async fn introspect(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Form(request): Form<IntrospectRequest>,
+) -> Result<impl IntoResponse, OAuthError> {
+    authenticate_machine_client(
+        request.client_id.as_deref(),
+        request.client_secret.as_ref(),
+        &headers,
+        &state,
+    )
+    .await?;
+    let token = request
+        .token
+        .ok_or(OAuthError::InvalidRequest("Missing token"))?;
+
+    // Expired, revoked and unknown tokens are all just inactive, as are refresh tokens
+    let response = match validate_any_token(
+        &token,
+        &JWT_AUDIENCE,
+        state.banned_token_store.clone(),
+        state.user_store.clone(),
+        state.oauth_client_store.clone(),
+    )
+    .await
+    {
+        Ok(claims) => IntrospectResponse {
+            active: true,
+            token_type: Some("Bearer".to_owned()),
+            claims: Some(claims),
+        },
+        Err(_) => IntrospectResponse {
+            active: false,
+            token_type: None,
+            claims: None,
+        },
+    };
+
+    Ok((
+        StatusCode::OK,
+        [(CACHE_CONTROL, "no-store"), (PRAGMA, "no-cache")],
+        Json(response),
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IntrospectRequest {
+    pub token: Option<Secret<String>>,
+    /// Only access tokens can be active, so the hint makes no difference
+    pub token_type_hint: Option<String>,
+    pub client_id: Option<String>,
+    /// Machine clients may send their secret here instead of in an `Authorization: Basic` header
+    pub client_secret: Option<Secret<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IntrospectResponse {
+    pub active: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+    /// The claims of an active token, which inactive ones reveal nothing of
+    #[serde(flatten)]
+    pub claims: Option<Claims>,
+}
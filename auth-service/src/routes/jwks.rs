@@ -0,0 +1,15 @@
+use axum::Json;
+use jsonwebtoken::jwk::JwkSet;
+
+use crate::utils::constants::JWT_KEYRING;
+
+/// Publishes the public signing keys, so other services can verify tokens without calling /verify-token.
+#[tracing::instrument(name = "JWKS", skip_all)]
+pub async fn jwks() -> Json<JwkSet> {
+    Json(
+        JWT_KEYRING
+            .read()
+            .expect("JWT keyring lock poisoned")
+            .jwks(),
+    )
+}
@@ -1,17 +1,27 @@
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
-use axum_extra::extract::CookieJar;
+use axum_extra::extract::{cookie::Cookie, CookieJar};
+use color_eyre::eyre::{Context, Result};
 use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
 
 use crate::{
     app_state::AppState,
-    domain::{AuthAPIError, Email, LoginAttemptId, Password, TwoFACode},
-    utils::auth::generate_auth_cookie,
+    domain::{
+        AuditEventKind, AuthAPIError, AuthMethod, Email, LoginAttemptId, Password,
+        RefreshTokenFamilyId, Session, SessionClient, TwoFACode,
+    },
+    utils::{
+        audit::record_audit_event,
+        auth::{generate_auth_cookie, generate_refresh_cookie},
+        brute_force::{check_lockout, email_key, ip_key, record_failed_attempt},
+        constants::{MAX_FAILED_LOGIN_ATTEMPTS, MAX_FAILED_LOGIN_ATTEMPTS_PER_IP},
+    },
 };
 
 #[tracing::instrument(name = "Login", skip_all)]
 pub async fn login(
     State(state): State<AppState>,
+    client: SessionClient,
     jar: CookieJar,
     Json(request): Json<LoginRequest>,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
@@ -25,10 +35,41 @@ pub async fn login(
         Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
     };
 
+    let email_key = email_key(&email);
+    let ip_key = ip_key(client.ip_address);
+
+    // Locked out clients are turned away before their password is even checked
+    if let Err(e) = check_lockout(
+        &state.failed_attempt_store,
+        &[email_key.clone(), ip_key.clone()],
+    )
+    .await
+    {
+        return (jar, Err(e));
+    }
+
     let user_store = &state.user_store.read().await;
 
     // Return AuthAPIError::IncorrectCredentials if validation fails
     if user_store.validate_user(&email, &password).await.is_err() {
+        if let Err(e) = record_failed_attempt(
+            &state.failed_attempt_store,
+            &email_key,
+            *MAX_FAILED_LOGIN_ATTEMPTS,
+        )
+        .await
+        {
+            return (jar, Err(e));
+        }
+        if let Err(e) = record_failed_attempt(
+            &state.failed_attempt_store,
+            &ip_key,
+            *MAX_FAILED_LOGIN_ATTEMPTS_PER_IP,
+        )
+        .await
+        {
+            return (jar, Err(e));
+        }
         return (jar, Err(AuthAPIError::IncorrectCredentials));
     }
 
@@ -38,9 +79,18 @@ pub async fn login(
         Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
     };
 
+    // Only checked once the password is known to be right, so it can't be used to probe accounts
+    if state.require_email_verification && !user.verified {
+        return (jar, Err(AuthAPIError::EmailNotVerified));
+    }
+
+    if user.disabled {
+        return (jar, Err(AuthAPIError::AccountDisabled));
+    }
+
     match user.requires_2fa {
         true => handle_2fa(&user.email, &state, jar).await,
-        false => handle_no_2fa(&user.email, jar).await,
+        false => handle_no_2fa(&user.email, vec![AuthMethod::Password], client, &state, jar).await,
     }
 }
 
@@ -83,20 +133,34 @@ async fn handle_2fa(
 }
 
 #[tracing::instrument(name = "Handle non-2FA flow", skip_all)]
-async fn handle_no_2fa(
+pub(crate) async fn handle_no_2fa(
     email: &Email,
+    auth_methods: Vec<AuthMethod>,
+    client: SessionClient,
+    state: &AppState,
     jar: CookieJar,
 ) -> (
     CookieJar,
     Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
 ) {
-    // Generate auth cookie
-    let auth_cookie = match generate_auth_cookie(email) {
-        Ok(cookie) => cookie,
-        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
-    };
+    // The account's failure count only clears once the user is fully logged in, not after just the password
+    if let Err(e) = state
+        .failed_attempt_store
+        .write()
+        .await
+        .reset(&email_key(email))
+        .await
+    {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
 
-    let updated_jar = jar.add(auth_cookie);
+    let (auth_cookie, refresh_cookie) =
+        match start_session(email, auth_methods, client, state).await {
+            Ok(cookies) => cookies,
+            Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+        };
+
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
 
     (
         updated_jar,
@@ -104,6 +168,47 @@ async fn handle_no_2fa(
     )
 }
 
+/// Records a new session for the user, who just logged in with `auth_methods`, and issues its auth
+/// and refresh cookies.
+#[tracing::instrument(name = "Start session", skip_all)]
+pub(crate) async fn start_session(
+    email: &Email,
+    auth_methods: Vec<AuthMethod>,
+    client: SessionClient,
+    state: &AppState,
+) -> Result<(Cookie<'static>, Cookie<'static>)> {
+    // Every login starts a new refresh token family, which doubles as the session
+    let session = Session::for_login(
+        RefreshTokenFamilyId::default(),
+        email.clone(),
+        client,
+        auth_methods,
+    );
+    let session_id = session.id.clone();
+
+    state
+        .session_store
+        .write()
+        .await
+        .add_session(session)
+        .await
+        .wrap_err("Failed to add session")?;
+
+    let auth_cookie = generate_auth_cookie(
+        email,
+        &session_id,
+        state.user_store.clone(),
+        state.role_store.clone(),
+    )
+    .await?;
+    let refresh_cookie =
+        generate_refresh_cookie(email, session_id, state.refresh_token_store.clone()).await?;
+
+    record_audit_event(email, AuditEventKind::Login, state).await;
+
+    Ok((auth_cookie, refresh_cookie))
+}
+
 #[derive(Deserialize)]
 pub struct LoginRequest {
     pub email: Secret<String>,
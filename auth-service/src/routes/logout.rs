@@ -4,8 +4,12 @@ use secrecy::Secret;
 
 use crate::{
     app_state::AppState,
-    domain::AuthAPIError,
-    utils::{auth::validate_token, constants::JWT_COOKIE_NAME},
+    domain::{AuditEventKind, AuthAPIError, RefreshTokenFamilyId},
+    utils::{
+        audit::record_audit_event,
+        auth::{authenticate, revoke_session, revoke_token, revoke_user_sessions, validate_token},
+        constants::{JWT_AUDIENCE, JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+    },
 };
 
 #[tracing::instrument(name = "Logout", skip_all)]
@@ -20,24 +24,83 @@ pub async fn logout(
 
     // Validate JWT token
     let token = Secret::new(cookie.value().to_owned());
-    let _ = match validate_token(&token, state.banned_token_store.clone()).await {
+    let (claims, email) = match validate_token(
+        &token,
+        &JWT_AUDIENCE,
+        state.banned_token_store.clone(),
+        state.user_store.clone(),
+    )
+    .await
+    {
         Ok(claims) => claims,
         Err(_) => return (jar, Err(AuthAPIError::InvalidToken)),
     };
 
-    // Add JWT token to BannedUserStore
-    if let Err(e) = state
-        .banned_token_store
-        .write()
-        .await
-        .add_banned_token(token.to_owned())
-        .await
+    // Ban the JWT for the rest of its lifetime
+    if let Err(e) = revoke_token(&claims, state.banned_token_store.clone()).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e)));
+    }
+
+    // Revoke the rest of the session, so it can't be resumed with the refresh token
+    let session_id = match RefreshTokenFamilyId::parse(claims.sid().to_owned()) {
+        Ok(session_id) => session_id,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
+    if let Err(e) = revoke_session(
+        &session_id,
+        state.banned_token_store.clone(),
+        state.refresh_token_store.clone(),
+        state.session_store.clone(),
+    )
+    .await
     {
-        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+        return (jar, Err(AuthAPIError::UnexpectedError(e)));
     }
 
-    // Remove JWT cookie
-    let jar = jar.remove(cookie::Cookie::from(JWT_COOKIE_NAME));
+    record_audit_event(&email, AuditEventKind::Logout, &state).await;
+
+    // Remove JWT and refresh cookies
+    let jar = jar
+        .remove(cookie::Cookie::from(JWT_COOKIE_NAME))
+        .remove(cookie::Cookie::from(REFRESH_TOKEN_COOKIE_NAME));
+
+    (jar, Ok(StatusCode::OK))
+}
+
+/// Logs the user out of every device, e.g. when the account has been compromised.
+#[tracing::instrument(name = "Logout all", skip_all)]
+pub async fn logout_all(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let email = match authenticate(
+        &jar,
+        state.banned_token_store.clone(),
+        state.user_store.clone(),
+    )
+    .await
+    {
+        Ok(email) => email,
+        Err(e) => return (jar, Err(e)),
+    };
+
+    if let Err(e) = revoke_user_sessions(
+        &email,
+        state.user_store.clone(),
+        state.banned_token_store.clone(),
+        state.refresh_token_store.clone(),
+        state.session_store.clone(),
+    )
+    .await
+    {
+        return (jar, Err(AuthAPIError::UnexpectedError(e)));
+    }
+
+    record_audit_event(&email, AuditEventKind::LogoutAll, &state).await;
+
+    let jar = jar
+        .remove(cookie::Cookie::from(JWT_COOKIE_NAME))
+        .remove(cookie::Cookie::from(REFRESH_TOKEN_COOKIE_NAME));
 
     (jar, Ok(StatusCode::OK))
 }
@@ -0,0 +1,743 @@
+use axum::{
+    extract::{Query, RawQuery, State},
+    http::{
+        header::{CACHE_CONTROL, PRAGMA},
+        HeaderMap, StatusCode,
+    },
+    response::{IntoResponse, Redirect, Response},
+    Form, Json,
+};
+use axum_extra::extract::CookieJar;
+use color_eyre::eyre::{eyre, Context, Result};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use url::{form_urlencoded, Url};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        is_pkce_value, AuthAPIError, AuthorizationCode, AuthorizationCodeGrant,
+        AuthorizationCodeStoreError, ClientSecret, OAuthClient, OAuthClientId, OAuthClientKind,
+        OAuthClientStoreError, OAuthError, OAuthGrant, OAuthScope, RefreshToken,
+        RefreshTokenFamilyId, RefreshTokenStoreError, Session, SessionClient, SessionStoreError,
+        User, UserId, UserStoreError,
+    },
+    utils::auth::{
+        authenticate_claims, basic_credentials, generate_access_token, generate_client_token,
+        generate_id_token, generate_refresh_token, revoke_session, touch_session,
+        TOKEN_TTL_SECONDS,
+    },
+};
+
+/// Starts the authorization code flow. Users who are logged in and already consented are sent straight
+/// back to the client with a code; everyone else goes through the login page first.
+#[tracing::instrument(name = "OAuth authorize", skip_all)]
+pub async fn oauth_authorize(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    RawQuery(query): RawQuery,
+    Query(request): Query<AuthorizeRequest>,
+) -> Result<Response, OAuthError> {
+    // Without a trustworthy redirect URI the error can only be shown here, never sent to the client
+    let (client, redirect_uri) = find_client(&request, &state).await?;
+
+    let (scope, code_challenge) = match check_authorize_request(&request) {
+        Ok(checked) => checked,
+        Err(OAuthError::UnexpectedError(e)) => return Err(OAuthError::UnexpectedError(e)),
+        Err(e) => {
+            let location = redirect_with_error(&redirect_uri, &e, request.state.as_deref())
+                .map_err(OAuthError::UnexpectedError)?;
+            return Ok(Redirect::to(&location).into_response());
+        }
+    };
+
+    // The login page comes back here with the same request once the user is logged in
+    let query = query.unwrap_or_default();
+    let session = match find_session(&jar, &state).await {
+        Ok(session) => session,
+        Err(AuthAPIError::UnexpectedError(e)) => return Err(OAuthError::UnexpectedError(e)),
+        Err(_) => return Ok(Redirect::to(&login_page(&[("authorize", &query)])).into_response()),
+    };
+
+    let user = state
+        .user_store
+        .read()
+        .await
+        .get_user(&session.email)
+        .await
+        .map_err(|e| OAuthError::UnexpectedError(e.into()))?;
+
+    let consent = state
+        .oauth_consent_store
+        .read()
+        .await
+        .get_consent(&user.id, &client.id)
+        .await
+        .map_err(|e| OAuthError::UnexpectedError(e.into()))?;
+
+    // Clients the user never approved always get the consent page, even when asking for no scope
+    if !consent.is_some_and(|consent| consent.includes(&scope)) {
+        let location = login_page(&[("authorize", &query), ("client", &client.name)]);
+        return Ok(Redirect::to(&location).into_response());
+    }
+
+    let grant = AuthorizationCodeGrant {
+        client_id: client.id,
+        user_id: user.id,
+        redirect_uri,
+        scope,
+        code_challenge,
+        nonce: request.nonce.clone(),
+        authentication: session.authentication,
+    };
+    let location = issue_code(grant, request.state.as_deref(), &state)
+        .await
+        .map_err(OAuthError::UnexpectedError)?;
+
+    Ok(Redirect::to(&location).into_response())
+}
+
+/// Records the logged in user's answer to the consent page, which then sends them where this says.
+#[tracing::instrument(name = "OAuth consent", skip_all)]
+pub async fn oauth_consent(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Query(request): Query<AuthorizeRequest>,
+    Json(consent): Json<ConsentRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let session = find_session(&jar, &state).await?;
+
+    let (client, redirect_uri) = find_client(&request, &state)
+        .await
+        .map_err(into_auth_api_error)?;
+
+    let (scope, code_challenge) = match check_authorize_request(&request) {
+        Ok(checked) => checked,
+        Err(OAuthError::UnexpectedError(e)) => return Err(AuthAPIError::UnexpectedError(e)),
+        Err(e) => {
+            let redirect_to = redirect_with_error(&redirect_uri, &e, request.state.as_deref())
+                .map_err(AuthAPIError::UnexpectedError)?;
+            return Ok((StatusCode::OK, Json(ConsentResponse { redirect_to })));
+        }
+    };
+
+    if !consent.approve {
+        let redirect_to = redirect_with_error(
+            &redirect_uri,
+            &OAuthError::AccessDenied,
+            request.state.as_deref(),
+        )
+        .map_err(AuthAPIError::UnexpectedError)?;
+        return Ok((StatusCode::OK, Json(ConsentResponse { redirect_to })));
+    }
+
+    let user = state
+        .user_store
+        .read()
+        .await
+        .get_user(&session.email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    // Consent accumulates, so asking for less later doesn't take back what the client was granted
+    let mut consent_store = state.oauth_consent_store.write().await;
+    let consented = consent_store
+        .get_consent(&user.id, &client.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    consent_store
+        .set_consent(
+            &user.id,
+            &client.id,
+            &consented.unwrap_or_default().union(&scope),
+        )
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    drop(consent_store);
+
+    let grant = AuthorizationCodeGrant {
+        client_id: client.id,
+        user_id: user.id,
+        redirect_uri,
+        scope,
+        code_challenge,
+        nonce: request.nonce.clone(),
+        authentication: session.authentication,
+    };
+    let redirect_to = issue_code(grant, request.state.as_deref(), &state)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    Ok((StatusCode::OK, Json(ConsentResponse { redirect_to })))
+}
+
+/// Trades an authorization code or a refresh token for tokens, as public OAuth clients do, or a
+/// machine client's credentials for a token of its own.
+#[tracing::instrument(name = "OAuth token", skip_all)]
+pub async fn oauth_token(
+    State(state): State<AppState>,
+    client_info: SessionClient,
+    headers: HeaderMap,
+    Form(request): Form<TokenRequest>,
+) -> Result<impl IntoResponse, OAuthError> {
+    let response = match request.grant_type.as_deref() {
+        Some("authorization_code") => {
+            Json(exchange_code(&request, client_info, &state).await?).into_response()
+        }
+        Some("refresh_token") => {
+            Json(exchange_refresh_token(&request, client_info, &state).await?).into_response()
+        }
+        Some("client_credentials") => {
+            Json(exchange_client_credentials(&request, &headers, &state).await?).into_response()
+        }
+        Some(_) => return Err(OAuthError::UnsupportedGrantType),
+        None => return Err(OAuthError::InvalidRequest("Missing grant_type")),
+    };
+
+    // Tokens must not be cached along the way (RFC 6749, section 5.1)
+    Ok((
+        StatusCode::OK,
+        [(CACHE_CONTROL, "no-store"), (PRAGMA, "no-cache")],
+        response,
+    ))
+}
+
+#[tracing::instrument(name = "Exchange authorization code", skip_all)]
+async fn exchange_code(
+    request: &TokenRequest,
+    client_info: SessionClient,
+    state: &AppState,
+) -> Result<TokenResponse, OAuthError> {
+    let code = request
+        .code
+        .clone()
+        .ok_or(OAuthError::InvalidRequest("Missing code"))?;
+    let code_verifier = request
+        .code_verifier
+        .as_deref()
+        .ok_or(OAuthError::InvalidRequest("Missing code_verifier"))?;
+    let client = authenticate_client(request.client_id.as_deref(), state).await?;
+
+    let code = AuthorizationCode::parse(Secret::new(code)).map_err(|_| OAuthError::InvalidGrant)?;
+
+    // Taken before anything is checked, so a failed exchange still uses up the code
+    let grant = match state
+        .authorization_code_store
+        .write()
+        .await
+        .take_code(&code)
+        .await
+    {
+        Ok(grant) => grant,
+        Err(AuthorizationCodeStoreError::CodeNotFound) => return Err(OAuthError::InvalidGrant),
+        Err(e) => return Err(OAuthError::UnexpectedError(e.into())),
+    };
+
+    if grant.client_id != client.id
+        || client.redirect_uri(request.redirect_uri.as_deref()) != Some(grant.redirect_uri.as_str())
+        || !grant.verify(code_verifier)
+    {
+        return Err(OAuthError::InvalidGrant);
+    }
+
+    let user = find_user(&grant.user_id, state).await?;
+
+    let oauth = OAuthGrant {
+        client_id: client.id,
+        scope: grant.scope,
+    };
+    let session = Session::for_oauth_client(
+        RefreshTokenFamilyId::default(),
+        user.email.clone(),
+        client_info,
+        oauth,
+        grant.authentication,
+    );
+
+    state
+        .session_store
+        .write()
+        .await
+        .add_session(session.clone())
+        .await
+        .map_err(|e| OAuthError::UnexpectedError(e.into()))?;
+
+    issue_tokens(&user, &session, grant.nonce.as_deref(), state)
+        .await
+        .map_err(OAuthError::UnexpectedError)
+}
+
+#[tracing::instrument(name = "Exchange refresh token", skip_all)]
+async fn exchange_refresh_token(
+    request: &TokenRequest,
+    client_info: SessionClient,
+    state: &AppState,
+) -> Result<TokenResponse, OAuthError> {
+    let token = request
+        .refresh_token
+        .clone()
+        .ok_or(OAuthError::InvalidRequest("Missing refresh_token"))?;
+    let client = authenticate_client(request.client_id.as_deref(), state).await?;
+
+    let token = RefreshToken::parse(Secret::new(token)).map_err(|_| OAuthError::InvalidGrant)?;
+
+    // Only the client the session was started for can refresh it. This is checked before the token is
+    // consumed, so a token sent by anyone else doesn't log its session out.
+    let family_id = match state
+        .refresh_token_store
+        .read()
+        .await
+        .get_family(&token)
+        .await
+    {
+        Ok(family_id) => family_id,
+        Err(RefreshTokenStoreError::TokenNotFound) => return Err(OAuthError::InvalidGrant),
+        Err(e) => return Err(OAuthError::UnexpectedError(e.into())),
+    };
+
+    let session = match state
+        .session_store
+        .read()
+        .await
+        .get_session(&family_id)
+        .await
+    {
+        Ok(session) => session,
+        Err(SessionStoreError::SessionNotFound) => return Err(OAuthError::InvalidGrant),
+        Err(e) => return Err(OAuthError::UnexpectedError(e.into())),
+    };
+    match &session.oauth {
+        Some(oauth) if oauth.client_id == client.id => {}
+        _ => return Err(OAuthError::InvalidGrant),
+    }
+
+    // Each refresh token can only be traded in once
+    let consumed = state
+        .refresh_token_store
+        .write()
+        .await
+        .consume_token(&token)
+        .await;
+
+    let (email, family_id) = match consumed {
+        Ok(consumed) => consumed,
+        Err(RefreshTokenStoreError::TokenReused(family_id)) => {
+            // As with cookies, a rotated token presented again may have been stolen
+            tracing::warn!("Refresh token reuse detected, revoking token family");
+            revoke_session(
+                &family_id,
+                state.banned_token_store.clone(),
+                state.refresh_token_store.clone(),
+                state.session_store.clone(),
+            )
+            .await
+            .map_err(OAuthError::UnexpectedError)?;
+            return Err(OAuthError::InvalidGrant);
+        }
+        Err(RefreshTokenStoreError::TokenNotFound) => return Err(OAuthError::InvalidGrant),
+        Err(e) => return Err(OAuthError::UnexpectedError(e.into())),
+    };
+
+    touch_session(&family_id, &email, client_info, state.session_store.clone())
+        .await
+        .map_err(OAuthError::UnexpectedError)?;
+
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => return Err(OAuthError::InvalidGrant),
+        Err(e) => return Err(OAuthError::UnexpectedError(e.into())),
+    };
+    if user.disabled {
+        return Err(OAuthError::InvalidGrant);
+    }
+
+    // Rotate the refresh token, keeping it in the same family. The nonce was for the first ID token only.
+    issue_tokens(&user, &session, None, state)
+        .await
+        .map_err(OAuthError::UnexpectedError)
+}
+
+#[tracing::instrument(name = "Exchange client credentials", skip_all)]
+async fn exchange_client_credentials(
+    request: &TokenRequest,
+    headers: &HeaderMap,
+    state: &AppState,
+) -> Result<ClientTokenResponse, OAuthError> {
+    let client = authenticate_machine_client(
+        request.client_id.as_deref(),
+        request.client_secret.as_ref(),
+        headers,
+        state,
+    )
+    .await?;
+
+    // Clients get everything they're allowed unless they ask for less
+    let scope = match request.scope.as_deref() {
+        Some(scope) => OAuthScope::parse(scope).map_err(|_| OAuthError::InvalidScope)?,
+        None => client.scope.clone(),
+    };
+    if !client.scope.includes(&scope) {
+        return Err(OAuthError::InvalidScope);
+    }
+
+    let access_token =
+        generate_client_token(&client, &scope).map_err(OAuthError::UnexpectedError)?;
+
+    Ok(ClientTokenResponse {
+        access_token: access_token.expose_secret().to_owned(),
+        token_type: "Bearer".to_owned(),
+        expires_in: TOKEN_TTL_SECONDS,
+        scope: scope.to_string(),
+    })
+}
+
+// Issues the tokens of an OAuth client's session, which must have been started for the client
+async fn issue_tokens(
+    user: &User,
+    session: &Session,
+    nonce: Option<&str>,
+    state: &AppState,
+) -> Result<TokenResponse> {
+    let oauth = session
+        .oauth
+        .as_ref()
+        .ok_or_else(|| eyre!("Session was not started by an OAuth client"))?;
+
+    let access_token = generate_access_token(
+        &user.email,
+        &session.id,
+        oauth,
+        state.user_store.clone(),
+        state.role_store.clone(),
+    )
+    .await?;
+    let refresh_token = generate_refresh_token(
+        &user.email,
+        session.id.clone(),
+        state.refresh_token_store.clone(),
+    )
+    .await?;
+
+    // Only OpenID Connect clients get to know who the user is from the token response
+    let id_token = match oauth.scope.contains(OAuthScope::OPENID) {
+        true => Some(generate_id_token(
+            user,
+            oauth,
+            &session.authentication,
+            nonce,
+        )?),
+        false => None,
+    };
+
+    Ok(TokenResponse {
+        access_token: access_token.expose_secret().to_owned(),
+        token_type: "Bearer".to_owned(),
+        expires_in: TOKEN_TTL_SECONDS,
+        refresh_token: refresh_token.as_ref().expose_secret().to_owned(),
+        scope: oauth.scope.to_string(),
+        id_token: id_token.map(|id_token| id_token.expose_secret().to_owned()),
+    })
+}
+
+// Public clients have no secret, so they only identify themselves rather than authenticate
+pub(crate) async fn authenticate_client(
+    client_id: Option<&str>,
+    state: &AppState,
+) -> Result<OAuthClient, OAuthError> {
+    let client_id = client_id.ok_or(OAuthError::InvalidRequest("Missing client_id"))?;
+    let client_id =
+        OAuthClientId::parse(client_id.to_owned()).map_err(|_| OAuthError::InvalidClient)?;
+
+    match state
+        .oauth_client_store
+        .read()
+        .await
+        .get_client(&client_id)
+        .await
+    {
+        Ok(client) if client.kind == OAuthClientKind::Public => Ok(client),
+        Ok(_) => Err(OAuthError::UnauthorizedClient),
+        Err(OAuthClientStoreError::ClientNotFound) => Err(OAuthError::InvalidClient),
+        Err(e) => Err(OAuthError::UnexpectedError(e.into())),
+    }
+}
+
+// Machine clients send their secret in an `Authorization: Basic` header, or along with the form
+pub(crate) async fn authenticate_machine_client(
+    client_id: Option<&str>,
+    client_secret: Option<&Secret<String>>,
+    headers: &HeaderMap,
+    state: &AppState,
+) -> Result<OAuthClient, OAuthError> {
+    let (client_id, client_secret) = match basic_credentials(headers) {
+        Some(credentials) => credentials,
+        None => (
+            client_id
+                .ok_or(OAuthError::InvalidRequest("Missing client_id"))?
+                .to_owned(),
+            client_secret.cloned().ok_or(OAuthError::InvalidClient)?,
+        ),
+    };
+    let client_id = OAuthClientId::parse(client_id).map_err(|_| OAuthError::InvalidClient)?;
+    let client_secret =
+        ClientSecret::parse(client_secret).map_err(|_| OAuthError::InvalidClient)?;
+
+    let client = match state
+        .oauth_client_store
+        .read()
+        .await
+        .get_client(&client_id)
+        .await
+    {
+        Ok(client) => client,
+        Err(OAuthClientStoreError::ClientNotFound) => return Err(OAuthError::InvalidClient),
+        Err(e) => return Err(OAuthError::UnexpectedError(e.into())),
+    };
+
+    if !client.verify_secret(&client_secret) {
+        return Err(OAuthError::InvalidClient);
+    }
+
+    Ok(client)
+}
+
+// Either kind of client, telling them apart by whether a secret was sent
+pub(crate) async fn authenticate_any_client(
+    client_id: Option<&str>,
+    client_secret: Option<&Secret<String>>,
+    headers: &HeaderMap,
+    state: &AppState,
+) -> Result<OAuthClient, OAuthError> {
+    match client_secret.is_some() || basic_credentials(headers).is_some() {
+        true => authenticate_machine_client(client_id, client_secret, headers, state).await,
+        false => authenticate_client(client_id, state).await,
+    }
+}
+
+// The session the user is logged in with, which grants inherit how and when they logged in from
+async fn find_session(jar: &CookieJar, state: &AppState) -> Result<Session, AuthAPIError> {
+    let (claims, _) = authenticate_claims(
+        jar,
+        state.banned_token_store.clone(),
+        state.user_store.clone(),
+    )
+    .await?;
+    let session_id = RefreshTokenFamilyId::parse(claims.sid().to_owned())
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    match state
+        .session_store
+        .read()
+        .await
+        .get_session(&session_id)
+        .await
+    {
+        Ok(session) => Ok(session),
+        Err(SessionStoreError::SessionNotFound) => Err(AuthAPIError::InvalidToken),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+async fn find_user(id: &UserId, state: &AppState) -> Result<User, OAuthError> {
+    match state.user_store.read().await.get_user_by_id(id).await {
+        Ok(user) if !user.disabled => Ok(user),
+        Ok(_) | Err(UserStoreError::UserNotFound) => Err(OAuthError::InvalidGrant),
+        Err(e) => Err(OAuthError::UnexpectedError(e.into())),
+    }
+}
+
+// Returns the client making the request and the URI its response goes to
+async fn find_client(
+    request: &AuthorizeRequest,
+    state: &AppState,
+) -> Result<(OAuthClient, String), OAuthError> {
+    let client_id = request
+        .client_id
+        .clone()
+        .ok_or(OAuthError::InvalidRequest("Missing client_id"))?;
+    let client_id = OAuthClientId::parse(client_id)
+        .map_err(|_| OAuthError::InvalidRequest("Unknown client"))?;
+
+    let client = match state
+        .oauth_client_store
+        .read()
+        .await
+        .get_client(&client_id)
+        .await
+    {
+        Ok(client) => client,
+        Err(OAuthClientStoreError::ClientNotFound) => {
+            return Err(OAuthError::InvalidRequest("Unknown client"))
+        }
+        Err(e) => return Err(OAuthError::UnexpectedError(e.into())),
+    };
+
+    // Machine clients only ever act for themselves
+    if client.kind != OAuthClientKind::Public {
+        return Err(OAuthError::UnauthorizedClient);
+    }
+
+    let redirect_uri = client
+        .redirect_uri(request.redirect_uri.as_deref())
+        .ok_or(OAuthError::InvalidRequest("Invalid redirect_uri"))?
+        .to_owned();
+
+    Ok((client, redirect_uri))
+}
+
+// Returns the requested scope and the PKCE challenge, which every request must make
+fn check_authorize_request(request: &AuthorizeRequest) -> Result<(OAuthScope, String), OAuthError> {
+    match request.response_type.as_deref() {
+        Some("code") => {}
+        Some(_) => return Err(OAuthError::UnsupportedResponseType),
+        None => return Err(OAuthError::InvalidRequest("Missing response_type")),
+    }
+
+    let code_challenge = request
+        .code_challenge
+        .clone()
+        .ok_or(OAuthError::InvalidRequest("PKCE is required"))?;
+    // Plain challenges would give away the verifier, so only S256 is accepted
+    if request.code_challenge_method.as_deref() != Some("S256") {
+        return Err(OAuthError::InvalidRequest(
+            "code_challenge_method must be S256",
+        ));
+    }
+    if !is_pkce_value(&code_challenge) {
+        return Err(OAuthError::InvalidRequest("Invalid code_challenge"));
+    }
+
+    let scope = OAuthScope::parse(request.scope.as_deref().unwrap_or_default())
+        .map_err(|_| OAuthError::InvalidScope)?;
+
+    Ok((scope, code_challenge))
+}
+
+// Stores a code for the grant and returns where to send the user with it
+async fn issue_code(
+    grant: AuthorizationCodeGrant,
+    request_state: Option<&str>,
+    state: &AppState,
+) -> Result<String> {
+    let code = AuthorizationCode::default();
+    let redirect_uri = grant.redirect_uri.clone();
+
+    state
+        .authorization_code_store
+        .write()
+        .await
+        .add_code(code.clone(), grant)
+        .await
+        .wrap_err("Failed to store authorization code")?;
+
+    with_params(
+        &redirect_uri,
+        &[("code", code.as_ref().expose_secret())],
+        request_state,
+    )
+}
+
+fn redirect_with_error(
+    redirect_uri: &str,
+    error: &OAuthError,
+    request_state: Option<&str>,
+) -> Result<String> {
+    let mut params = vec![("error", error.code())];
+    if let Some(description) = error.description() {
+        params.push(("error_description", description));
+    }
+    with_params(redirect_uri, &params, request_state)
+}
+
+// Clients match responses to their requests by the state they sent, so it's always passed back
+fn with_params(
+    redirect_uri: &str,
+    params: &[(&str, &str)],
+    request_state: Option<&str>,
+) -> Result<String> {
+    let mut url = Url::parse(redirect_uri).wrap_err("Invalid redirect URI")?;
+    {
+        let mut query = url.query_pairs_mut();
+        query.extend_pairs(params);
+        if let Some(request_state) = request_state {
+            query.append_pair("state", request_state);
+        }
+    }
+    Ok(url.into())
+}
+
+fn login_page(params: &[(&str, &str)]) -> String {
+    let query = form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(params)
+        .finish();
+    format!("/?{}", query)
+}
+
+fn into_auth_api_error(e: OAuthError) -> AuthAPIError {
+    match e {
+        OAuthError::UnexpectedError(e) => AuthAPIError::UnexpectedError(e),
+        _ => AuthAPIError::InvalidCredentials,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuthorizeRequest {
+    pub response_type: Option<String>,
+    pub client_id: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub scope: Option<String>,
+    pub state: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+    /// Sent back in the ID token, for OpenID Connect clients to tie it to their request
+    pub nonce: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ConsentRequest {
+    pub approve: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConsentResponse {
+    #[serde(rename = "redirectTo")]
+    pub redirect_to: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TokenRequest {
+    pub grant_type: Option<String>,
+    pub client_id: Option<String>,
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub code_verifier: Option<String>,
+    pub refresh_token: Option<String>,
+    /// Machine clients may send their secret here instead of in an `Authorization: Basic` header
+    pub client_secret: Option<Secret<String>>,
+    /// What a machine client asks for, all it's allowed if left out
+    pub scope: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    /// Seconds until the access token expires
+    pub expires_in: i64,
+    pub refresh_token: String,
+    pub scope: String,
+    /// Issued when the scope includes `openid`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
+}
+
+/// Machine clients can authenticate again whenever they need a token, so they get no refresh token
+/// (RFC 6749, section 4.4.3).
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClientTokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    /// Seconds until the access token expires
+    pub expires_in: i64,
+    pub scope: String,
+}
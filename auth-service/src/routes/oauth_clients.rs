@@ -0,0 +1,242 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::SecondsFormat;
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuthAPIError, ClientSecret, OAuthClient, OAuthClientId, OAuthClientKind, OAuthClientSecret,
+        OAuthClientStoreError, OAuthScope,
+    },
+};
+
+/// Registers a client. Machine clients are issued their first secret, which is only ever shown here.
+#[tracing::instrument(name = "Admin create OAuth client", skip_all)]
+pub async fn admin_create_oauth_client(
+    State(state): State<AppState>,
+    Json(request): Json<CreateOAuthClientRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let kind = OAuthClientKind::parse(request.kind.as_deref().unwrap_or("public"))
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let (client, client_secret) = match kind {
+        OAuthClientKind::Public => {
+            let client = OAuthClient::new(request.name, request.redirect_uris)
+                .map_err(|_| AuthAPIError::InvalidCredentials)?;
+            (client, None)
+        }
+        OAuthClientKind::Machine => {
+            // Machine clients never send users anywhere
+            if !request.redirect_uris.is_empty() {
+                return Err(AuthAPIError::InvalidCredentials);
+            }
+            let scope = OAuthScope::parse(request.scope.as_deref().unwrap_or_default())
+                .map_err(|_| AuthAPIError::InvalidCredentials)?;
+            let mut client = OAuthClient::new_machine(request.name, scope)
+                .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+            let client_secret = ClientSecret::default();
+            client.secret = Some(OAuthClientSecret::new(&client_secret));
+            (client, Some(client_secret))
+        }
+    };
+
+    state
+        .oauth_client_store
+        .write()
+        .await
+        .add_client(client.clone())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let mut response = OAuthClientResponse::from(&client);
+    response.client_secret = client_secret.map(|secret| secret.as_ref().expose_secret().to_owned());
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// Issues a machine client a new secret in place of its current one, whose tokens stop working.
+#[tracing::instrument(name = "Admin rotate OAuth client secret", skip_all)]
+pub async fn admin_rotate_oauth_client_secret(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let client = find_machine_client(id, &state).await?;
+
+    let client_secret = ClientSecret::default();
+    let secret = OAuthClientSecret::new(&client_secret);
+
+    set_client_secret(&client.id, Some(secret.clone()), &state).await?;
+
+    let response = Json(OAuthClientSecretResponse {
+        client_secret: client_secret.as_ref().expose_secret().to_owned(),
+        created_at: secret.created_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+/// Revokes a machine client's secret, along with its tokens, until a new one is issued.
+#[tracing::instrument(name = "Admin revoke OAuth client secret", skip_all)]
+pub async fn admin_revoke_oauth_client_secret(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let client = find_machine_client(id, &state).await?;
+
+    set_client_secret(&client.id, None, &state).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[tracing::instrument(name = "Admin list OAuth clients", skip_all)]
+pub async fn admin_list_oauth_clients(
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let clients = state
+        .oauth_client_store
+        .read()
+        .await
+        .list_clients()
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response = Json(OAuthClientsResponse {
+        clients: clients.iter().map(OAuthClientResponse::from).collect(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Admin get OAuth client", skip_all)]
+pub async fn admin_get_oauth_client(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let id = OAuthClientId::parse(id).map_err(|_| AuthAPIError::ClientNotFound)?;
+
+    let client = match state.oauth_client_store.read().await.get_client(&id).await {
+        Ok(client) => client,
+        Err(OAuthClientStoreError::ClientNotFound) => return Err(AuthAPIError::ClientNotFound),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    Ok((StatusCode::OK, Json(OAuthClientResponse::from(&client))))
+}
+
+async fn find_machine_client(id: String, state: &AppState) -> Result<OAuthClient, AuthAPIError> {
+    let id = OAuthClientId::parse(id).map_err(|_| AuthAPIError::ClientNotFound)?;
+
+    let client = match state.oauth_client_store.read().await.get_client(&id).await {
+        Ok(client) => client,
+        Err(OAuthClientStoreError::ClientNotFound) => return Err(AuthAPIError::ClientNotFound),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    // Public clients have no secret to rotate or revoke
+    if client.kind != OAuthClientKind::Machine {
+        return Err(AuthAPIError::InvalidCredentials);
+    }
+
+    Ok(client)
+}
+
+async fn set_client_secret(
+    id: &OAuthClientId,
+    secret: Option<OAuthClientSecret>,
+    state: &AppState,
+) -> Result<(), AuthAPIError> {
+    match state
+        .oauth_client_store
+        .write()
+        .await
+        .set_client_secret(id, secret)
+        .await
+    {
+        Ok(()) => Ok(()),
+        Err(OAuthClientStoreError::ClientNotFound) => Err(AuthAPIError::ClientNotFound),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CreateOAuthClientRequest {
+    pub name: String,
+    /// "public", the default, or "machine"
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    #[serde(rename = "redirectUris", default)]
+    pub redirect_uris: Vec<String>,
+    /// The most a machine client may ask for, space separated
+    pub scope: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OAuthClientsResponse {
+    pub clients: Vec<OAuthClientResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct OAuthClientResponse {
+    /// Doubles as the `client_id` the client sends to the OAuth endpoints
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(rename = "redirectUris")]
+    pub redirect_uris: Vec<String>,
+    /// Set for machine clients
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    /// When the machine client's current secret was issued, unset once it's revoked
+    #[serde(
+        rename = "secretCreatedAt",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub secret_created_at: Option<String>,
+    /// Only set when a machine client is created, as its secret is never shown again
+    #[serde(
+        rename = "clientSecret",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub client_secret: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
+
+impl From<&OAuthClient> for OAuthClientResponse {
+    fn from(client: &OAuthClient) -> Self {
+        let machine = client.kind == OAuthClientKind::Machine;
+
+        Self {
+            id: client.id.to_string(),
+            name: client.name.clone(),
+            kind: client.kind.as_str().to_owned(),
+            redirect_uris: client.redirect_uris.clone(),
+            scope: machine.then(|| client.scope.to_string()),
+            secret_created_at: client
+                .secret
+                .as_ref()
+                .map(|secret| secret.created_at.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            client_secret: None,
+            created_at: client.created_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OAuthClientSecretResponse {
+    /// Only ever shown here
+    #[serde(rename = "clientSecret")]
+    pub client_secret: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
@@ -0,0 +1,129 @@
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use jsonwebtoken::Algorithm;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, OAuthScope, UserInfo, UserStoreError},
+    utils::{
+        auth::{bearer_token, validate_token},
+        constants::{AUTH_SERVICE_URL, JWT_AUDIENCE, JWT_ISSUER, JWT_KEYRING},
+    },
+};
+
+/// Describes this service as an OpenID Connect provider, so client libraries can configure themselves.
+#[tracing::instrument(name = "OpenID configuration", skip_all)]
+pub async fn openid_configuration() -> Json<OpenIdConfiguration> {
+    let algorithm = JWT_KEYRING
+        .read()
+        .expect("JWT keyring lock poisoned")
+        .signing_key()
+        .algorithm();
+    let endpoint = |path: &str| format!("{}{}", AUTH_SERVICE_URL.trim_end_matches('/'), path);
+
+    Json(OpenIdConfiguration {
+        issuer: JWT_ISSUER.to_owned(),
+        authorization_endpoint: endpoint("/oauth/authorize"),
+        token_endpoint: endpoint("/oauth/token"),
+        userinfo_endpoint: endpoint("/userinfo"),
+        introspection_endpoint: endpoint("/oauth/introspect"),
+        revocation_endpoint: endpoint("/oauth/revoke"),
+        jwks_uri: endpoint("/.well-known/jwks.json"),
+        scopes_supported: [OAuthScope::OPENID, OAuthScope::EMAIL, OAuthScope::PROFILE]
+            .map(str::to_owned)
+            .to_vec(),
+        response_types_supported: vec!["code".to_owned()],
+        grant_types_supported: ["authorization_code", "refresh_token", "client_credentials"]
+            .map(str::to_owned)
+            .to_vec(),
+        subject_types_supported: vec!["public".to_owned()],
+        id_token_signing_alg_values_supported: vec![algorithm],
+        // Public clients don't authenticate at the token endpoint, machine clients send their secret
+        token_endpoint_auth_methods_supported: [
+            "none",
+            "client_secret_basic",
+            "client_secret_post",
+        ]
+        .map(str::to_owned)
+        .to_vec(),
+        code_challenge_methods_supported: vec!["S256".to_owned()],
+        claims_supported: [
+            "iss",
+            "sub",
+            "aud",
+            "exp",
+            "iat",
+            "auth_time",
+            "nonce",
+            "amr",
+            "email",
+            "email_verified",
+            "preferred_username",
+        ]
+        .map(str::to_owned)
+        .to_vec(),
+    })
+}
+
+/// Tells an OpenID Connect client about the user its access token was issued for, as far as its scope allows.
+#[tracing::instrument(name = "User info", skip_all)]
+pub async fn userinfo(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let token = bearer_token(&headers).ok_or(AuthAPIError::MissingToken)?;
+
+    let (claims, email) = validate_token(
+        &token,
+        &JWT_AUDIENCE,
+        state.banned_token_store.clone(),
+        state.user_store.clone(),
+    )
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    // Only clients granted the openid scope may ask who the user is
+    let scope = match (claims.client_id(), claims.scope()) {
+        (Some(_), Some(scope)) => {
+            OAuthScope::parse(scope).map_err(|_| AuthAPIError::InvalidToken)?
+        }
+        _ => return Err(AuthAPIError::Forbidden),
+    };
+    if !scope.contains(OAuthScope::OPENID) {
+        return Err(AuthAPIError::Forbidden);
+    }
+
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    Ok((StatusCode::OK, Json(UserInfo::new(&user, &scope))))
+}
+
+/// The OpenID Provider Metadata of OpenID Connect Discovery 1.0, section 3.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OpenIdConfiguration {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub userinfo_endpoint: String,
+    /// Token introspection (RFC 7662) and revocation (RFC 7009), from the metadata of RFC 8414
+    pub introspection_endpoint: String,
+    pub revocation_endpoint: String,
+    pub jwks_uri: String,
+    pub scopes_supported: Vec<String>,
+    pub response_types_supported: Vec<String>,
+    pub grant_types_supported: Vec<String>,
+    pub subject_types_supported: Vec<String>,
+    pub id_token_signing_alg_values_supported: Vec<Algorithm>,
+    pub token_endpoint_auth_methods_supported: Vec<String>,
+    pub code_challenge_methods_supported: Vec<String>,
+    pub claims_supported: Vec<String>,
+}
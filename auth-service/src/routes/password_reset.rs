@@ -0,0 +1,166 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use color_eyre::eyre::Result;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use tracing::Instrument;
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuditEventKind, AuthAPIError, Email, Password, PasswordResetToken,
+        PasswordResetTokenStoreError, UserStoreError,
+    },
+    utils::{
+        audit::record_audit_event,
+        auth::{revoke_user_sessions, PASSWORD_RESET_TOKEN_TTL_SECONDS},
+        constants::AUTH_SERVICE_URL,
+    },
+};
+
+#[tracing::instrument(name = "Request password reset", skip_all)]
+pub async fn request_password_reset(
+    State(state): State<AppState>,
+    Json(request): Json<PasswordResetRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let user_exists = state.user_store.read().await.get_user(&email).await.is_ok();
+
+    // Answer the same way, and as quickly, whether or not the account exists, so this route
+    // cannot be used to find out which emails are registered. The email is sent in the background
+    // as talking to the email provider takes a noticeable time.
+    if user_exists {
+        let state = state.clone();
+        tokio::spawn(
+            async move {
+                if let Err(e) = send_password_reset_email(&email, &state).await {
+                    tracing::error!("Failed to send password reset email: {:?}", e);
+                }
+            }
+            .in_current_span(),
+        );
+    }
+
+    let response = Json(PasswordResetResponse {
+        message: "If an account exists for this email, a password reset link has been sent"
+            .to_owned(),
+    });
+
+    Ok((StatusCode::ACCEPTED, response))
+}
+
+#[tracing::instrument(name = "Confirm password reset", skip_all)]
+pub async fn confirm_password_reset(
+    State(state): State<AppState>,
+    Json(request): Json<PasswordResetConfirmRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let token = PasswordResetToken::parse(request.token).map_err(|_| AuthAPIError::InvalidToken)?;
+
+    // Check the new password before redeeming the token, so a rejected password does not burn it
+    let password =
+        Password::parse(request.password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let taken = state
+        .password_reset_token_store
+        .write()
+        .await
+        .take_token(&token)
+        .await;
+
+    let email = match taken {
+        Ok(email) => email,
+        Err(PasswordResetTokenStoreError::TokenNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    match state
+        .user_store
+        .write()
+        .await
+        .update_password(&email, password)
+        .await
+    {
+        Ok(()) => {}
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    record_audit_event(&email, AuditEventKind::PasswordReset, &state).await;
+
+    // Whoever knew the old password must not stay logged in
+    revoke_user_sessions(
+        &email,
+        state.user_store.clone(),
+        state.banned_token_store.clone(),
+        state.refresh_token_store.clone(),
+        state.session_store.clone(),
+    )
+    .await
+    .map_err(AuthAPIError::UnexpectedError)?;
+
+    // API keys outlive any session, so a key minted with the old password is revoked as well
+    let user = state
+        .user_store
+        .read()
+        .await
+        .get_user(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    state
+        .api_key_store
+        .write()
+        .await
+        .remove_user_keys(&user.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response = Json(PasswordResetResponse {
+        message: "Password reset successfully".to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Send password reset email", skip_all)]
+pub(crate) async fn send_password_reset_email(email: &Email, state: &AppState) -> Result<()> {
+    let token = PasswordResetToken::default();
+
+    state
+        .password_reset_token_store
+        .write()
+        .await
+        .add_token(email.clone(), token.clone())
+        .await?;
+
+    let link = format!(
+        "{}/?reset_token={}",
+        AUTH_SERVICE_URL.as_str(),
+        token.as_ref().expose_secret()
+    );
+    let content = format!(
+        "Use the following link to reset your password. It expires in {} minutes and can only be used once: {}",
+        PASSWORD_RESET_TOKEN_TTL_SECONDS / 60,
+        link
+    );
+
+    state
+        .email_client
+        .send_email(email, "Password reset", &content)
+        .await
+}
+
+#[derive(Deserialize)]
+pub struct PasswordResetRequest {
+    pub email: Secret<String>,
+}
+
+#[derive(Deserialize)]
+pub struct PasswordResetConfirmRequest {
+    pub token: Secret<String>,
+    pub password: Secret<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct PasswordResetResponse {
+    pub message: String,
+}
@@ -0,0 +1,55 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum_extra::extract::CookieJar;
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuditEventKind, AuthAPIError, RecoveryCode},
+    utils::{audit::record_audit_event, auth::authenticate},
+};
+
+const RECOVERY_CODE_COUNT: usize = 10;
+
+#[tracing::instrument(name = "Generate recovery codes", skip_all)]
+pub async fn generate_recovery_codes(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = authenticate(
+        &jar,
+        state.banned_token_store.clone(),
+        state.user_store.clone(),
+    )
+    .await?;
+
+    let codes: Vec<RecoveryCode> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| RecoveryCode::default())
+        .collect();
+
+    // Only hashes are stored, so this response is the one chance to see the codes
+    state
+        .recovery_code_store
+        .write()
+        .await
+        .set_codes(&email, codes.clone())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    record_audit_event(&email, AuditEventKind::RecoveryCodesGenerated, &state).await;
+
+    let response = Json(RecoveryCodesResponse {
+        recovery_codes: codes
+            .iter()
+            .map(|code| code.as_ref().expose_secret().to_owned())
+            .collect(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecoveryCodesResponse {
+    #[serde(rename = "recoveryCodes")]
+    pub recovery_codes: Vec<String>,
+}
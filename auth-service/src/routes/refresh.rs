@@ -0,0 +1,120 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse};
+use axum_extra::extract::{cookie, CookieJar};
+use secrecy::Secret;
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuthAPIError, RefreshToken, RefreshTokenStoreError, SessionClient, SessionStoreError,
+    },
+    utils::{
+        auth::{generate_auth_cookie, generate_refresh_cookie, revoke_session, touch_session},
+        constants::REFRESH_TOKEN_COOKIE_NAME,
+    },
+};
+
+#[tracing::instrument(name = "Refresh", skip_all)]
+pub async fn refresh(
+    State(state): State<AppState>,
+    client: SessionClient,
+    jar: CookieJar,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let cookie = match jar.get(REFRESH_TOKEN_COOKIE_NAME) {
+        Some(cookie) => cookie,
+        None => return (jar, Err(AuthAPIError::MissingToken)),
+    };
+
+    let token = match RefreshToken::parse(Secret::new(cookie.value().to_owned())) {
+        Ok(token) => token,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken)),
+    };
+
+    // Refresh tokens of OAuth clients are only redeemed at the token endpoint, for tokens limited to their scope.
+    // Checked before consuming the token, so one sent here by mistake still works at the token endpoint.
+    let family_id = match state
+        .refresh_token_store
+        .read()
+        .await
+        .get_family(&token)
+        .await
+    {
+        Ok(family_id) => family_id,
+        Err(RefreshTokenStoreError::TokenNotFound) => {
+            return (jar, Err(AuthAPIError::InvalidToken))
+        }
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    match state
+        .session_store
+        .read()
+        .await
+        .get_session(&family_id)
+        .await
+    {
+        Ok(session) if session.oauth.is_some() => return (jar, Err(AuthAPIError::InvalidToken)),
+        Ok(_) | Err(SessionStoreError::SessionNotFound) => {}
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    }
+
+    // Each refresh token can only be traded in once
+    let consumed = state
+        .refresh_token_store
+        .write()
+        .await
+        .consume_token(&token)
+        .await;
+
+    let (email, family_id) = match consumed {
+        Ok(consumed) => consumed,
+        Err(RefreshTokenStoreError::TokenReused(family_id)) => {
+            // A rotated token was presented again, so it may have been stolen.
+            // Revoke the whole session to log out both the thief and the legitimate user.
+            tracing::warn!("Refresh token reuse detected, revoking token family");
+            if let Err(e) = revoke_session(
+                &family_id,
+                state.banned_token_store.clone(),
+                state.refresh_token_store.clone(),
+                state.session_store.clone(),
+            )
+            .await
+            {
+                return (jar, Err(AuthAPIError::UnexpectedError(e)));
+            }
+
+            let jar = jar.remove(cookie::Cookie::from(REFRESH_TOKEN_COOKIE_NAME));
+            return (jar, Err(AuthAPIError::InvalidToken));
+        }
+        Err(RefreshTokenStoreError::TokenNotFound) => {
+            return (jar, Err(AuthAPIError::InvalidToken))
+        }
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    if let Err(e) = touch_session(&family_id, &email, client, state.session_store.clone()).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e)));
+    }
+
+    let auth_cookie = match generate_auth_cookie(
+        &email,
+        &family_id,
+        state.user_store.clone(),
+        state.role_store.clone(),
+    )
+    .await
+    {
+        Ok(cookie) => cookie,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
+
+    // Rotate the refresh token, keeping it in the same family
+    let refresh_cookie =
+        match generate_refresh_cookie(&email, family_id, state.refresh_token_store.clone()).await {
+            Ok(cookie) => cookie,
+            Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+        };
+
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
+
+    (updated_jar, Ok(StatusCode::OK))
+}
@@ -0,0 +1,128 @@
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Form,
+};
+use secrecy::Secret;
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{OAuthClient, OAuthError, RefreshToken, RefreshTokenStoreError, SessionStoreError},
+    routes::oauth::authenticate_any_client,
+    utils::{
+        auth::{revoke_session, revoke_token, validate_any_token},
+        constants::JWT_AUDIENCE,
+    },
+};
+
+/// Lets an OAuth client revoke an access or refresh token it was issued (RFC 7009). Revoking a refresh
+/// token logs its whole session out.
+#[tracing::instrument(name = "Revoke token", skip_all)]
+pub async fn revoke(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Form(request): Form<RevokeRequest>,
+) -> Result<impl IntoResponse, OAuthError> {
+    let client = authenticate_any_client(
+        request.client_id.as_deref(),
+        request.client_secret.as_ref(),
+        &headers,
+        &state,
+    )
+    .await?;
+    let token = request
+        .token
+        .ok_or(OAuthError::InvalidRequest("Missing token"))?;
+
+    // Refresh tokens and JWTs look nothing alike, so there's no need for the hint
+    match RefreshToken::parse(token.clone()) {
+        Ok(token) => revoke_refresh_token(&token, &client, &state).await?,
+        Err(_) => revoke_access_token(&token, &client, &state).await?,
+    }
+
+    Ok(StatusCode::OK)
+}
+
+// Tokens that are unknown, expired or already revoked need no revoking (RFC 7009, section 2.2)
+async fn revoke_refresh_token(
+    token: &RefreshToken,
+    client: &OAuthClient,
+    state: &AppState,
+) -> Result<(), OAuthError> {
+    let family_id = match state
+        .refresh_token_store
+        .read()
+        .await
+        .get_family(token)
+        .await
+    {
+        Ok(family_id) => family_id,
+        Err(RefreshTokenStoreError::TokenNotFound) => return Ok(()),
+        Err(e) => return Err(OAuthError::UnexpectedError(e.into())),
+    };
+
+    let session = match state
+        .session_store
+        .read()
+        .await
+        .get_session(&family_id)
+        .await
+    {
+        Ok(session) => session,
+        Err(SessionStoreError::SessionNotFound) => return Ok(()),
+        Err(e) => return Err(OAuthError::UnexpectedError(e.into())),
+    };
+    match &session.oauth {
+        Some(oauth) if oauth.client_id == client.id => {}
+        _ => return Err(OAuthError::UnauthorizedClient),
+    }
+
+    revoke_session(
+        &family_id,
+        state.banned_token_store.clone(),
+        state.refresh_token_store.clone(),
+        state.session_store.clone(),
+    )
+    .await
+    .map_err(OAuthError::UnexpectedError)
+}
+
+// Only the token itself is banned, leaving the rest of its session alone
+async fn revoke_access_token(
+    token: &Secret<String>,
+    client: &OAuthClient,
+    state: &AppState,
+) -> Result<(), OAuthError> {
+    let claims = match validate_any_token(
+        token,
+        &JWT_AUDIENCE,
+        state.banned_token_store.clone(),
+        state.user_store.clone(),
+        state.oauth_client_store.clone(),
+    )
+    .await
+    {
+        Ok(claims) => claims,
+        Err(_) => return Ok(()),
+    };
+
+    if claims.client_id() != Some(client.id.to_string().as_str()) {
+        return Err(OAuthError::UnauthorizedClient);
+    }
+
+    revoke_token(&claims, state.banned_token_store.clone())
+        .await
+        .map_err(OAuthError::UnexpectedError)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RevokeRequest {
+    pub token: Option<Secret<String>>,
+    /// Refresh tokens and access tokens are told apart by their format, so the hint makes no difference
+    pub token_type_hint: Option<String>,
+    pub client_id: Option<String>,
+    /// Machine clients may send their secret here instead of in an `Authorization: Basic` header
+    pub client_secret: Option<Secret<String>>,
+}
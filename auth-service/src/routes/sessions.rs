@@ -0,0 +1,150 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::{cookie, CookieJar};
+use chrono::SecondsFormat;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, RefreshTokenFamilyId, Session, SessionStoreError},
+    utils::{
+        auth::{authenticate_claims, revoke_session},
+        constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+    },
+};
+
+#[tracing::instrument(name = "List sessions", skip_all)]
+pub async fn list_sessions(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (claims, email) = authenticate_claims(
+        &jar,
+        state.banned_token_store.clone(),
+        state.user_store.clone(),
+    )
+    .await?;
+
+    let sessions = state
+        .session_store
+        .read()
+        .await
+        .get_user_sessions(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response = Json(SessionsResponse {
+        sessions: sessions
+            .iter()
+            .map(|session| SessionResponse::new(session, claims.sid()))
+            .collect(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Revoke session", skip_all)]
+pub async fn delete_session(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(id): Path<String>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let (claims, email) = match authenticate_claims(
+        &jar,
+        state.banned_token_store.clone(),
+        state.user_store.clone(),
+    )
+    .await
+    {
+        Ok(authenticated) => authenticated,
+        Err(e) => return (jar, Err(e)),
+    };
+
+    let Ok(session_id) = RefreshTokenFamilyId::parse(id) else {
+        return (jar, Err(AuthAPIError::SessionNotFound));
+    };
+
+    // Someone else's session looks just like one that doesn't exist
+    match state
+        .session_store
+        .read()
+        .await
+        .get_session(&session_id)
+        .await
+    {
+        Ok(session) if session.email == email => {}
+        Ok(_) | Err(SessionStoreError::SessionNotFound) => {
+            return (jar, Err(AuthAPIError::SessionNotFound))
+        }
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    }
+
+    if let Err(e) = revoke_session(
+        &session_id,
+        state.banned_token_store.clone(),
+        state.refresh_token_store.clone(),
+        state.session_store.clone(),
+    )
+    .await
+    {
+        return (jar, Err(AuthAPIError::UnexpectedError(e)));
+    }
+
+    // Revoking the current session logs this device out as well
+    let jar = if session_id.as_ref() == claims.sid() {
+        jar.remove(cookie::Cookie::from(JWT_COOKIE_NAME))
+            .remove(cookie::Cookie::from(REFRESH_TOKEN_COOKIE_NAME))
+    } else {
+        jar
+    };
+
+    (jar, Ok(StatusCode::OK))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionsResponse {
+    pub sessions: Vec<SessionResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionResponse {
+    pub id: String,
+    #[serde(rename = "userAgent")]
+    pub user_agent: Option<String>,
+    #[serde(rename = "ipAddress")]
+    pub ip_address: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "lastSeenAt")]
+    pub last_seen_at: String,
+    /// Whether this is the session the request was made with
+    pub current: bool,
+    /// The OAuth client the session was started for, if it isn't a login to this service itself
+    #[serde(rename = "oauthClientId")]
+    pub oauth_client_id: Option<String>,
+}
+
+impl SessionResponse {
+    pub(crate) fn new(session: &Session, current_session_id: &str) -> Self {
+        Self {
+            id: session.id.as_ref().to_owned(),
+            user_agent: session.client.user_agent.clone(),
+            ip_address: session.client.ip_address.to_string(),
+            created_at: session
+                .created_at
+                .to_rfc3339_opts(SecondsFormat::Secs, true),
+            last_seen_at: session
+                .last_seen_at
+                .to_rfc3339_opts(SecondsFormat::Secs, true),
+            current: session.id.as_ref() == current_session_id,
+            oauth_client_id: session
+                .oauth
+                .as_ref()
+                .map(|grant| grant.client_id.to_string()),
+        }
+    }
+}
@@ -5,6 +5,7 @@ use serde::{Deserialize, Serialize};
 use crate::{
     app_state::AppState,
     domain::{AuthAPIError, Email, Password, User},
+    routes::send_verification_email,
 };
 
 #[tracing::instrument(name = "Signup", skip_all)]
@@ -26,10 +27,20 @@ pub async fn signup(
         return Err(AuthAPIError::UserAlreadyExists);
     }
 
-    if let Err(e) = user_store.add_user(user).await {
+    if let Err(e) = user_store.add_user(user.clone()).await {
         return Err(AuthAPIError::UnexpectedError(e.into()));
     }
 
+    // Don't hold the lock while the email is sent
+    drop(user_store);
+
+    // The account exists either way, and a lost email can be sent again via /resend-verification
+    if state.require_email_verification {
+        if let Err(e) = send_verification_email(&user.email, &state).await {
+            tracing::error!("Failed to send verification email: {:?}", e);
+        }
+    }
+
     let response = Json(SignupResponse {
         message: "User created successfully!".to_string(),
     });
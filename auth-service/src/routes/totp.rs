@@ -0,0 +1,138 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum_extra::extract::CookieJar;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuditEventKind, AuthAPIError, TotpSecret, TwoFACode},
+    utils::{
+        audit::record_audit_event,
+        auth::authenticate,
+        totp::{generate_qr_code_svg, generate_totp_uri, verify_totp_code},
+    },
+};
+
+#[tracing::instrument(name = "Enroll TOTP", skip_all)]
+pub async fn enroll_totp(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = authenticate(
+        &jar,
+        state.banned_token_store.clone(),
+        state.user_store.clone(),
+    )
+    .await?;
+
+    let mut user_store = state.user_store.write().await;
+
+    let user = user_store
+        .get_user(&email)
+        .await
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    if user.totp_enabled {
+        return Err(AuthAPIError::TotpAlreadyEnabled);
+    }
+
+    // Starting over replaces any secret that was never confirmed
+    let secret = TotpSecret::default();
+
+    user_store
+        .set_totp_secret(&email, secret.clone())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let otpauth_uri = generate_totp_uri(&secret, &email).map_err(AuthAPIError::UnexpectedError)?;
+    let qr_code = generate_qr_code_svg(&otpauth_uri).map_err(AuthAPIError::UnexpectedError)?;
+
+    let response = Json(EnrollTotpResponse {
+        secret: secret.as_ref().expose_secret().to_owned(),
+        otpauth_uri,
+        qr_code,
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Confirm TOTP", skip_all)]
+pub async fn confirm_totp(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<ConfirmTotpRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = authenticate(
+        &jar,
+        state.banned_token_store.clone(),
+        state.user_store.clone(),
+    )
+    .await?;
+
+    let code = TwoFACode::parse(request.code).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let mut user_store = state.user_store.write().await;
+
+    let user = user_store
+        .get_user(&email)
+        .await
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    if user.totp_enabled {
+        return Err(AuthAPIError::TotpAlreadyEnabled);
+    }
+
+    // Confirming without enrolling first is a malformed request
+    let secret = user_store
+        .get_totp_secret(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?
+        .ok_or(AuthAPIError::InvalidCredentials)?;
+
+    // Only switch TOTP on once the user has shown their authenticator produces valid codes
+    let step = verify_totp_code(&secret, &email, &code)
+        .map_err(AuthAPIError::UnexpectedError)?
+        .ok_or(AuthAPIError::IncorrectCredentials)?;
+
+    // The code confirming the secret can't be used to log in as well
+    let first_use = user_store
+        .record_totp_step(&email, step)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    if !first_use {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    user_store
+        .enable_totp(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    drop(user_store);
+
+    record_audit_event(&email, AuditEventKind::TotpEnabled, &state).await;
+
+    let response = Json(ConfirmTotpResponse {
+        message: "TOTP enabled successfully".to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EnrollTotpResponse {
+    pub secret: String,
+    #[serde(rename = "otpauthUri")]
+    pub otpauth_uri: String,
+    #[serde(rename = "qrCode")]
+    pub qr_code: String,
+}
+
+#[derive(Deserialize)]
+pub struct ConfirmTotpRequest {
+    pub code: Secret<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ConfirmTotpResponse {
+    pub message: String,
+}
@@ -1,17 +1,34 @@
+use std::net::IpAddr;
+
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 use axum_extra::extract::CookieJar;
+use color_eyre::eyre::Result;
 use secrecy::Secret;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     app_state::AppState,
-    domain::{AuthAPIError, Email, LoginAttemptId, TwoFACode},
-    utils::auth::generate_auth_cookie,
+    domain::{
+        AuthAPIError, AuthMethod, Email, LoginAttemptId, RecoveryCode, RecoveryCodeStoreError,
+        SessionClient, TwoFACode, TwoFACodeStore,
+    },
+    routes::login::start_session,
+    utils::{
+        brute_force::{
+            check_lockout, email_key, ip_key, login_attempt_key, record_failed_attempt,
+            FAILED_ATTEMPT_WINDOW_SECONDS,
+        },
+        constants::{
+            MAX_FAILED_2FA_ATTEMPTS, MAX_FAILED_LOGIN_ATTEMPTS, MAX_FAILED_LOGIN_ATTEMPTS_PER_IP,
+        },
+        totp::verify_totp_code,
+    },
 };
 
 #[tracing::instrument(name = "Verify 2FA", skip_all)]
 pub async fn verify_2fa(
     State(state): State<AppState>,
+    client: SessionClient,
     jar: CookieJar,
     Json(request): Json<Verify2FARequest>,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
@@ -25,11 +42,24 @@ pub async fn verify_2fa(
         Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
     };
 
-    let two_fa_code = match TwoFACode::parse(request.two_fa_code) {
-        Ok(two_fa_code) => two_fa_code,
-        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+    // The 2FA code field also takes recovery codes, which never look like a 6 digit code
+    let second_factor = match TwoFACode::parse(request.two_fa_code.clone()) {
+        Ok(two_fa_code) => SecondFactor::Code(two_fa_code),
+        Err(_) => match RecoveryCode::parse(request.two_fa_code) {
+            Ok(recovery_code) => SecondFactor::RecoveryCode(recovery_code),
+            Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+        },
     };
 
+    if let Err(e) = check_lockout(
+        &state.failed_attempt_store,
+        &[email_key(&email), ip_key(client.ip_address)],
+    )
+    .await
+    {
+        return (jar, Err(e));
+    }
+
     let mut two_fa_code_store = state.two_fa_code_store.write().await;
 
     let code_tuple = match two_fa_code_store.get_code(&email).await {
@@ -37,22 +67,165 @@ pub async fn verify_2fa(
         Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
     };
 
-    if code_tuple != (login_attempt_id, two_fa_code) {
+    let (expected_login_attempt_id, expected_two_fa_code) = code_tuple;
+
+    if expected_login_attempt_id != login_attempt_id {
         return (jar, Err(AuthAPIError::IncorrectCredentials));
     }
 
+    let verification = match second_factor {
+        SecondFactor::Code(two_fa_code) => {
+            // Users with an authenticator app can use its code in place of the emailed one
+            if expected_two_fa_code == two_fa_code {
+                Ok(None)
+            } else {
+                match is_valid_totp_code(&email, &two_fa_code, &state).await {
+                    Ok(true) => Ok(None),
+                    Ok(false) => Err(AuthAPIError::IncorrectCredentials),
+                    Err(e) => Err(AuthAPIError::UnexpectedError(e)),
+                }
+            }
+        }
+        SecondFactor::RecoveryCode(recovery_code) => {
+            match state
+                .recovery_code_store
+                .write()
+                .await
+                .use_code(&email, &recovery_code)
+                .await
+            {
+                Ok(remaining) => Ok(Some(remaining)),
+                Err(RecoveryCodeStoreError::RecoveryCodeNotFound) => {
+                    Err(AuthAPIError::IncorrectCredentials)
+                }
+                Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+            }
+        }
+    };
+
+    let recovery_codes_remaining = match verification {
+        Ok(recovery_codes_remaining) => recovery_codes_remaining,
+        Err(AuthAPIError::IncorrectCredentials) => {
+            if let Err(e) = handle_failed_2fa(
+                &email,
+                &login_attempt_id,
+                client.ip_address,
+                &state,
+                &mut *two_fa_code_store,
+            )
+            .await
+            {
+                return (jar, Err(e));
+            }
+            return (jar, Err(AuthAPIError::IncorrectCredentials));
+        }
+        Err(e) => return (jar, Err(e)),
+    };
+
     if let Err(e) = two_fa_code_store.remove_code(&email).await {
         return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
     }
 
-    let cookie = match generate_auth_cookie(&email) {
-        Ok(cookie) => cookie,
+    if let Err(e) = state
+        .failed_attempt_store
+        .write()
+        .await
+        .reset(&email_key(&email))
+        .await
+    {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+
+    let auth_methods = vec![AuthMethod::Password, AuthMethod::OneTimePassword];
+    let (cookie, refresh_cookie) = match start_session(&email, auth_methods, client, &state).await {
+        Ok(cookies) => cookies,
         Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
     };
 
-    let updated_jar = jar.add(cookie);
+    let updated_jar = jar.add(cookie).add(refresh_cookie);
+
+    // Let the user know when they are running out of recovery codes
+    let response = match recovery_codes_remaining {
+        Some(remaining) => (
+            StatusCode::OK,
+            Json(RecoveryCodeLoginResponse {
+                recovery_codes_remaining: remaining,
+            }),
+        )
+            .into_response(),
+        None => StatusCode::OK.into_response(),
+    };
+
+    (updated_jar, Ok(response))
+}
+
+enum SecondFactor {
+    Code(TwoFACode),
+    RecoveryCode(RecoveryCode),
+}
+
+// Wrong codes count towards the account and IP lockouts, so guessing can't continue across
+// fresh login attempts, and enough of them throw away the current login attempt altogether
+#[tracing::instrument(name = "Handle failed 2FA attempt", skip_all)]
+async fn handle_failed_2fa(
+    email: &Email,
+    login_attempt_id: &LoginAttemptId,
+    client_ip: IpAddr,
+    state: &AppState,
+    two_fa_code_store: &mut (dyn TwoFACodeStore + Send + Sync),
+) -> Result<(), AuthAPIError> {
+    record_failed_attempt(
+        &state.failed_attempt_store,
+        &email_key(email),
+        *MAX_FAILED_LOGIN_ATTEMPTS,
+    )
+    .await?;
+    record_failed_attempt(
+        &state.failed_attempt_store,
+        &ip_key(client_ip),
+        *MAX_FAILED_LOGIN_ATTEMPTS_PER_IP,
+    )
+    .await?;
+
+    let failed_codes = state
+        .failed_attempt_store
+        .write()
+        .await
+        .record_failure(
+            &login_attempt_key(login_attempt_id),
+            FAILED_ATTEMPT_WINDOW_SECONDS,
+        )
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    if failed_codes >= *MAX_FAILED_2FA_ATTEMPTS {
+        two_fa_code_store
+            .remove_code(email)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    }
+
+    Ok(())
+}
+
+// A code that has already been accepted is rejected, so one seen over the user's shoulder or
+// intercepted can't be replayed while it's still current
+#[tracing::instrument(name = "Check TOTP code", skip_all)]
+async fn is_valid_totp_code(email: &Email, code: &TwoFACode, state: &AppState) -> Result<bool> {
+    let mut user_store = state.user_store.write().await;
+
+    if !user_store.get_user(email).await?.totp_enabled {
+        return Ok(false);
+    }
+
+    let Some(secret) = user_store.get_totp_secret(email).await? else {
+        return Ok(false);
+    };
 
-    (updated_jar, Ok(StatusCode::OK.into_response()))
+    match verify_totp_code(&secret, email, code)? {
+        Some(step) => Ok(user_store.record_totp_step(email, step).await?),
+        None => Ok(false),
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -63,3 +236,9 @@ pub struct Verify2FARequest {
     #[serde(rename = "2FACode")]
     pub two_fa_code: Secret<String>,
 }
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecoveryCodeLoginResponse {
+    #[serde(rename = "recoveryCodesRemaining")]
+    pub recovery_codes_remaining: usize,
+}
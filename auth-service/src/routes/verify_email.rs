@@ -0,0 +1,119 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use color_eyre::eyre::Result;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use tracing::Instrument;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email, UserStoreError},
+    utils::{
+        auth::{
+            generate_email_verification_token, validate_email_verification_token,
+            EMAIL_VERIFICATION_TOKEN_TTL_SECONDS,
+        },
+        constants::AUTH_SERVICE_URL,
+    },
+};
+
+#[tracing::instrument(name = "Verify email", skip_all)]
+pub async fn verify_email(
+    State(state): State<AppState>,
+    Query(query): Query<VerifyEmailQuery>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email =
+        validate_email_verification_token(&query.token).map_err(|_| AuthAPIError::InvalidToken)?;
+
+    // Verifying an already verified address is not an error, so the link can be clicked twice
+    match state.user_store.write().await.mark_verified(&email).await {
+        Ok(()) => {}
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    let response = Json(VerifyEmailResponse {
+        message: "Email verified successfully".to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Resend verification", skip_all)]
+pub async fn resend_verification(
+    State(state): State<AppState>,
+    Json(request): Json<ResendVerificationRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let unverified = state
+        .user_store
+        .read()
+        .await
+        .get_user(&email)
+        .await
+        .is_ok_and(|user| !user.verified);
+
+    // Answer the same way, and as quickly, whatever the state of the account, so this route cannot be
+    // used to find out which emails are registered or verified. The email is sent in the background
+    // as talking to the email provider takes a noticeable time.
+    if unverified {
+        let state = state.clone();
+        tokio::spawn(
+            async move {
+                if let Err(e) = send_verification_email(&email, &state).await {
+                    tracing::error!("Failed to send verification email: {:?}", e);
+                }
+            }
+            .in_current_span(),
+        );
+    }
+
+    let response = Json(VerifyEmailResponse {
+        message:
+            "If an unverified account exists for this email, a verification link has been sent"
+                .to_owned(),
+    });
+
+    Ok((StatusCode::ACCEPTED, response))
+}
+
+#[tracing::instrument(name = "Send verification email", skip_all)]
+pub(crate) async fn send_verification_email(email: &Email, state: &AppState) -> Result<()> {
+    let token = generate_email_verification_token(email)?;
+
+    let link = format!(
+        "{}/verify-email?token={}",
+        AUTH_SERVICE_URL.as_str(),
+        token.expose_secret()
+    );
+    let content = format!(
+        "Use the following link to verify your email address. It expires in {} hours: {}",
+        EMAIL_VERIFICATION_TOKEN_TTL_SECONDS / 3600,
+        link
+    );
+
+    state
+        .email_client
+        .send_email(email, "Verify your email", &content)
+        .await
+}
+
+#[derive(Deserialize)]
+pub struct VerifyEmailQuery {
+    pub token: Secret<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ResendVerificationRequest {
+    pub email: Secret<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct VerifyEmailResponse {
+    pub message: String,
+}
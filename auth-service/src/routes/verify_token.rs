@@ -1,16 +1,40 @@
-use axum::{extract::State, http::StatusCode, Json};
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 use secrecy::Secret;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::{app_state::AppState, domain::AuthAPIError, utils::auth::validate_token};
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, SubjectType},
+    utils::{auth::validate_any_token, constants::JWT_AUDIENCE},
+};
 
 #[tracing::instrument(name = "Verify token", skip_all)]
 pub async fn verify_token(
     State(state): State<AppState>,
     Json(request): Json<VerifyTokenRequest>,
-) -> Result<StatusCode, AuthAPIError> {
-    match validate_token(&request.token, state.banned_token_store.clone()).await {
-        Ok(_) => Ok(StatusCode::OK),
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let audience = request.audience.as_deref().unwrap_or(&JWT_AUDIENCE);
+
+    match validate_any_token(
+        &request.token,
+        audience,
+        state.banned_token_store.clone(),
+        state.user_store.clone(),
+        state.oauth_client_store.clone(),
+    )
+    .await
+    {
+        // Relying services authorize requests by what the token was granted
+        Ok(claims) => {
+            let response = Json(VerifyTokenResponse {
+                subject_type: claims.sub_type(),
+                roles: claims.roles().to_vec(),
+                permissions: claims.permissions().to_vec(),
+                client_id: claims.client_id().map(str::to_owned),
+                scope: claims.scope().map(str::to_owned),
+            });
+            Ok((StatusCode::OK, response))
+        }
         Err(_) => Err(AuthAPIError::InvalidToken),
     }
 }
@@ -18,4 +42,21 @@ pub async fn verify_token(
 #[derive(Debug, Deserialize)]
 pub struct VerifyTokenRequest {
     token: Secret<String>,
+    // Lets each relying service insist on tokens issued for it
+    audience: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct VerifyTokenResponse {
+    /// Whether the token acts for a user, or for a machine client with no user present
+    #[serde(rename = "subjectType")]
+    pub subject_type: SubjectType,
+    pub roles: Vec<String>,
+    pub permissions: Vec<String>,
+    /// Set when the token was issued to an OAuth client, which may only act within its scope. That's
+    /// always the case for machine clients' own tokens.
+    #[serde(rename = "clientId", default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
 }
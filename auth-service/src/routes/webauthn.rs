@@ -0,0 +1,465 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum_extra::extract::CookieJar;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD as BASE64_URL, Engine};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuditEventKind, AuthAPIError, AuthMethod, Email, SessionClient, WebAuthnCeremony,
+        WebAuthnChallenge, WebAuthnCredential, WebAuthnCredentialId, WebAuthnCredentialStoreError,
+    },
+    routes::login::handle_no_2fa,
+    utils::{
+        audit::record_audit_event,
+        auth::authenticate,
+        brute_force::{check_lockout, email_key, ip_key, record_failed_attempt},
+        constants::{
+            MAX_FAILED_LOGIN_ATTEMPTS, MAX_FAILED_LOGIN_ATTEMPTS_PER_IP, WEBAUTHN_RP_ID,
+            WEBAUTHN_RP_NAME,
+        },
+        webauthn::{
+            verify_assertion, verify_attestation, verify_client_data, CLIENT_DATA_TYPE_CREATE,
+            CLIENT_DATA_TYPE_GET, COSE_ALG_ES256, WEBAUTHN_CHALLENGE_TTL_SECONDS,
+        },
+    },
+};
+
+const PUBLIC_KEY_CREDENTIAL_TYPE: &str = "public-key";
+
+#[tracing::instrument(name = "Start WebAuthn registration", skip_all)]
+pub async fn webauthn_register_start(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = authenticate(
+        &jar,
+        state.banned_token_store.clone(),
+        state.user_store.clone(),
+    )
+    .await?;
+
+    // Stops the browser from registering a second passkey on an authenticator that already has one
+    let exclude_credentials = state
+        .webauthn_credential_store
+        .read()
+        .await
+        .get_user_credentials(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?
+        .iter()
+        .map(credential_descriptor)
+        .collect();
+
+    let challenge = WebAuthnChallenge::default();
+
+    state
+        .webauthn_challenge_store
+        .write()
+        .await
+        .add_challenge(
+            challenge.clone(),
+            email.clone(),
+            WebAuthnCeremony::Registration,
+        )
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let email_str = email.as_ref().expose_secret();
+
+    let response = Json(WebAuthnRegisterStartResponse {
+        public_key: PublicKeyCredentialCreationOptions {
+            challenge: challenge.as_ref().to_owned(),
+            rp: RelyingParty {
+                id: WEBAUTHN_RP_ID.to_owned(),
+                name: WEBAUTHN_RP_NAME.to_owned(),
+            },
+            user: UserEntity {
+                // The spec asks for an opaque handle rather than anything identifying
+                id: BASE64_URL.encode(Sha256::digest(email_str.as_bytes())),
+                name: email_str.to_owned(),
+                display_name: email_str.to_owned(),
+            },
+            pub_key_cred_params: vec![CredentialParameters {
+                credential_type: PUBLIC_KEY_CREDENTIAL_TYPE.to_owned(),
+                alg: COSE_ALG_ES256,
+            }],
+            timeout: WEBAUTHN_CHALLENGE_TTL_SECONDS * 1000,
+            attestation: "none".to_owned(),
+            exclude_credentials,
+            authenticator_selection: AuthenticatorSelection {
+                resident_key: "preferred".to_owned(),
+                user_verification: "required".to_owned(),
+            },
+        },
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Finish WebAuthn registration", skip_all)]
+pub async fn webauthn_register_finish(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<WebAuthnRegisterFinishRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = authenticate(
+        &jar,
+        state.banned_token_store.clone(),
+        state.user_store.clone(),
+    )
+    .await?;
+
+    let credential_id =
+        WebAuthnCredentialId::parse(request.id).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let client_data_json = decode(&request.response.client_data_json)?;
+    let attestation_object = decode(&request.response.attestation_object)?;
+
+    let challenge = verify_client_data(&client_data_json, CLIENT_DATA_TYPE_CREATE)
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+    take_challenge(&state, &challenge, &email, WebAuthnCeremony::Registration).await?;
+
+    let attested =
+        verify_attestation(&attestation_object).map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+    if attested.credential_id != credential_id {
+        return Err(AuthAPIError::InvalidCredentials);
+    }
+
+    let credential = WebAuthnCredential {
+        credential_id: attested.credential_id,
+        email: email.clone(),
+        public_key: attested.public_key,
+        sign_count: attested.sign_count,
+    };
+
+    state
+        .webauthn_credential_store
+        .write()
+        .await
+        .add_credential(credential)
+        .await
+        .map_err(|e| match e {
+            WebAuthnCredentialStoreError::CredentialAlreadyExists => {
+                AuthAPIError::PasskeyAlreadyRegistered
+            }
+            e => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+
+    record_audit_event(&email, AuditEventKind::PasskeyAdded, &state).await;
+
+    let response = Json(WebAuthnRegisterFinishResponse {
+        message: "Passkey registered successfully".to_owned(),
+    });
+
+    Ok((StatusCode::CREATED, response))
+}
+
+#[tracing::instrument(name = "Start WebAuthn login", skip_all)]
+pub async fn webauthn_login_start(
+    State(state): State<AppState>,
+    Json(request): Json<WebAuthnLoginStartRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    // Unknown accounts and accounts without passkeys get a challenge too, just with no passkeys to
+    // choose from, so this route can't be used to find out which emails are registered
+    let credentials = state
+        .webauthn_credential_store
+        .read()
+        .await
+        .get_user_credentials(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let challenge = WebAuthnChallenge::default();
+
+    state
+        .webauthn_challenge_store
+        .write()
+        .await
+        .add_challenge(challenge.clone(), email, WebAuthnCeremony::Authentication)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response = Json(WebAuthnLoginStartResponse {
+        public_key: PublicKeyCredentialRequestOptions {
+            challenge: challenge.as_ref().to_owned(),
+            rp_id: WEBAUTHN_RP_ID.to_owned(),
+            allow_credentials: credentials.iter().map(credential_descriptor).collect(),
+            timeout: WEBAUTHN_CHALLENGE_TTL_SECONDS * 1000,
+            user_verification: "required".to_owned(),
+        },
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Finish WebAuthn login", skip_all)]
+pub async fn webauthn_login_finish(
+    State(state): State<AppState>,
+    client: SessionClient,
+    jar: CookieJar,
+    Json(request): Json<WebAuthnLoginFinishRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let ip_key = ip_key(client.ip_address);
+
+    // Locked out clients are turned away before their passkey is even checked
+    if let Err(e) = check_lockout(&state.failed_attempt_store, std::slice::from_ref(&ip_key)).await
+    {
+        return (jar, Err(e));
+    }
+
+    let email = match verify_login(&state, request).await {
+        Ok(email) => email,
+        Err(AuthAPIError::IncorrectCredentials) => {
+            if let Err(e) = record_failed_attempt(
+                &state.failed_attempt_store,
+                &ip_key,
+                *MAX_FAILED_LOGIN_ATTEMPTS_PER_IP,
+            )
+            .await
+            {
+                return (jar, Err(e));
+            }
+            return (jar, Err(AuthAPIError::IncorrectCredentials));
+        }
+        Err(e) => return (jar, Err(e)),
+    };
+
+    // A passkey proves possession and user verification in one step, so it stands in for 2FA too
+    handle_no_2fa(&email, vec![AuthMethod::HardwareKey], client, &state, jar).await
+}
+
+async fn verify_login(
+    state: &AppState,
+    request: WebAuthnLoginFinishRequest,
+) -> Result<Email, AuthAPIError> {
+    let credential_id =
+        WebAuthnCredentialId::parse(request.id).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let client_data_json = decode(&request.response.client_data_json)?;
+    let authenticator_data = decode(&request.response.authenticator_data)?;
+    let signature = decode(&request.response.signature)?;
+
+    let challenge = verify_client_data(&client_data_json, CLIENT_DATA_TYPE_GET)
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+    let mut credential_store = state.webauthn_credential_store.write().await;
+
+    let credential = credential_store
+        .get_credential(&credential_id)
+        .await
+        .map_err(|e| match e {
+            WebAuthnCredentialStoreError::CredentialNotFound => AuthAPIError::IncorrectCredentials,
+            e => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+
+    // The passkey's account is locked out along with its password, and failures count towards both
+    let email_key = email_key(&credential.email);
+    check_lockout(
+        &state.failed_attempt_store,
+        std::slice::from_ref(&email_key),
+    )
+    .await?;
+
+    // The challenge was issued for one account; a passkey belonging to another can't answer it
+    let verified = take_challenge(
+        state,
+        &challenge,
+        &credential.email,
+        WebAuthnCeremony::Authentication,
+    )
+    .await
+    .and_then(|()| {
+        verify_assertion(
+            &credential,
+            &authenticator_data,
+            &client_data_json,
+            &signature,
+        )
+        .map_err(|_| AuthAPIError::IncorrectCredentials)
+    });
+
+    let sign_count = match verified {
+        Ok(sign_count) => sign_count,
+        Err(AuthAPIError::IncorrectCredentials) => {
+            record_failed_attempt(
+                &state.failed_attempt_store,
+                &email_key,
+                *MAX_FAILED_LOGIN_ATTEMPTS,
+            )
+            .await?;
+            return Err(AuthAPIError::IncorrectCredentials);
+        }
+        Err(e) => return Err(e),
+    };
+
+    credential_store
+        .update_sign_count(&credential_id, sign_count)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let user = state
+        .user_store
+        .read()
+        .await
+        .get_user(&credential.email)
+        .await
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+    if state.require_email_verification && !user.verified {
+        return Err(AuthAPIError::EmailNotVerified);
+    }
+
+    if user.disabled {
+        return Err(AuthAPIError::AccountDisabled);
+    }
+
+    Ok(user.email)
+}
+
+async fn take_challenge(
+    state: &AppState,
+    challenge: &WebAuthnChallenge,
+    email: &Email,
+    ceremony: WebAuthnCeremony,
+) -> Result<(), AuthAPIError> {
+    let (expected_email, expected_ceremony) = state
+        .webauthn_challenge_store
+        .write()
+        .await
+        .take_challenge(challenge)
+        .await
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+    if &expected_email != email || expected_ceremony != ceremony {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    Ok(())
+}
+
+fn decode(value: &str) -> Result<Vec<u8>, AuthAPIError> {
+    BASE64_URL
+        .decode(value)
+        .map_err(|_| AuthAPIError::InvalidCredentials)
+}
+
+fn credential_descriptor(credential: &WebAuthnCredential) -> CredentialDescriptor {
+    CredentialDescriptor {
+        credential_type: PUBLIC_KEY_CREDENTIAL_TYPE.to_owned(),
+        id: credential.credential_id.as_ref().to_owned(),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WebAuthnRegisterStartResponse {
+    #[serde(rename = "publicKey")]
+    pub public_key: PublicKeyCredentialCreationOptions,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicKeyCredentialCreationOptions {
+    pub challenge: String,
+    pub rp: RelyingParty,
+    pub user: UserEntity,
+    pub pub_key_cred_params: Vec<CredentialParameters>,
+    pub timeout: i64,
+    pub attestation: String,
+    pub exclude_credentials: Vec<CredentialDescriptor>,
+    pub authenticator_selection: AuthenticatorSelection,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RelyingParty {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserEntity {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "displayName")]
+    pub display_name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CredentialParameters {
+    #[serde(rename = "type")]
+    pub credential_type: String,
+    pub alg: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CredentialDescriptor {
+    #[serde(rename = "type")]
+    pub credential_type: String,
+    pub id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthenticatorSelection {
+    pub resident_key: String,
+    pub user_verification: String,
+}
+
+#[derive(Deserialize)]
+pub struct WebAuthnRegisterFinishRequest {
+    pub id: String,
+    pub response: AttestationResponse,
+}
+
+#[derive(Deserialize)]
+pub struct AttestationResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "attestationObject")]
+    pub attestation_object: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct WebAuthnRegisterFinishResponse {
+    pub message: String,
+}
+
+#[derive(Deserialize)]
+pub struct WebAuthnLoginStartRequest {
+    pub email: Secret<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WebAuthnLoginStartResponse {
+    #[serde(rename = "publicKey")]
+    pub public_key: PublicKeyCredentialRequestOptions,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicKeyCredentialRequestOptions {
+    pub challenge: String,
+    pub rp_id: String,
+    pub allow_credentials: Vec<CredentialDescriptor>,
+    pub timeout: i64,
+    pub user_verification: String,
+}
+
+#[derive(Deserialize)]
+pub struct WebAuthnLoginFinishRequest {
+    pub id: String,
+    pub response: AssertionResponse,
+}
+
+#[derive(Deserialize)]
+pub struct AssertionResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "authenticatorData")]
+    pub authenticator_data: String,
+    pub signature: String,
+}
@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{ApiKeySecret, ApiKeyStore, ApiKeyStoreError},
+    ApiKey, ApiKeyId, UserId,
+};
+
+#[derive(Default)]
+pub struct HashMapApiKeyStore {
+    keys: HashMap<ApiKeyId, ApiKey>,
+}
+
+impl HashMapApiKeyStore {
+    // Someone else's key looks just like one that doesn't exist
+    fn get_user_key_mut(
+        &mut self,
+        user_id: &UserId,
+        id: &ApiKeyId,
+    ) -> Result<&mut ApiKey, ApiKeyStoreError> {
+        self.keys
+            .get_mut(id)
+            .filter(|key| &key.user_id == user_id)
+            .ok_or(ApiKeyStoreError::KeyNotFound)
+    }
+}
+
+#[async_trait::async_trait]
+impl ApiKeyStore for HashMapApiKeyStore {
+    async fn add_key(&mut self, key: ApiKey) -> Result<(), ApiKeyStoreError> {
+        self.keys.insert(key.id, key);
+        Ok(())
+    }
+
+    async fn get_key(&self, secret: &ApiKeySecret) -> Result<ApiKey, ApiKeyStoreError> {
+        let hash = secret.hash();
+        self.keys
+            .values()
+            .find(|key| key.hash == hash)
+            .cloned()
+            .ok_or(ApiKeyStoreError::KeyNotFound)
+    }
+
+    async fn get_user_keys(&self, user_id: &UserId) -> Result<Vec<ApiKey>, ApiKeyStoreError> {
+        let mut keys: Vec<ApiKey> = self
+            .keys
+            .values()
+            .filter(|key| &key.user_id == user_id)
+            .cloned()
+            .collect();
+        keys.sort_by_key(|key| key.created_at);
+        Ok(keys)
+    }
+
+    async fn rename_key(
+        &mut self,
+        user_id: &UserId,
+        id: &ApiKeyId,
+        name: &str,
+    ) -> Result<(), ApiKeyStoreError> {
+        self.get_user_key_mut(user_id, id)?.name = name.to_owned();
+        Ok(())
+    }
+
+    async fn remove_key(
+        &mut self,
+        user_id: &UserId,
+        id: &ApiKeyId,
+    ) -> Result<(), ApiKeyStoreError> {
+        self.get_user_key_mut(user_id, id)?;
+        self.keys.remove(id);
+        Ok(())
+    }
+
+    async fn remove_user_keys(&mut self, user_id: &UserId) -> Result<(), ApiKeyStoreError> {
+        self.keys.retain(|_, key| &key.user_id != user_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::OAuthScope;
+
+    use super::*;
+
+    fn key(user_id: UserId, name: &str) -> (ApiKey, ApiKeySecret) {
+        let secret = ApiKeySecret::default();
+        let key = ApiKey::new(
+            user_id,
+            name.to_owned(),
+            OAuthScope::parse("reports:read").unwrap(),
+            None,
+            &secret,
+        )
+        .unwrap();
+        (key, secret)
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_key() {
+        let mut store = HashMapApiKeyStore::default();
+        let (key, secret) = key(UserId::default(), "Deploy script");
+        store.add_key(key.clone()).await.unwrap();
+
+        assert_eq!(store.get_key(&secret).await, Ok(key));
+        assert_eq!(
+            store.get_key(&ApiKeySecret::default()).await,
+            Err(ApiKeyStoreError::KeyNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_user_keys_oldest_first() {
+        let mut store = HashMapApiKeyStore::default();
+        let user_id = UserId::default();
+        let (first, _) = key(user_id, "First");
+        let (second, _) = key(user_id, "Second");
+        let (other, _) = key(UserId::default(), "Other");
+        store.add_key(second.clone()).await.unwrap();
+        store.add_key(other).await.unwrap();
+        store.add_key(first.clone()).await.unwrap();
+
+        assert_eq!(store.get_user_keys(&user_id).await, Ok(vec![first, second]));
+    }
+
+    #[tokio::test]
+    async fn test_only_the_owner_can_rename_or_remove_a_key() {
+        let mut store = HashMapApiKeyStore::default();
+        let user_id = UserId::default();
+        let (key, secret) = key(user_id, "Deploy script");
+        store.add_key(key.clone()).await.unwrap();
+
+        let other_user_id = UserId::default();
+        assert_eq!(
+            store.rename_key(&other_user_id, &key.id, "Mine now").await,
+            Err(ApiKeyStoreError::KeyNotFound)
+        );
+        assert_eq!(
+            store.remove_key(&other_user_id, &key.id).await,
+            Err(ApiKeyStoreError::KeyNotFound)
+        );
+
+        store
+            .rename_key(&user_id, &key.id, "Backups")
+            .await
+            .unwrap();
+        assert_eq!(store.get_key(&secret).await.unwrap().name, "Backups");
+
+        store.remove_key(&user_id, &key.id).await.unwrap();
+        assert_eq!(
+            store.get_key(&secret).await,
+            Err(ApiKeyStoreError::KeyNotFound)
+        );
+        assert_eq!(
+            store.remove_key(&user_id, &key.id).await,
+            Err(ApiKeyStoreError::KeyNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_remove_user_keys() {
+        let mut store = HashMapApiKeyStore::default();
+        let user_id = UserId::default();
+        let (first, _) = key(user_id, "First");
+        let (second, _) = key(user_id, "Second");
+        let (other, _) = key(UserId::default(), "Other");
+        store.add_key(first).await.unwrap();
+        store.add_key(second).await.unwrap();
+        store.add_key(other.clone()).await.unwrap();
+
+        store.remove_user_keys(&user_id).await.unwrap();
+
+        assert_eq!(store.get_user_keys(&user_id).await, Ok(Vec::new()));
+        assert_eq!(store.get_user_keys(&other.user_id).await, Ok(vec![other]));
+    }
+}
@@ -0,0 +1,77 @@
+use crate::domain::{
+    data_stores::{AuditEventStore, AuditEventStoreError},
+    AuditEvent, UserId,
+};
+
+// Events are only ever appended, so they stay in the order they happened
+#[derive(Default)]
+pub struct HashMapAuditEventStore {
+    events: Vec<AuditEvent>,
+}
+
+#[async_trait::async_trait]
+impl AuditEventStore for HashMapAuditEventStore {
+    async fn add_event(&mut self, event: AuditEvent) -> Result<(), AuditEventStoreError> {
+        self.events.push(event);
+        Ok(())
+    }
+
+    async fn get_user_events(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<AuditEvent>, AuditEventStoreError> {
+        Ok(self
+            .events
+            .iter()
+            .rev()
+            .filter(|event| &event.user_id == user_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn remove_user_events(&mut self, user_id: &UserId) -> Result<(), AuditEventStoreError> {
+        self.events.retain(|event| &event.user_id != user_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::AuditEventKind;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_get_user_events_most_recent_first() {
+        let mut store = HashMapAuditEventStore::default();
+        let user_id = UserId::default();
+        let login = AuditEvent::new(user_id, AuditEventKind::Login);
+        let other = AuditEvent::new(UserId::default(), AuditEventKind::Login);
+        let logout = AuditEvent::new(user_id, AuditEventKind::Logout);
+        store.add_event(login.clone()).await.unwrap();
+        store.add_event(other).await.unwrap();
+        store.add_event(logout.clone()).await.unwrap();
+
+        assert_eq!(
+            store.get_user_events(&user_id).await,
+            Ok(vec![logout, login])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_remove_user_events() {
+        let mut store = HashMapAuditEventStore::default();
+        let user_id = UserId::default();
+        let other = AuditEvent::new(UserId::default(), AuditEventKind::Login);
+        store
+            .add_event(AuditEvent::new(user_id, AuditEventKind::Login))
+            .await
+            .unwrap();
+        store.add_event(other.clone()).await.unwrap();
+
+        store.remove_user_events(&user_id).await.unwrap();
+
+        assert_eq!(store.get_user_events(&user_id).await, Ok(Vec::new()));
+        assert_eq!(store.get_user_events(&other.user_id).await, Ok(vec![other]));
+    }
+}
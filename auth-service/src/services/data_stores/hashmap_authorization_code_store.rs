@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::{
+    domain::{
+        data_stores::{AuthorizationCode, AuthorizationCodeStore, AuthorizationCodeStoreError},
+        AuthorizationCodeGrant,
+    },
+    utils::auth::AUTHORIZATION_CODE_TTL_SECONDS,
+};
+
+#[derive(Default)]
+pub struct HashMapAuthorizationCodeStore {
+    codes: HashMap<String, (AuthorizationCodeGrant, DateTime<Utc>)>,
+}
+
+#[async_trait::async_trait]
+impl AuthorizationCodeStore for HashMapAuthorizationCodeStore {
+    async fn add_code(
+        &mut self,
+        code: AuthorizationCode,
+        grant: AuthorizationCodeGrant,
+    ) -> Result<(), AuthorizationCodeStoreError> {
+        let expires_at = Utc::now() + Duration::seconds(AUTHORIZATION_CODE_TTL_SECONDS);
+        self.codes.insert(code.hash(), (grant, expires_at));
+        Ok(())
+    }
+
+    async fn take_code(
+        &mut self,
+        code: &AuthorizationCode,
+    ) -> Result<AuthorizationCodeGrant, AuthorizationCodeStoreError> {
+        match self.codes.remove(&code.hash()) {
+            Some((grant, expires_at)) if expires_at > Utc::now() => Ok(grant),
+            _ => Err(AuthorizationCodeStoreError::CodeNotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::{AuthMethod, Authentication, OAuthClientId, OAuthScope, UserId};
+
+    use super::*;
+
+    fn grant() -> AuthorizationCodeGrant {
+        AuthorizationCodeGrant {
+            client_id: OAuthClientId::default(),
+            user_id: UserId::default(),
+            redirect_uri: "https://app.example.com/callback".to_owned(),
+            scope: OAuthScope::parse("read").unwrap(),
+            code_challenge: "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM".to_owned(),
+            nonce: Some("n-0S6_WzA2Mj".to_owned()),
+            authentication: Authentication::new(vec![AuthMethod::Password]),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_take_code_can_only_be_used_once() {
+        let mut store = HashMapAuthorizationCodeStore::default();
+        let code = AuthorizationCode::default();
+        let grant = grant();
+        store.add_code(code.clone(), grant.clone()).await.unwrap();
+
+        assert_eq!(store.take_code(&code).await, Ok(grant));
+        assert_eq!(
+            store.take_code(&code).await,
+            Err(AuthorizationCodeStoreError::CodeNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_take_expired_code() {
+        let mut store = HashMapAuthorizationCodeStore::default();
+        let code = AuthorizationCode::default();
+        store
+            .codes
+            .insert(code.hash(), (grant(), Utc::now() - Duration::seconds(1)));
+
+        assert_eq!(
+            store.take_code(&code).await,
+            Err(AuthorizationCodeStoreError::CodeNotFound)
+        );
+    }
+}
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::domain::data_stores::{FailedAttemptStore, FailedAttemptStoreError};
+
+#[derive(Default)]
+pub struct HashMapFailedAttemptStore {
+    failures: HashMap<String, (u32, DateTime<Utc>)>,
+    lockouts: HashMap<String, DateTime<Utc>>,
+}
+
+#[async_trait::async_trait]
+impl FailedAttemptStore for HashMapFailedAttemptStore {
+    async fn record_failure(
+        &mut self,
+        key: &str,
+        window_seconds: u64,
+    ) -> Result<u32, FailedAttemptStoreError> {
+        let now = Utc::now();
+        let expires_at = now + Duration::seconds(window_seconds as i64);
+
+        let count = match self.failures.get(key) {
+            Some((count, expires_at)) if *expires_at > now => count + 1,
+            _ => 1,
+        };
+        self.failures.insert(key.to_owned(), (count, expires_at));
+
+        Ok(count)
+    }
+
+    async fn reset(&mut self, key: &str) -> Result<(), FailedAttemptStoreError> {
+        self.failures.remove(key);
+        Ok(())
+    }
+
+    async fn lock(&mut self, key: &str, seconds: u64) -> Result<(), FailedAttemptStoreError> {
+        self.lockouts.insert(
+            key.to_owned(),
+            Utc::now() + Duration::seconds(seconds as i64),
+        );
+        Ok(())
+    }
+
+    async fn get_lockout(&self, key: &str) -> Result<Option<u64>, FailedAttemptStoreError> {
+        let remaining = self
+            .lockouts
+            .get(key)
+            .map(|locked_until| (*locked_until - Utc::now()).num_seconds())
+            .filter(|seconds| *seconds > 0)
+            .map(|seconds| seconds as u64);
+        Ok(remaining)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_record_failure_counts_up() {
+        let mut store = HashMapFailedAttemptStore::default();
+
+        assert_eq!(store.record_failure("key", 60).await, Ok(1));
+        assert_eq!(store.record_failure("key", 60).await, Ok(2));
+        assert_eq!(store.record_failure("other", 60).await, Ok(1));
+    }
+
+    #[tokio::test]
+    async fn test_record_failure_after_window_expired() {
+        let mut store = HashMapFailedAttemptStore::default();
+        store
+            .failures
+            .insert("key".to_owned(), (5, Utc::now() - Duration::seconds(1)));
+
+        assert_eq!(store.record_failure("key", 60).await, Ok(1));
+    }
+
+    #[tokio::test]
+    async fn test_reset() {
+        let mut store = HashMapFailedAttemptStore::default();
+        store.record_failure("key", 60).await.unwrap();
+
+        assert_eq!(store.reset("key").await, Ok(()));
+        assert_eq!(store.record_failure("key", 60).await, Ok(1));
+    }
+
+    #[tokio::test]
+    async fn test_lock() {
+        let mut store = HashMapFailedAttemptStore::default();
+        assert_eq!(store.get_lockout("key").await, Ok(None));
+
+        store.lock("key", 60).await.unwrap();
+
+        let remaining = store.get_lockout("key").await.unwrap().unwrap();
+        assert!(remaining > 0 && remaining <= 60);
+    }
+
+    #[tokio::test]
+    async fn test_expired_lockout() {
+        let mut store = HashMapFailedAttemptStore::default();
+        store
+            .lockouts
+            .insert("key".to_owned(), Utc::now() - Duration::seconds(1));
+
+        assert_eq!(store.get_lockout("key").await, Ok(None));
+    }
+}
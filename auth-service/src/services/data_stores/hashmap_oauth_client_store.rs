@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{OAuthClientStore, OAuthClientStoreError},
+    OAuthClient, OAuthClientId, OAuthClientSecret,
+};
+
+#[derive(Default)]
+pub struct HashMapOAuthClientStore {
+    clients: HashMap<OAuthClientId, OAuthClient>,
+}
+
+#[async_trait::async_trait]
+impl OAuthClientStore for HashMapOAuthClientStore {
+    async fn add_client(&mut self, client: OAuthClient) -> Result<(), OAuthClientStoreError> {
+        self.clients.insert(client.id, client);
+        Ok(())
+    }
+
+    async fn get_client(&self, id: &OAuthClientId) -> Result<OAuthClient, OAuthClientStoreError> {
+        self.clients
+            .get(id)
+            .cloned()
+            .ok_or(OAuthClientStoreError::ClientNotFound)
+    }
+
+    async fn list_clients(&self) -> Result<Vec<OAuthClient>, OAuthClientStoreError> {
+        let mut clients: Vec<OAuthClient> = self.clients.values().cloned().collect();
+        clients.sort_by_key(|client| client.created_at);
+        Ok(clients)
+    }
+
+    async fn set_client_secret(
+        &mut self,
+        id: &OAuthClientId,
+        secret: Option<OAuthClientSecret>,
+    ) -> Result<(), OAuthClientStoreError> {
+        let client = self
+            .clients
+            .get_mut(id)
+            .ok_or(OAuthClientStoreError::ClientNotFound)?;
+        client.secret = secret;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::{ClientSecret, OAuthScope};
+
+    use super::*;
+
+    fn client(name: &str) -> OAuthClient {
+        OAuthClient::new(
+            name.to_owned(),
+            vec!["https://app.example.com/callback".to_owned()],
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_client() {
+        let mut store = HashMapOAuthClientStore::default();
+        let client = client("App");
+        store.add_client(client.clone()).await.unwrap();
+
+        assert_eq!(store.get_client(&client.id).await, Ok(client));
+        assert_eq!(
+            store.get_client(&OAuthClientId::default()).await,
+            Err(OAuthClientStoreError::ClientNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_clients_oldest_first() {
+        let mut store = HashMapOAuthClientStore::default();
+        let first = client("First");
+        let second = client("Second");
+        store.add_client(second.clone()).await.unwrap();
+        store.add_client(first.clone()).await.unwrap();
+
+        assert_eq!(store.list_clients().await, Ok(vec![first, second]));
+    }
+
+    #[tokio::test]
+    async fn test_set_client_secret() {
+        let mut store = HashMapOAuthClientStore::default();
+        let client = OAuthClient::new_machine("Jobs".to_owned(), OAuthScope::default()).unwrap();
+        store.add_client(client.clone()).await.unwrap();
+
+        let secret = OAuthClientSecret::new(&ClientSecret::default());
+        store
+            .set_client_secret(&client.id, Some(secret.clone()))
+            .await
+            .unwrap();
+        assert_eq!(
+            store.get_client(&client.id).await.unwrap().secret,
+            Some(secret)
+        );
+
+        store.set_client_secret(&client.id, None).await.unwrap();
+        assert_eq!(store.get_client(&client.id).await.unwrap().secret, None);
+
+        assert_eq!(
+            store
+                .set_client_secret(&OAuthClientId::default(), None)
+                .await,
+            Err(OAuthClientStoreError::ClientNotFound)
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{OAuthConsentStore, OAuthConsentStoreError},
+    OAuthClientId, OAuthScope, UserId,
+};
+
+#[derive(Default)]
+pub struct HashMapOAuthConsentStore {
+    consents: HashMap<(UserId, OAuthClientId), OAuthScope>,
+}
+
+#[async_trait::async_trait]
+impl OAuthConsentStore for HashMapOAuthConsentStore {
+    async fn get_consent(
+        &self,
+        user_id: &UserId,
+        client_id: &OAuthClientId,
+    ) -> Result<Option<OAuthScope>, OAuthConsentStoreError> {
+        Ok(self.consents.get(&(*user_id, *client_id)).cloned())
+    }
+
+    async fn get_user_consents(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<(OAuthClientId, OAuthScope)>, OAuthConsentStoreError> {
+        Ok(self
+            .consents
+            .iter()
+            .filter(|((consenting_user_id, _), _)| consenting_user_id == user_id)
+            .map(|((_, client_id), scope)| (*client_id, scope.clone()))
+            .collect())
+    }
+
+    async fn set_consent(
+        &mut self,
+        user_id: &UserId,
+        client_id: &OAuthClientId,
+        scope: &OAuthScope,
+    ) -> Result<(), OAuthConsentStoreError> {
+        self.consents.insert((*user_id, *client_id), scope.clone());
+        Ok(())
+    }
+
+    async fn remove_user_consents(
+        &mut self,
+        user_id: &UserId,
+    ) -> Result<(), OAuthConsentStoreError> {
+        self.consents
+            .retain(|(consenting_user_id, _), _| consenting_user_id != user_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_set_and_remove_consents() {
+        let mut store = HashMapOAuthConsentStore::default();
+        let user_id = UserId::default();
+        let client_id = OAuthClientId::default();
+        let scope = OAuthScope::parse("read write").unwrap();
+
+        // Never approving a client isn't the same as approving it for no scope
+        assert_eq!(store.get_consent(&user_id, &client_id).await, Ok(None));
+
+        store
+            .set_consent(&user_id, &client_id, &scope)
+            .await
+            .unwrap();
+        assert_eq!(
+            store.get_consent(&user_id, &client_id).await,
+            Ok(Some(scope.clone()))
+        );
+        assert_eq!(
+            store.get_consent(&UserId::default(), &client_id).await,
+            Ok(None)
+        );
+        assert_eq!(
+            store.get_user_consents(&user_id).await,
+            Ok(vec![(client_id, scope)])
+        );
+        assert_eq!(
+            store.get_user_consents(&UserId::default()).await,
+            Ok(vec![])
+        );
+
+        store.remove_user_consents(&user_id).await.unwrap();
+        assert_eq!(store.get_consent(&user_id, &client_id).await, Ok(None));
+        assert_eq!(store.get_user_consents(&user_id).await, Ok(vec![]));
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::{
+    domain::{
+        data_stores::{PasswordResetToken, PasswordResetTokenStore, PasswordResetTokenStoreError},
+        email::Email,
+    },
+    utils::auth::PASSWORD_RESET_TOKEN_TTL_SECONDS,
+};
+
+#[derive(Default)]
+pub struct HashMapPasswordResetTokenStore {
+    tokens: HashMap<String, (Email, DateTime<Utc>)>,
+}
+
+#[async_trait::async_trait]
+impl PasswordResetTokenStore for HashMapPasswordResetTokenStore {
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: PasswordResetToken,
+    ) -> Result<(), PasswordResetTokenStoreError> {
+        let expires_at = Utc::now() + Duration::seconds(PASSWORD_RESET_TOKEN_TTL_SECONDS);
+        self.tokens.insert(token.hash(), (email, expires_at));
+        Ok(())
+    }
+
+    async fn take_token(
+        &mut self,
+        token: &PasswordResetToken,
+    ) -> Result<Email, PasswordResetTokenStoreError> {
+        match self.tokens.remove(&token.hash()) {
+            Some((email, expires_at)) if expires_at > Utc::now() => Ok(email),
+            _ => Err(PasswordResetTokenStoreError::TokenNotFound),
+        }
+    }
+
+    async fn remove_user_tokens(
+        &mut self,
+        email: &Email,
+    ) -> Result<(), PasswordResetTokenStoreError> {
+        self.tokens
+            .retain(|_, (token_email, _)| token_email != email);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_add_token() {
+        let mut store = HashMapPasswordResetTokenStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let token = PasswordResetToken::default();
+
+        let result = store.add_token(email.clone(), token.clone()).await;
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(store.tokens.get(&token.hash()).unwrap().0, email);
+    }
+
+    #[tokio::test]
+    async fn test_take_token_can_only_be_used_once() {
+        let mut store = HashMapPasswordResetTokenStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let token = PasswordResetToken::default();
+        store.add_token(email.clone(), token.clone()).await.unwrap();
+
+        let result = store.take_token(&token).await;
+        assert_eq!(result, Ok(email));
+
+        let result = store.take_token(&token).await;
+        assert_eq!(result, Err(PasswordResetTokenStoreError::TokenNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_take_expired_token() {
+        let mut store = HashMapPasswordResetTokenStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let token = PasswordResetToken::default();
+        store
+            .tokens
+            .insert(token.hash(), (email, Utc::now() - Duration::seconds(1)));
+
+        let result = store.take_token(&token).await;
+
+        assert_eq!(result, Err(PasswordResetTokenStoreError::TokenNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_remove_user_tokens() {
+        let mut store = HashMapPasswordResetTokenStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let other_email = Email::parse(Secret::new("other@example.com".to_owned())).unwrap();
+        let first = PasswordResetToken::default();
+        let second = PasswordResetToken::default();
+        let other = PasswordResetToken::default();
+        store.add_token(email.clone(), first.clone()).await.unwrap();
+        store
+            .add_token(email.clone(), second.clone())
+            .await
+            .unwrap();
+        store.add_token(other_email, other.clone()).await.unwrap();
+
+        let result = store.remove_user_tokens(&email).await;
+
+        assert_eq!(result, Ok(()));
+        assert!(!store.tokens.contains_key(&first.hash()));
+        assert!(!store.tokens.contains_key(&second.hash()));
+        assert!(store.tokens.contains_key(&other.hash()));
+    }
+}
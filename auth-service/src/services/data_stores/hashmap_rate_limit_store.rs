@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+
+use crate::domain::data_stores::{RateLimitStore, RateLimitStoreError, TokenBucket};
+
+// Past this many buckets, the ones that have refilled completely are dropped, as they are
+// indistinguishable from buckets that were never used
+const MAX_BUCKETS: usize = 10_000;
+
+struct Bucket {
+    tokens: f64,
+    updated_at: DateTime<Utc>,
+    refill_seconds: u64,
+}
+
+#[derive(Default)]
+pub struct HashMapRateLimitStore {
+    buckets: HashMap<String, Bucket>,
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for HashMapRateLimitStore {
+    async fn take_token(
+        &mut self,
+        key: &str,
+        capacity: u32,
+        refill_seconds: u64,
+    ) -> Result<TokenBucket, RateLimitStoreError> {
+        let now = Utc::now();
+
+        if self.buckets.len() >= MAX_BUCKETS {
+            self.buckets.retain(|_, bucket| {
+                (now - bucket.updated_at).num_seconds() < bucket.refill_seconds as i64
+            });
+        }
+
+        let capacity = f64::from(capacity);
+        let bucket = self.buckets.entry(key.to_owned()).or_insert(Bucket {
+            tokens: capacity,
+            updated_at: now,
+            refill_seconds,
+        });
+
+        let elapsed_seconds = (now - bucket.updated_at).num_milliseconds().max(0) as f64 / 1000.0;
+        let refilled = elapsed_seconds * capacity / refill_seconds.max(1) as f64;
+        let mut tokens = (bucket.tokens + refilled).min(capacity);
+
+        let allowed = tokens >= 1.0;
+        if allowed {
+            tokens -= 1.0;
+        }
+
+        bucket.tokens = tokens;
+        bucket.updated_at = now;
+        bucket.refill_seconds = refill_seconds;
+
+        Ok(TokenBucket { allowed, tokens })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_take_token_until_empty() {
+        let mut store = HashMapRateLimitStore::default();
+
+        for _ in 0..3 {
+            assert!(store.take_token("key", 3, 60).await.unwrap().allowed);
+        }
+
+        let bucket = store.take_token("key", 3, 60).await.unwrap();
+        assert!(!bucket.allowed);
+        assert!(bucket.tokens < 1.0);
+
+        // Other keys have their own bucket
+        assert!(store.take_token("other", 3, 60).await.unwrap().allowed);
+    }
+
+    #[tokio::test]
+    async fn test_take_token_after_refill() {
+        let mut store = HashMapRateLimitStore::default();
+        store.take_token("key", 2, 60).await.unwrap();
+        store.take_token("key", 2, 60).await.unwrap();
+
+        // Half the refill period gives back half the capacity
+        store.buckets.get_mut("key").unwrap().updated_at = Utc::now() - Duration::seconds(30);
+
+        let bucket = store.take_token("key", 2, 60).await.unwrap();
+        assert!(bucket.allowed);
+        assert!(bucket.tokens < 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_refill_is_capped_at_capacity() {
+        let mut store = HashMapRateLimitStore::default();
+        store.take_token("key", 2, 60).await.unwrap();
+
+        store.buckets.get_mut("key").unwrap().updated_at = Utc::now() - Duration::days(1);
+
+        let bucket = store.take_token("key", 2, 60).await.unwrap();
+        assert_eq!(bucket.tokens, 1.0);
+    }
+}
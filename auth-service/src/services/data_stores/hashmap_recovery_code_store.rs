@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{RecoveryCode, RecoveryCodeStore, RecoveryCodeStoreError},
+    Email,
+};
+
+#[derive(Default)]
+pub struct HashMapRecoveryCodeStore {
+    codes: HashMap<Email, Vec<RecoveryCode>>,
+}
+
+#[async_trait::async_trait]
+impl RecoveryCodeStore for HashMapRecoveryCodeStore {
+    async fn set_codes(
+        &mut self,
+        email: &Email,
+        codes: Vec<RecoveryCode>,
+    ) -> Result<(), RecoveryCodeStoreError> {
+        self.codes.insert(email.clone(), codes);
+        Ok(())
+    }
+
+    async fn use_code(
+        &mut self,
+        email: &Email,
+        code: &RecoveryCode,
+    ) -> Result<usize, RecoveryCodeStoreError> {
+        let codes = self
+            .codes
+            .get_mut(email)
+            .ok_or(RecoveryCodeStoreError::RecoveryCodeNotFound)?;
+
+        let index = codes
+            .iter()
+            .position(|c| c == code)
+            .ok_or(RecoveryCodeStoreError::RecoveryCodeNotFound)?;
+        codes.remove(index);
+
+        Ok(codes.len())
+    }
+
+    async fn count_codes(&self, email: &Email) -> Result<usize, RecoveryCodeStoreError> {
+        Ok(self.codes.get(email).map(Vec::len).unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_use_code_can_only_be_used_once() {
+        let mut store = HashMapRecoveryCodeStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let codes = vec![RecoveryCode::default(), RecoveryCode::default()];
+        store.set_codes(&email, codes.clone()).await.unwrap();
+
+        let result = store.use_code(&email, &codes[0]).await;
+        assert_eq!(result, Ok(1));
+        assert_eq!(store.count_codes(&email).await, Ok(1));
+
+        let result = store.use_code(&email, &codes[0]).await;
+        assert_eq!(result, Err(RecoveryCodeStoreError::RecoveryCodeNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_set_codes_replaces_existing_codes() {
+        let mut store = HashMapRecoveryCodeStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let old_code = RecoveryCode::default();
+        store
+            .set_codes(&email, vec![old_code.clone()])
+            .await
+            .unwrap();
+        store
+            .set_codes(&email, vec![RecoveryCode::default()])
+            .await
+            .unwrap();
+
+        let result = store.use_code(&email, &old_code).await;
+
+        assert_eq!(result, Err(RecoveryCodeStoreError::RecoveryCodeNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_use_code_of_another_user() {
+        let mut store = HashMapRecoveryCodeStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let other_email = Email::parse(Secret::new("other@example.com".to_owned())).unwrap();
+        let code = RecoveryCode::default();
+        store.set_codes(&email, vec![code.clone()]).await.unwrap();
+
+        let result = store.use_code(&other_email, &code).await;
+
+        assert_eq!(result, Err(RecoveryCodeStoreError::RecoveryCodeNotFound));
+    }
+}
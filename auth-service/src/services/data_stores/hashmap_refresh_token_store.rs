@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::{
+    domain::{
+        data_stores::{
+            RefreshToken, RefreshTokenFamilyId, RefreshTokenStore, RefreshTokenStoreError,
+        },
+        email::Email,
+    },
+    utils::auth::REFRESH_TOKEN_TTL_SECONDS,
+};
+
+#[derive(Default)]
+pub struct HashMapRefreshTokenStore {
+    tokens: HashMap<String, RefreshTokenEntry>,
+}
+
+#[derive(Debug, Clone)]
+struct RefreshTokenEntry {
+    email: Email,
+    family_id: RefreshTokenFamilyId,
+    used: bool,
+    expires_at: DateTime<Utc>,
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for HashMapRefreshTokenStore {
+    async fn add_token(
+        &mut self,
+        token: RefreshToken,
+        email: Email,
+        family_id: RefreshTokenFamilyId,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let entry = RefreshTokenEntry {
+            email,
+            family_id,
+            used: false,
+            expires_at: Utc::now() + Duration::seconds(REFRESH_TOKEN_TTL_SECONDS),
+        };
+        self.tokens.insert(token.hash(), entry);
+        Ok(())
+    }
+
+    async fn consume_token(
+        &mut self,
+        token: &RefreshToken,
+    ) -> Result<(Email, RefreshTokenFamilyId), RefreshTokenStoreError> {
+        let entry = match self.tokens.get_mut(&token.hash()) {
+            Some(entry) if entry.expires_at > Utc::now() => entry,
+            _ => return Err(RefreshTokenStoreError::TokenNotFound),
+        };
+
+        if entry.used {
+            return Err(RefreshTokenStoreError::TokenReused(entry.family_id.clone()));
+        }
+
+        entry.used = true;
+        Ok((entry.email.clone(), entry.family_id.clone()))
+    }
+
+    async fn get_family(
+        &self,
+        token: &RefreshToken,
+    ) -> Result<RefreshTokenFamilyId, RefreshTokenStoreError> {
+        match self.tokens.get(&token.hash()) {
+            Some(entry) if entry.expires_at > Utc::now() => Ok(entry.family_id.clone()),
+            _ => Err(RefreshTokenStoreError::TokenNotFound),
+        }
+    }
+
+    async fn revoke_family(
+        &mut self,
+        family_id: &RefreshTokenFamilyId,
+    ) -> Result<(), RefreshTokenStoreError> {
+        self.tokens.retain(|_, entry| &entry.family_id != family_id);
+        Ok(())
+    }
+
+    async fn revoke_user_tokens(&mut self, email: &Email) -> Result<(), RefreshTokenStoreError> {
+        self.tokens.retain(|_, entry| &entry.email != email);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+
+    fn email() -> Email {
+        Email::parse(Secret::new("test@example.com".to_owned())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_add_token() {
+        let mut store = HashMapRefreshTokenStore::default();
+        let token = RefreshToken::default();
+        let family_id = RefreshTokenFamilyId::default();
+
+        let result = store
+            .add_token(token.clone(), email(), family_id.clone())
+            .await;
+
+        assert_eq!(result, Ok(()));
+        let entry = store.tokens.get(&token.hash()).unwrap();
+        assert_eq!(entry.email, email());
+        assert_eq!(entry.family_id, family_id);
+        assert!(!entry.used);
+    }
+
+    #[tokio::test]
+    async fn test_consume_token() {
+        let mut store = HashMapRefreshTokenStore::default();
+        let token = RefreshToken::default();
+        let family_id = RefreshTokenFamilyId::default();
+        store
+            .add_token(token.clone(), email(), family_id.clone())
+            .await
+            .unwrap();
+
+        let result = store.consume_token(&token).await;
+
+        assert_eq!(result, Ok((email(), family_id)));
+        assert!(store.tokens.get(&token.hash()).unwrap().used);
+    }
+
+    #[tokio::test]
+    async fn test_consume_token_twice_is_reported_as_reuse() {
+        let mut store = HashMapRefreshTokenStore::default();
+        let token = RefreshToken::default();
+        let family_id = RefreshTokenFamilyId::default();
+        store
+            .add_token(token.clone(), email(), family_id.clone())
+            .await
+            .unwrap();
+        store.consume_token(&token).await.unwrap();
+
+        let result = store.consume_token(&token).await;
+
+        assert_eq!(result, Err(RefreshTokenStoreError::TokenReused(family_id)));
+    }
+
+    #[tokio::test]
+    async fn test_consume_token_not_found() {
+        let mut store = HashMapRefreshTokenStore::default();
+
+        let result = store.consume_token(&RefreshToken::default()).await;
+
+        assert_eq!(result, Err(RefreshTokenStoreError::TokenNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_consume_expired_token() {
+        let mut store = HashMapRefreshTokenStore::default();
+        let token = RefreshToken::default();
+        store.tokens.insert(
+            token.hash(),
+            RefreshTokenEntry {
+                email: email(),
+                family_id: RefreshTokenFamilyId::default(),
+                used: false,
+                expires_at: Utc::now() - Duration::seconds(1),
+            },
+        );
+
+        let result = store.consume_token(&token).await;
+
+        assert_eq!(result, Err(RefreshTokenStoreError::TokenNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_get_family_does_not_use_up_token() {
+        let mut store = HashMapRefreshTokenStore::default();
+        let token = RefreshToken::default();
+        let family_id = RefreshTokenFamilyId::default();
+        store
+            .add_token(token.clone(), email(), family_id.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(store.get_family(&token).await, Ok(family_id.clone()));
+        assert!(!store.tokens.get(&token.hash()).unwrap().used);
+
+        store.consume_token(&token).await.unwrap();
+
+        assert_eq!(store.get_family(&token).await, Ok(family_id));
+        assert_eq!(
+            store.get_family(&RefreshToken::default()).await,
+            Err(RefreshTokenStoreError::TokenNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_revoke_family() {
+        let mut store = HashMapRefreshTokenStore::default();
+        let family_id = RefreshTokenFamilyId::default();
+        let first = RefreshToken::default();
+        let second = RefreshToken::default();
+        let other = RefreshToken::default();
+        store
+            .add_token(first.clone(), email(), family_id.clone())
+            .await
+            .unwrap();
+        store
+            .add_token(second.clone(), email(), family_id.clone())
+            .await
+            .unwrap();
+        store
+            .add_token(other.clone(), email(), RefreshTokenFamilyId::default())
+            .await
+            .unwrap();
+
+        let result = store.revoke_family(&family_id).await;
+
+        assert!(result.is_ok());
+        assert!(!store.tokens.contains_key(&first.hash()));
+        assert!(!store.tokens.contains_key(&second.hash()));
+        assert!(store.tokens.contains_key(&other.hash()));
+    }
+
+    #[tokio::test]
+    async fn test_revoke_user_tokens() {
+        let mut store = HashMapRefreshTokenStore::default();
+        let other_email = Email::parse(Secret::new("other@example.com".to_owned())).unwrap();
+        let first = RefreshToken::default();
+        let second = RefreshToken::default();
+        let other = RefreshToken::default();
+        store
+            .add_token(first.clone(), email(), RefreshTokenFamilyId::default())
+            .await
+            .unwrap();
+        store
+            .add_token(second.clone(), email(), RefreshTokenFamilyId::default())
+            .await
+            .unwrap();
+        store
+            .add_token(other.clone(), other_email, RefreshTokenFamilyId::default())
+            .await
+            .unwrap();
+
+        let result = store.revoke_user_tokens(&email()).await;
+
+        assert!(result.is_ok());
+        assert!(!store.tokens.contains_key(&first.hash()));
+        assert!(!store.tokens.contains_key(&second.hash()));
+        assert!(store.tokens.contains_key(&other.hash()));
+    }
+}
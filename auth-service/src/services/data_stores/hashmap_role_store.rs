@@ -0,0 +1,146 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::domain::{
+    data_stores::{RoleStore, RoleStoreError},
+    Permission, Role, UserId,
+};
+
+#[derive(Default)]
+pub struct HashMapRoleStore {
+    roles: HashMap<UserId, BTreeSet<Role>>,
+    permissions: HashMap<UserId, BTreeSet<Permission>>,
+}
+
+#[async_trait::async_trait]
+impl RoleStore for HashMapRoleStore {
+    async fn grant_role(&mut self, user_id: &UserId, role: &Role) -> Result<(), RoleStoreError> {
+        self.roles.entry(*user_id).or_default().insert(role.clone());
+        Ok(())
+    }
+
+    async fn revoke_role(&mut self, user_id: &UserId, role: &Role) -> Result<(), RoleStoreError> {
+        if let Some(roles) = self.roles.get_mut(user_id) {
+            roles.remove(role);
+        }
+        Ok(())
+    }
+
+    async fn get_roles(&self, user_id: &UserId) -> Result<Vec<Role>, RoleStoreError> {
+        Ok(self
+            .roles
+            .get(user_id)
+            .map(|roles| roles.iter().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    async fn grant_permission(
+        &mut self,
+        user_id: &UserId,
+        permission: &Permission,
+    ) -> Result<(), RoleStoreError> {
+        self.permissions
+            .entry(*user_id)
+            .or_default()
+            .insert(permission.clone());
+        Ok(())
+    }
+
+    async fn revoke_permission(
+        &mut self,
+        user_id: &UserId,
+        permission: &Permission,
+    ) -> Result<(), RoleStoreError> {
+        if let Some(permissions) = self.permissions.get_mut(user_id) {
+            permissions.remove(permission);
+        }
+        Ok(())
+    }
+
+    async fn get_permissions(&self, user_id: &UserId) -> Result<Vec<Permission>, RoleStoreError> {
+        Ok(self
+            .permissions
+            .get(user_id)
+            .map(|permissions| permissions.iter().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    async fn revoke_all(&mut self, user_id: &UserId) -> Result<(), RoleStoreError> {
+        self.roles.remove(user_id);
+        self.permissions.remove(user_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn role(name: &str) -> Role {
+        Role::parse(name.to_owned()).unwrap()
+    }
+
+    fn permission(name: &str) -> Permission {
+        Permission::parse(name.to_owned()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_grant_and_revoke_role() {
+        let mut store = HashMapRoleStore::default();
+        let user_id = UserId::default();
+
+        // Granting twice is the same as granting once
+        store.grant_role(&user_id, &role("editor")).await.unwrap();
+        store.grant_role(&user_id, &role("admin")).await.unwrap();
+        store.grant_role(&user_id, &role("admin")).await.unwrap();
+        assert_eq!(
+            store.get_roles(&user_id).await,
+            Ok(vec![role("admin"), role("editor")])
+        );
+
+        store.revoke_role(&user_id, &role("admin")).await.unwrap();
+        assert_eq!(store.get_roles(&user_id).await, Ok(vec![role("editor")]));
+
+        // Revoking what was never granted does nothing
+        let result = store.revoke_role(&user_id, &role("admin")).await;
+        assert_eq!(result, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_grant_and_revoke_permission() {
+        let mut store = HashMapRoleStore::default();
+        let user_id = UserId::default();
+        let other_user_id = UserId::default();
+
+        store
+            .grant_permission(&user_id, &permission("reports:read"))
+            .await
+            .unwrap();
+        assert_eq!(
+            store.get_permissions(&user_id).await,
+            Ok(vec![permission("reports:read")])
+        );
+        assert_eq!(store.get_permissions(&other_user_id).await, Ok(vec![]));
+
+        store
+            .revoke_permission(&user_id, &permission("reports:read"))
+            .await
+            .unwrap();
+        assert_eq!(store.get_permissions(&user_id).await, Ok(vec![]));
+    }
+
+    #[tokio::test]
+    async fn test_revoke_all() {
+        let mut store = HashMapRoleStore::default();
+        let user_id = UserId::default();
+        store.grant_role(&user_id, &role("admin")).await.unwrap();
+        store
+            .grant_permission(&user_id, &permission("reports:read"))
+            .await
+            .unwrap();
+
+        store.revoke_all(&user_id).await.unwrap();
+
+        assert_eq!(store.get_roles(&user_id).await, Ok(vec![]));
+        assert_eq!(store.get_permissions(&user_id).await, Ok(vec![]));
+    }
+}
@@ -0,0 +1,195 @@
+use std::{cmp::Reverse, collections::HashMap};
+
+use chrono::{Duration, Utc};
+
+use crate::{
+    domain::{
+        data_stores::{RefreshTokenFamilyId, SessionStore, SessionStoreError},
+        Email, Session, SessionClient,
+    },
+    utils::auth::REFRESH_TOKEN_TTL_SECONDS,
+};
+
+#[derive(Default)]
+pub struct HashMapSessionStore {
+    sessions: HashMap<RefreshTokenFamilyId, Session>,
+}
+
+// A session whose refresh token hasn't been used within its lifetime can't be resumed
+fn is_live(session: &Session) -> bool {
+    session.last_seen_at + Duration::seconds(REFRESH_TOKEN_TTL_SECONDS) > Utc::now()
+}
+
+#[async_trait::async_trait]
+impl SessionStore for HashMapSessionStore {
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError> {
+        self.sessions.insert(session.id.clone(), session);
+        Ok(())
+    }
+
+    async fn touch_session(
+        &mut self,
+        id: &RefreshTokenFamilyId,
+        client: SessionClient,
+    ) -> Result<(), SessionStoreError> {
+        match self.sessions.get_mut(id) {
+            Some(session) if is_live(session) => {
+                session.client = client;
+                session.last_seen_at = Utc::now();
+                Ok(())
+            }
+            _ => Err(SessionStoreError::SessionNotFound),
+        }
+    }
+
+    async fn get_session(&self, id: &RefreshTokenFamilyId) -> Result<Session, SessionStoreError> {
+        self.sessions
+            .get(id)
+            .filter(|session| is_live(session))
+            .cloned()
+            .ok_or(SessionStoreError::SessionNotFound)
+    }
+
+    async fn get_user_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError> {
+        let mut sessions: Vec<Session> = self
+            .sessions
+            .values()
+            .filter(|session| &session.email == email && is_live(session))
+            .cloned()
+            .collect();
+        sessions.sort_by_key(|session| Reverse(session.last_seen_at));
+        Ok(sessions)
+    }
+
+    async fn remove_session(&mut self, id: &RefreshTokenFamilyId) -> Result<(), SessionStoreError> {
+        self.sessions.remove(id);
+        Ok(())
+    }
+
+    async fn remove_user_sessions(&mut self, email: &Email) -> Result<(), SessionStoreError> {
+        self.sessions.retain(|_, session| &session.email != email);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use secrecy::Secret;
+
+    use super::*;
+
+    fn email(email: &str) -> Email {
+        Email::parse(Secret::new(email.to_owned())).unwrap()
+    }
+
+    fn client(user_agent: &str) -> SessionClient {
+        SessionClient::new(Some(user_agent), IpAddr::V4(Ipv4Addr::LOCALHOST))
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_session() {
+        let mut store = HashMapSessionStore::default();
+        let session = Session::new(
+            RefreshTokenFamilyId::default(),
+            email("test@example.com"),
+            client("Firefox"),
+        );
+
+        store.add_session(session.clone()).await.unwrap();
+
+        assert_eq!(store.get_session(&session.id).await, Ok(session));
+        assert_eq!(
+            store.get_session(&RefreshTokenFamilyId::default()).await,
+            Err(SessionStoreError::SessionNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_touch_session() {
+        let mut store = HashMapSessionStore::default();
+        let session = Session::new(
+            RefreshTokenFamilyId::default(),
+            email("test@example.com"),
+            client("Firefox"),
+        );
+        store.add_session(session.clone()).await.unwrap();
+
+        store
+            .touch_session(&session.id, client("Chrome"))
+            .await
+            .unwrap();
+
+        let touched = store.get_session(&session.id).await.unwrap();
+        assert_eq!(touched.client, client("Chrome"));
+        assert_eq!(touched.created_at, session.created_at);
+        assert!(touched.last_seen_at >= session.last_seen_at);
+
+        let result = store
+            .touch_session(&RefreshTokenFamilyId::default(), client("Chrome"))
+            .await;
+        assert_eq!(result, Err(SessionStoreError::SessionNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_expired_sessions_are_hidden() {
+        let mut store = HashMapSessionStore::default();
+        let mut session = Session::new(
+            RefreshTokenFamilyId::default(),
+            email("test@example.com"),
+            client("Firefox"),
+        );
+        session.last_seen_at -= Duration::seconds(REFRESH_TOKEN_TTL_SECONDS + 1);
+        store.add_session(session.clone()).await.unwrap();
+
+        assert_eq!(
+            store.get_session(&session.id).await,
+            Err(SessionStoreError::SessionNotFound)
+        );
+        assert_eq!(store.get_user_sessions(&session.email).await, Ok(vec![]));
+    }
+
+    #[tokio::test]
+    async fn test_get_user_sessions() {
+        let mut store = HashMapSessionStore::default();
+        let user = email("test@example.com");
+
+        let mut older = Session::new(RefreshTokenFamilyId::default(), user.clone(), client("A"));
+        older.last_seen_at -= Duration::seconds(60);
+        let newer = Session::new(RefreshTokenFamilyId::default(), user.clone(), client("B"));
+        let other = Session::new(
+            RefreshTokenFamilyId::default(),
+            email("other@example.com"),
+            client("C"),
+        );
+
+        for session in [older.clone(), newer.clone(), other.clone()] {
+            store.add_session(session).await.unwrap();
+        }
+
+        assert_eq!(store.get_user_sessions(&user).await, Ok(vec![newer, older]));
+
+        store.remove_user_sessions(&user).await.unwrap();
+        assert_eq!(store.get_user_sessions(&user).await, Ok(vec![]));
+        assert_eq!(store.get_session(&other.id).await, Ok(other));
+    }
+
+    #[tokio::test]
+    async fn test_remove_session() {
+        let mut store = HashMapSessionStore::default();
+        let session = Session::new(
+            RefreshTokenFamilyId::default(),
+            email("test@example.com"),
+            client("Firefox"),
+        );
+        store.add_session(session.clone()).await.unwrap();
+
+        store.remove_session(&session.id).await.unwrap();
+
+        assert_eq!(
+            store.get_session(&session.id).await,
+            Err(SessionStoreError::SessionNotFound)
+        );
+    }
+}
@@ -1,10 +1,15 @@
 use std::collections::HashMap;
 
-use crate::domain::{Email, Password, User, UserStore, UserStoreError};
+use secrecy::ExposeSecret;
+
+use crate::domain::{Email, Password, TotpSecret, User, UserId, UserStore, UserStoreError};
 
 #[derive(Default)]
 pub struct HashMapUserStore {
     users: HashMap<Email, User>,
+    totp_secrets: HashMap<Email, TotpSecret>,
+    totp_last_steps: HashMap<Email, u64>,
+    token_generations: HashMap<Email, i64>,
 }
 
 #[async_trait::async_trait]
@@ -26,6 +31,14 @@ impl UserStore for HashMapUserStore {
         }
     }
 
+    async fn get_user_by_id(&self, id: &UserId) -> Result<User, UserStoreError> {
+        self.users
+            .values()
+            .find(|user| &user.id == id)
+            .cloned()
+            .ok_or(UserStoreError::UserNotFound)
+    }
+
     async fn validate_user(
         &self,
         email: &Email,
@@ -42,6 +55,207 @@ impl UserStore for HashMapUserStore {
             None => Err(UserStoreError::UserNotFound),
         }
     }
+
+    async fn update_password(
+        &mut self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some(user) => {
+                user.password = password;
+                Ok(())
+            }
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn mark_verified(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some(user) => {
+                user.verified = true;
+                Ok(())
+            }
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn update_email(
+        &mut self,
+        email: &Email,
+        new_email: &Email,
+        token_generation: i64,
+    ) -> Result<(), UserStoreError> {
+        if self.get_token_generation(email).await? != token_generation {
+            return Err(UserStoreError::InvalidCredentials);
+        }
+        if self.users.contains_key(new_email) {
+            return Err(UserStoreError::UserAlreadyExists);
+        }
+        let mut user = self
+            .users
+            .remove(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        user.email = new_email.clone();
+        user.verified = true;
+        self.users.insert(new_email.clone(), user);
+
+        if let Some(secret) = self.totp_secrets.remove(email) {
+            self.totp_secrets.insert(new_email.clone(), secret);
+        }
+        if let Some(step) = self.totp_last_steps.remove(email) {
+            self.totp_last_steps.insert(new_email.clone(), step);
+        }
+        self.token_generations.remove(email);
+        self.token_generations
+            .insert(new_email.clone(), token_generation + 1);
+        Ok(())
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        self.users
+            .remove(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        self.totp_secrets.remove(email);
+        self.totp_last_steps.remove(email);
+        self.token_generations.remove(email);
+        Ok(())
+    }
+
+    async fn set_totp_secret(
+        &mut self,
+        email: &Email,
+        secret: TotpSecret,
+    ) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some(user) => {
+                user.totp_enabled = false;
+                self.totp_secrets.insert(email.clone(), secret);
+                self.totp_last_steps.remove(email);
+                Ok(())
+            }
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn get_totp_secret(&self, email: &Email) -> Result<Option<TotpSecret>, UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        Ok(self.totp_secrets.get(email).cloned())
+    }
+
+    async fn enable_totp(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some(user) if self.totp_secrets.contains_key(email) => {
+                user.totp_enabled = true;
+                user.requires_2fa = true;
+                Ok(())
+            }
+            _ => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn record_totp_step(&mut self, email: &Email, step: u64) -> Result<bool, UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        match self.totp_last_steps.get(email) {
+            Some(&last_step) if last_step >= step => Ok(false),
+            _ => {
+                self.totp_last_steps.insert(email.clone(), step);
+                Ok(true)
+            }
+        }
+    }
+
+    async fn get_token_generation(&self, email: &Email) -> Result<i64, UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        Ok(self
+            .token_generations
+            .get(email)
+            .copied()
+            .unwrap_or_default())
+    }
+
+    async fn increment_token_generation(&mut self, email: &Email) -> Result<i64, UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        let generation = self.token_generations.entry(email.clone()).or_default();
+        *generation += 1;
+        Ok(*generation)
+    }
+
+    async fn list_users(
+        &self,
+        email_search: Option<&str>,
+        offset: u32,
+        limit: u32,
+    ) -> Result<Vec<User>, UserStoreError> {
+        let mut users = self.matching_users(email_search);
+        users.sort_by(|a, b| {
+            a.email
+                .as_ref()
+                .expose_secret()
+                .cmp(b.email.as_ref().expose_secret())
+        });
+
+        Ok(users
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn count_users(&self, email_search: Option<&str>) -> Result<usize, UserStoreError> {
+        Ok(self.matching_users(email_search).len())
+    }
+
+    async fn set_disabled(&mut self, email: &Email, disabled: bool) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some(user) => {
+                user.disabled = disabled;
+                Ok(())
+            }
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn set_requires_2fa(
+        &mut self,
+        email: &Email,
+        requires_2fa: bool,
+    ) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some(user) => {
+                user.requires_2fa = requires_2fa;
+                Ok(())
+            }
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+}
+
+impl HashMapUserStore {
+    fn matching_users(&self, email_search: Option<&str>) -> Vec<&User> {
+        let email_search = email_search.map(str::to_lowercase);
+        self.users
+            .values()
+            .filter(|user| match &email_search {
+                Some(search) => user
+                    .email
+                    .as_ref()
+                    .expose_secret()
+                    .to_lowercase()
+                    .contains(search.as_str()),
+                None => true,
+            })
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -90,6 +304,26 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_get_user_by_id() {
+        let mut user_store = HashMapUserStore::default();
+        let email = Email::parse(Secret::new("test@test.com".to_string())).unwrap();
+        let user = User::new(
+            email,
+            Password::parse(Secret::new("password".to_owned())).unwrap(),
+            false,
+        );
+        user_store.add_user(user.clone()).await.unwrap();
+
+        // Test getting a user that exists
+        let result = user_store.get_user_by_id(&user.id).await;
+        assert_eq!(result, Ok(user));
+
+        // Test getting a user that doesn't exist
+        let result = user_store.get_user_by_id(&UserId::default()).await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+    }
+
     #[tokio::test]
     async fn test_validate_user() {
         let mut user_store = HashMapUserStore::default();
@@ -116,4 +350,287 @@ mod tests {
             .await;
         assert_eq!(result, Err(UserStoreError::UserNotFound));
     }
+
+    #[tokio::test]
+    async fn test_update_password() {
+        let mut user_store = HashMapUserStore::default();
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        let password = Password::parse(Secret::new("password".to_owned())).unwrap();
+        let user = User::new(email.clone(), password.clone(), false);
+        user_store.add_user(user).await.unwrap();
+
+        // Test updating the password of an existing user
+        let new_password = Password::parse(Secret::new("new_password".to_owned())).unwrap();
+        let result = user_store
+            .update_password(&email, new_password.clone())
+            .await;
+        assert!(result.is_ok());
+        assert_eq!(
+            user_store.validate_user(&email, &password).await,
+            Err(UserStoreError::InvalidCredentials)
+        );
+        assert!(user_store
+            .validate_user(&email, &new_password)
+            .await
+            .is_ok());
+
+        // Test updating the password of a user that doesn't exist
+        let result = user_store
+            .update_password(
+                &Email::parse(Secret::new("nonexistant@test.com".to_owned())).unwrap(),
+                new_password,
+            )
+            .await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_mark_verified() {
+        let mut user_store = HashMapUserStore::default();
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        let password = Password::parse(Secret::new("password".to_owned())).unwrap();
+        let user = User::new(email.clone(), password, false);
+        user_store.add_user(user).await.unwrap();
+
+        // New users start out unverified
+        assert!(!user_store.get_user(&email).await.unwrap().verified);
+
+        // Test verifying an existing user
+        let result = user_store.mark_verified(&email).await;
+        assert!(result.is_ok());
+        assert!(user_store.get_user(&email).await.unwrap().verified);
+
+        // Test verifying a user that doesn't exist
+        let result = user_store
+            .mark_verified(&Email::parse(Secret::new("nonexistant@test.com".to_owned())).unwrap())
+            .await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_update_email() {
+        let mut user_store = HashMapUserStore::default();
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        let new_email = Email::parse(Secret::new("new@test.com".to_owned())).unwrap();
+        let password = Password::parse(Secret::new("password".to_owned())).unwrap();
+        let user = User::new(email.clone(), password.clone(), false);
+        user_store.add_user(user).await.unwrap();
+        user_store.increment_token_generation(&email).await.unwrap();
+
+        // Test a change approved under an earlier token generation
+        let result = user_store.update_email(&email, &new_email, 0).await;
+        assert_eq!(result, Err(UserStoreError::InvalidCredentials));
+
+        let result = user_store.update_email(&email, &new_email, 1).await;
+        assert!(result.is_ok());
+
+        // The user and everything stored with it moves to the new email
+        assert_eq!(
+            user_store.get_user(&email).await,
+            Err(UserStoreError::UserNotFound)
+        );
+        let user = user_store.get_user(&new_email).await.unwrap();
+        assert_eq!(user.email, new_email);
+        assert!(user.verified);
+        assert_eq!(user_store.get_token_generation(&new_email).await, Ok(2));
+
+        // Test moving to an email that is already taken
+        let other_email = Email::parse(Secret::new("other@test.com".to_owned())).unwrap();
+        let other_user = User::new(other_email.clone(), password, false);
+        user_store.add_user(other_user).await.unwrap();
+        let result = user_store.update_email(&new_email, &other_email, 2).await;
+        assert_eq!(result, Err(UserStoreError::UserAlreadyExists));
+
+        // Test a user that doesn't exist
+        let result = user_store
+            .update_email(
+                &email,
+                &Email::parse(Secret::new("unused@test.com".to_owned())).unwrap(),
+                0,
+            )
+            .await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_delete_user() {
+        let mut user_store = HashMapUserStore::default();
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        let password = Password::parse(Secret::new("password".to_owned())).unwrap();
+        let user = User::new(email.clone(), password.clone(), false);
+        user_store.add_user(user).await.unwrap();
+        user_store.increment_token_generation(&email).await.unwrap();
+
+        let result = user_store.delete_user(&email).await;
+        assert!(result.is_ok());
+        assert_eq!(
+            user_store.get_user(&email).await,
+            Err(UserStoreError::UserNotFound)
+        );
+
+        // Signing up again with the same email starts from scratch
+        let user = User::new(email.clone(), password, false);
+        user_store.add_user(user).await.unwrap();
+        assert_eq!(user_store.get_token_generation(&email).await, Ok(0));
+
+        // Test a user that doesn't exist
+        let result = user_store
+            .delete_user(&Email::parse(Secret::new("nonexistant@test.com".to_owned())).unwrap())
+            .await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_list_users() {
+        let mut user_store = HashMapUserStore::default();
+        let password = Password::parse(Secret::new("password".to_owned())).unwrap();
+        for email in ["carol@test.com", "alice@test.com", "bob@example.com"] {
+            let email = Email::parse(Secret::new(email.to_owned())).unwrap();
+            let user = User::new(email, password.clone(), false);
+            user_store.add_user(user).await.unwrap();
+        }
+
+        let emails = |users: Vec<User>| -> Vec<String> {
+            users
+                .iter()
+                .map(|user| user.email.as_ref().expose_secret().to_owned())
+                .collect()
+        };
+
+        // Users come ordered by email, a page at a time
+        let result = user_store.list_users(None, 0, 2).await.unwrap();
+        assert_eq!(emails(result), ["alice@test.com", "bob@example.com"]);
+        let result = user_store.list_users(None, 2, 2).await.unwrap();
+        assert_eq!(emails(result), ["carol@test.com"]);
+        assert_eq!(user_store.count_users(None).await, Ok(3));
+
+        // Searching ignores case
+        let result = user_store
+            .list_users(Some("TEST.com"), 0, 10)
+            .await
+            .unwrap();
+        assert_eq!(emails(result), ["alice@test.com", "carol@test.com"]);
+        assert_eq!(user_store.count_users(Some("TEST.com")).await, Ok(2));
+    }
+
+    #[tokio::test]
+    async fn test_admin_updates() {
+        let mut user_store = HashMapUserStore::default();
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        let password = Password::parse(Secret::new("password".to_owned())).unwrap();
+        let user = User::new(email.clone(), password, false);
+        user_store.add_user(user).await.unwrap();
+
+        user_store.set_disabled(&email, true).await.unwrap();
+        user_store.set_requires_2fa(&email, true).await.unwrap();
+
+        let user = user_store.get_user(&email).await.unwrap();
+        assert!(user.disabled);
+        assert!(user.requires_2fa);
+
+        // Test a user that doesn't exist
+        let email = Email::parse(Secret::new("nonexistant@test.com".to_owned())).unwrap();
+        assert_eq!(
+            user_store.set_disabled(&email, true).await,
+            Err(UserStoreError::UserNotFound)
+        );
+        assert_eq!(
+            user_store.set_requires_2fa(&email, true).await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_totp_enrolment() {
+        let mut user_store = HashMapUserStore::default();
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        let password = Password::parse(Secret::new("password".to_owned())).unwrap();
+        let user = User::new(email.clone(), password, false);
+        user_store.add_user(user).await.unwrap();
+
+        // No secret until enrolment starts
+        assert_eq!(user_store.get_totp_secret(&email).await, Ok(None));
+
+        let secret = TotpSecret::default();
+        let result = user_store.set_totp_secret(&email, secret.clone()).await;
+        assert!(result.is_ok());
+        assert_eq!(user_store.get_totp_secret(&email).await, Ok(Some(secret)));
+
+        // The secret is pending until confirmed
+        let user = user_store.get_user(&email).await.unwrap();
+        assert!(!user.totp_enabled);
+        assert!(!user.requires_2fa);
+
+        let result = user_store.enable_totp(&email).await;
+        assert!(result.is_ok());
+        let user = user_store.get_user(&email).await.unwrap();
+        assert!(user.totp_enabled);
+        assert!(user.requires_2fa);
+
+        // Test enrolling a user that doesn't exist
+        let result = user_store
+            .set_totp_secret(
+                &Email::parse(Secret::new("nonexistant@test.com".to_owned())).unwrap(),
+                TotpSecret::default(),
+            )
+            .await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_record_totp_step() {
+        let mut user_store = HashMapUserStore::default();
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        let password = Password::parse(Secret::new("password".to_owned())).unwrap();
+        let user = User::new(email.clone(), password, false);
+        user_store.add_user(user).await.unwrap();
+
+        assert_eq!(user_store.record_totp_step(&email, 10).await, Ok(true));
+
+        // Codes for the same or an earlier step can't be used again
+        assert_eq!(user_store.record_totp_step(&email, 10).await, Ok(false));
+        assert_eq!(user_store.record_totp_step(&email, 9).await, Ok(false));
+        assert_eq!(user_store.record_totp_step(&email, 11).await, Ok(true));
+
+        // A new secret starts afresh
+        user_store
+            .set_totp_secret(&email, TotpSecret::default())
+            .await
+            .unwrap();
+        assert_eq!(user_store.record_totp_step(&email, 5).await, Ok(true));
+
+        // Test recording a step for a user that doesn't exist
+        let result = user_store
+            .record_totp_step(
+                &Email::parse(Secret::new("nonexistant@test.com".to_owned())).unwrap(),
+                5,
+            )
+            .await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_token_generation() {
+        let mut user_store = HashMapUserStore::default();
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        let password = Password::parse(Secret::new("password".to_owned())).unwrap();
+        let user = User::new(email.clone(), password, false);
+        user_store.add_user(user).await.unwrap();
+
+        assert_eq!(user_store.get_token_generation(&email).await, Ok(0));
+        assert_eq!(user_store.increment_token_generation(&email).await, Ok(1));
+        assert_eq!(user_store.increment_token_generation(&email).await, Ok(2));
+        assert_eq!(user_store.get_token_generation(&email).await, Ok(2));
+
+        // Test a user that doesn't exist
+        let nonexistent = Email::parse(Secret::new("nonexistant@test.com".to_owned())).unwrap();
+        assert_eq!(
+            user_store.get_token_generation(&nonexistent).await,
+            Err(UserStoreError::UserNotFound)
+        );
+        assert_eq!(
+            user_store.increment_token_generation(&nonexistent).await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
 }
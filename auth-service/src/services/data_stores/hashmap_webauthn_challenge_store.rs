@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::{
+    domain::{
+        data_stores::{WebAuthnChallengeStore, WebAuthnChallengeStoreError},
+        Email, WebAuthnCeremony, WebAuthnChallenge,
+    },
+    utils::webauthn::WEBAUTHN_CHALLENGE_TTL_SECONDS,
+};
+
+#[derive(Default)]
+pub struct HashMapWebAuthnChallengeStore {
+    challenges: HashMap<String, (Email, WebAuthnCeremony, DateTime<Utc>)>,
+}
+
+#[async_trait::async_trait]
+impl WebAuthnChallengeStore for HashMapWebAuthnChallengeStore {
+    async fn add_challenge(
+        &mut self,
+        challenge: WebAuthnChallenge,
+        email: Email,
+        ceremony: WebAuthnCeremony,
+    ) -> Result<(), WebAuthnChallengeStoreError> {
+        let expires_at = Utc::now() + Duration::seconds(WEBAUTHN_CHALLENGE_TTL_SECONDS);
+        self.challenges
+            .insert(challenge.hash(), (email, ceremony, expires_at));
+        Ok(())
+    }
+
+    async fn take_challenge(
+        &mut self,
+        challenge: &WebAuthnChallenge,
+    ) -> Result<(Email, WebAuthnCeremony), WebAuthnChallengeStoreError> {
+        match self.challenges.remove(&challenge.hash()) {
+            Some((email, ceremony, expires_at)) if expires_at > Utc::now() => Ok((email, ceremony)),
+            _ => Err(WebAuthnChallengeStoreError::ChallengeNotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_take_challenge_can_only_be_used_once() {
+        let mut store = HashMapWebAuthnChallengeStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let challenge = WebAuthnChallenge::default();
+        store
+            .add_challenge(
+                challenge.clone(),
+                email.clone(),
+                WebAuthnCeremony::Authentication,
+            )
+            .await
+            .unwrap();
+
+        let result = store.take_challenge(&challenge).await;
+        assert_eq!(result, Ok((email, WebAuthnCeremony::Authentication)));
+
+        let result = store.take_challenge(&challenge).await;
+        assert_eq!(result, Err(WebAuthnChallengeStoreError::ChallengeNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_take_expired_challenge() {
+        let mut store = HashMapWebAuthnChallengeStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let challenge = WebAuthnChallenge::default();
+        store.challenges.insert(
+            challenge.hash(),
+            (
+                email,
+                WebAuthnCeremony::Registration,
+                Utc::now() - Duration::seconds(1),
+            ),
+        );
+
+        let result = store.take_challenge(&challenge).await;
+
+        assert_eq!(result, Err(WebAuthnChallengeStoreError::ChallengeNotFound));
+    }
+}
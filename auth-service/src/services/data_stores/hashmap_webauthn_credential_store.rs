@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{WebAuthnCredentialStore, WebAuthnCredentialStoreError},
+    Email, WebAuthnCredential, WebAuthnCredentialId,
+};
+
+#[derive(Default)]
+pub struct HashMapWebAuthnCredentialStore {
+    credentials: HashMap<WebAuthnCredentialId, WebAuthnCredential>,
+}
+
+#[async_trait::async_trait]
+impl WebAuthnCredentialStore for HashMapWebAuthnCredentialStore {
+    async fn add_credential(
+        &mut self,
+        credential: WebAuthnCredential,
+    ) -> Result<(), WebAuthnCredentialStoreError> {
+        if self.credentials.contains_key(&credential.credential_id) {
+            return Err(WebAuthnCredentialStoreError::CredentialAlreadyExists);
+        }
+        self.credentials
+            .insert(credential.credential_id.clone(), credential);
+        Ok(())
+    }
+
+    async fn get_credential(
+        &self,
+        credential_id: &WebAuthnCredentialId,
+    ) -> Result<WebAuthnCredential, WebAuthnCredentialStoreError> {
+        self.credentials
+            .get(credential_id)
+            .cloned()
+            .ok_or(WebAuthnCredentialStoreError::CredentialNotFound)
+    }
+
+    async fn get_user_credentials(
+        &self,
+        email: &Email,
+    ) -> Result<Vec<WebAuthnCredential>, WebAuthnCredentialStoreError> {
+        Ok(self
+            .credentials
+            .values()
+            .filter(|credential| &credential.email == email)
+            .cloned()
+            .collect())
+    }
+
+    async fn update_sign_count(
+        &mut self,
+        credential_id: &WebAuthnCredentialId,
+        sign_count: u32,
+    ) -> Result<(), WebAuthnCredentialStoreError> {
+        let credential = self
+            .credentials
+            .get_mut(credential_id)
+            .ok_or(WebAuthnCredentialStoreError::CredentialNotFound)?;
+        credential.sign_count = sign_count;
+        Ok(())
+    }
+
+    async fn remove_user_credentials(
+        &mut self,
+        email: &Email,
+    ) -> Result<(), WebAuthnCredentialStoreError> {
+        self.credentials
+            .retain(|_, credential| &credential.email != email);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+
+    fn credential(id: &[u8], email: &str) -> WebAuthnCredential {
+        WebAuthnCredential {
+            credential_id: WebAuthnCredentialId::from_bytes(id).unwrap(),
+            email: Email::parse(Secret::new(email.to_owned())).unwrap(),
+            public_key: vec![4; 65],
+            sign_count: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_credential() {
+        let mut store = HashMapWebAuthnCredentialStore::default();
+        let credential = credential(&[1], "test@example.com");
+
+        let result = store.add_credential(credential.clone()).await;
+        assert_eq!(result, Ok(()));
+
+        let result = store.add_credential(credential).await;
+        assert_eq!(
+            result,
+            Err(WebAuthnCredentialStoreError::CredentialAlreadyExists)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_credential() {
+        let mut store = HashMapWebAuthnCredentialStore::default();
+        let credential = credential(&[1], "test@example.com");
+        store.add_credential(credential.clone()).await.unwrap();
+
+        let result = store.get_credential(&credential.credential_id).await;
+        assert_eq!(result, Ok(credential));
+
+        let other_id = WebAuthnCredentialId::from_bytes(&[2]).unwrap();
+        let result = store.get_credential(&other_id).await;
+        assert_eq!(
+            result,
+            Err(WebAuthnCredentialStoreError::CredentialNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_user_credentials() {
+        let mut store = HashMapWebAuthnCredentialStore::default();
+        let mine = credential(&[1], "test@example.com");
+        let theirs = credential(&[2], "other@example.com");
+        store.add_credential(mine.clone()).await.unwrap();
+        store.add_credential(theirs).await.unwrap();
+
+        let result = store.get_user_credentials(&mine.email).await;
+
+        assert_eq!(result, Ok(vec![mine]));
+    }
+
+    #[tokio::test]
+    async fn test_remove_user_credentials() {
+        let mut store = HashMapWebAuthnCredentialStore::default();
+        let mine = credential(&[1], "test@example.com");
+        let theirs = credential(&[2], "other@example.com");
+        store.add_credential(mine.clone()).await.unwrap();
+        store.add_credential(theirs.clone()).await.unwrap();
+
+        let result = store.remove_user_credentials(&mine.email).await;
+        assert_eq!(result, Ok(()));
+
+        assert_eq!(store.get_user_credentials(&mine.email).await, Ok(vec![]));
+        assert_eq!(
+            store.get_user_credentials(&theirs.email).await,
+            Ok(vec![theirs])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_sign_count() {
+        let mut store = HashMapWebAuthnCredentialStore::default();
+        let credential = credential(&[1], "test@example.com");
+        store.add_credential(credential.clone()).await.unwrap();
+
+        let result = store.update_sign_count(&credential.credential_id, 5).await;
+        assert_eq!(result, Ok(()));
+
+        let stored = store
+            .get_credential(&credential.credential_id)
+            .await
+            .unwrap();
+        assert_eq!(stored.sign_count, 5);
+    }
+}
@@ -1,26 +1,100 @@
-use std::collections::HashSet;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Weak},
+    time::Duration,
+};
 
+use chrono::Utc;
 use secrecy::{ExposeSecret, Secret};
+use tokio::{sync::RwLock, task::JoinHandle};
 
-use crate::domain::{BannedTokenStore, BannedTokenStoreError};
+use crate::{
+    domain::{BannedTokenStore, BannedTokenStoreError},
+    utils::{
+        auth::{TOKEN_LEEWAY_SECONDS, TOKEN_TTL_SECONDS},
+        constants::BANNED_TOKEN_EVICTION_INTERVAL,
+    },
+};
 
 #[derive(Default)]
 pub struct HashSetBannedTokenStore {
-    banned_tokens: HashSet<String>,
+    // Token `jti` or session `sid` to the time the ban can be forgotten
+    banned_tokens: HashMap<String, i64>,
+    // Subject to (revocation timestamp, time the revocation can be forgotten)
+    revoked_subjects: HashMap<String, (i64, i64)>,
+}
+
+impl HashSetBannedTokenStore {
+    /// A store to share, e.g. through `AppState`, which evicts expired entries every
+    /// `BANNED_TOKEN_EVICTION_INTERVAL` for as long as it's in use. Must be called within a Tokio runtime.
+    pub fn shared() -> Arc<RwLock<Self>> {
+        let store = Arc::new(RwLock::new(Self::default()));
+        Self::spawn_eviction(&store, BANNED_TOKEN_EVICTION_INTERVAL);
+        store
+    }
+
+    /// Forgets the bans and revocations that only cover tokens which have expired by `now`.
+    pub fn evict_expired(&mut self, now: i64) {
+        self.banned_tokens.retain(|_, expires_at| *expires_at > now);
+        self.revoked_subjects
+            .retain(|_, (_, expires_at)| *expires_at > now);
+    }
+
+    /// Evicts expired entries from `store` every `interval`, until the store is dropped.
+    pub fn spawn_eviction(store: &Arc<RwLock<Self>>, interval: Duration) -> JoinHandle<()> {
+        let store: Weak<RwLock<Self>> = Arc::downgrade(store);
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                let Some(store) = store.upgrade() else {
+                    return;
+                };
+                store.write().await.evict_expired(Utc::now().timestamp());
+            }
+        })
+    }
 }
 
 #[async_trait::async_trait]
 impl BannedTokenStore for HashSetBannedTokenStore {
     async fn add_banned_token(
         &mut self,
-        token: Secret<String>,
+        id: String,
+        expires_at: i64,
     ) -> Result<(), BannedTokenStoreError> {
-        self.banned_tokens.insert(token.expose_secret().to_owned());
+        self.banned_tokens.insert(id, expires_at);
         Ok(())
     }
 
-    async fn is_banned(&self, token: &Secret<String>) -> Result<bool, BannedTokenStoreError> {
-        Ok(self.banned_tokens.contains(token.expose_secret()))
+    async fn is_banned(&self, id: &str) -> Result<bool, BannedTokenStoreError> {
+        Ok(self
+            .banned_tokens
+            .get(id)
+            .is_some_and(|expires_at| *expires_at > Utc::now().timestamp()))
+    }
+
+    async fn revoke_tokens_issued_before(
+        &mut self,
+        subject: Secret<String>,
+        timestamp: i64,
+    ) -> Result<(), BannedTokenStoreError> {
+        // Every token issued before `timestamp` has expired by then
+        let expires_at = timestamp + TOKEN_TTL_SECONDS + TOKEN_LEEWAY_SECONDS as i64;
+        self.revoked_subjects
+            .insert(subject.expose_secret().to_owned(), (timestamp, expires_at));
+        Ok(())
+    }
+
+    async fn get_revocation_timestamp(
+        &self,
+        subject: &Secret<String>,
+    ) -> Result<Option<i64>, BannedTokenStoreError> {
+        Ok(self
+            .revoked_subjects
+            .get(subject.expose_secret())
+            .map(|(timestamp, _)| *timestamp))
     }
 }
 
@@ -31,28 +105,120 @@ mod tests {
     #[tokio::test]
     async fn add_banned_token() {
         let mut banned_token_store = HashSetBannedTokenStore::default();
+        let expires_at = Utc::now().timestamp() + 600;
 
-        // Test adding a new banned token
-        let token = Secret::new("token".to_owned());
-
-        let result = banned_token_store.add_banned_token(token.clone()).await;
+        let result = banned_token_store
+            .add_banned_token("jti".to_owned(), expires_at)
+            .await;
 
         assert!(result.is_ok());
-        assert!(banned_token_store
-            .banned_tokens
-            .contains(token.expose_secret()));
+        assert_eq!(
+            banned_token_store.banned_tokens.get("jti"),
+            Some(&expires_at)
+        );
     }
 
     #[tokio::test]
     async fn is_banned() {
         let mut banned_token_store = HashSetBannedTokenStore::default();
-        let token = Secret::new("token".to_owned());
         banned_token_store
             .banned_tokens
-            .insert(token.expose_secret().to_owned());
+            .insert("jti".to_owned(), Utc::now().timestamp() + 600);
+
+        assert!(banned_token_store.is_banned("jti").await.unwrap());
+        assert!(!banned_token_store.is_banned("other").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn is_banned_after_expiry() {
+        let mut banned_token_store = HashSetBannedTokenStore::default();
+        banned_token_store
+            .banned_tokens
+            .insert("jti".to_owned(), Utc::now().timestamp() - 1);
 
-        let result = banned_token_store.is_banned(&token).await;
+        assert!(!banned_token_store.is_banned("jti").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn evict_expired() {
+        let mut banned_token_store = HashSetBannedTokenStore::default();
+        banned_token_store
+            .add_banned_token("expired".to_owned(), 100)
+            .await
+            .unwrap();
+        banned_token_store
+            .add_banned_token("live".to_owned(), 200)
+            .await
+            .unwrap();
+        banned_token_store
+            .revoke_tokens_issued_before(Secret::new("subject".to_owned()), 100)
+            .await
+            .unwrap();
+
+        banned_token_store.evict_expired(150);
+
+        assert!(!banned_token_store.banned_tokens.contains_key("expired"));
+        assert!(banned_token_store.banned_tokens.contains_key("live"));
+        // Still covers tokens issued before 100 that expire after 150
+        assert_eq!(banned_token_store.revoked_subjects.len(), 1);
+
+        banned_token_store.evict_expired(100 + TOKEN_TTL_SECONDS + TOKEN_LEEWAY_SECONDS as i64);
+        assert!(banned_token_store.revoked_subjects.is_empty());
+    }
+
+    #[tokio::test]
+    async fn spawn_eviction() {
+        let store = Arc::new(RwLock::new(HashSetBannedTokenStore::default()));
+        store
+            .write()
+            .await
+            .add_banned_token("expired".to_owned(), Utc::now().timestamp() - 1)
+            .await
+            .unwrap();
 
-        assert!(result.unwrap());
+        let handle = HashSetBannedTokenStore::spawn_eviction(&store, Duration::from_millis(10));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(store.read().await.banned_tokens.is_empty());
+
+        // The task stops once nothing else holds the store
+        drop(store);
+        tokio::time::timeout(Duration::from_secs(1), handle)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn shared_store_is_evicted() {
+        let store = HashSetBannedTokenStore::shared();
+
+        // The eviction task holds on to the store, without keeping it alive
+        assert_eq!(Arc::weak_count(&store), 1);
+    }
+
+    #[tokio::test]
+    async fn revoke_tokens_issued_before() {
+        let mut banned_token_store = HashSetBannedTokenStore::default();
+        let subject = Secret::new("subject".to_owned());
+
+        let result = banned_token_store
+            .revoke_tokens_issued_before(subject.clone(), 1_700_000_000)
+            .await;
+
+        assert!(result.is_ok());
+        assert_eq!(
+            banned_token_store
+                .get_revocation_timestamp(&subject)
+                .await
+                .unwrap(),
+            Some(1_700_000_000)
+        );
+        assert_eq!(
+            banned_token_store
+                .get_revocation_timestamp(&Secret::new("other".to_owned()))
+                .await
+                .unwrap(),
+            None
+        );
     }
 }
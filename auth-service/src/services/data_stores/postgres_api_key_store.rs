@@ -0,0 +1,180 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::{
+    data_stores::{ApiKeySecret, ApiKeyStore, ApiKeyStoreError},
+    ApiKey, ApiKeyId, OAuthScope, UserId,
+};
+
+pub struct PostgresApiKeyStore {
+    pool: PgPool,
+}
+
+impl PostgresApiKeyStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl ApiKeyStore for PostgresApiKeyStore {
+    #[tracing::instrument(name = "Adding API key to PostgreSQL", skip_all)]
+    async fn add_key(&mut self, key: ApiKey) -> Result<(), ApiKeyStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO api_keys (id, user_id, name, scope, hint, key_hash, created_at, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+            key.id.as_ref(),
+            key.user_id.as_ref(),
+            key.name,
+            key.scope.to_string(),
+            key.hint,
+            key.hash,
+            key.created_at,
+            key.expires_at,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ApiKeyStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving API key from PostgreSQL", skip_all)]
+    async fn get_key(&self, secret: &ApiKeySecret) -> Result<ApiKey, ApiKeyStoreError> {
+        let row = sqlx::query_as!(
+            ApiKeyRow,
+            r#"
+            SELECT id, user_id, name, scope, hint, key_hash, created_at, expires_at
+            FROM api_keys
+            WHERE key_hash = $1
+            "#,
+            secret.hash(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ApiKeyStoreError::UnexpectedError(e.into()))?
+        .ok_or(ApiKeyStoreError::KeyNotFound)?;
+
+        row.try_into()
+    }
+
+    #[tracing::instrument(name = "Listing API keys from PostgreSQL", skip_all)]
+    async fn get_user_keys(&self, user_id: &UserId) -> Result<Vec<ApiKey>, ApiKeyStoreError> {
+        let rows = sqlx::query_as!(
+            ApiKeyRow,
+            r#"
+            SELECT id, user_id, name, scope, hint, key_hash, created_at, expires_at
+            FROM api_keys
+            WHERE user_id = $1
+            ORDER BY created_at, id
+            "#,
+            user_id.as_ref(),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ApiKeyStoreError::UnexpectedError(e.into()))?;
+
+        rows.into_iter().map(ApiKey::try_from).collect()
+    }
+
+    #[tracing::instrument(name = "Renaming API key in PostgreSQL", skip_all)]
+    async fn rename_key(
+        &mut self,
+        user_id: &UserId,
+        id: &ApiKeyId,
+        name: &str,
+    ) -> Result<(), ApiKeyStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE api_keys
+            SET name = $3
+            WHERE id = $1 AND user_id = $2
+            "#,
+            id.as_ref(),
+            user_id.as_ref(),
+            name,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ApiKeyStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(ApiKeyStoreError::KeyNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing API key from PostgreSQL", skip_all)]
+    async fn remove_key(
+        &mut self,
+        user_id: &UserId,
+        id: &ApiKeyId,
+    ) -> Result<(), ApiKeyStoreError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM api_keys
+            WHERE id = $1 AND user_id = $2
+            "#,
+            id.as_ref(),
+            user_id.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ApiKeyStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(ApiKeyStoreError::KeyNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing user API keys from PostgreSQL", skip_all)]
+    async fn remove_user_keys(&mut self, user_id: &UserId) -> Result<(), ApiKeyStoreError> {
+        sqlx::query!(
+            r#"
+            DELETE FROM api_keys
+            WHERE user_id = $1
+            "#,
+            user_id.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ApiKeyStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
+
+// The columns every query loading whole keys selects
+struct ApiKeyRow {
+    id: Uuid,
+    user_id: Uuid,
+    name: String,
+    scope: String,
+    hint: String,
+    key_hash: String,
+    created_at: DateTime<Utc>,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<ApiKeyRow> for ApiKey {
+    type Error = ApiKeyStoreError;
+
+    fn try_from(row: ApiKeyRow) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: ApiKeyId::from(row.id),
+            user_id: UserId::from(row.user_id),
+            name: row.name,
+            scope: OAuthScope::parse(&row.scope).map_err(ApiKeyStoreError::UnexpectedError)?,
+            hint: row.hint,
+            hash: row.key_hash,
+            created_at: row.created_at,
+            expires_at: row.expires_at,
+        })
+    }
+}
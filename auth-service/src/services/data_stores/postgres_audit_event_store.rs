@@ -0,0 +1,97 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::{
+    data_stores::{AuditEventStore, AuditEventStoreError},
+    AuditEvent, AuditEventKind, UserId,
+};
+
+pub struct PostgresAuditEventStore {
+    pool: PgPool,
+}
+
+impl PostgresAuditEventStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuditEventStore for PostgresAuditEventStore {
+    #[tracing::instrument(name = "Adding audit event to PostgreSQL", skip_all)]
+    async fn add_event(&mut self, event: AuditEvent) -> Result<(), AuditEventStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO audit_events (user_id, kind, created_at)
+            VALUES ($1, $2, $3)
+            "#,
+            event.user_id.as_ref(),
+            event.kind.as_str(),
+            event.created_at,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AuditEventStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Listing audit events from PostgreSQL", skip_all)]
+    async fn get_user_events(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<AuditEvent>, AuditEventStoreError> {
+        // Events recorded within the same microsecond still come back in the order they were added
+        let rows = sqlx::query_as!(
+            AuditEventRow,
+            r#"
+            SELECT user_id, kind, created_at
+            FROM audit_events
+            WHERE user_id = $1
+            ORDER BY created_at DESC, id DESC
+            "#,
+            user_id.as_ref(),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AuditEventStoreError::UnexpectedError(e.into()))?;
+
+        rows.into_iter().map(AuditEvent::try_from).collect()
+    }
+
+    #[tracing::instrument(name = "Removing user audit events from PostgreSQL", skip_all)]
+    async fn remove_user_events(&mut self, user_id: &UserId) -> Result<(), AuditEventStoreError> {
+        sqlx::query!(
+            r#"
+            DELETE FROM audit_events
+            WHERE user_id = $1
+            "#,
+            user_id.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AuditEventStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
+
+struct AuditEventRow {
+    user_id: Uuid,
+    kind: String,
+    created_at: DateTime<Utc>,
+}
+
+impl TryFrom<AuditEventRow> for AuditEvent {
+    type Error = AuditEventStoreError;
+
+    fn try_from(row: AuditEventRow) -> Result<Self, Self::Error> {
+        Ok(Self {
+            user_id: UserId::from(row.user_id),
+            kind: AuditEventKind::parse(&row.kind)
+                .map_err(AuditEventStoreError::UnexpectedError)?,
+            created_at: row.created_at,
+        })
+    }
+}
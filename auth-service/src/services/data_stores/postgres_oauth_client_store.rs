@@ -0,0 +1,153 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::{
+    data_stores::{OAuthClientStore, OAuthClientStoreError},
+    OAuthClient, OAuthClientId, OAuthClientKind, OAuthClientSecret, OAuthScope,
+};
+
+pub struct PostgresOAuthClientStore {
+    pool: PgPool,
+}
+
+impl PostgresOAuthClientStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl OAuthClientStore for PostgresOAuthClientStore {
+    #[tracing::instrument(name = "Adding OAuth client to PostgreSQL", skip_all)]
+    async fn add_client(&mut self, client: OAuthClient) -> Result<(), OAuthClientStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO oauth_clients (
+                id, name, kind, redirect_uris, scope,
+                secret_id, secret_hash, secret_created_at, created_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            "#,
+            client.id.as_ref(),
+            client.name,
+            client.kind.as_str(),
+            &client.redirect_uris,
+            client.scope.to_string(),
+            client.secret.as_ref().map(|secret| secret.id),
+            client.secret.as_ref().map(|secret| secret.hash.clone()),
+            client.secret.as_ref().map(|secret| secret.created_at),
+            client.created_at,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| OAuthClientStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving OAuth client from PostgreSQL", skip_all)]
+    async fn get_client(&self, id: &OAuthClientId) -> Result<OAuthClient, OAuthClientStoreError> {
+        let row = sqlx::query_as!(
+            OAuthClientRow,
+            r#"
+            SELECT id, name, kind, redirect_uris, scope,
+                secret_id, secret_hash, secret_created_at, created_at
+            FROM oauth_clients
+            WHERE id = $1
+            "#,
+            id.as_ref(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| OAuthClientStoreError::UnexpectedError(e.into()))?
+        .ok_or(OAuthClientStoreError::ClientNotFound)?;
+
+        row.try_into()
+    }
+
+    #[tracing::instrument(name = "Listing OAuth clients from PostgreSQL", skip_all)]
+    async fn list_clients(&self) -> Result<Vec<OAuthClient>, OAuthClientStoreError> {
+        let rows = sqlx::query_as!(
+            OAuthClientRow,
+            r#"
+            SELECT id, name, kind, redirect_uris, scope,
+                secret_id, secret_hash, secret_created_at, created_at
+            FROM oauth_clients
+            ORDER BY created_at, id
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| OAuthClientStoreError::UnexpectedError(e.into()))?;
+
+        rows.into_iter().map(OAuthClient::try_from).collect()
+    }
+
+    #[tracing::instrument(name = "Setting OAuth client secret in PostgreSQL", skip_all)]
+    async fn set_client_secret(
+        &mut self,
+        id: &OAuthClientId,
+        secret: Option<OAuthClientSecret>,
+    ) -> Result<(), OAuthClientStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE oauth_clients
+            SET secret_id = $2, secret_hash = $3, secret_created_at = $4
+            WHERE id = $1
+            "#,
+            id.as_ref(),
+            secret.as_ref().map(|secret| secret.id),
+            secret.as_ref().map(|secret| secret.hash.clone()),
+            secret.as_ref().map(|secret| secret.created_at),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| OAuthClientStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(OAuthClientStoreError::ClientNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+// The columns every query loading whole clients selects
+struct OAuthClientRow {
+    id: Uuid,
+    name: String,
+    kind: String,
+    redirect_uris: Vec<String>,
+    scope: String,
+    secret_id: Option<Uuid>,
+    secret_hash: Option<String>,
+    secret_created_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+}
+
+impl TryFrom<OAuthClientRow> for OAuthClient {
+    type Error = OAuthClientStoreError;
+
+    fn try_from(row: OAuthClientRow) -> Result<Self, Self::Error> {
+        let secret = match (row.secret_id, row.secret_hash, row.secret_created_at) {
+            (Some(id), Some(hash), Some(created_at)) => Some(OAuthClientSecret {
+                id,
+                hash,
+                created_at,
+            }),
+            _ => None,
+        };
+
+        Ok(Self {
+            id: OAuthClientId::from(row.id),
+            name: row.name,
+            kind: OAuthClientKind::parse(&row.kind)
+                .map_err(OAuthClientStoreError::UnexpectedError)?,
+            redirect_uris: row.redirect_uris,
+            scope: OAuthScope::parse(&row.scope).map_err(OAuthClientStoreError::UnexpectedError)?,
+            secret,
+            created_at: row.created_at,
+        })
+    }
+}
@@ -0,0 +1,112 @@
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{OAuthConsentStore, OAuthConsentStoreError},
+    OAuthClientId, OAuthScope, UserId,
+};
+
+pub struct PostgresOAuthConsentStore {
+    pool: PgPool,
+}
+
+impl PostgresOAuthConsentStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl OAuthConsentStore for PostgresOAuthConsentStore {
+    #[tracing::instrument(name = "Retrieving OAuth consent from PostgreSQL", skip_all)]
+    async fn get_consent(
+        &self,
+        user_id: &UserId,
+        client_id: &OAuthClientId,
+    ) -> Result<Option<OAuthScope>, OAuthConsentStoreError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT scope
+            FROM oauth_consents
+            WHERE user_id = $1 AND client_id = $2
+            "#,
+            user_id.as_ref(),
+            client_id.as_ref(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| OAuthConsentStoreError::UnexpectedError(e.into()))?;
+
+        row.map(|row| OAuthScope::parse(&row.scope))
+            .transpose()
+            .map_err(OAuthConsentStoreError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Retrieving OAuth consents from PostgreSQL", skip_all)]
+    async fn get_user_consents(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<(OAuthClientId, OAuthScope)>, OAuthConsentStoreError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT client_id, scope
+            FROM oauth_consents
+            WHERE user_id = $1
+            "#,
+            user_id.as_ref(),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| OAuthConsentStoreError::UnexpectedError(e.into()))?;
+
+        rows.into_iter()
+            .map(|row| {
+                let scope = OAuthScope::parse(&row.scope)
+                    .map_err(OAuthConsentStoreError::UnexpectedError)?;
+                Ok((OAuthClientId::from(row.client_id), scope))
+            })
+            .collect::<Result<_, _>>()
+    }
+
+    #[tracing::instrument(name = "Setting OAuth consent in PostgreSQL", skip_all)]
+    async fn set_consent(
+        &mut self,
+        user_id: &UserId,
+        client_id: &OAuthClientId,
+        scope: &OAuthScope,
+    ) -> Result<(), OAuthConsentStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO oauth_consents (user_id, client_id, scope)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_id, client_id) DO UPDATE SET scope = EXCLUDED.scope
+            "#,
+            user_id.as_ref(),
+            client_id.as_ref(),
+            scope.to_string(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| OAuthConsentStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing OAuth consents from PostgreSQL", skip_all)]
+    async fn remove_user_consents(
+        &mut self,
+        user_id: &UserId,
+    ) -> Result<(), OAuthConsentStoreError> {
+        sqlx::query!(
+            r#"
+            DELETE FROM oauth_consents
+            WHERE user_id = $1
+            "#,
+            user_id.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| OAuthConsentStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,166 @@
+use chrono::{Duration, Utc};
+use color_eyre::eyre::eyre;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+
+use crate::{
+    domain::{
+        data_stores::{
+            RefreshToken, RefreshTokenFamilyId, RefreshTokenStore, RefreshTokenStoreError,
+        },
+        Email,
+    },
+    utils::auth::REFRESH_TOKEN_TTL_SECONDS,
+};
+
+pub struct PostgresRefreshTokenStore {
+    pool: PgPool,
+}
+
+impl PostgresRefreshTokenStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for PostgresRefreshTokenStore {
+    #[tracing::instrument(name = "Adding refresh token to PostgreSQL", skip_all)]
+    async fn add_token(
+        &mut self,
+        token: RefreshToken,
+        email: Email,
+        family_id: RefreshTokenFamilyId,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let expires_at = Utc::now() + Duration::seconds(REFRESH_TOKEN_TTL_SECONDS);
+
+        sqlx::query!(
+            r#"
+            INSERT INTO refresh_tokens (token_hash, email, family_id, expires_at)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            token.hash(),
+            email.as_ref().expose_secret(),
+            family_id.as_ref(),
+            expires_at,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Consuming refresh token in PostgreSQL", skip_all)]
+    async fn consume_token(
+        &mut self,
+        token: &RefreshToken,
+    ) -> Result<(Email, RefreshTokenFamilyId), RefreshTokenStoreError> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        // Lock the row so two concurrent refreshes cannot both see the token as unused
+        let row = sqlx::query!(
+            r#"
+            SELECT email, family_id, used
+            FROM refresh_tokens
+            WHERE token_hash = $1 AND expires_at > NOW()
+            FOR UPDATE
+            "#,
+            token.hash(),
+        )
+        .fetch_optional(&mut transaction)
+        .await
+        .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?
+        .ok_or(RefreshTokenStoreError::TokenNotFound)?;
+
+        let family_id = RefreshTokenFamilyId::parse(row.family_id)
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(eyre!(e)))?;
+
+        if row.used {
+            return Err(RefreshTokenStoreError::TokenReused(family_id));
+        }
+
+        let email = Email::parse(Secret::new(row.email))
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(eyre!(e)))?;
+
+        sqlx::query!(
+            r#"
+            UPDATE refresh_tokens
+            SET used = TRUE
+            WHERE token_hash = $1
+            "#,
+            token.hash(),
+        )
+        .execute(&mut transaction)
+        .await
+        .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok((email, family_id))
+    }
+
+    #[tracing::instrument(name = "Getting refresh token family from PostgreSQL", skip_all)]
+    async fn get_family(
+        &self,
+        token: &RefreshToken,
+    ) -> Result<RefreshTokenFamilyId, RefreshTokenStoreError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT family_id
+            FROM refresh_tokens
+            WHERE token_hash = $1 AND expires_at > NOW()
+            "#,
+            token.hash(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?
+        .ok_or(RefreshTokenStoreError::TokenNotFound)?;
+
+        RefreshTokenFamilyId::parse(row.family_id)
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(eyre!(e)))
+    }
+
+    #[tracing::instrument(name = "Revoking refresh token family in PostgreSQL", skip_all)]
+    async fn revoke_family(
+        &mut self,
+        family_id: &RefreshTokenFamilyId,
+    ) -> Result<(), RefreshTokenStoreError> {
+        sqlx::query!(
+            r#"
+            DELETE FROM refresh_tokens
+            WHERE family_id = $1
+            "#,
+            family_id.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Revoking user refresh tokens in PostgreSQL", skip_all)]
+    async fn revoke_user_tokens(&mut self, email: &Email) -> Result<(), RefreshTokenStoreError> {
+        sqlx::query!(
+            r#"
+            DELETE FROM refresh_tokens
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+use color_eyre::eyre::eyre;
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{RoleStore, RoleStoreError},
+    Permission, Role, UserId,
+};
+
+pub struct PostgresRoleStore {
+    pool: PgPool,
+}
+
+impl PostgresRoleStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl RoleStore for PostgresRoleStore {
+    #[tracing::instrument(name = "Granting role in PostgreSQL", skip_all)]
+    async fn grant_role(&mut self, user_id: &UserId, role: &Role) -> Result<(), RoleStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO user_roles (user_id, role)
+            VALUES ($1, $2)
+            ON CONFLICT DO NOTHING
+            "#,
+            user_id.as_ref(),
+            role.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Revoking role in PostgreSQL", skip_all)]
+    async fn revoke_role(&mut self, user_id: &UserId, role: &Role) -> Result<(), RoleStoreError> {
+        sqlx::query!(
+            r#"
+            DELETE FROM user_roles
+            WHERE user_id = $1 AND role = $2
+            "#,
+            user_id.as_ref(),
+            role.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Getting roles from PostgreSQL", skip_all)]
+    async fn get_roles(&self, user_id: &UserId) -> Result<Vec<Role>, RoleStoreError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT role
+            FROM user_roles
+            WHERE user_id = $1
+            ORDER BY role
+            "#,
+            user_id.as_ref(),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+
+        rows.into_iter()
+            .map(|row| Role::parse(row.role).map_err(|e| RoleStoreError::UnexpectedError(eyre!(e))))
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Granting permission in PostgreSQL", skip_all)]
+    async fn grant_permission(
+        &mut self,
+        user_id: &UserId,
+        permission: &Permission,
+    ) -> Result<(), RoleStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO user_permissions (user_id, permission)
+            VALUES ($1, $2)
+            ON CONFLICT DO NOTHING
+            "#,
+            user_id.as_ref(),
+            permission.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Revoking permission in PostgreSQL", skip_all)]
+    async fn revoke_permission(
+        &mut self,
+        user_id: &UserId,
+        permission: &Permission,
+    ) -> Result<(), RoleStoreError> {
+        sqlx::query!(
+            r#"
+            DELETE FROM user_permissions
+            WHERE user_id = $1 AND permission = $2
+            "#,
+            user_id.as_ref(),
+            permission.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Getting permissions from PostgreSQL", skip_all)]
+    async fn get_permissions(&self, user_id: &UserId) -> Result<Vec<Permission>, RoleStoreError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT permission
+            FROM user_permissions
+            WHERE user_id = $1
+            ORDER BY permission
+            "#,
+            user_id.as_ref(),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+
+        rows.into_iter()
+            .map(|row| {
+                Permission::parse(row.permission)
+                    .map_err(|e| RoleStoreError::UnexpectedError(eyre!(e)))
+            })
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Revoking all grants in PostgreSQL", skip_all)]
+    async fn revoke_all(&mut self, user_id: &UserId) -> Result<(), RoleStoreError> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM user_roles
+            WHERE user_id = $1
+            "#,
+            user_id.as_ref(),
+        )
+        .execute(&mut transaction)
+        .await
+        .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM user_permissions
+            WHERE user_id = $1
+            "#,
+            user_id.as_ref(),
+        )
+        .execute(&mut transaction)
+        .await
+        .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,226 @@
+use chrono::{DateTime, Duration, Utc};
+use color_eyre::eyre::{eyre, Context};
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    domain::{
+        data_stores::{RefreshTokenFamilyId, SessionStore, SessionStoreError},
+        AuthMethod, Authentication, Email, OAuthClientId, OAuthGrant, OAuthScope, Session,
+        SessionClient,
+    },
+    utils::auth::REFRESH_TOKEN_TTL_SECONDS,
+};
+
+pub struct PostgresSessionStore {
+    pool: PgPool,
+}
+
+impl PostgresSessionStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+// Sessions last seen before this can no longer be resumed, as their refresh token has expired
+fn live_since() -> DateTime<Utc> {
+    Utc::now() - Duration::seconds(REFRESH_TOKEN_TTL_SECONDS)
+}
+
+#[async_trait::async_trait]
+impl SessionStore for PostgresSessionStore {
+    #[tracing::instrument(name = "Adding session to PostgreSQL", skip_all)]
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO sessions (id, email, user_agent, ip_address, created_at, last_seen_at,
+                oauth_client_id, oauth_scope, auth_time, auth_methods)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            "#,
+            session.id.as_ref(),
+            session.email.as_ref().expose_secret(),
+            session.client.user_agent,
+            session.client.ip_address.to_string(),
+            session.created_at,
+            session.last_seen_at,
+            session
+                .oauth
+                .as_ref()
+                .map(|grant| *grant.client_id.as_ref()),
+            session.oauth.as_ref().map(|grant| grant.scope.to_string()),
+            session.authentication.time,
+            &session
+                .authentication
+                .methods
+                .iter()
+                .map(|method| method.as_str().to_owned())
+                .collect::<Vec<_>>(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Touching session in PostgreSQL", skip_all)]
+    async fn touch_session(
+        &mut self,
+        id: &RefreshTokenFamilyId,
+        client: SessionClient,
+    ) -> Result<(), SessionStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE sessions
+            SET user_agent = $2, ip_address = $3, last_seen_at = NOW()
+            WHERE id = $1 AND last_seen_at > $4
+            "#,
+            id.as_ref(),
+            client.user_agent,
+            client.ip_address.to_string(),
+            live_since(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(SessionStoreError::SessionNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving session from PostgreSQL", skip_all)]
+    async fn get_session(&self, id: &RefreshTokenFamilyId) -> Result<Session, SessionStoreError> {
+        let row = sqlx::query_as!(
+            SessionRow,
+            r#"
+            SELECT id, email, user_agent, ip_address, created_at, last_seen_at, oauth_client_id,
+                oauth_scope, auth_time, auth_methods
+            FROM sessions
+            WHERE id = $1 AND last_seen_at > $2
+            "#,
+            id.as_ref(),
+            live_since(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?
+        .ok_or(SessionStoreError::SessionNotFound)?;
+
+        row.try_into()
+    }
+
+    #[tracing::instrument(name = "Retrieving user sessions from PostgreSQL", skip_all)]
+    async fn get_user_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError> {
+        let rows = sqlx::query_as!(
+            SessionRow,
+            r#"
+            SELECT id, email, user_agent, ip_address, created_at, last_seen_at, oauth_client_id,
+                oauth_scope, auth_time, auth_methods
+            FROM sessions
+            WHERE email = $1 AND last_seen_at > $2
+            ORDER BY last_seen_at DESC
+            "#,
+            email.as_ref().expose_secret(),
+            live_since(),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+
+        rows.into_iter().map(Session::try_from).collect()
+    }
+
+    #[tracing::instrument(name = "Removing session from PostgreSQL", skip_all)]
+    async fn remove_session(&mut self, id: &RefreshTokenFamilyId) -> Result<(), SessionStoreError> {
+        sqlx::query!(
+            r#"
+            DELETE FROM sessions
+            WHERE id = $1
+            "#,
+            id.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing user sessions from PostgreSQL", skip_all)]
+    async fn remove_user_sessions(&mut self, email: &Email) -> Result<(), SessionStoreError> {
+        sqlx::query!(
+            r#"
+            DELETE FROM sessions
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
+
+// The columns every query loading whole sessions selects
+struct SessionRow {
+    id: String,
+    email: String,
+    user_agent: Option<String>,
+    ip_address: String,
+    created_at: DateTime<Utc>,
+    last_seen_at: DateTime<Utc>,
+    oauth_client_id: Option<Uuid>,
+    oauth_scope: Option<String>,
+    auth_time: DateTime<Utc>,
+    auth_methods: Vec<String>,
+}
+
+impl TryFrom<SessionRow> for Session {
+    type Error = SessionStoreError;
+
+    fn try_from(row: SessionRow) -> Result<Self, Self::Error> {
+        let oauth = match (row.oauth_client_id, row.oauth_scope) {
+            (Some(client_id), Some(scope)) => Some(OAuthGrant {
+                client_id: OAuthClientId::from(client_id),
+                scope: OAuthScope::parse(&scope)
+                    .map_err(|e| SessionStoreError::UnexpectedError(eyre!(e)))?,
+            }),
+            _ => None,
+        };
+
+        let methods = row
+            .auth_methods
+            .iter()
+            .map(|method| AuthMethod::parse(method))
+            .collect::<Result<_, _>>()
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        Ok(Session {
+            id: RefreshTokenFamilyId::parse(row.id)
+                .map_err(|e| SessionStoreError::UnexpectedError(eyre!(e)))?,
+            email: Email::parse(Secret::new(row.email))
+                .map_err(|e| SessionStoreError::UnexpectedError(eyre!(e)))?,
+            client: SessionClient {
+                user_agent: row.user_agent,
+                ip_address: row
+                    .ip_address
+                    .parse()
+                    .wrap_err("Stored IP address is invalid")
+                    .map_err(SessionStoreError::UnexpectedError)?,
+            },
+            created_at: row.created_at,
+            last_seen_at: row.last_seen_at,
+            oauth,
+            authentication: Authentication {
+                time: row.auth_time,
+                methods,
+            },
+        })
+    }
+}
@@ -1,17 +1,30 @@
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Key, Nonce,
+};
 use argon2::{
     password_hash::SaltString, Algorithm, Argon2, Params, PasswordHash, PasswordHasher,
     PasswordVerifier, Version,
 };
 
-use color_eyre::eyre::{eyre, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use color_eyre::eyre::{eyre, Context, Result};
 use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
 use sqlx::PgPool;
+use uuid::Uuid;
 
-use crate::domain::{
-    data_stores::{UserStore, UserStoreError},
-    Email, Password, User,
+use crate::{
+    domain::{
+        data_stores::{UserStore, UserStoreError},
+        Email, Password, TotpSecret, User, UserId,
+    },
+    utils::constants::TOTP_ENCRYPTION_KEY,
 };
 
+// Postgres error code for a unique constraint violation
+const UNIQUE_VIOLATION: &str = "23505";
+
 pub struct PostgresUserStore {
     pool: PgPool,
 }
@@ -32,12 +45,15 @@ impl UserStore for PostgresUserStore {
 
         sqlx::query!(
             r#"
-            INSERT INTO users (email, password_hash, requires_2fa)
-            VALUES ($1, $2, $3)
+            INSERT INTO users (id, email, password_hash, requires_2fa, verified, disabled)
+            VALUES ($1, $2, $3, $4, $5, $6)
             "#,
+            user.id.as_ref(),
             user.email.as_ref().expose_secret(),
             &password_hash.expose_secret(),
             user.requires_2fa,
+            user.verified,
+            user.disabled,
         )
         .execute(&self.pool)
         .await
@@ -48,9 +64,10 @@ impl UserStore for PostgresUserStore {
 
     #[tracing::instrument(name = "Getting user from PostgreSQL", skip_all)]
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
-        sqlx::query!(
+        sqlx::query_as!(
+            UserRow,
             r#"
-            SELECT email, password_hash, requires_2fa
+            SELECT id, email, password_hash, requires_2fa, verified, totp_enabled, disabled
             FROM users
             WHERE email = $1
             "#,
@@ -59,16 +76,26 @@ impl UserStore for PostgresUserStore {
         .fetch_optional(&self.pool)
         .await
         .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
-        .map(|row| {
-            Ok(User {
-                email: Email::parse(Secret::new(row.email))
-                    .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
-                password: Password::parse(Secret::new(row.password_hash))
-                    .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
-                requires_2fa: row.requires_2fa,
-            })
-        })
         .ok_or(UserStoreError::UserNotFound)?
+        .try_into()
+    }
+
+    #[tracing::instrument(name = "Getting user by ID from PostgreSQL", skip_all)]
+    async fn get_user_by_id(&self, id: &UserId) -> Result<User, UserStoreError> {
+        sqlx::query_as!(
+            UserRow,
+            r#"
+            SELECT id, email, password_hash, requires_2fa, verified, totp_enabled, disabled
+            FROM users
+            WHERE id = $1
+            "#,
+            id.as_ref(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .ok_or(UserStoreError::UserNotFound)?
+        .try_into()
     }
 
     #[tracing::instrument(name = "Validating user credentials in PostgreSQL", skip_all)]
@@ -86,9 +113,447 @@ impl UserStore for PostgresUserStore {
         .await
         .map_err(|_| UserStoreError::InvalidCredentials)
     }
+
+    #[tracing::instrument(name = "Updating user password in PostgreSQL", skip_all)]
+    async fn update_password(
+        &mut self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        let password_hash = compute_password_hash(password.as_ref().to_owned())
+            .await
+            .map_err(UserStoreError::UnexpectedError)?;
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET password_hash = $1
+            WHERE email = $2
+            "#,
+            &password_hash.expose_secret(),
+            email.as_ref().expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Marking user as verified in PostgreSQL", skip_all)]
+    async fn mark_verified(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET verified = TRUE
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Updating user email in PostgreSQL", skip_all)]
+    async fn update_email(
+        &mut self,
+        email: &Email,
+        new_email: &Email,
+        token_generation: i64,
+    ) -> Result<(), UserStoreError> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        // Lock the row so the same change cannot be confirmed twice concurrently
+        let row = sqlx::query!(
+            r#"
+            SELECT token_generation
+            FROM users
+            WHERE email = $1
+            FOR UPDATE
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .fetch_optional(&mut transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .ok_or(UserStoreError::UserNotFound)?;
+
+        if row.token_generation != token_generation {
+            return Err(UserStoreError::InvalidCredentials);
+        }
+
+        // Every table referencing the user's email is updated along with it (ON UPDATE CASCADE)
+        sqlx::query!(
+            r#"
+            UPDATE users
+            SET email = $2, verified = TRUE, token_generation = token_generation + 1
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+            new_email.as_ref().expose_secret(),
+        )
+        .execute(&mut transaction)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(e) if e.code().as_deref() == Some(UNIQUE_VIOLATION) => {
+                UserStoreError::UserAlreadyExists
+            }
+            e => UserStoreError::UnexpectedError(e.into()),
+        })?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Deleting user from PostgreSQL", skip_all)]
+    async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        // Every table referencing the user's email is cleared along with it (ON DELETE CASCADE)
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM users
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Storing TOTP secret in PostgreSQL", skip_all)]
+    async fn set_totp_secret(
+        &mut self,
+        email: &Email,
+        secret: TotpSecret,
+    ) -> Result<(), UserStoreError> {
+        let encrypted_secret =
+            encrypt_totp_secret(&secret).map_err(UserStoreError::UnexpectedError)?;
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET totp_secret = $1, totp_enabled = FALSE, totp_last_step = NULL
+            WHERE email = $2
+            "#,
+            encrypted_secret,
+            email.as_ref().expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Getting TOTP secret from PostgreSQL", skip_all)]
+    async fn get_totp_secret(&self, email: &Email) -> Result<Option<TotpSecret>, UserStoreError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT totp_secret
+            FROM users
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .ok_or(UserStoreError::UserNotFound)?;
+
+        row.totp_secret
+            .map(|encrypted_secret| decrypt_totp_secret(&encrypted_secret))
+            .transpose()
+            .map_err(UserStoreError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Enabling TOTP in PostgreSQL", skip_all)]
+    async fn enable_totp(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET totp_enabled = TRUE, requires_2fa = TRUE
+            WHERE email = $1 AND totp_secret IS NOT NULL
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Recording TOTP time step in PostgreSQL", skip_all)]
+    async fn record_totp_step(&mut self, email: &Email, step: u64) -> Result<bool, UserStoreError> {
+        let step = i64::try_from(step).map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        // Only update if no code for this or a later step was accepted, so two concurrent
+        // requests with the same code can't both succeed
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET totp_last_step = $2
+            WHERE email = $1 AND (totp_last_step IS NULL OR totp_last_step < $2)
+            "#,
+            email.as_ref().expose_secret(),
+            step,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() > 0 {
+            return Ok(true);
+        }
+
+        // Nothing was updated, either because the user doesn't exist or the code was replayed
+        sqlx::query!(
+            r#"
+            SELECT email
+            FROM users
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .ok_or(UserStoreError::UserNotFound)?;
+
+        Ok(false)
+    }
+
+    #[tracing::instrument(name = "Getting token generation from PostgreSQL", skip_all)]
+    async fn get_token_generation(&self, email: &Email) -> Result<i64, UserStoreError> {
+        sqlx::query!(
+            r#"
+            SELECT token_generation
+            FROM users
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .map(|row| row.token_generation)
+        .ok_or(UserStoreError::UserNotFound)
+    }
+
+    #[tracing::instrument(name = "Incrementing token generation in PostgreSQL", skip_all)]
+    async fn increment_token_generation(&mut self, email: &Email) -> Result<i64, UserStoreError> {
+        sqlx::query!(
+            r#"
+            UPDATE users
+            SET token_generation = token_generation + 1
+            WHERE email = $1
+            RETURNING token_generation
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .map(|row| row.token_generation)
+        .ok_or(UserStoreError::UserNotFound)
+    }
+
+    #[tracing::instrument(name = "Listing users from PostgreSQL", skip_all)]
+    async fn list_users(
+        &self,
+        email_search: Option<&str>,
+        offset: u32,
+        limit: u32,
+    ) -> Result<Vec<User>, UserStoreError> {
+        // strpos rather than LIKE, so wildcards in the search are taken literally
+        let rows = sqlx::query_as!(
+            UserRow,
+            r#"
+            SELECT id, email, password_hash, requires_2fa, verified, totp_enabled, disabled
+            FROM users
+            WHERE $1::TEXT IS NULL OR strpos(lower(email), lower($1)) > 0
+            ORDER BY email
+            OFFSET $2
+            LIMIT $3
+            "#,
+            email_search,
+            i64::from(offset),
+            i64::from(limit),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        rows.into_iter().map(User::try_from).collect()
+    }
+
+    #[tracing::instrument(name = "Counting users in PostgreSQL", skip_all)]
+    async fn count_users(&self, email_search: Option<&str>) -> Result<usize, UserStoreError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM users
+            WHERE $1::TEXT IS NULL OR strpos(lower(email), lower($1)) > 0
+            "#,
+            email_search,
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        usize::try_from(row.count).map_err(|e| UserStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Setting user disabled in PostgreSQL", skip_all)]
+    async fn set_disabled(&mut self, email: &Email, disabled: bool) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET disabled = $2
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+            disabled,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Setting user requires 2FA in PostgreSQL", skip_all)]
+    async fn set_requires_2fa(
+        &mut self,
+        email: &Email,
+        requires_2fa: bool,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET requires_2fa = $2
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+            requires_2fa,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+fn totp_cipher() -> Aes256Gcm {
+    // Derive a 256-bit key so the configured secret can be any string, like JWT_SECRET
+    let key = Sha256::digest(TOTP_ENCRYPTION_KEY.expose_secret().as_bytes());
+    Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+}
+
+// The columns every query loading whole users selects
+struct UserRow {
+    id: Uuid,
+    email: String,
+    password_hash: String,
+    requires_2fa: bool,
+    verified: bool,
+    totp_enabled: bool,
+    disabled: bool,
+}
+
+impl TryFrom<UserRow> for User {
+    type Error = UserStoreError;
+
+    fn try_from(row: UserRow) -> Result<Self, Self::Error> {
+        Ok(User {
+            id: UserId::from(row.id),
+            email: Email::parse(Secret::new(row.email))
+                .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
+            password: Password::parse(Secret::new(row.password_hash))
+                .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
+            requires_2fa: row.requires_2fa,
+            verified: row.verified,
+            totp_enabled: row.totp_enabled,
+            disabled: row.disabled,
+        })
+    }
+}
+
+#[tracing::instrument(name = "Encrypting TOTP secret", skip_all)]
+fn encrypt_totp_secret(secret: &TotpSecret) -> Result<String> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = totp_cipher()
+        .encrypt(&nonce, secret.as_ref().expose_secret().as_bytes())
+        .map_err(|_| eyre!("Failed to encrypt TOTP secret"))?;
+
+    let mut payload = nonce.to_vec();
+    payload.extend_from_slice(&ciphertext);
+
+    Ok(BASE64.encode(payload))
 }
+
+#[tracing::instrument(name = "Decrypting TOTP secret", skip_all)]
+fn decrypt_totp_secret(encrypted_secret: &str) -> Result<TotpSecret> {
+    let payload = BASE64
+        .decode(encrypted_secret)
+        .wrap_err("Encrypted TOTP secret is not valid base64")?;
+
+    // AES-GCM nonces are 96 bits
+    if payload.len() < 12 {
+        return Err(eyre!("Encrypted TOTP secret is too short"));
+    }
+    let (nonce, ciphertext) = payload.split_at(12);
+
+    let plaintext = totp_cipher()
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| eyre!("Failed to decrypt TOTP secret"))?;
+    let secret = String::from_utf8(plaintext).wrap_err("Decrypted TOTP secret is not UTF-8")?;
+
+    TotpSecret::parse(Secret::new(secret))
+}
+
 #[tracing::instrument(name = "Verifying password hash", skip_all)]
-async fn verify_password_hash(
+pub(crate) async fn verify_password_hash(
     expected_password_hash: Secret<String>,
     password_candidate: Secret<String>,
 ) -> Result<()> {
@@ -113,7 +578,7 @@ async fn verify_password_hash(
 }
 
 #[tracing::instrument(name = "Computing password hash", skip_all)]
-async fn compute_password_hash(password: Secret<String>) -> Result<Secret<String>> {
+pub(crate) async fn compute_password_hash(password: Secret<String>) -> Result<Secret<String>> {
     let current_span: tracing::Span = tracing::Span::current();
 
     let result = tokio::task::spawn_blocking(move || {
@@ -134,3 +599,34 @@ async fn compute_password_hash(password: Secret<String>) -> Result<Secret<String
 
     result?
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_decrypt_totp_secret() {
+        let secret = TotpSecret::default();
+        let encrypted = encrypt_totp_secret(&secret).unwrap();
+        assert!(!encrypted.contains(secret.as_ref().expose_secret()));
+        assert_eq!(decrypt_totp_secret(&encrypted).unwrap(), secret);
+    }
+
+    #[test]
+    fn test_encryption_uses_a_fresh_nonce() {
+        let secret = TotpSecret::default();
+        assert_ne!(
+            encrypt_totp_secret(&secret).unwrap(),
+            encrypt_totp_secret(&secret).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_decrypt_tampered_totp_secret() {
+        let encrypted = encrypt_totp_secret(&TotpSecret::default()).unwrap();
+        let mut payload = BASE64.decode(encrypted).unwrap();
+        let last = payload.len() - 1;
+        payload[last] ^= 1;
+        assert!(decrypt_totp_secret(&BASE64.encode(payload)).is_err());
+    }
+}
@@ -0,0 +1,158 @@
+use color_eyre::eyre::{eyre, Context};
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{WebAuthnCredentialStore, WebAuthnCredentialStoreError},
+    Email, WebAuthnCredential, WebAuthnCredentialId,
+};
+
+pub struct PostgresWebAuthnCredentialStore {
+    pool: PgPool,
+}
+
+impl PostgresWebAuthnCredentialStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl WebAuthnCredentialStore for PostgresWebAuthnCredentialStore {
+    #[tracing::instrument(name = "Adding WebAuthn credential to PostgreSQL", skip_all)]
+    async fn add_credential(
+        &mut self,
+        credential: WebAuthnCredential,
+    ) -> Result<(), WebAuthnCredentialStoreError> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO webauthn_credentials (credential_id, email, public_key, sign_count)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (credential_id) DO NOTHING
+            "#,
+            credential.credential_id.as_ref(),
+            credential.email.as_ref().expose_secret(),
+            credential.public_key,
+            i64::from(credential.sign_count),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| WebAuthnCredentialStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(WebAuthnCredentialStoreError::CredentialAlreadyExists);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving WebAuthn credential from PostgreSQL", skip_all)]
+    async fn get_credential(
+        &self,
+        credential_id: &WebAuthnCredentialId,
+    ) -> Result<WebAuthnCredential, WebAuthnCredentialStoreError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT credential_id, email, public_key, sign_count
+            FROM webauthn_credentials
+            WHERE credential_id = $1
+            "#,
+            credential_id.as_ref(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| WebAuthnCredentialStoreError::UnexpectedError(e.into()))?
+        .ok_or(WebAuthnCredentialStoreError::CredentialNotFound)?;
+
+        to_credential(row.credential_id, row.email, row.public_key, row.sign_count)
+    }
+
+    #[tracing::instrument(
+        name = "Retrieving user WebAuthn credentials from PostgreSQL",
+        skip_all
+    )]
+    async fn get_user_credentials(
+        &self,
+        email: &Email,
+    ) -> Result<Vec<WebAuthnCredential>, WebAuthnCredentialStoreError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT credential_id, email, public_key, sign_count
+            FROM webauthn_credentials
+            WHERE email = $1
+            ORDER BY created_at
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| WebAuthnCredentialStoreError::UnexpectedError(e.into()))?;
+
+        rows.into_iter()
+            .map(|row| to_credential(row.credential_id, row.email, row.public_key, row.sign_count))
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Updating WebAuthn sign count in PostgreSQL", skip_all)]
+    async fn update_sign_count(
+        &mut self,
+        credential_id: &WebAuthnCredentialId,
+        sign_count: u32,
+    ) -> Result<(), WebAuthnCredentialStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE webauthn_credentials
+            SET sign_count = $2
+            WHERE credential_id = $1
+            "#,
+            credential_id.as_ref(),
+            i64::from(sign_count),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| WebAuthnCredentialStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(WebAuthnCredentialStoreError::CredentialNotFound);
+        }
+
+        Ok(())
+    }
+    #[tracing::instrument(name = "Removing user WebAuthn credentials from PostgreSQL", skip_all)]
+    async fn remove_user_credentials(
+        &mut self,
+        email: &Email,
+    ) -> Result<(), WebAuthnCredentialStoreError> {
+        sqlx::query!(
+            r#"
+            DELETE FROM webauthn_credentials
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| WebAuthnCredentialStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
+
+fn to_credential(
+    credential_id: String,
+    email: String,
+    public_key: Vec<u8>,
+    sign_count: i64,
+) -> Result<WebAuthnCredential, WebAuthnCredentialStoreError> {
+    Ok(WebAuthnCredential {
+        credential_id: WebAuthnCredentialId::parse(credential_id)
+            .map_err(|e| WebAuthnCredentialStoreError::UnexpectedError(eyre!(e)))?,
+        email: Email::parse(Secret::new(email))
+            .map_err(|e| WebAuthnCredentialStoreError::UnexpectedError(eyre!(e)))?,
+        public_key,
+        sign_count: sign_count
+            .try_into()
+            .wrap_err("Stored sign count is out of range")
+            .map_err(WebAuthnCredentialStoreError::UnexpectedError)?,
+    })
+}
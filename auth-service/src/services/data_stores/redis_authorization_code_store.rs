@@ -0,0 +1,136 @@
+use std::sync::Arc;
+
+use chrono::DateTime;
+use color_eyre::eyre::{Context, ContextCompat};
+use redis::{Commands, Connection};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        data_stores::{AuthorizationCode, AuthorizationCodeStore, AuthorizationCodeStoreError},
+        AuthMethod, Authentication, AuthorizationCodeGrant, OAuthClientId, OAuthScope, UserId,
+    },
+    utils::auth::AUTHORIZATION_CODE_TTL_SECONDS,
+};
+
+pub struct RedisAuthorizationCodeStore {
+    connection: Arc<RwLock<Connection>>,
+}
+
+impl RedisAuthorizationCodeStore {
+    pub fn new(connection: Arc<RwLock<Connection>>) -> Self {
+        Self { connection }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuthorizationCodeStore for RedisAuthorizationCodeStore {
+    #[tracing::instrument(name = "Storing authorization code in Redis", skip_all)]
+    async fn add_code(
+        &mut self,
+        code: AuthorizationCode,
+        grant: AuthorizationCodeGrant,
+    ) -> Result<(), AuthorizationCodeStoreError> {
+        let key = get_key(&code);
+
+        let data = CodeInfo {
+            client_id: grant.client_id.to_string(),
+            user_id: grant.user_id.to_string(),
+            redirect_uri: grant.redirect_uri,
+            scope: grant.scope.to_string(),
+            code_challenge: grant.code_challenge,
+            nonce: grant.nonce,
+            auth_time: grant.authentication.time.timestamp(),
+            auth_methods: grant
+                .authentication
+                .methods
+                .iter()
+                .map(|method| method.as_str().to_owned())
+                .collect(),
+        };
+        let serialized_data = serde_json::to_string(&data)
+            .wrap_err("Failed to serialize authorization code")
+            .map_err(AuthorizationCodeStoreError::UnexpectedError)?;
+
+        let ttl: u64 = AUTHORIZATION_CODE_TTL_SECONDS
+            .try_into()
+            .wrap_err("Failed to cast AUTHORIZATION_CODE_TTL_SECONDS to u64")
+            .map_err(AuthorizationCodeStoreError::UnexpectedError)?;
+
+        let _: () = self
+            .connection
+            .write()
+            .await
+            .set_ex(&key, serialized_data, ttl)
+            .wrap_err("Failed to set authorization code in Redis")
+            .map_err(AuthorizationCodeStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Taking authorization code from Redis", skip_all)]
+    async fn take_code(
+        &mut self,
+        code: &AuthorizationCode,
+    ) -> Result<AuthorizationCodeGrant, AuthorizationCodeStoreError> {
+        let key = get_key(code);
+
+        // GETDEL reads and removes the code atomically, so it cannot be exchanged twice
+        let value: Option<String> = self
+            .connection
+            .write()
+            .await
+            .get_del(&key)
+            .wrap_err("Failed to take authorization code from Redis")
+            .map_err(AuthorizationCodeStoreError::UnexpectedError)?;
+
+        let value = value.ok_or(AuthorizationCodeStoreError::CodeNotFound)?;
+
+        let data: CodeInfo = serde_json::from_str(&value)
+            .wrap_err("Failed to deserialize authorization code")
+            .map_err(AuthorizationCodeStoreError::UnexpectedError)?;
+
+        let time = DateTime::from_timestamp(data.auth_time, 0)
+            .wrap_err("Invalid authentication time")
+            .map_err(AuthorizationCodeStoreError::UnexpectedError)?;
+        let methods = data
+            .auth_methods
+            .iter()
+            .map(|method| AuthMethod::parse(method))
+            .collect::<Result<_, _>>()
+            .map_err(AuthorizationCodeStoreError::UnexpectedError)?;
+
+        Ok(AuthorizationCodeGrant {
+            client_id: OAuthClientId::parse(data.client_id)
+                .map_err(AuthorizationCodeStoreError::UnexpectedError)?,
+            user_id: UserId::parse(data.user_id)
+                .map_err(AuthorizationCodeStoreError::UnexpectedError)?,
+            redirect_uri: data.redirect_uri,
+            scope: OAuthScope::parse(&data.scope)
+                .map_err(AuthorizationCodeStoreError::UnexpectedError)?,
+            code_challenge: data.code_challenge,
+            nonce: data.nonce,
+            authentication: Authentication { time, methods },
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CodeInfo {
+    client_id: String,
+    user_id: String,
+    redirect_uri: String,
+    scope: String,
+    code_challenge: String,
+    nonce: Option<String>,
+    // Seconds since the epoch
+    auth_time: i64,
+    auth_methods: Vec<String>,
+}
+
+const AUTHORIZATION_CODE_KEY_PREFIX: &str = "authorization_code:";
+
+fn get_key(code: &AuthorizationCode) -> String {
+    format!("{}{}", AUTHORIZATION_CODE_KEY_PREFIX, code.hash())
+}
@@ -1,5 +1,6 @@
 use std::sync::Arc;
 
+use chrono::Utc;
 use color_eyre::eyre::Context;
 use redis::{Commands, Connection};
 use secrecy::{ExposeSecret, Secret};
@@ -7,7 +8,7 @@ use tokio::sync::RwLock;
 
 use crate::{
     domain::data_stores::{BannedTokenStore, BannedTokenStoreError},
-    utils::auth::TOKEN_TTL_SECONDS,
+    utils::auth::{TOKEN_LEEWAY_SECONDS, TOKEN_TTL_SECONDS},
 };
 
 pub struct RedisBannedTokenStore {
@@ -25,30 +26,31 @@ impl BannedTokenStore for RedisBannedTokenStore {
     #[tracing::instrument(name = "Storing banned JWT in Redis", skip_all)]
     async fn add_banned_token(
         &mut self,
-        token: Secret<String>,
+        id: String,
+        expires_at: i64,
     ) -> Result<(), BannedTokenStoreError> {
-        let token_key = get_key(token.expose_secret());
+        let token_key = get_key(&id);
 
-        let value = true;
-
-        let ttl: u64 = TOKEN_TTL_SECONDS
-            .try_into()
-            .wrap_err("Failed to cast TOKEN_TTL_SECONDS to u64")
-            .map_err(BannedTokenStoreError::UnexpectedError)?;
+        // The ban only has to outlive the token itself
+        let ttl = expires_at - Utc::now().timestamp();
+        if ttl <= 0 {
+            return Ok(());
+        }
 
         let _: () = self
             .connection
             .write()
             .await
-            .set_ex(token_key, value, ttl)
+            .set_ex(token_key, true, ttl as u64)
             .wrap_err("Failed to set banned token in Redis")
             .map_err(BannedTokenStoreError::UnexpectedError)?;
 
         Ok(())
     }
+
     #[tracing::instrument(name = "Checking if JWT is banned in Redis", skip_all)]
-    async fn is_banned(&self, token: &Secret<String>) -> Result<bool, BannedTokenStoreError> {
-        let token_key = get_key(token.expose_secret());
+    async fn is_banned(&self, id: &str) -> Result<bool, BannedTokenStoreError> {
+        let token_key = get_key(id);
 
         let is_banned: bool = self
             .connection
@@ -60,11 +62,59 @@ impl BannedTokenStore for RedisBannedTokenStore {
 
         Ok(is_banned)
     }
+
+    #[tracing::instrument(name = "Revoking subject tokens in Redis", skip_all)]
+    async fn revoke_tokens_issued_before(
+        &mut self,
+        subject: Secret<String>,
+        timestamp: i64,
+    ) -> Result<(), BannedTokenStoreError> {
+        let subject_key = get_revoked_subject_key(subject.expose_secret());
+
+        // Once TOKEN_TTL_SECONDS have passed every token issued before `timestamp` has expired
+        let ttl: u64 = (TOKEN_TTL_SECONDS + TOKEN_LEEWAY_SECONDS as i64)
+            .try_into()
+            .wrap_err("Failed to cast TOKEN_TTL_SECONDS to u64")
+            .map_err(BannedTokenStoreError::UnexpectedError)?;
+
+        let _: () = self
+            .connection
+            .write()
+            .await
+            .set_ex(subject_key, timestamp, ttl)
+            .wrap_err("Failed to set subject revocation timestamp in Redis")
+            .map_err(BannedTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Getting subject revocation timestamp from Redis", skip_all)]
+    async fn get_revocation_timestamp(
+        &self,
+        subject: &Secret<String>,
+    ) -> Result<Option<i64>, BannedTokenStoreError> {
+        let subject_key = get_revoked_subject_key(subject.expose_secret());
+
+        let timestamp: Option<i64> = self
+            .connection
+            .write()
+            .await
+            .get(&subject_key)
+            .wrap_err("Failed to get subject revocation timestamp from Redis")
+            .map_err(BannedTokenStoreError::UnexpectedError)?;
+
+        Ok(timestamp)
+    }
 }
 
 // We are using a key prefix to prevent collisions with other keys in the Redis database
 const BANNED_TOKEN_KEY_PREFIX: &str = "banned_token:";
+const REVOKED_SUBJECT_KEY_PREFIX: &str = "revoked_subject:";
+
+pub fn get_key(id: &str) -> String {
+    format!("{}{}", BANNED_TOKEN_KEY_PREFIX, id)
+}
 
-pub fn get_key(token: &str) -> String {
-    format!("{}{}", BANNED_TOKEN_KEY_PREFIX, token)
+fn get_revoked_subject_key(subject: &str) -> String {
+    format!("{}{}", REVOKED_SUBJECT_KEY_PREFIX, subject)
 }
@@ -0,0 +1,96 @@
+use std::sync::Arc;
+
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection};
+use tokio::sync::RwLock;
+
+use crate::domain::data_stores::{FailedAttemptStore, FailedAttemptStoreError};
+
+pub struct RedisFailedAttemptStore {
+    connection: Arc<RwLock<Connection>>,
+}
+
+impl RedisFailedAttemptStore {
+    pub fn new(connection: Arc<RwLock<Connection>>) -> Self {
+        Self { connection }
+    }
+}
+
+#[async_trait::async_trait]
+impl FailedAttemptStore for RedisFailedAttemptStore {
+    #[tracing::instrument(name = "Recording failed attempt in Redis", skip_all)]
+    async fn record_failure(
+        &mut self,
+        key: &str,
+        window_seconds: u64,
+    ) -> Result<u32, FailedAttemptStoreError> {
+        let key = get_failures_key(key);
+
+        let ttl: i64 = window_seconds
+            .try_into()
+            .wrap_err("Failed to cast window_seconds to i64")
+            .map_err(FailedAttemptStoreError::UnexpectedError)?;
+
+        // INCR and EXPIRE together, so a counter is never left behind without a TTL
+        let (count, _): (u32, i64) = redis::pipe()
+            .atomic()
+            .incr(&key, 1)
+            .expire(&key, ttl)
+            .query(&mut *self.connection.write().await)
+            .wrap_err("Failed to record failed attempt in Redis")
+            .map_err(FailedAttemptStoreError::UnexpectedError)?;
+
+        Ok(count)
+    }
+
+    #[tracing::instrument(name = "Resetting failed attempts in Redis", skip_all)]
+    async fn reset(&mut self, key: &str) -> Result<(), FailedAttemptStoreError> {
+        let _: () = self
+            .connection
+            .write()
+            .await
+            .del(get_failures_key(key))
+            .wrap_err("Failed to reset failed attempts in Redis")
+            .map_err(FailedAttemptStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Locking out in Redis", skip_all)]
+    async fn lock(&mut self, key: &str, seconds: u64) -> Result<(), FailedAttemptStoreError> {
+        let _: () = self
+            .connection
+            .write()
+            .await
+            .set_ex(get_lockout_key(key), true, seconds)
+            .wrap_err("Failed to set lockout in Redis")
+            .map_err(FailedAttemptStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Getting lockout from Redis", skip_all)]
+    async fn get_lockout(&self, key: &str) -> Result<Option<u64>, FailedAttemptStoreError> {
+        // TTL is -2 when the key doesn't exist
+        let ttl: i64 = self
+            .connection
+            .write()
+            .await
+            .ttl(get_lockout_key(key))
+            .wrap_err("Failed to get lockout from Redis")
+            .map_err(FailedAttemptStoreError::UnexpectedError)?;
+
+        Ok(u64::try_from(ttl).ok().filter(|seconds| *seconds > 0))
+    }
+}
+
+const FAILED_ATTEMPTS_KEY_PREFIX: &str = "failed_attempts:";
+const LOCKOUT_KEY_PREFIX: &str = "lockout:";
+
+fn get_failures_key(key: &str) -> String {
+    format!("{}{}", FAILED_ATTEMPTS_KEY_PREFIX, key)
+}
+
+fn get_lockout_key(key: &str) -> String {
+    format!("{}{}", LOCKOUT_KEY_PREFIX, key)
+}
@@ -0,0 +1,129 @@
+use std::sync::Arc;
+
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection};
+use secrecy::{ExposeSecret, Secret};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        data_stores::{PasswordResetToken, PasswordResetTokenStore, PasswordResetTokenStoreError},
+        Email,
+    },
+    utils::auth::PASSWORD_RESET_TOKEN_TTL_SECONDS,
+};
+
+pub struct RedisPasswordResetTokenStore {
+    connection: Arc<RwLock<Connection>>,
+}
+
+impl RedisPasswordResetTokenStore {
+    pub fn new(connection: Arc<RwLock<Connection>>) -> Self {
+        Self { connection }
+    }
+}
+
+#[async_trait::async_trait]
+impl PasswordResetTokenStore for RedisPasswordResetTokenStore {
+    #[tracing::instrument(name = "Storing password reset token in Redis", skip_all)]
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: PasswordResetToken,
+    ) -> Result<(), PasswordResetTokenStoreError> {
+        let key = get_key(&token);
+        let user_key = get_user_key(&email);
+
+        let ttl: u64 = PASSWORD_RESET_TOKEN_TTL_SECONDS
+            .try_into()
+            .wrap_err("Failed to cast PASSWORD_RESET_TOKEN_TTL_SECONDS to u64")
+            .map_err(PasswordResetTokenStoreError::UnexpectedError)?;
+
+        let mut connection = self.connection.write().await;
+
+        let _: () = connection
+            .set_ex(&key, email.as_ref().expose_secret(), ttl)
+            .wrap_err("Failed to set password reset token in Redis")
+            .map_err(PasswordResetTokenStoreError::UnexpectedError)?;
+
+        // Track every token of the user so they can all be removed at once
+        let _: () = connection
+            .sadd(&user_key, &key)
+            .wrap_err("Failed to add password reset token to its user in Redis")
+            .map_err(PasswordResetTokenStoreError::UnexpectedError)?;
+
+        let _: () = connection
+            .expire(&user_key, PASSWORD_RESET_TOKEN_TTL_SECONDS)
+            .wrap_err("Failed to set password reset token user expiry in Redis")
+            .map_err(PasswordResetTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Taking password reset token from Redis", skip_all)]
+    async fn take_token(
+        &mut self,
+        token: &PasswordResetToken,
+    ) -> Result<Email, PasswordResetTokenStoreError> {
+        let key = get_key(token);
+
+        // GETDEL reads and removes the token atomically, so it cannot be redeemed twice
+        let value: Option<String> = self
+            .connection
+            .write()
+            .await
+            .get_del(&key)
+            .wrap_err("Failed to take password reset token from Redis")
+            .map_err(PasswordResetTokenStoreError::UnexpectedError)?;
+
+        match value {
+            Some(email) => Email::parse(Secret::new(email))
+                .map_err(PasswordResetTokenStoreError::UnexpectedError),
+            None => Err(PasswordResetTokenStoreError::TokenNotFound),
+        }
+    }
+
+    #[tracing::instrument(name = "Removing user password reset tokens from Redis", skip_all)]
+    async fn remove_user_tokens(
+        &mut self,
+        email: &Email,
+    ) -> Result<(), PasswordResetTokenStoreError> {
+        let user_key = get_user_key(email);
+
+        let mut connection = self.connection.write().await;
+
+        let keys: Vec<String> = connection
+            .smembers(&user_key)
+            .wrap_err("Failed to get password reset tokens of user from Redis")
+            .map_err(PasswordResetTokenStoreError::UnexpectedError)?;
+
+        if !keys.is_empty() {
+            let _: () = connection
+                .del(keys)
+                .wrap_err("Failed to remove password reset tokens from Redis")
+                .map_err(PasswordResetTokenStoreError::UnexpectedError)?;
+        }
+
+        let _: () = connection
+            .del(&user_key)
+            .wrap_err("Failed to remove password reset tokens of user from Redis")
+            .map_err(PasswordResetTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+}
+
+const PASSWORD_RESET_TOKEN_KEY_PREFIX: &str = "password_reset_token:";
+const PASSWORD_RESET_TOKEN_USER_KEY_PREFIX: &str = "password_reset_token_user:";
+
+fn get_key(token: &PasswordResetToken) -> String {
+    format!("{}{}", PASSWORD_RESET_TOKEN_KEY_PREFIX, token.hash())
+}
+
+fn get_user_key(email: &Email) -> String {
+    format!(
+        "{}{}",
+        PASSWORD_RESET_TOKEN_USER_KEY_PREFIX,
+        email.as_ref().expose_secret()
+    )
+}
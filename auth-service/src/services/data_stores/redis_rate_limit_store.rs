@@ -0,0 +1,87 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use color_eyre::eyre::Context;
+use redis::{Connection, Script};
+use tokio::sync::RwLock;
+
+use crate::domain::data_stores::{RateLimitStore, RateLimitStoreError, TokenBucket};
+
+// Runs as one script so replicas sharing the bucket can't interleave between the read and the write.
+// Token counts are returned as strings because Redis truncates Lua numbers to integers.
+const TAKE_TOKEN_SCRIPT: &str = r#"
+local capacity = tonumber(ARGV[1])
+local refill_ms = tonumber(ARGV[2])
+local now = tonumber(ARGV[3])
+
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'updated_at')
+local tokens = tonumber(bucket[1]) or capacity
+local updated_at = tonumber(bucket[2]) or now
+
+local elapsed = math.max(0, now - updated_at)
+tokens = math.min(capacity, tokens + elapsed * capacity / refill_ms)
+
+local allowed = 0
+if tokens >= 1 then
+    tokens = tokens - 1
+    allowed = 1
+end
+
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'updated_at', now)
+redis.call('PEXPIRE', KEYS[1], refill_ms)
+
+return {allowed, tostring(tokens)}
+"#;
+
+pub struct RedisRateLimitStore {
+    connection: Arc<RwLock<Connection>>,
+    script: Script,
+}
+
+impl RedisRateLimitStore {
+    pub fn new(connection: Arc<RwLock<Connection>>) -> Self {
+        Self {
+            connection,
+            script: Script::new(TAKE_TOKEN_SCRIPT),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for RedisRateLimitStore {
+    #[tracing::instrument(name = "Taking rate limit token from Redis", skip_all)]
+    async fn take_token(
+        &mut self,
+        key: &str,
+        capacity: u32,
+        refill_seconds: u64,
+    ) -> Result<TokenBucket, RateLimitStoreError> {
+        let refill_ms = refill_seconds.max(1).saturating_mul(1000);
+
+        let (allowed, tokens): (i64, String) = self
+            .script
+            .key(get_key(key))
+            .arg(capacity)
+            .arg(refill_ms)
+            .arg(Utc::now().timestamp_millis())
+            .invoke(&mut *self.connection.write().await)
+            .wrap_err("Failed to take rate limit token from Redis")
+            .map_err(RateLimitStoreError::UnexpectedError)?;
+
+        let tokens = tokens
+            .parse()
+            .wrap_err("Failed to parse token count from Redis")
+            .map_err(RateLimitStoreError::UnexpectedError)?;
+
+        Ok(TokenBucket {
+            allowed: allowed == 1,
+            tokens,
+        })
+    }
+}
+
+const RATE_LIMIT_KEY_PREFIX: &str = "rate_limit:";
+
+fn get_key(key: &str) -> String {
+    format!("{}{}", RATE_LIMIT_KEY_PREFIX, key)
+}
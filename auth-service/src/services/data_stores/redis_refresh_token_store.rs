@@ -0,0 +1,248 @@
+use std::sync::Arc;
+
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection, Script};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        data_stores::{
+            RefreshToken, RefreshTokenFamilyId, RefreshTokenStore, RefreshTokenStoreError,
+        },
+        Email,
+    },
+    utils::auth::REFRESH_TOKEN_TTL_SECONDS,
+};
+
+// Runs as one script so two replicas can't both read the token as unused before either marks it.
+// Returns the entry as it was, and keeps the used token around until it expires so a replay can be
+// detected.
+const CONSUME_TOKEN_SCRIPT: &str = r#"
+local value = redis.call('GET', KEYS[1])
+if not value then
+    return false
+end
+
+local entry = cjson.decode(value)
+if not entry.used then
+    entry.used = true
+    redis.call('SET', KEYS[1], cjson.encode(entry), 'KEEPTTL')
+end
+
+return value
+"#;
+
+pub struct RedisRefreshTokenStore {
+    connection: Arc<RwLock<Connection>>,
+    consume_script: Script,
+}
+
+impl RedisRefreshTokenStore {
+    pub fn new(connection: Arc<RwLock<Connection>>) -> Self {
+        Self {
+            connection,
+            consume_script: Script::new(CONSUME_TOKEN_SCRIPT),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for RedisRefreshTokenStore {
+    #[tracing::instrument(name = "Storing refresh token in Redis", skip_all)]
+    async fn add_token(
+        &mut self,
+        token: RefreshToken,
+        email: Email,
+        family_id: RefreshTokenFamilyId,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let token_key = get_token_key(&token);
+        let family_key = get_family_key(family_id.as_ref());
+        let user_key = get_user_key(&email);
+
+        let data = RefreshTokenEntry {
+            email: email.as_ref().expose_secret().to_owned(),
+            family_id: family_id.as_ref().to_owned(),
+            used: false,
+        };
+
+        let serialized_data = serde_json::to_string(&data)
+            .wrap_err("Failed to serialize refresh token entry")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        let ttl: u64 = REFRESH_TOKEN_TTL_SECONDS
+            .try_into()
+            .wrap_err("Failed to cast REFRESH_TOKEN_TTL_SECONDS to u64")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        let mut connection = self.connection.write().await;
+
+        let _: () = connection
+            .set_ex(&token_key, serialized_data, ttl)
+            .wrap_err("Failed to set refresh token in Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        // Track every token of the family so the whole chain can be revoked at once
+        let _: () = connection
+            .sadd(&family_key, &token_key)
+            .wrap_err("Failed to add refresh token to its family in Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        let _: () = connection
+            .expire(&family_key, REFRESH_TOKEN_TTL_SECONDS)
+            .wrap_err("Failed to set refresh token family expiry in Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        // Track every family of the user so all their sessions can be revoked at once
+        let _: () = connection
+            .sadd(&user_key, family_id.as_ref())
+            .wrap_err("Failed to add refresh token family to its user in Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        let _: () = connection
+            .expire(&user_key, REFRESH_TOKEN_TTL_SECONDS)
+            .wrap_err("Failed to set refresh token user expiry in Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Consuming refresh token in Redis", skip_all)]
+    async fn consume_token(
+        &mut self,
+        token: &RefreshToken,
+    ) -> Result<(Email, RefreshTokenFamilyId), RefreshTokenStoreError> {
+        let value: String = self
+            .consume_script
+            .key(get_token_key(token))
+            .invoke::<Option<String>>(&mut *self.connection.write().await)
+            .wrap_err("Failed to consume refresh token in Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?
+            .ok_or(RefreshTokenStoreError::TokenNotFound)?;
+
+        let data: RefreshTokenEntry = serde_json::from_str(&value)
+            .wrap_err("Failed to deserialize refresh token entry")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        let family_id = RefreshTokenFamilyId::parse(data.family_id)
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        if data.used {
+            return Err(RefreshTokenStoreError::TokenReused(family_id));
+        }
+
+        let email = Email::parse(Secret::new(data.email))
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        Ok((email, family_id))
+    }
+
+    #[tracing::instrument(name = "Getting refresh token family from Redis", skip_all)]
+    async fn get_family(
+        &self,
+        token: &RefreshToken,
+    ) -> Result<RefreshTokenFamilyId, RefreshTokenStoreError> {
+        let token_key = get_token_key(token);
+
+        let mut connection = self.connection.write().await;
+
+        let value: String = connection
+            .get::<_, Option<String>>(&token_key)
+            .wrap_err("Failed to get refresh token from Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?
+            .ok_or(RefreshTokenStoreError::TokenNotFound)?;
+
+        let data: RefreshTokenEntry = serde_json::from_str(&value)
+            .wrap_err("Failed to deserialize refresh token entry")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        RefreshTokenFamilyId::parse(data.family_id).map_err(RefreshTokenStoreError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Revoking refresh token family in Redis", skip_all)]
+    async fn revoke_family(
+        &mut self,
+        family_id: &RefreshTokenFamilyId,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let mut connection = self.connection.write().await;
+
+        delete_family(&mut connection, family_id.as_ref())
+    }
+
+    #[tracing::instrument(name = "Revoking user refresh tokens in Redis", skip_all)]
+    async fn revoke_user_tokens(&mut self, email: &Email) -> Result<(), RefreshTokenStoreError> {
+        let user_key = get_user_key(email);
+
+        let mut connection = self.connection.write().await;
+
+        let family_ids: Vec<String> = connection
+            .smembers(&user_key)
+            .wrap_err("Failed to get refresh token families of user from Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        for family_id in family_ids {
+            delete_family(&mut connection, &family_id)?;
+        }
+
+        let _: () = connection
+            .del(&user_key)
+            .wrap_err("Failed to remove refresh token families of user from Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+}
+
+fn delete_family(
+    connection: &mut Connection,
+    family_id: &str,
+) -> Result<(), RefreshTokenStoreError> {
+    let family_key = get_family_key(family_id);
+
+    let token_keys: Vec<String> = connection
+        .smembers(&family_key)
+        .wrap_err("Failed to get refresh token family from Redis")
+        .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+    if !token_keys.is_empty() {
+        let _: () = connection
+            .del(token_keys)
+            .wrap_err("Failed to remove refresh tokens from Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+    }
+
+    let _: () = connection
+        .del(&family_key)
+        .wrap_err("Failed to remove refresh token family from Redis")
+        .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+    Ok(())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RefreshTokenEntry {
+    email: String,
+    family_id: String,
+    used: bool,
+}
+
+const REFRESH_TOKEN_KEY_PREFIX: &str = "refresh_token:";
+const REFRESH_TOKEN_FAMILY_KEY_PREFIX: &str = "refresh_token_family:";
+const REFRESH_TOKEN_USER_KEY_PREFIX: &str = "refresh_token_user:";
+
+fn get_token_key(token: &RefreshToken) -> String {
+    format!("{}{}", REFRESH_TOKEN_KEY_PREFIX, token.hash())
+}
+
+fn get_family_key(family_id: &str) -> String {
+    format!("{}{}", REFRESH_TOKEN_FAMILY_KEY_PREFIX, family_id)
+}
+
+fn get_user_key(email: &Email) -> String {
+    format!(
+        "{}{}",
+        REFRESH_TOKEN_USER_KEY_PREFIX,
+        email.as_ref().expose_secret()
+    )
+}
@@ -0,0 +1,101 @@
+use std::sync::Arc;
+
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        data_stores::{WebAuthnChallengeStore, WebAuthnChallengeStoreError},
+        Email, WebAuthnCeremony, WebAuthnChallenge,
+    },
+    utils::webauthn::WEBAUTHN_CHALLENGE_TTL_SECONDS,
+};
+
+pub struct RedisWebAuthnChallengeStore {
+    connection: Arc<RwLock<Connection>>,
+}
+
+impl RedisWebAuthnChallengeStore {
+    pub fn new(connection: Arc<RwLock<Connection>>) -> Self {
+        Self { connection }
+    }
+}
+
+#[async_trait::async_trait]
+impl WebAuthnChallengeStore for RedisWebAuthnChallengeStore {
+    #[tracing::instrument(name = "Storing WebAuthn challenge in Redis", skip_all)]
+    async fn add_challenge(
+        &mut self,
+        challenge: WebAuthnChallenge,
+        email: Email,
+        ceremony: WebAuthnCeremony,
+    ) -> Result<(), WebAuthnChallengeStoreError> {
+        let key = get_key(&challenge);
+
+        let data = ChallengeInfo {
+            email: email.as_ref().expose_secret().to_owned(),
+            ceremony,
+        };
+        let serialized_data = serde_json::to_string(&data)
+            .wrap_err("Failed to serialize WebAuthn challenge")
+            .map_err(WebAuthnChallengeStoreError::UnexpectedError)?;
+
+        let ttl: u64 = WEBAUTHN_CHALLENGE_TTL_SECONDS
+            .try_into()
+            .wrap_err("Failed to cast WEBAUTHN_CHALLENGE_TTL_SECONDS to u64")
+            .map_err(WebAuthnChallengeStoreError::UnexpectedError)?;
+
+        let _: () = self
+            .connection
+            .write()
+            .await
+            .set_ex(&key, serialized_data, ttl)
+            .wrap_err("Failed to set WebAuthn challenge in Redis")
+            .map_err(WebAuthnChallengeStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Taking WebAuthn challenge from Redis", skip_all)]
+    async fn take_challenge(
+        &mut self,
+        challenge: &WebAuthnChallenge,
+    ) -> Result<(Email, WebAuthnCeremony), WebAuthnChallengeStoreError> {
+        let key = get_key(challenge);
+
+        // GETDEL reads and removes the challenge atomically, so it cannot be answered twice
+        let value: Option<String> = self
+            .connection
+            .write()
+            .await
+            .get_del(&key)
+            .wrap_err("Failed to take WebAuthn challenge from Redis")
+            .map_err(WebAuthnChallengeStoreError::UnexpectedError)?;
+
+        let value = value.ok_or(WebAuthnChallengeStoreError::ChallengeNotFound)?;
+
+        let data: ChallengeInfo = serde_json::from_str(&value)
+            .wrap_err("Failed to deserialize WebAuthn challenge")
+            .map_err(WebAuthnChallengeStoreError::UnexpectedError)?;
+
+        let email = Email::parse(Secret::new(data.email))
+            .map_err(WebAuthnChallengeStoreError::UnexpectedError)?;
+
+        Ok((email, data.ceremony))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ChallengeInfo {
+    email: String,
+    ceremony: WebAuthnCeremony,
+}
+
+const WEBAUTHN_CHALLENGE_KEY_PREFIX: &str = "webauthn_challenge:";
+
+fn get_key(challenge: &WebAuthnChallenge) -> String {
+    format!("{}{}", WEBAUTHN_CHALLENGE_KEY_PREFIX, challenge.hash())
+}
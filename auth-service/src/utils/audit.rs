@@ -0,0 +1,29 @@
+use color_eyre::eyre::Result;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuditEvent, AuditEventKind, Email},
+};
+
+/// Records an event on the user's account. What it records has already happened, so a failure is
+/// logged rather than failing the request.
+#[tracing::instrument(name = "Record audit event", skip_all)]
+pub async fn record_audit_event(email: &Email, kind: AuditEventKind, state: &AppState) {
+    if let Err(e) = add_audit_event(email, kind, state).await {
+        tracing::error!("Failed to record {} audit event: {:?}", kind.as_str(), e);
+    }
+}
+
+// Events are kept by user ID, so they stay with the user should their email change
+async fn add_audit_event(email: &Email, kind: AuditEventKind, state: &AppState) -> Result<()> {
+    let user = state.user_store.read().await.get_user(email).await?;
+
+    state
+        .audit_event_store
+        .write()
+        .await
+        .add_event(AuditEvent::new(user.id, kind))
+        .await?;
+
+    Ok(())
+}
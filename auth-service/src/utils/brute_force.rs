@@ -0,0 +1,121 @@
+use std::net::IpAddr;
+
+use secrecy::ExposeSecret;
+
+use crate::{
+    app_state::FailedAttemptStoreType,
+    domain::{AuthAPIError, Email, LoginAttemptId},
+};
+
+use super::constants::LOCKOUT_BASE_SECONDS;
+
+pub const MAX_LOCKOUT_SECONDS: u64 = 60 * 60;
+
+// Counters have to outlive the longest lockout, otherwise the backoff would start over after it
+pub const FAILED_ATTEMPT_WINDOW_SECONDS: u64 = 2 * MAX_LOCKOUT_SECONDS;
+
+// Normalised like the rate limiter's email key, so changing the case doesn't buy a fresh counter
+pub fn email_key(email: &Email) -> String {
+    format!(
+        "email:{}",
+        email.as_ref().expose_secret().trim().to_lowercase()
+    )
+}
+
+pub fn ip_key(ip: IpAddr) -> String {
+    format!("ip:{}", ip)
+}
+
+pub fn login_attempt_key(login_attempt_id: &LoginAttemptId) -> String {
+    format!(
+        "login_attempt:{}",
+        login_attempt_id.as_ref().expose_secret()
+    )
+}
+
+/// Rejects the request if any of the keys is currently locked out.
+#[tracing::instrument(name = "Check lockout", skip_all)]
+pub async fn check_lockout(
+    store: &FailedAttemptStoreType,
+    keys: &[String],
+) -> Result<(), AuthAPIError> {
+    let store = store.read().await;
+
+    let mut retry_after = None;
+    for key in keys {
+        let lockout = store
+            .get_lockout(key)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+        retry_after = retry_after.max(lockout);
+    }
+
+    match retry_after {
+        Some(seconds) => Err(AuthAPIError::TooManyAttempts(seconds)),
+        None => Ok(()),
+    }
+}
+
+/// Counts a failure against the key and locks it out once `max_attempts` is reached.
+/// Every failure past the limit doubles the lockout.
+#[tracing::instrument(name = "Record failed attempt", skip_all)]
+pub async fn record_failed_attempt(
+    store: &FailedAttemptStoreType,
+    key: &str,
+    max_attempts: u32,
+) -> Result<(), AuthAPIError> {
+    let mut store = store.write().await;
+
+    let count = store
+        .record_failure(key, FAILED_ATTEMPT_WINDOW_SECONDS)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    if count >= max_attempts {
+        let seconds = lockout_seconds(*LOCKOUT_BASE_SECONDS, count - max_attempts);
+        store
+            .lock(key, seconds)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    }
+
+    Ok(())
+}
+
+fn lockout_seconds(base_seconds: u64, failures_over_limit: u32) -> u64 {
+    2u64.checked_pow(failures_over_limit)
+        .and_then(|factor| base_seconds.checked_mul(factor))
+        .map_or(MAX_LOCKOUT_SECONDS, |seconds| {
+            seconds.min(MAX_LOCKOUT_SECONDS)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+
+    #[test]
+    fn test_lockout_doubles_with_each_failure() {
+        assert_eq!(lockout_seconds(30, 0), 30);
+        assert_eq!(lockout_seconds(30, 1), 60);
+        assert_eq!(lockout_seconds(30, 3), 240);
+    }
+
+    #[test]
+    fn test_lockout_is_capped() {
+        assert_eq!(lockout_seconds(30, 10), MAX_LOCKOUT_SECONDS);
+        assert_eq!(lockout_seconds(30, u32::MAX), MAX_LOCKOUT_SECONDS);
+    }
+
+    #[test]
+    fn test_email_key_ignores_case() {
+        let email = |s: &str| Email::parse(Secret::new(s.to_owned())).unwrap();
+
+        assert_eq!(
+            email_key(&email("User@Example.com")),
+            email_key(&email("user@example.com"))
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use std::net::{IpAddr, SocketAddr};
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{request::Parts, HeaderMap, HeaderName},
+};
+use ipnet::IpNet;
+
+use super::constants::TRUSTED_PROXIES;
+
+const X_FORWARDED_FOR: HeaderName = HeaderName::from_static("x-forwarded-for");
+
+/// The IP address of the client making the request, rather than of a load balancer in front of the service.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClientIp(pub IpAddr);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ClientIp {
+    type Rejection = <ConnectInfo<SocketAddr> as FromRequestParts<S>>::Rejection;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let ConnectInfo(address) =
+            ConnectInfo::<SocketAddr>::from_request_parts(parts, state).await?;
+
+        Ok(ClientIp(client_ip(
+            address.ip(),
+            &parts.headers,
+            &TRUSTED_PROXIES,
+        )))
+    }
+}
+
+/// Follows `X-Forwarded-For` back from the peer for as long as the hops are trusted proxies.
+/// Anyone can send the header, so only the entries appended by trusted proxies are believed.
+pub fn client_ip(peer: IpAddr, headers: &HeaderMap, trusted_proxies: &[IpNet]) -> IpAddr {
+    let is_trusted = |ip: &IpAddr| trusted_proxies.iter().any(|network| network.contains(ip));
+
+    if !is_trusted(&peer) {
+        return peer;
+    }
+
+    // Each proxy appends the address it got the request from, so the nearest hop comes last
+    let forwarded_for: Vec<&str> = headers
+        .get_all(X_FORWARDED_FOR)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .collect();
+
+    let mut client = peer;
+    for hop in forwarded_for.into_iter().rev() {
+        match hop.parse() {
+            Ok(ip) => client = ip,
+            // Whatever a trusted proxy put there, it isn't an address to count requests against
+            Err(_) => break,
+        }
+        if !is_trusted(&client) {
+            break;
+        }
+    }
+
+    client
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    fn headers(forwarded_for: &[&str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for value in forwarded_for {
+            headers.append(X_FORWARDED_FOR, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_peer_is_the_client_without_trusted_proxies() {
+        let headers = headers(&["203.0.113.7"]);
+
+        assert_eq!(
+            client_ip(ip("198.51.100.1"), &headers, &[]),
+            ip("198.51.100.1")
+        );
+    }
+
+    #[test]
+    fn test_forwarded_for_is_ignored_from_untrusted_peers() {
+        let trusted_proxies = ["10.0.0.0/8".parse().unwrap()];
+        let headers = headers(&["203.0.113.7"]);
+
+        assert_eq!(
+            client_ip(ip("198.51.100.1"), &headers, &trusted_proxies),
+            ip("198.51.100.1")
+        );
+    }
+
+    #[test]
+    fn test_client_is_the_last_untrusted_hop() {
+        let trusted_proxies = [
+            "10.0.0.0/8".parse().unwrap(),
+            "192.0.2.1/32".parse().unwrap(),
+        ];
+        // The client made up the first entry, the load balancers appended the rest
+        let headers = headers(&["1.2.3.4, 203.0.113.7", "192.0.2.1"]);
+
+        assert_eq!(
+            client_ip(ip("10.0.0.2"), &headers, &trusted_proxies),
+            ip("203.0.113.7")
+        );
+    }
+
+    #[test]
+    fn test_client_behind_trusted_proxy_without_header() {
+        let trusted_proxies = ["10.0.0.0/8".parse().unwrap()];
+
+        assert_eq!(
+            client_ip(ip("10.0.0.2"), &HeaderMap::new(), &trusted_proxies),
+            ip("10.0.0.2")
+        );
+    }
+
+    #[test]
+    fn test_malformed_hop_stops_the_search() {
+        let trusted_proxies = ["10.0.0.0/8".parse().unwrap()];
+        let headers = headers(&["203.0.113.7, unknown, 10.0.0.3"]);
+
+        assert_eq!(
+            client_ip(ip("10.0.0.2"), &headers, &trusted_proxies),
+            ip("10.0.0.3")
+        );
+    }
+}
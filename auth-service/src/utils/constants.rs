@@ -1,14 +1,39 @@
+use chrono::Utc;
 use dotenvy::dotenv;
+use ipnet::IpNet;
+use jsonwebtoken::Algorithm;
 use lazy_static::lazy_static;
 use secrecy::Secret;
-use std::env as std_env;
+use std::{env as std_env, net::IpAddr, path::Path, str::FromStr, sync::RwLock};
+
+use super::{
+    auth::EMAIL_VERIFICATION_TOKEN_TTL_SECONDS,
+    jwt_keys::{JwtKeyring, JwtSigningKey},
+};
 
 // Define a lazily evaluated static. lazy_static is needed because std::env::var is not a const function.
 lazy_static! {
     pub static ref JWT_SECRET: Secret<String> = set_token();
+    pub static ref JWT_KEYS_DIR: Option<String> = set_jwt_keys_dir();
+    pub static ref JWT_KEY_RETENTION_SECONDS: i64 = set_jwt_key_retention_seconds();
+    pub static ref JWT_KEYRING: RwLock<JwtKeyring> = RwLock::new(set_jwt_keyring());
     pub static ref DATABASE_URL: Secret<String> = set_db_url();
     pub static ref REDIS_HOST_NAME: String = set_redis_host();
     pub static ref POSTMARK_AUTH_TOKEN: Secret<String> = set_postmark_auth_token();
+    pub static ref AUTH_SERVICE_URL: String = set_auth_service_url();
+    pub static ref JWT_ISSUER: String = set_jwt_issuer();
+    pub static ref JWT_AUDIENCE: String = set_jwt_audience();
+    pub static ref REQUIRE_EMAIL_VERIFICATION: bool = set_require_email_verification();
+    pub static ref ACCEPT_EMAIL_SUBJECTS: bool = set_accept_email_subjects();
+    pub static ref TOTP_ENCRYPTION_KEY: Secret<String> = set_totp_encryption_key();
+    pub static ref TOTP_SKEW: u8 = set_totp_skew();
+    pub static ref WEBAUTHN_RP_ID: String = set_webauthn_rp_id();
+    pub static ref MAX_FAILED_LOGIN_ATTEMPTS: u32 = set_max_failed_login_attempts();
+    pub static ref MAX_FAILED_LOGIN_ATTEMPTS_PER_IP: u32 = set_max_failed_login_attempts_per_ip();
+    pub static ref MAX_FAILED_2FA_ATTEMPTS: u32 = set_max_failed_2fa_attempts();
+    pub static ref LOCKOUT_BASE_SECONDS: u64 = set_lockout_base_seconds();
+    pub static ref RATE_LIMITS: String = set_rate_limits();
+    pub static ref TRUSTED_PROXIES: Vec<IpNet> = set_trusted_proxies();
 }
 
 fn set_token() -> Secret<String> {
@@ -20,6 +45,65 @@ fn set_token() -> Secret<String> {
     Secret::new(secret)
 }
 
+fn set_jwt_keys_dir() -> Option<String> {
+    dotenv().ok();
+    std_env::var(env::JWT_KEYS_DIR_ENV_VAR).ok()
+}
+
+fn set_jwt_key_retention_seconds() -> i64 {
+    dotenv().ok();
+    match std_env::var(env::JWT_KEY_RETENTION_SECONDS_ENV_VAR) {
+        Ok(value) => value
+            .parse()
+            .expect("JWT_KEY_RETENTION_SECONDS must be a number of seconds"),
+        Err(_) => DEFAULT_JWT_KEY_RETENTION_SECONDS,
+    }
+}
+
+fn set_jwt_keyring() -> JwtKeyring {
+    dotenv().ok();
+    if let Some(dir) = JWT_KEYS_DIR.as_ref() {
+        return JwtKeyring::load_dir(
+            Path::new(dir),
+            Utc::now().timestamp(),
+            *JWT_KEY_RETENTION_SECONDS,
+        )
+        .expect("Failed to load JWT keys");
+    }
+
+    // Without a key directory, rotate the secret by moving the old one to JWT_PREVIOUS_SECRETS
+    let previous_keys = match std_env::var(env::JWT_PREVIOUS_SECRETS_ENV_VAR) {
+        Ok(value) => value
+            .split(',')
+            .filter(|secret| !secret.is_empty())
+            .map(|secret| JwtSigningKey::from_secret(&Secret::new(secret.to_owned())))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+
+    JwtKeyring::new(set_jwt_signing_key(), previous_keys)
+}
+
+fn set_jwt_signing_key() -> JwtSigningKey {
+    dotenv().ok();
+    let algorithm = match std_env::var(env::JWT_SIGNING_ALGORITHM_ENV_VAR) {
+        Ok(value) => Algorithm::from_str(&value)
+            .expect("JWT_SIGNING_ALGORITHM must be one of HS256, RS256 or EdDSA"),
+        Err(_) => DEFAULT_JWT_SIGNING_ALGORITHM,
+    };
+
+    // The shared secret is only needed when tokens are signed with it
+    if algorithm == Algorithm::HS256 {
+        return JwtSigningKey::from_secret(&JWT_SECRET);
+    }
+
+    let path = std_env::var(env::JWT_PRIVATE_KEY_PATH_ENV_VAR)
+        .expect("JWT_PRIVATE_KEY_PATH must be set for asymmetric signing");
+    let pem = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("Failed to read JWT private key from {}: {}", path, e));
+    JwtSigningKey::from_pem(algorithm, &pem).expect("Invalid JWT private key")
+}
+
 fn set_db_url() -> Secret<String> {
     dotenv().ok();
     Secret::new(std_env::var(env::DATABASE_URL_ENV_VAR).expect("DATABASE_URL must be set"))
@@ -37,18 +121,224 @@ fn set_postmark_auth_token() -> Secret<String> {
     )
 }
 
+fn set_auth_service_url() -> String {
+    dotenv().ok();
+    std_env::var(env::AUTH_SERVICE_URL_ENV_VAR).unwrap_or(DEFAULT_AUTH_SERVICE_URL.to_owned())
+}
+
+fn set_jwt_issuer() -> String {
+    dotenv().ok();
+    std_env::var(env::JWT_ISSUER_ENV_VAR).unwrap_or(AUTH_SERVICE_URL.to_owned())
+}
+
+fn set_jwt_audience() -> String {
+    dotenv().ok();
+    std_env::var(env::JWT_AUDIENCE_ENV_VAR).unwrap_or(DEFAULT_JWT_AUDIENCE.to_owned())
+}
+
+fn set_require_email_verification() -> bool {
+    dotenv().ok();
+    match std_env::var(env::REQUIRE_EMAIL_VERIFICATION_ENV_VAR) {
+        Ok(value) => value
+            .parse()
+            .expect("REQUIRE_EMAIL_VERIFICATION must be true or false"),
+        Err(_) => DEFAULT_REQUIRE_EMAIL_VERIFICATION,
+    }
+}
+
+fn set_accept_email_subjects() -> bool {
+    dotenv().ok();
+    match std_env::var(env::ACCEPT_EMAIL_SUBJECTS_ENV_VAR) {
+        Ok(value) => value
+            .parse()
+            .expect("ACCEPT_EMAIL_SUBJECTS must be true or false"),
+        Err(_) => DEFAULT_ACCEPT_EMAIL_SUBJECTS,
+    }
+}
+
+fn set_totp_encryption_key() -> Secret<String> {
+    dotenv().ok();
+    let key =
+        std_env::var(env::TOTP_ENCRYPTION_KEY_ENV_VAR).expect("TOTP_ENCRYPTION_KEY must be set");
+    if key.is_empty() {
+        panic!("TOTP_ENCRYPTION_KEY must not be empty.");
+    }
+    Secret::new(key)
+}
+
+fn set_totp_skew() -> u8 {
+    dotenv().ok();
+    match std_env::var(env::TOTP_SKEW_ENV_VAR) {
+        Ok(value) => value
+            .parse()
+            .expect("TOTP_SKEW must be a number of 30 second steps"),
+        Err(_) => DEFAULT_TOTP_SKEW,
+    }
+}
+
+fn set_webauthn_rp_id() -> String {
+    dotenv().ok();
+    std_env::var(env::WEBAUTHN_RP_ID_ENV_VAR).unwrap_or(DEFAULT_WEBAUTHN_RP_ID.to_owned())
+}
+
+fn set_max_failed_login_attempts() -> u32 {
+    dotenv().ok();
+    match std_env::var(env::MAX_FAILED_LOGIN_ATTEMPTS_ENV_VAR) {
+        Ok(value) => value
+            .parse()
+            .expect("MAX_FAILED_LOGIN_ATTEMPTS must be a number"),
+        Err(_) => DEFAULT_MAX_FAILED_LOGIN_ATTEMPTS,
+    }
+}
+
+fn set_max_failed_login_attempts_per_ip() -> u32 {
+    dotenv().ok();
+    match std_env::var(env::MAX_FAILED_LOGIN_ATTEMPTS_PER_IP_ENV_VAR) {
+        Ok(value) => value
+            .parse()
+            .expect("MAX_FAILED_LOGIN_ATTEMPTS_PER_IP must be a number"),
+        Err(_) => DEFAULT_MAX_FAILED_LOGIN_ATTEMPTS_PER_IP,
+    }
+}
+
+fn set_max_failed_2fa_attempts() -> u32 {
+    dotenv().ok();
+    match std_env::var(env::MAX_FAILED_2FA_ATTEMPTS_ENV_VAR) {
+        Ok(value) => value
+            .parse()
+            .expect("MAX_FAILED_2FA_ATTEMPTS must be a number"),
+        Err(_) => DEFAULT_MAX_FAILED_2FA_ATTEMPTS,
+    }
+}
+
+fn set_lockout_base_seconds() -> u64 {
+    dotenv().ok();
+    match std_env::var(env::LOCKOUT_BASE_SECONDS_ENV_VAR) {
+        Ok(value) => value
+            .parse()
+            .expect("LOCKOUT_BASE_SECONDS must be a number of seconds"),
+        Err(_) => DEFAULT_LOCKOUT_BASE_SECONDS,
+    }
+}
+
+fn set_rate_limits() -> String {
+    dotenv().ok();
+    std_env::var(env::RATE_LIMITS_ENV_VAR).unwrap_or(DEFAULT_RATE_LIMITS.to_owned())
+}
+
+fn set_trusted_proxies() -> Vec<IpNet> {
+    dotenv().ok();
+    match std_env::var(env::TRUSTED_PROXIES_ENV_VAR) {
+        Ok(value) => value
+            .split(',')
+            .map(str::trim)
+            .filter(|proxy| !proxy.is_empty())
+            .map(|proxy| {
+                // A bare address trusts just that host
+                proxy
+                    .parse()
+                    .or_else(|_| proxy.parse::<IpAddr>().map(IpNet::from))
+                    .unwrap_or_else(|_| {
+                        panic!(
+                            "TRUSTED_PROXIES must list IP addresses or networks, not {}",
+                            proxy
+                        )
+                    })
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Panics unless the settings whose defaults only suit local development are configured.
+/// Without them signups get verification links to localhost and passkeys are bound to it.
+pub fn check_prod_env() {
+    dotenv().ok();
+    for var in prod::REQUIRED_ENV_VARS {
+        match std_env::var(var) {
+            Ok(value) if !value.is_empty() => {}
+            _ => panic!("{} must be set", var),
+        }
+    }
+}
+
 pub mod env {
     pub const JWT_SECRET_ENV_VAR: &str = "JWT_SECRET";
+    pub const JWT_SIGNING_ALGORITHM_ENV_VAR: &str = "JWT_SIGNING_ALGORITHM";
+    pub const JWT_PRIVATE_KEY_PATH_ENV_VAR: &str = "JWT_PRIVATE_KEY_PATH";
+    pub const JWT_PREVIOUS_SECRETS_ENV_VAR: &str = "JWT_PREVIOUS_SECRETS";
+    pub const JWT_KEYS_DIR_ENV_VAR: &str = "JWT_KEYS_DIR";
+    pub const JWT_KEY_RETENTION_SECONDS_ENV_VAR: &str = "JWT_KEY_RETENTION_SECONDS";
     pub const DATABASE_URL_ENV_VAR: &str = "DATABASE_URL";
     pub const REDIS_HOST_NAME_ENV_VAR: &str = "REDIS_HOST_NAME";
     pub const POSTMARK_AUTH_TOKEN_ENV_VAR: &str = "POSTMARK_AUTH_TOKEN";
+    pub const AUTH_SERVICE_URL_ENV_VAR: &str = "AUTH_SERVICE_URL";
+    pub const JWT_ISSUER_ENV_VAR: &str = "JWT_ISSUER";
+    pub const JWT_AUDIENCE_ENV_VAR: &str = "JWT_AUDIENCE";
+    pub const REQUIRE_EMAIL_VERIFICATION_ENV_VAR: &str = "REQUIRE_EMAIL_VERIFICATION";
+    pub const ACCEPT_EMAIL_SUBJECTS_ENV_VAR: &str = "ACCEPT_EMAIL_SUBJECTS";
+    pub const TOTP_ENCRYPTION_KEY_ENV_VAR: &str = "TOTP_ENCRYPTION_KEY";
+    pub const TOTP_SKEW_ENV_VAR: &str = "TOTP_SKEW";
+    pub const WEBAUTHN_RP_ID_ENV_VAR: &str = "WEBAUTHN_RP_ID";
+    pub const MAX_FAILED_LOGIN_ATTEMPTS_ENV_VAR: &str = "MAX_FAILED_LOGIN_ATTEMPTS";
+    pub const MAX_FAILED_LOGIN_ATTEMPTS_PER_IP_ENV_VAR: &str = "MAX_FAILED_LOGIN_ATTEMPTS_PER_IP";
+    pub const MAX_FAILED_2FA_ATTEMPTS_ENV_VAR: &str = "MAX_FAILED_2FA_ATTEMPTS";
+    pub const LOCKOUT_BASE_SECONDS_ENV_VAR: &str = "LOCKOUT_BASE_SECONDS";
+    pub const RATE_LIMITS_ENV_VAR: &str = "RATE_LIMITS";
+    pub const TRUSTED_PROXIES_ENV_VAR: &str = "TRUSTED_PROXIES";
 }
 
 pub const JWT_COOKIE_NAME: &str = "jwt";
+pub const REFRESH_TOKEN_COOKIE_NAME: &str = "refresh_token";
+pub const DEFAULT_JWT_SIGNING_ALGORITHM: Algorithm = Algorithm::HS256;
+// Long enough for every token a retired key signed to expire, the longest lived being email verification links
+pub const DEFAULT_JWT_KEY_RETENTION_SECONDS: i64 = EMAIL_VERIFICATION_TOKEN_TTL_SECONDS;
+// How often the key directory is re-read, so scheduled keys take over without a restart
+pub const JWT_KEYRING_RELOAD_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+// How often the in-memory banned token store forgets the bans on tokens that have since expired
+pub const BANNED_TOKEN_EVICTION_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+// New keys are published this long before they sign anything, so services caching the JWKS pick them up first
+pub const DEFAULT_JWT_KEY_ACTIVATION_DELAY_SECONDS: i64 = 60 * 60;
 pub const DEFAULT_REDIS_HOST_NAME: &str = "127.0.0.1";
+pub const DEFAULT_AUTH_SERVICE_URL: &str = "http://localhost:3000";
+// The service auth tokens are issued for, unless a relying service asks /verify-token for its own
+pub const DEFAULT_JWT_AUDIENCE: &str = "app-service";
+pub const DEFAULT_REQUIRE_EMAIL_VERIFICATION: bool = true;
+// Auth tokens used to name the user by email rather than ID. Those still around are accepted until
+// they expire, after which this can be turned off.
+pub const DEFAULT_ACCEPT_EMAIL_SUBJECTS: bool = true;
+// Accept codes from one 30 second step either side of the current one, to allow for clock drift
+pub const DEFAULT_TOTP_SKEW: u8 = 1;
+pub const TOTP_ISSUER: &str = "Rust Web App";
+// Passkeys are scoped to this domain, so it has to match the host the login page is served from
+pub const DEFAULT_WEBAUTHN_RP_ID: &str = "localhost";
+pub const WEBAUTHN_RP_NAME: &str = "Rust Web App";
+// Failures per account, counting both wrong passwords and wrong 2FA codes
+pub const DEFAULT_MAX_FAILED_LOGIN_ATTEMPTS: u32 = 5;
+// Higher than the per-account limit, as many users can share an IP behind NAT
+pub const DEFAULT_MAX_FAILED_LOGIN_ATTEMPTS_PER_IP: u32 = 50;
+// Wrong codes allowed before the login attempt is thrown away and the password must be entered again
+pub const DEFAULT_MAX_FAILED_2FA_ATTEMPTS: u32 = 3;
+// First lockout length; it doubles with every further failure
+pub const DEFAULT_LOCKOUT_BASE_SECONDS: u64 = 30;
+// Requests per client IP, with tighter budgets on the routes that send emails or check credentials.
+// Behind a load balancer, list it in TRUSTED_PROXIES or every client shares its IP's budget.
+pub const DEFAULT_RATE_LIMITS: &str = "*=ip:300/60;\
+    /signup=ip:10/3600;\
+    /resend-verification=ip:10/3600,email:3/3600;\
+    /password-reset/request=ip:10/3600,email:3/3600;\
+    /login=ip:30/60,email:10/60;\
+    /verify-2fa=ip:30/60";
 
 pub mod prod {
+    use super::env;
+
     pub const APP_ADDRESS: &str = "0.0.0.0:3000";
+    pub const REQUIRED_ENV_VARS: [&str; 3] = [
+        env::AUTH_SERVICE_URL_ENV_VAR,
+        env::JWT_ISSUER_ENV_VAR,
+        env::WEBAUTHN_RP_ID_ENV_VAR,
+    ];
     pub mod email_client {
         use std::time::Duration;
 
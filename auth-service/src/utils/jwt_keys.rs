@@ -0,0 +1,619 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    os::unix::fs::OpenOptionsExt,
+    path::{Path, PathBuf},
+    sync::RwLock,
+    time::Duration,
+};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use color_eyre::eyre::{eyre, Context, ContextCompat, Result};
+use jsonwebtoken::{
+    decode, decode_header, encode,
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+        OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+    },
+    Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use ring::{
+    rand::SystemRandom,
+    rsa::{self, PublicKeyComponents},
+    signature::{Ed25519KeyPair, KeyPair},
+};
+use secrecy::{ExposeSecret, Secret};
+use serde::{de::DeserializeOwned, Serialize};
+use sha2::{Digest, Sha256};
+
+/// A key tokens are signed or verified with.
+/// Asymmetric keys publish their public half as a JWK, so other services can verify tokens themselves.
+pub struct JwtSigningKey {
+    algorithm: Algorithm,
+    kid: String,
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    public_jwk: Option<Jwk>,
+}
+
+impl JwtSigningKey {
+    pub fn from_secret(secret: &Secret<String>) -> Self {
+        let secret = secret.expose_secret().as_bytes();
+        let thumbprint = format!(
+            r#"{{"k":"{}","kty":"oct"}}"#,
+            URL_SAFE_NO_PAD.encode(secret)
+        );
+
+        Self {
+            algorithm: Algorithm::HS256,
+            kid: compute_thumbprint(&thumbprint),
+            encoding_key: EncodingKey::from_secret(secret),
+            decoding_key: DecodingKey::from_secret(secret),
+            public_jwk: None,
+        }
+    }
+
+    /// Loads an RS256 key from a PKCS#1 or PKCS#8 PEM, or an EdDSA key from a PKCS#8 PEM.
+    pub fn from_pem(algorithm: Algorithm, pem: &str) -> Result<Self> {
+        let parsed = pem::parse(pem).wrap_err("Failed to parse PEM")?;
+
+        let (encoding_key, decoding_key, parameters, thumbprint) = match algorithm {
+            Algorithm::RS256 => {
+                let key_pair = match parsed.tag() {
+                    "PRIVATE KEY" => rsa::KeyPair::from_pkcs8(parsed.contents()),
+                    "RSA PRIVATE KEY" => rsa::KeyPair::from_der(parsed.contents()),
+                    tag => return Err(eyre!("Expected an RSA private key, found {}", tag)),
+                }
+                .map_err(|e| eyre!("Invalid RSA private key: {}", e))?;
+
+                let components = PublicKeyComponents::<Vec<u8>>::from(key_pair.public());
+                let n = URL_SAFE_NO_PAD.encode(components.n);
+                let e = URL_SAFE_NO_PAD.encode(components.e);
+
+                (
+                    EncodingKey::from_rsa_pem(pem.as_bytes())
+                        .wrap_err("Failed to load RSA private key")?,
+                    DecodingKey::from_rsa_components(&n, &e)
+                        .wrap_err("Failed to load RSA public key")?,
+                    AlgorithmParameters::RSA(RSAKeyParameters {
+                        key_type: RSAKeyType::RSA,
+                        n: n.clone(),
+                        e: e.clone(),
+                    }),
+                    format!(r#"{{"e":"{}","kty":"RSA","n":"{}"}}"#, e, n),
+                )
+            }
+            Algorithm::EdDSA => {
+                let key_pair = Ed25519KeyPair::from_pkcs8_maybe_unchecked(parsed.contents())
+                    .map_err(|e| eyre!("Invalid Ed25519 private key: {}", e))?;
+
+                let x = URL_SAFE_NO_PAD.encode(key_pair.public_key());
+
+                (
+                    EncodingKey::from_ed_der(parsed.contents()),
+                    DecodingKey::from_ed_components(&x)
+                        .wrap_err("Failed to load Ed25519 public key")?,
+                    AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                        key_type: OctetKeyPairType::OctetKeyPair,
+                        curve: EllipticCurve::Ed25519,
+                        x: x.clone(),
+                    }),
+                    format!(r#"{{"crv":"Ed25519","kty":"OKP","x":"{}"}}"#, x),
+                )
+            }
+            _ => {
+                return Err(eyre!(
+                    "{:?} is not a supported signing algorithm",
+                    algorithm
+                ))
+            }
+        };
+
+        let kid = compute_thumbprint(&thumbprint);
+
+        let public_jwk = Jwk {
+            common: CommonParameters {
+                public_key_use: Some(PublicKeyUse::Signature),
+                key_algorithm: Some(match algorithm {
+                    Algorithm::RS256 => KeyAlgorithm::RS256,
+                    _ => KeyAlgorithm::EdDSA,
+                }),
+                key_id: Some(kid.clone()),
+                ..Default::default()
+            },
+            algorithm: parameters,
+        };
+
+        Ok(Self {
+            algorithm,
+            kid,
+            encoding_key,
+            decoding_key,
+            public_jwk: Some(public_jwk),
+        })
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    pub fn header(&self) -> Header {
+        let mut header = Header::new(self.algorithm);
+        header.kid = Some(self.kid.clone());
+        header
+    }
+
+    /// Loads an EdDSA or RS256 key, whichever the PEM holds.
+    pub fn from_any_pem(pem: &str) -> Result<Self> {
+        Self::from_pem(Algorithm::EdDSA, pem)
+            .or_else(|_| Self::from_pem(Algorithm::RS256, pem))
+            .wrap_err("Expected an Ed25519 or RSA private key")
+    }
+}
+
+/// The key new tokens are signed with, plus the other keys tokens can still be verified with:
+/// keys scheduled to take over from it, and retired keys whose tokens may not have expired yet.
+pub struct JwtKeyring {
+    signing_key: JwtSigningKey,
+    verification_keys: Vec<JwtSigningKey>,
+}
+
+impl JwtKeyring {
+    pub fn new(signing_key: JwtSigningKey, verification_keys: Vec<JwtSigningKey>) -> Self {
+        Self {
+            signing_key,
+            verification_keys,
+        }
+    }
+
+    /// Loads the keys in `dir`, each named after the Unix timestamp it becomes the signing key at,
+    /// e.g. `1720000000.pem`. The latest key whose time has come signs tokens. Each older key is
+    /// retired when the key after it takes over, and dropped `retention_seconds` later.
+    pub fn load_dir(dir: &Path, now: i64, retention_seconds: i64) -> Result<Self> {
+        let key_files = read_key_files(dir)?;
+        let current = find_current_key(&key_files, now)
+            .wrap_err_with(|| format!("{} has no key that is active yet", dir.display()))?;
+
+        let mut signing_key = None;
+        let mut verification_keys = Vec::new();
+
+        for (index, (_, path)) in key_files.iter().enumerate() {
+            if is_expired(&key_files, index, current, now, retention_seconds) {
+                continue;
+            }
+
+            let pem = fs::read_to_string(path)
+                .wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+            let key = JwtSigningKey::from_any_pem(&pem)
+                .wrap_err_with(|| format!("Failed to load {}", path.display()))?;
+
+            if index == current {
+                signing_key = Some(key);
+            } else {
+                verification_keys.push(key);
+            }
+        }
+
+        Ok(Self::new(
+            signing_key.expect("the current key is never expired"),
+            verification_keys,
+        ))
+    }
+
+    pub fn signing_key(&self) -> &JwtSigningKey {
+        &self.signing_key
+    }
+
+    #[tracing::instrument(name = "Sign token", skip_all)]
+    pub fn encode<T: Serialize>(&self, claims: &T) -> Result<Secret<String>> {
+        encode(
+            &self.signing_key.header(),
+            claims,
+            &self.signing_key.encoding_key,
+        )
+        .map(Secret::new)
+        .wrap_err("Failed to sign token")
+    }
+
+    /// Verifies the token with the key its `kid` header names. Tokens without one predate key ids,
+    /// so they can only have been signed with the current key.
+    #[tracing::instrument(name = "Verify token signature", skip_all)]
+    pub fn decode<T: DeserializeOwned>(
+        &self,
+        token: &str,
+        mut validation: Validation,
+    ) -> Result<T> {
+        let header = decode_header(token).wrap_err("Failed to decode token header")?;
+
+        let key = match header.kid {
+            Some(kid) => self
+                .keys()
+                .find(|key| key.kid == kid)
+                .ok_or_else(|| eyre!("Token was signed with an unknown key"))?,
+            None => &self.signing_key,
+        };
+
+        validation.algorithms = vec![key.algorithm];
+
+        decode::<T>(token, &key.decoding_key, &validation)
+            .map(|data| data.claims)
+            .wrap_err("Failed to decode token")
+    }
+
+    /// The public keys tokens can be verified with. HS256 keys are left out, as they are the shared secret.
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: self
+                .keys()
+                .filter_map(|key| key.public_jwk.clone())
+                .collect(),
+        }
+    }
+
+    fn keys(&self) -> impl Iterator<Item = &JwtSigningKey> {
+        std::iter::once(&self.signing_key).chain(&self.verification_keys)
+    }
+}
+
+/// Reloads the keyring from `dir` every `interval`, so keys can be added, take over and retire
+/// without a restart.
+pub async fn reload_keyring(
+    keyring: &'static RwLock<JwtKeyring>,
+    dir: PathBuf,
+    retention_seconds: i64,
+    interval: Duration,
+) {
+    let mut interval = tokio::time::interval(interval);
+    loop {
+        interval.tick().await;
+
+        match JwtKeyring::load_dir(&dir, Utc::now().timestamp(), retention_seconds) {
+            Ok(loaded) => *keyring.write().expect("JWT keyring lock poisoned") = loaded,
+            // Keep signing with the keys we have rather than taking every route down
+            Err(e) => tracing::error!("Failed to reload JWT keys: {:?}", e),
+        }
+    }
+}
+
+/// Generates an Ed25519 key in `dir` that becomes the signing key at `activate_at`.
+pub fn generate_key_file(dir: &Path, activate_at: i64) -> Result<PathBuf> {
+    let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+        .map_err(|_| eyre!("Failed to generate Ed25519 key"))?;
+    let pem = pem::encode(&pem::Pem::new("PRIVATE KEY", pkcs8.as_ref()));
+
+    let path = dir.join(format!("{}.pem", activate_at));
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&path)
+        .and_then(|mut file| file.write_all(pem.as_bytes()))
+        .wrap_err_with(|| format!("Failed to write {}", path.display()))?;
+
+    Ok(path)
+}
+
+/// Deletes the keys in `dir` that `JwtKeyring::load_dir` would no longer load.
+pub fn prune_key_files(dir: &Path, now: i64, retention_seconds: i64) -> Result<Vec<PathBuf>> {
+    let key_files = read_key_files(dir)?;
+    let Some(current) = find_current_key(&key_files, now) else {
+        return Ok(Vec::new());
+    };
+
+    let mut pruned = Vec::new();
+    for (index, (_, path)) in key_files.iter().enumerate() {
+        if is_expired(&key_files, index, current, now, retention_seconds) {
+            fs::remove_file(path)
+                .wrap_err_with(|| format!("Failed to delete {}", path.display()))?;
+            pruned.push(path.clone());
+        }
+    }
+
+    Ok(pruned)
+}
+
+/// The key files in `dir` with the time each becomes the signing key at, oldest first.
+fn read_key_files(dir: &Path) -> Result<Vec<(i64, PathBuf)>> {
+    let mut key_files = Vec::new();
+
+    for entry in fs::read_dir(dir).wrap_err_with(|| format!("Failed to read {}", dir.display()))? {
+        let path = entry.wrap_err("Failed to read key directory entry")?.path();
+        if path.extension().and_then(|extension| extension.to_str()) != Some("pem") {
+            continue;
+        }
+
+        let activate_at = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.parse().ok())
+            .ok_or_else(|| {
+                eyre!(
+                    "{} must be named after the Unix timestamp it becomes active at",
+                    path.display()
+                )
+            })?;
+
+        key_files.push((activate_at, path));
+    }
+
+    key_files.sort();
+    Ok(key_files)
+}
+
+fn find_current_key(key_files: &[(i64, PathBuf)], now: i64) -> Option<usize> {
+    key_files
+        .iter()
+        .rposition(|(activate_at, _)| *activate_at <= now)
+}
+
+fn is_expired(
+    key_files: &[(i64, PathBuf)],
+    index: usize,
+    current: usize,
+    now: i64,
+    retention_seconds: i64,
+) -> bool {
+    // A key retires when the next one takes over
+    index < current && key_files[index + 1].0 + retention_seconds <= now
+}
+
+// RFC 7638 thumbprint, so the kid stays the same for as long as the key does
+fn compute_thumbprint(members: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(members.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use uuid::Uuid;
+
+    use super::*;
+
+    const RS256_PEM: &str = include_str!("../../tests/fixtures/jwt_rs256_private_key.pem");
+    const EDDSA_PEM: &str = include_str!("../../tests/fixtures/jwt_eddsa_private_key.pem");
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct TestClaims {
+        sub: String,
+        exp: usize,
+    }
+
+    fn round_trip(key: &JwtSigningKey) {
+        let claims = TestClaims {
+            sub: "test@example.com".to_owned(),
+            exp: 4_000_000_000,
+        };
+
+        let token = encode(&key.header(), &claims, &key.encoding_key).unwrap();
+
+        let header = decode_header(&token).unwrap();
+        assert_eq!(header.alg, key.algorithm);
+        assert_eq!(header.kid.as_deref(), Some(key.kid.as_str()));
+
+        let decoded =
+            decode::<TestClaims>(&token, &key.decoding_key, &Validation::new(key.algorithm))
+                .unwrap();
+        assert_eq!(decoded.claims, claims);
+    }
+
+    fn test_claims() -> TestClaims {
+        TestClaims {
+            sub: "test@example.com".to_owned(),
+            exp: 4_000_000_000,
+        }
+    }
+
+    fn secret_key(secret: &str) -> JwtSigningKey {
+        JwtSigningKey::from_secret(&Secret::new(secret.to_owned()))
+    }
+
+    fn key_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        fs::create_dir(&dir).unwrap();
+        dir
+    }
+
+    fn kids(keyring: &JwtKeyring) -> Vec<String> {
+        keyring.keys().map(|key| key.kid.clone()).collect()
+    }
+
+    fn file_kid(dir: &Path, activate_at: i64) -> String {
+        let pem = fs::read_to_string(dir.join(format!("{}.pem", activate_at))).unwrap();
+        JwtSigningKey::from_any_pem(&pem).unwrap().kid
+    }
+
+    #[test]
+    fn test_hs256_round_trip() {
+        let key = secret_key("secret");
+        round_trip(&key);
+        assert!(JwtKeyring::new(key, vec![]).jwks().keys.is_empty());
+    }
+
+    #[test]
+    fn test_rs256_round_trip() {
+        let key = JwtSigningKey::from_pem(Algorithm::RS256, RS256_PEM).unwrap();
+        round_trip(&key);
+    }
+
+    #[test]
+    fn test_eddsa_round_trip() {
+        let key = JwtSigningKey::from_pem(Algorithm::EdDSA, EDDSA_PEM).unwrap();
+        round_trip(&key);
+    }
+
+    #[test]
+    fn test_jwks_verifies_tokens() {
+        for (algorithm, pem) in [(Algorithm::RS256, RS256_PEM), (Algorithm::EdDSA, EDDSA_PEM)] {
+            let key = JwtSigningKey::from_pem(algorithm, pem).unwrap();
+            let claims = TestClaims {
+                sub: "test@example.com".to_owned(),
+                exp: 4_000_000_000,
+            };
+            let token = encode(&key.header(), &claims, &key.encoding_key).unwrap();
+            let keyring = JwtKeyring::new(key, vec![]);
+
+            // Serialize the set, as a verifying service would receive it
+            let jwks: JwkSet =
+                serde_json::from_str(&serde_json::to_string(&keyring.jwks()).unwrap()).unwrap();
+            let kid = decode_header(&token).unwrap().kid.unwrap();
+            let jwk = jwks.find(&kid).unwrap();
+
+            let decoded = decode::<TestClaims>(
+                &token,
+                &DecodingKey::from_jwk(jwk).unwrap(),
+                &Validation::new(algorithm),
+            )
+            .unwrap();
+            assert_eq!(decoded.claims, claims);
+        }
+    }
+
+    #[test]
+    fn test_kid_is_stable() {
+        let first = JwtSigningKey::from_pem(Algorithm::EdDSA, EDDSA_PEM).unwrap();
+        let second = JwtSigningKey::from_pem(Algorithm::EdDSA, EDDSA_PEM).unwrap();
+        assert_eq!(first.kid, second.kid);
+
+        let other = secret_key("secret");
+        assert_ne!(first.kid, other.kid);
+    }
+
+    #[test]
+    fn test_from_pem_rejects_mismatched_key() {
+        assert!(JwtSigningKey::from_pem(Algorithm::RS256, EDDSA_PEM).is_err());
+        assert!(JwtSigningKey::from_pem(Algorithm::EdDSA, RS256_PEM).is_err());
+        assert!(JwtSigningKey::from_pem(Algorithm::ES256, EDDSA_PEM).is_err());
+        assert!(JwtSigningKey::from_pem(Algorithm::RS256, "not a pem").is_err());
+    }
+
+    #[test]
+    fn test_from_any_pem() {
+        let key = JwtSigningKey::from_any_pem(RS256_PEM).unwrap();
+        assert_eq!(key.algorithm, Algorithm::RS256);
+        let key = JwtSigningKey::from_any_pem(EDDSA_PEM).unwrap();
+        assert_eq!(key.algorithm, Algorithm::EdDSA);
+    }
+
+    #[test]
+    fn test_keyring_verifies_tokens_signed_with_previous_key() {
+        let old_keyring = JwtKeyring::new(secret_key("old"), vec![]);
+        let token = old_keyring.encode(&test_claims()).unwrap();
+
+        let keyring = JwtKeyring::new(secret_key("new"), vec![secret_key("old")]);
+        let claims: TestClaims = keyring
+            .decode(token.expose_secret(), Validation::default())
+            .unwrap();
+        assert_eq!(claims, test_claims());
+
+        // Without the old key, its tokens are no longer accepted
+        let keyring = JwtKeyring::new(secret_key("new"), vec![]);
+        let result = keyring.decode::<TestClaims>(token.expose_secret(), Validation::default());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_keyring_signs_with_current_key() {
+        let keyring = JwtKeyring::new(secret_key("new"), vec![secret_key("old")]);
+        let token = keyring.encode(&test_claims()).unwrap();
+
+        let header = decode_header(token.expose_secret()).unwrap();
+        assert_eq!(header.kid, Some(secret_key("new").kid));
+    }
+
+    #[test]
+    fn test_keyring_rejects_algorithm_mismatch() {
+        // An HS256 token signed with the RSA public key must not verify against the RSA key
+        let rsa_key = JwtSigningKey::from_pem(Algorithm::RS256, RS256_PEM).unwrap();
+        let mut header = Header::new(Algorithm::HS256);
+        header.kid = Some(rsa_key.kid.clone());
+        let token = encode(
+            &header,
+            &test_claims(),
+            &EncodingKey::from_secret(b"secret"),
+        )
+        .unwrap();
+
+        let keyring = JwtKeyring::new(rsa_key, vec![]);
+        assert!(keyring
+            .decode::<TestClaims>(&token, Validation::default())
+            .is_err());
+    }
+
+    #[test]
+    fn test_load_dir_schedule() {
+        let dir = key_dir();
+        for activate_at in [100, 200, 300] {
+            generate_key_file(&dir, activate_at).unwrap();
+        }
+
+        // Not active yet
+        assert!(JwtKeyring::load_dir(&dir, 50, 60).is_err());
+
+        // The next key is published ahead of taking over
+        let keyring = JwtKeyring::load_dir(&dir, 150, 60).unwrap();
+        assert_eq!(keyring.signing_key().kid, file_kid(&dir, 100));
+        assert_eq!(
+            kids(&keyring),
+            vec![
+                file_kid(&dir, 100),
+                file_kid(&dir, 200),
+                file_kid(&dir, 300)
+            ]
+        );
+
+        // Retired at 200, kept until 260 for the tokens it already signed
+        let keyring = JwtKeyring::load_dir(&dir, 250, 60).unwrap();
+        assert_eq!(keyring.signing_key().kid, file_kid(&dir, 200));
+        assert_eq!(keyring.keys().count(), 3);
+
+        let keyring = JwtKeyring::load_dir(&dir, 260, 60).unwrap();
+        assert_eq!(
+            kids(&keyring),
+            vec![file_kid(&dir, 200), file_kid(&dir, 300)]
+        );
+
+        let keyring = JwtKeyring::load_dir(&dir, 1000, 60).unwrap();
+        assert_eq!(kids(&keyring), vec![file_kid(&dir, 300)]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_dir_rejects_misnamed_keys() {
+        let dir = key_dir();
+        generate_key_file(&dir, 100).unwrap();
+        fs::write(dir.join("README"), "ignored").unwrap();
+        assert!(JwtKeyring::load_dir(&dir, 100, 60).is_ok());
+
+        fs::copy(dir.join("100.pem"), dir.join("current.pem")).unwrap();
+        assert!(JwtKeyring::load_dir(&dir, 100, 60).is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_generate_key_file_refuses_to_overwrite() {
+        let dir = key_dir();
+        generate_key_file(&dir, 100).unwrap();
+        assert!(generate_key_file(&dir, 100).is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_prune_key_files() {
+        let dir = key_dir();
+        for activate_at in [100, 200, 300] {
+            generate_key_file(&dir, activate_at).unwrap();
+        }
+
+        let pruned = prune_key_files(&dir, 290, 60).unwrap();
+        assert_eq!(pruned, vec![dir.join("100.pem")]);
+
+        let keyring = JwtKeyring::load_dir(&dir, 290, 60).unwrap();
+        assert_eq!(keyring.keys().count(), 2);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
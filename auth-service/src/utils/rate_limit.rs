@@ -0,0 +1,392 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{MatchedPath, Request, State},
+    http::{header::RETRY_AFTER, HeaderMap, HeaderName, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use color_eyre::eyre::{eyre, Result};
+
+use crate::{app_state::RateLimitStoreType, domain::TokenBucket, ErrorResponse};
+
+use super::client_ip::ClientIp;
+
+// Auth request bodies are tiny, so there's no need to buffer more than this to find the email
+const MAX_BUFFERED_BODY_BYTES: usize = 64 * 1024;
+
+// Rules under this path apply to every route that doesn't have rules of its own
+const DEFAULT_ROUTE: &str = "*";
+
+const RATE_LIMIT_LIMIT: HeaderName = HeaderName::from_static("ratelimit-limit");
+const RATE_LIMIT_REMAINING: HeaderName = HeaderName::from_static("ratelimit-remaining");
+const RATE_LIMIT_RESET: HeaderName = HeaderName::from_static("ratelimit-reset");
+
+/// What a rate limit budget is shared by.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RateLimitKey {
+    Ip,
+    Header(HeaderName),
+    /// The `email` field of a JSON request body
+    Email,
+}
+
+/// A token bucket holding `capacity` requests, which refills completely over `period_seconds`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitRule {
+    pub key: RateLimitKey,
+    pub capacity: u32,
+    pub period_seconds: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RateLimitConfig {
+    routes: HashMap<String, Vec<RateLimitRule>>,
+}
+
+impl RateLimitConfig {
+    /// Parses route rules such as `*=ip:100/60;/login=ip:20/60,email:10/60;/sessions/:id=ip:5/1`.
+    /// Each rule allows `N` requests per `S` seconds; `*` covers the routes not listed. Routes are
+    /// named the way the router declares them, so one budget covers every ID of `/sessions/:id`.
+    pub fn parse(s: &str) -> Result<Self> {
+        let mut routes = HashMap::new();
+
+        for route in s
+            .split(';')
+            .map(str::trim)
+            .filter(|route| !route.is_empty())
+        {
+            let (path, rules) = route
+                .split_once('=')
+                .ok_or_else(|| eyre!("{} is missing the rules for the route", route))?;
+
+            let rules = rules
+                .split(',')
+                .map(|rule| RateLimitRule::parse(rule.trim()))
+                .collect::<Result<Vec<_>>>()?;
+
+            routes.insert(path.trim().to_owned(), rules);
+        }
+
+        Ok(Self { routes })
+    }
+
+    fn rules_for(&self, path: &str) -> &[RateLimitRule] {
+        self.routes
+            .get(path)
+            .or_else(|| self.routes.get(DEFAULT_ROUTE))
+            .map_or(&[], Vec::as_slice)
+    }
+}
+
+impl RateLimitRule {
+    fn parse(s: &str) -> Result<Self> {
+        let (key, limit) = s
+            .split_once(':')
+            .ok_or_else(|| eyre!("{} is not a valid rate limit rule", s))?;
+
+        let key = match key {
+            "ip" => RateLimitKey::Ip,
+            "email" => RateLimitKey::Email,
+            _ => match key.strip_prefix("header.") {
+                Some(name) => RateLimitKey::Header(
+                    HeaderName::try_from(name)
+                        .map_err(|_| eyre!("{} is not a valid header name", name))?,
+                ),
+                None => return Err(eyre!("{} is not a valid rate limit key", key)),
+            },
+        };
+
+        let (capacity, period_seconds) = limit
+            .split_once('/')
+            .ok_or_else(|| eyre!("{} is not a valid rate limit", limit))?;
+        let capacity = capacity
+            .parse()
+            .map_err(|_| eyre!("{} is not a valid number of requests", capacity))?;
+        let period_seconds = period_seconds
+            .parse()
+            .map_err(|_| eyre!("{} is not a valid number of seconds", period_seconds))?;
+
+        if capacity == 0 || period_seconds == 0 {
+            return Err(eyre!(
+                "{} must allow at least one request over a non-empty period",
+                s
+            ));
+        }
+
+        Ok(Self {
+            key,
+            capacity,
+            period_seconds,
+        })
+    }
+
+    fn key_name(&self) -> String {
+        let kind = match &self.key {
+            RateLimitKey::Ip => "ip".to_owned(),
+            RateLimitKey::Header(name) => format!("header.{}", name),
+            RateLimitKey::Email => "email".to_owned(),
+        };
+        format!("{}:{}/{}", kind, self.capacity, self.period_seconds)
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimiter {
+    store: RateLimitStoreType,
+    config: Arc<RateLimitConfig>,
+}
+
+impl RateLimiter {
+    pub fn new(store: RateLimitStoreType, config: RateLimitConfig) -> Self {
+        Self {
+            store,
+            config: Arc::new(config),
+        }
+    }
+}
+
+/// How a request left one of the buckets it was counted against.
+struct RateLimitStatus {
+    rule: RateLimitRule,
+    bucket: TokenBucket,
+}
+
+impl RateLimitStatus {
+    fn remaining(&self) -> u64 {
+        self.bucket.tokens.floor() as u64
+    }
+
+    /// Seconds until the bucket is full again.
+    fn reset_seconds(&self) -> u64 {
+        self.seconds_until(f64::from(self.rule.capacity))
+    }
+
+    /// Seconds until the bucket holds a token for the next request.
+    fn retry_after_seconds(&self) -> u64 {
+        self.seconds_until(1.0).max(1)
+    }
+
+    fn seconds_until(&self, tokens: f64) -> u64 {
+        let missing = (tokens - self.bucket.tokens).max(0.0);
+        (missing * self.rule.period_seconds as f64 / f64::from(self.rule.capacity)).ceil() as u64
+    }
+
+    fn add_headers(&self, headers: &mut HeaderMap) {
+        headers.insert(RATE_LIMIT_LIMIT, HeaderValue::from(self.rule.capacity));
+        headers.insert(RATE_LIMIT_REMAINING, HeaderValue::from(self.remaining()));
+        headers.insert(RATE_LIMIT_RESET, HeaderValue::from(self.reset_seconds()));
+    }
+}
+
+/// Counts the request against every rule for its route and rejects it once any of their buckets is empty.
+/// Requests without a value for a rule's key, such as a missing header, aren't counted against that rule.
+#[tracing::instrument(name = "Rate limit", skip_all)]
+pub async fn rate_limit(
+    State(limiter): State<RateLimiter>,
+    ClientIp(client_ip): ClientIp,
+    request: Request,
+    next: Next,
+) -> Response {
+    // Requests to paths no route matches share the budget of the default route
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or(DEFAULT_ROUTE, MatchedPath::as_str)
+        .to_owned();
+    let rules = limiter.config.rules_for(&route);
+    if rules.is_empty() {
+        return next.run(request).await;
+    }
+
+    let (request, email) = if rules.iter().any(|rule| rule.key == RateLimitKey::Email) {
+        let (parts, body) = request.into_parts();
+        let bytes = match to_bytes(body, MAX_BUFFERED_BODY_BYTES).await {
+            Ok(bytes) => bytes,
+            Err(_) => return StatusCode::PAYLOAD_TOO_LARGE.into_response(),
+        };
+        let email = get_email(&bytes);
+        (Request::from_parts(parts, Body::from(bytes)), email)
+    } else {
+        (request, None)
+    };
+
+    let mut statuses = Vec::with_capacity(rules.len());
+    for rule in rules {
+        let value = match &rule.key {
+            RateLimitKey::Ip => Some(client_ip.to_string()),
+            RateLimitKey::Header(name) => request
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_owned),
+            RateLimitKey::Email => email.clone(),
+        };
+        let Some(value) = value else {
+            continue;
+        };
+
+        let key = format!("{}:{}:{}", route, rule.key_name(), value);
+        let result = limiter
+            .store
+            .write()
+            .await
+            .take_token(&key, rule.capacity, rule.period_seconds)
+            .await;
+
+        match result {
+            Ok(bucket) => statuses.push(RateLimitStatus {
+                rule: rule.clone(),
+                bucket,
+            }),
+            // Fail open, an unavailable store shouldn't take every route down with it
+            Err(e) => tracing::error!("Failed to take rate limit token: {:?}", e),
+        }
+    }
+
+    let denied = statuses
+        .iter()
+        .filter(|status| !status.bucket.allowed)
+        .max_by_key(|status| status.retry_after_seconds());
+
+    if let Some(status) = denied {
+        let mut response = (
+            StatusCode::TOO_MANY_REQUESTS,
+            Json(ErrorResponse {
+                error: "Too many requests".to_owned(),
+            }),
+        )
+            .into_response();
+        status.add_headers(response.headers_mut());
+        response
+            .headers_mut()
+            .insert(RETRY_AFTER, HeaderValue::from(status.retry_after_seconds()));
+        return response;
+    }
+
+    let mut response = next.run(request).await;
+    if let Some(status) = statuses
+        .iter()
+        .min_by(|a, b| a.bucket.tokens.total_cmp(&b.bucket.tokens))
+    {
+        status.add_headers(response.headers_mut());
+    }
+    response
+}
+
+fn get_email(body: &[u8]) -> Option<String> {
+    let body: serde_json::Value = serde_json::from_slice(body).ok()?;
+    // Emails that differ only in case shouldn't get a budget each
+    Some(body.get("email")?.as_str()?.trim().to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config = RateLimitConfig::parse(
+            "*=ip:100/60; /login=ip:20/60,email:10/3600;/refresh=header.x-api-key:5/1",
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.rules_for("/login"),
+            &[
+                RateLimitRule {
+                    key: RateLimitKey::Ip,
+                    capacity: 20,
+                    period_seconds: 60,
+                },
+                RateLimitRule {
+                    key: RateLimitKey::Email,
+                    capacity: 10,
+                    period_seconds: 3600,
+                },
+            ]
+        );
+        assert_eq!(
+            config.rules_for("/refresh"),
+            &[RateLimitRule {
+                key: RateLimitKey::Header(HeaderName::from_static("x-api-key")),
+                capacity: 5,
+                period_seconds: 1,
+            }]
+        );
+        assert_eq!(config.rules_for("/signup"), config.rules_for("*"));
+    }
+
+    #[test]
+    fn test_empty_config_is_unlimited() {
+        let config = RateLimitConfig::parse("").unwrap();
+        assert!(config.rules_for("/login").is_empty());
+    }
+
+    #[test]
+    fn test_parse_invalid_config() {
+        let configs = [
+            "/login",
+            "/login=ip",
+            "/login=ip:10",
+            "/login=ip:ten/60",
+            "/login=ip:10/0",
+            "/login=ip:0/60",
+            "/login=user:10/60",
+            "/login=header.x api key:10/60",
+        ];
+
+        for config in configs {
+            assert!(RateLimitConfig::parse(config).is_err(), "{}", config);
+        }
+    }
+
+    #[test]
+    fn test_status_headers() {
+        let status = RateLimitStatus {
+            rule: RateLimitRule {
+                key: RateLimitKey::Ip,
+                capacity: 10,
+                period_seconds: 60,
+            },
+            bucket: TokenBucket {
+                allowed: true,
+                tokens: 2.5,
+            },
+        };
+
+        assert_eq!(status.remaining(), 2);
+        assert_eq!(status.reset_seconds(), 45);
+        assert_eq!(status.retry_after_seconds(), 1);
+    }
+
+    #[test]
+    fn test_retry_after_when_empty() {
+        let status = RateLimitStatus {
+            rule: RateLimitRule {
+                key: RateLimitKey::Ip,
+                capacity: 2,
+                period_seconds: 60,
+            },
+            bucket: TokenBucket {
+                allowed: false,
+                tokens: 0.5,
+            },
+        };
+
+        assert_eq!(status.remaining(), 0);
+        assert_eq!(status.retry_after_seconds(), 15);
+    }
+
+    #[test]
+    fn test_get_email() {
+        assert_eq!(
+            get_email(br#"{"email": " User@Example.com ", "password": "password123"}"#),
+            Some("user@example.com".to_owned())
+        );
+        assert_eq!(get_email(br#"{"password": "password123"}"#), None);
+        assert_eq!(get_email(b"not json"), None);
+    }
+}
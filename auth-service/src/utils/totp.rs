@@ -0,0 +1,139 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use color_eyre::eyre::{Context, Result};
+use qrcode::{render::svg, QrCode};
+use secrecy::ExposeSecret;
+use totp_rs::{Algorithm, TOTP};
+
+use crate::domain::{Email, TotpSecret, TwoFACode};
+
+use super::constants::{TOTP_ISSUER, TOTP_SKEW};
+
+// RFC 6238 defaults, which is what authenticator apps assume when reading an otpauth:// URI
+const TOTP_DIGITS: usize = 6;
+const TOTP_STEP_SECONDS: u64 = 30;
+
+// Checks one time step at a time, as the step a code was accepted for has to be known
+#[tracing::instrument(name = "Build TOTP", skip_all)]
+fn build_totp(secret: &TotpSecret, email: &Email) -> Result<TOTP> {
+    TOTP::new(
+        Algorithm::SHA1,
+        TOTP_DIGITS,
+        0,
+        TOTP_STEP_SECONDS,
+        secret.to_bytes()?,
+        Some(TOTP_ISSUER.to_owned()),
+        email.as_ref().expose_secret().to_owned(),
+    )
+    .wrap_err("Failed to build TOTP")
+}
+
+#[tracing::instrument(name = "Generate TOTP URI", skip_all)]
+pub fn generate_totp_uri(secret: &TotpSecret, email: &Email) -> Result<String> {
+    Ok(build_totp(secret, email)?.get_url())
+}
+
+#[tracing::instrument(name = "Generate QR code", skip_all)]
+pub fn generate_qr_code_svg(uri: &str) -> Result<String> {
+    let code = QrCode::new(uri.as_bytes()).wrap_err("Failed to encode QR code")?;
+
+    Ok(code.render::<svg::Color>().min_dimensions(200, 200).build())
+}
+
+/// Returns the time step the code is valid for, if it's the current one or within `TOTP_SKEW` of it.
+/// Callers must record the step and not accept codes for it or earlier steps again.
+#[tracing::instrument(name = "Verify TOTP code", skip_all)]
+pub fn verify_totp_code(
+    secret: &TotpSecret,
+    email: &Email,
+    code: &TwoFACode,
+) -> Result<Option<u64>> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .wrap_err("System time is before the Unix epoch")?
+        .as_secs();
+
+    Ok(verify_totp_code_at(&build_totp(secret, email)?, code, now))
+}
+
+fn verify_totp_code_at(totp: &TOTP, code: &TwoFACode, time: u64) -> Option<u64> {
+    let current_step = time / TOTP_STEP_SECONDS;
+    let skew = u64::from(*TOTP_SKEW);
+
+    // Oldest first, so a code valid for two steps counts towards the earlier one
+    (current_step.saturating_sub(skew)..=current_step + skew)
+        .find(|step| totp.check(code.as_ref().expose_secret(), step * TOTP_STEP_SECONDS))
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+
+    fn email() -> Email {
+        Email::parse(Secret::new("test@example.com".to_owned())).unwrap()
+    }
+
+    #[test]
+    fn test_generate_totp_uri() {
+        let secret = TotpSecret::default();
+        let uri = generate_totp_uri(&secret, &email()).unwrap();
+        assert!(uri.starts_with("otpauth://totp/"));
+        assert!(uri.contains(&format!("secret={}", secret.as_ref().expose_secret())));
+    }
+
+    #[test]
+    fn test_generate_qr_code_svg() {
+        let svg = generate_qr_code_svg("otpauth://totp/test").unwrap();
+        assert!(svg.contains("<svg"));
+    }
+
+    #[test]
+    fn test_verify_totp_code() {
+        let secret = TotpSecret::default();
+        let current = build_totp(&secret, &email())
+            .unwrap()
+            .generate_current()
+            .unwrap();
+        let code = TwoFACode::parse(Secret::new(current)).unwrap();
+        assert!(verify_totp_code(&secret, &email(), &code)
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn test_verify_totp_code_returns_its_step() {
+        let totp = build_totp(&TotpSecret::default(), &email()).unwrap();
+        let time = 1_000 * TOTP_STEP_SECONDS;
+        let code = TwoFACode::parse(Secret::new(totp.generate(time))).unwrap();
+
+        assert_eq!(verify_totp_code_at(&totp, &code, time), Some(1_000));
+        // Codes from a step either side are accepted, to allow for clock drift
+        assert_eq!(
+            verify_totp_code_at(&totp, &code, time + TOTP_STEP_SECONDS),
+            Some(1_000)
+        );
+        assert_eq!(
+            verify_totp_code_at(&totp, &code, time - TOTP_STEP_SECONDS),
+            Some(1_000)
+        );
+        assert_eq!(
+            verify_totp_code_at(&totp, &code, time + 2 * TOTP_STEP_SECONDS),
+            None
+        );
+    }
+
+    #[test]
+    fn test_verify_totp_code_with_wrong_secret() {
+        let current = build_totp(&TotpSecret::default(), &email())
+            .unwrap()
+            .generate_current()
+            .unwrap();
+        let code = TwoFACode::parse(Secret::new(current)).unwrap();
+        assert_eq!(
+            verify_totp_code(&TotpSecret::default(), &email(), &code).unwrap(),
+            None
+        );
+    }
+}
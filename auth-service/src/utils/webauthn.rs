@@ -0,0 +1,411 @@
+use ciborium::value::Value;
+use color_eyre::eyre::{eyre, Context, Result};
+use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::domain::{WebAuthnChallenge, WebAuthnCredential, WebAuthnCredentialId};
+
+use super::constants::{AUTH_SERVICE_URL, WEBAUTHN_RP_ID};
+
+pub const WEBAUTHN_CHALLENGE_TTL_SECONDS: i64 = 300;
+
+// ES256, the only algorithm we advertise in pubKeyCredParams
+pub const COSE_ALG_ES256: i64 = -7;
+
+pub const CLIENT_DATA_TYPE_CREATE: &str = "webauthn.create";
+pub const CLIENT_DATA_TYPE_GET: &str = "webauthn.get";
+
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_USER_VERIFIED: u8 = 0x04;
+const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+// rpIdHash (32) + flags (1) + signCount (4)
+const AUTHENTICATOR_DATA_MIN_LEN: usize = 37;
+// AAGUID (16) + credentialIdLength (2)
+const ATTESTED_CREDENTIAL_DATA_HEADER_LEN: usize = 18;
+
+// COSE_Key labels and values for an EC2 P-256 key (RFC 9053)
+const COSE_KEY_KTY: i128 = 1;
+const COSE_KEY_ALG: i128 = 3;
+const COSE_KEY_CRV: i128 = -1;
+const COSE_KEY_X: i128 = -2;
+const COSE_KEY_Y: i128 = -3;
+const COSE_KTY_EC2: i128 = 2;
+const COSE_CRV_P256: i128 = 1;
+
+/// A credential the authenticator has just created, before it is tied to a user.
+#[derive(Debug, PartialEq)]
+pub struct AttestedCredential {
+    pub credential_id: WebAuthnCredentialId,
+    pub public_key: Vec<u8>,
+    pub sign_count: u32,
+}
+
+#[derive(Deserialize)]
+struct ClientData {
+    #[serde(rename = "type")]
+    ceremony_type: String,
+    challenge: String,
+    origin: String,
+}
+
+struct AuthenticatorData<'a> {
+    flags: u8,
+    sign_count: u32,
+    attested_credential_data: &'a [u8],
+}
+
+/// Checks the browser-supplied client data and returns the challenge it claims to answer.
+/// The challenge still has to be looked up in the challenge store by the caller.
+#[tracing::instrument(name = "Verify WebAuthn client data", skip_all)]
+pub fn verify_client_data(
+    client_data_json: &[u8],
+    expected_type: &str,
+) -> Result<WebAuthnChallenge> {
+    let client_data: ClientData =
+        serde_json::from_slice(client_data_json).wrap_err("Malformed clientDataJSON")?;
+
+    if client_data.ceremony_type != expected_type {
+        return Err(eyre!("Unexpected client data type"));
+    }
+
+    // A phishing site can relay our challenge, but the browser will stamp its own origin on it
+    if client_data.origin != *AUTH_SERVICE_URL {
+        return Err(eyre!("Unexpected client data origin"));
+    }
+
+    WebAuthnChallenge::parse(client_data.challenge)
+}
+
+/// Verifies a "none" attestation object from navigator.credentials.create() and extracts the new credential.
+#[tracing::instrument(name = "Verify WebAuthn attestation", skip_all)]
+pub fn verify_attestation(attestation_object: &[u8]) -> Result<AttestedCredential> {
+    let attestation: Value =
+        ciborium::de::from_reader(attestation_object).wrap_err("Malformed attestation object")?;
+    let attestation = attestation
+        .as_map()
+        .ok_or_else(|| eyre!("Attestation object is not a map"))?;
+
+    // We don't check authenticator provenance, so the registration options ask browsers to strip attestation
+    let fmt = map_get_text(attestation, "fmt").and_then(Value::as_text);
+    if fmt != Some("none") {
+        return Err(eyre!("Unsupported attestation format"));
+    }
+
+    let auth_data = map_get_text(attestation, "authData")
+        .and_then(Value::as_bytes)
+        .ok_or_else(|| eyre!("Attestation object is missing authData"))?;
+    let auth_data = parse_authenticator_data(auth_data)?;
+
+    if auth_data.flags & FLAG_ATTESTED_CREDENTIAL_DATA == 0 {
+        return Err(eyre!("Authenticator data has no attested credential"));
+    }
+
+    let data = auth_data.attested_credential_data;
+    if data.len() < ATTESTED_CREDENTIAL_DATA_HEADER_LEN {
+        return Err(eyre!("Attested credential data is truncated"));
+    }
+    let id_len = u16::from_be_bytes([data[16], data[17]]) as usize;
+    let id_end = ATTESTED_CREDENTIAL_DATA_HEADER_LEN + id_len;
+    if data.len() < id_end {
+        return Err(eyre!("Attested credential data is truncated"));
+    }
+
+    let credential_id =
+        WebAuthnCredentialId::from_bytes(&data[ATTESTED_CREDENTIAL_DATA_HEADER_LEN..id_end])?;
+    let public_key = parse_cose_key(&data[id_end..])?;
+
+    Ok(AttestedCredential {
+        credential_id,
+        public_key,
+        sign_count: auth_data.sign_count,
+    })
+}
+
+/// Verifies an assertion from navigator.credentials.get() against a stored credential and returns the new sign count.
+#[tracing::instrument(name = "Verify WebAuthn assertion", skip_all)]
+pub fn verify_assertion(
+    credential: &WebAuthnCredential,
+    authenticator_data: &[u8],
+    client_data_json: &[u8],
+    signature: &[u8],
+) -> Result<u32> {
+    let parsed = parse_authenticator_data(authenticator_data)?;
+
+    let verifying_key = VerifyingKey::from_sec1_bytes(&credential.public_key)
+        .wrap_err("Stored public key is invalid")?;
+    let signature = Signature::from_der(signature).wrap_err("Malformed signature")?;
+
+    let mut signed_data = authenticator_data.to_vec();
+    signed_data.extend_from_slice(&Sha256::digest(client_data_json));
+
+    verifying_key
+        .verify(&signed_data, &signature)
+        .wrap_err("Invalid signature")?;
+
+    // Counters only ever increase; going backwards suggests the authenticator has been cloned.
+    // Authenticators that don't keep a counter always report 0.
+    if (parsed.sign_count != 0 || credential.sign_count != 0)
+        && parsed.sign_count <= credential.sign_count
+    {
+        return Err(eyre!("Sign count did not increase"));
+    }
+
+    Ok(parsed.sign_count)
+}
+
+fn parse_authenticator_data(auth_data: &[u8]) -> Result<AuthenticatorData<'_>> {
+    if auth_data.len() < AUTHENTICATOR_DATA_MIN_LEN {
+        return Err(eyre!("Authenticator data is truncated"));
+    }
+
+    if auth_data[..32] != *Sha256::digest(WEBAUTHN_RP_ID.as_bytes()) {
+        return Err(eyre!("Authenticator data is for another relying party"));
+    }
+
+    let flags = auth_data[32];
+
+    // Passkeys replace the password entirely, so the authenticator must have checked a PIN or biometric
+    if flags & FLAG_USER_PRESENT == 0 || flags & FLAG_USER_VERIFIED == 0 {
+        return Err(eyre!("User was not verified by the authenticator"));
+    }
+
+    let sign_count =
+        u32::from_be_bytes([auth_data[33], auth_data[34], auth_data[35], auth_data[36]]);
+
+    Ok(AuthenticatorData {
+        flags,
+        sign_count,
+        attested_credential_data: &auth_data[AUTHENTICATOR_DATA_MIN_LEN..],
+    })
+}
+
+// Converts an EC2 COSE_Key into an uncompressed SEC1 point
+fn parse_cose_key(cose_key: &[u8]) -> Result<Vec<u8>> {
+    let key: Value = ciborium::de::from_reader(cose_key).wrap_err("Malformed COSE key")?;
+    let key = key.as_map().ok_or_else(|| eyre!("COSE key is not a map"))?;
+
+    let int_param = |label| {
+        map_get_int(key, label)
+            .and_then(Value::as_integer)
+            .map(i128::from)
+    };
+    let bytes_param = |label| map_get_int(key, label).and_then(Value::as_bytes);
+
+    if int_param(COSE_KEY_KTY) != Some(COSE_KTY_EC2)
+        || int_param(COSE_KEY_ALG) != Some(COSE_ALG_ES256.into())
+        || int_param(COSE_KEY_CRV) != Some(COSE_CRV_P256)
+    {
+        return Err(eyre!("Unsupported COSE key type"));
+    }
+
+    let (x, y) = bytes_param(COSE_KEY_X)
+        .zip(bytes_param(COSE_KEY_Y))
+        .ok_or_else(|| eyre!("COSE key is missing coordinates"))?;
+
+    let mut public_key = Vec::with_capacity(1 + x.len() + y.len());
+    public_key.push(0x04);
+    public_key.extend_from_slice(x);
+    public_key.extend_from_slice(y);
+
+    // Make sure the point is actually on the curve before we store it
+    VerifyingKey::from_sec1_bytes(&public_key).wrap_err("COSE key is not a valid P-256 point")?;
+
+    Ok(public_key)
+}
+
+fn map_get_text<'a>(map: &'a [(Value, Value)], key: &str) -> Option<&'a Value> {
+    map.iter()
+        .find(|(k, _)| k.as_text() == Some(key))
+        .map(|(_, v)| v)
+}
+
+fn map_get_int(map: &[(Value, Value)], key: i128) -> Option<&Value> {
+    map.iter()
+        .find(|(k, _)| k.as_integer().map(i128::from) == Some(key))
+        .map(|(_, v)| v)
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD as BASE64_URL, Engine};
+    use p256::ecdsa::{signature::Signer, SigningKey};
+    use secrecy::Secret;
+
+    use super::*;
+    use crate::domain::Email;
+
+    fn client_data(ceremony_type: &str, challenge: &WebAuthnChallenge, origin: &str) -> Vec<u8> {
+        serde_json::to_vec(&serde_json::json!({
+            "type": ceremony_type,
+            "challenge": challenge.as_ref(),
+            "origin": origin,
+        }))
+        .unwrap()
+    }
+
+    fn authenticator_data(flags: u8, sign_count: u32) -> Vec<u8> {
+        let mut data = Sha256::digest(WEBAUTHN_RP_ID.as_bytes()).to_vec();
+        data.push(flags);
+        data.extend_from_slice(&sign_count.to_be_bytes());
+        data
+    }
+
+    fn attestation_object(key: &SigningKey, credential_id: &[u8], alg: i64) -> Vec<u8> {
+        let point = key.verifying_key().to_encoded_point(false);
+        let cose_key = Value::Map(vec![
+            (Value::from(1), Value::from(2)),
+            (Value::from(3), Value::from(alg)),
+            (Value::from(-1), Value::from(1)),
+            (Value::from(-2), Value::Bytes(point.x().unwrap().to_vec())),
+            (Value::from(-3), Value::Bytes(point.y().unwrap().to_vec())),
+        ]);
+
+        let mut auth_data = authenticator_data(
+            FLAG_USER_PRESENT | FLAG_USER_VERIFIED | FLAG_ATTESTED_CREDENTIAL_DATA,
+            0,
+        );
+        auth_data.extend_from_slice(&[0; 16]);
+        auth_data.extend_from_slice(&(credential_id.len() as u16).to_be_bytes());
+        auth_data.extend_from_slice(credential_id);
+        ciborium::ser::into_writer(&cose_key, &mut auth_data).unwrap();
+
+        let attestation = Value::Map(vec![
+            (Value::from("fmt"), Value::from("none")),
+            (Value::from("attStmt"), Value::Map(vec![])),
+            (Value::from("authData"), Value::Bytes(auth_data)),
+        ]);
+        let mut bytes = Vec::new();
+        ciborium::ser::into_writer(&attestation, &mut bytes).unwrap();
+        bytes
+    }
+
+    fn credential(key: &SigningKey, sign_count: u32) -> WebAuthnCredential {
+        WebAuthnCredential {
+            credential_id: WebAuthnCredentialId::from_bytes(&[1, 2, 3]).unwrap(),
+            email: Email::parse(Secret::new("test@example.com".to_owned())).unwrap(),
+            public_key: key
+                .verifying_key()
+                .to_encoded_point(false)
+                .as_bytes()
+                .to_vec(),
+            sign_count,
+        }
+    }
+
+    fn sign(key: &SigningKey, auth_data: &[u8], client_data_json: &[u8]) -> Vec<u8> {
+        let mut signed_data = auth_data.to_vec();
+        signed_data.extend_from_slice(&Sha256::digest(client_data_json));
+        let signature: Signature = key.sign(&signed_data);
+        signature.to_der().as_bytes().to_vec()
+    }
+
+    #[test]
+    fn test_verify_client_data() {
+        let challenge = WebAuthnChallenge::default();
+        let data = client_data(CLIENT_DATA_TYPE_GET, &challenge, &AUTH_SERVICE_URL);
+
+        assert_eq!(
+            verify_client_data(&data, CLIENT_DATA_TYPE_GET).unwrap(),
+            challenge
+        );
+        assert!(verify_client_data(&data, CLIENT_DATA_TYPE_CREATE).is_err());
+    }
+
+    #[test]
+    fn test_verify_client_data_from_another_origin() {
+        let challenge = WebAuthnChallenge::default();
+        let data = client_data(CLIENT_DATA_TYPE_GET, &challenge, "https://evil.example.com");
+
+        assert!(verify_client_data(&data, CLIENT_DATA_TYPE_GET).is_err());
+    }
+
+    #[test]
+    fn test_verify_attestation() {
+        let key = SigningKey::random(&mut rand::thread_rng());
+        let attested =
+            verify_attestation(&attestation_object(&key, &[1, 2, 3], COSE_ALG_ES256)).unwrap();
+
+        assert_eq!(
+            attested.credential_id.as_ref(),
+            BASE64_URL.encode([1, 2, 3])
+        );
+        assert_eq!(attested.public_key, credential(&key, 0).public_key);
+        assert_eq!(attested.sign_count, 0);
+    }
+
+    #[test]
+    fn test_verify_attestation_with_unsupported_algorithm() {
+        let key = SigningKey::random(&mut rand::thread_rng());
+        // RS256
+        let attestation = attestation_object(&key, &[1, 2, 3], -257);
+
+        assert!(verify_attestation(&attestation).is_err());
+    }
+
+    #[test]
+    fn test_verify_assertion() {
+        let key = SigningKey::random(&mut rand::thread_rng());
+        let auth_data = authenticator_data(FLAG_USER_PRESENT | FLAG_USER_VERIFIED, 1);
+        let client_data = client_data(
+            CLIENT_DATA_TYPE_GET,
+            &WebAuthnChallenge::default(),
+            &AUTH_SERVICE_URL,
+        );
+        let signature = sign(&key, &auth_data, &client_data);
+
+        let result = verify_assertion(&credential(&key, 0), &auth_data, &client_data, &signature);
+
+        assert_eq!(result.unwrap(), 1);
+    }
+
+    #[test]
+    fn test_verify_assertion_signed_by_another_key() {
+        let key = SigningKey::random(&mut rand::thread_rng());
+        let other_key = SigningKey::random(&mut rand::thread_rng());
+        let auth_data = authenticator_data(FLAG_USER_PRESENT | FLAG_USER_VERIFIED, 1);
+        let client_data = client_data(
+            CLIENT_DATA_TYPE_GET,
+            &WebAuthnChallenge::default(),
+            &AUTH_SERVICE_URL,
+        );
+        let signature = sign(&other_key, &auth_data, &client_data);
+
+        let result = verify_assertion(&credential(&key, 0), &auth_data, &client_data, &signature);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_verify_assertion_without_user_verification() {
+        let key = SigningKey::random(&mut rand::thread_rng());
+        let auth_data = authenticator_data(FLAG_USER_PRESENT, 1);
+        let client_data = client_data(
+            CLIENT_DATA_TYPE_GET,
+            &WebAuthnChallenge::default(),
+            &AUTH_SERVICE_URL,
+        );
+        let signature = sign(&key, &auth_data, &client_data);
+
+        let result = verify_assertion(&credential(&key, 0), &auth_data, &client_data, &signature);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_verify_assertion_with_replayed_sign_count() {
+        let key = SigningKey::random(&mut rand::thread_rng());
+        let auth_data = authenticator_data(FLAG_USER_PRESENT | FLAG_USER_VERIFIED, 5);
+        let client_data = client_data(
+            CLIENT_DATA_TYPE_GET,
+            &WebAuthnChallenge::default(),
+            &AUTH_SERVICE_URL,
+        );
+        let signature = sign(&key, &auth_data, &client_data);
+
+        let result = verify_assertion(&credential(&key, 5), &auth_data, &client_data, &signature);
+
+        assert!(result.is_err());
+    }
+}
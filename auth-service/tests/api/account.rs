@@ -0,0 +1,242 @@
+use auth_service::{
+    domain::{Email, OAuthClient, OAuthScope, UserStoreError},
+    routes::AccountExportResponse,
+    utils::constants::JWT_COOKIE_NAME,
+    ErrorResponse,
+};
+use secrecy::Secret;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{TestApp, TestUser};
+use test_helpers::api_test;
+
+async fn verify_token(app: &TestApp, token: &str) -> u16 {
+    app.post_verify_token(&serde_json::json!({ "token": token }))
+        .await
+        .status()
+        .as_u16()
+}
+
+#[api_test]
+async fn should_return_204_and_delete_account() {
+    let TestUser {
+        email: random_email,
+        auth_token: token,
+        ..
+    } = app.signup_and_login(&[]).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .delete_account(&serde_json::json!({ "password": "password123" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 204);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    assert!(auth_cookie.value().is_empty());
+
+    let email = Email::parse(Secret::new(random_email.clone())).unwrap();
+
+    assert_eq!(
+        app.user_store.read().await.get_user(&email).await,
+        Err(UserStoreError::UserNotFound)
+    );
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    assert_eq!(verify_token(&app, &token).await, 401);
+
+    // The email is free to sign up with again, but old tokens stay revoked
+    app.signup_user(&random_email, false).await;
+
+    assert_eq!(verify_token(&app, &token).await, 401);
+}
+
+#[api_test]
+async fn should_return_401_if_password_incorrect() {
+    let TestUser {
+        email: random_email,
+        auth_token: token,
+        ..
+    } = app.signup_and_login(&[]).await;
+
+    let response = app
+        .delete_account(&serde_json::json!({ "password": "wrongpassword" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Incorrect credentials".to_owned()
+    );
+
+    // The account is left as it was
+    let email = Email::parse(Secret::new(random_email)).unwrap();
+
+    assert!(app.user_store.read().await.get_user(&email).await.is_ok());
+    assert_eq!(verify_token(&app, &token).await, 200);
+}
+
+#[api_test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let response = app
+        .delete_account(&serde_json::json!({ "password": "password123" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app.get_account_export().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_return_422_if_malformed_input() {
+    app.signup_and_login(&[]).await;
+
+    let response = app.delete_account(&serde_json::json!({})).await;
+
+    assert_eq!(response.status().as_u16(), 422);
+}
+
+#[api_test]
+async fn should_export_everything_stored_about_user() {
+    let TestUser {
+        email: random_email,
+        id: user_id,
+        ..
+    } = app.signup_and_login(&[]).await;
+
+    let response = app.post_recovery_codes().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_api_key(&serde_json::json!({
+            "name": "Deploy script",
+            "scope": "reports:read",
+            "expiresInDays": 30
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let client = OAuthClient::new(
+        "Example App".to_owned(),
+        vec!["https://app.example.com/callback".to_owned()],
+    )
+    .expect("Failed to create client");
+    app.oauth_client_store
+        .write()
+        .await
+        .add_client(client.clone())
+        .await
+        .expect("Failed to add client");
+    app.oauth_consent_store
+        .write()
+        .await
+        .set_consent(
+            &user_id,
+            &client.id,
+            &OAuthScope::parse("email profile").unwrap(),
+        )
+        .await
+        .expect("Failed to set consent");
+
+    let response = app.get_account_export().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let export = response
+        .json::<AccountExportResponse>()
+        .await
+        .expect("Could not deserialize response body to AccountExportResponse");
+
+    assert_eq!(export.profile.id, user_id.to_string());
+    assert_eq!(export.profile.email, random_email);
+    assert!(!export.two_factor.totp_enabled);
+    assert!(export.two_factor.passkeys.is_empty());
+    assert_eq!(export.two_factor.recovery_codes_remaining, 10);
+    assert_eq!(export.sessions.len(), 1);
+    assert!(export.sessions[0].current);
+    assert_eq!(export.api_keys.len(), 1);
+    assert_eq!(export.api_keys[0].name, "Deploy script");
+    assert_eq!(export.api_keys[0].scope, "reports:read");
+    assert!(export.api_keys[0].expires_at.is_some());
+    // The key itself is only ever shown when it's created
+    assert_eq!(export.api_keys[0].api_key, None);
+    assert_eq!(export.oauth_consents.len(), 1);
+    assert_eq!(export.oauth_consents[0].client_id, client.id.to_string());
+    assert_eq!(export.oauth_consents[0].client_name, "Example App");
+    assert_eq!(export.oauth_consents[0].scope, "email profile");
+
+    let events: Vec<&str> = export
+        .audit_events
+        .iter()
+        .map(|event| event.event.as_str())
+        .collect();
+    assert_eq!(events, ["recovery_codes_generated", "login"]);
+}
+
+#[api_test]
+async fn should_void_password_reset_links_when_deleting_account() {
+    let random_email = app.signup_and_login(&[]).await.email;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_password_reset_request(&serde_json::json!({ "email": random_email }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 202);
+
+    let reset_token = app.password_reset_token(1).await;
+
+    let response = app
+        .delete_account(&serde_json::json!({ "password": "password123" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 204);
+
+    // Whoever registers the email next must not be open to the old link
+    app.signup_user(&random_email, false).await;
+
+    let response = app
+        .post_password_reset_confirm(&serde_json::json!({
+            "token": reset_token,
+            "password": "newpassword123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
@@ -0,0 +1,394 @@
+use auth_service::{
+    domain::{Email, Role, SubjectType, UserStoreError},
+    routes::{AdminUserResponse, AdminUsersResponse, ApiKeyResponse, VerifyTokenResponse},
+    utils::constants::JWT_COOKIE_NAME,
+    ErrorResponse,
+};
+use secrecy::Secret;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp, TestUser};
+use test_helpers::api_test;
+
+async fn error_message(response: reqwest::Response) -> String {
+    response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse")
+        .error
+}
+
+#[api_test]
+async fn should_return_403_if_not_admin() {
+    let random_email = get_random_email();
+    let id = app.signup_user(&random_email, false).await.to_string();
+
+    let response = app.login_user(&random_email).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.get_admin_users(&[]).await;
+
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = app.delete_admin_user(&id).await;
+
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[api_test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let response = app.get_admin_users(&[]).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_list_users_a_page_at_a_time() {
+    // Emails share a tag that's unique to this test, so other users don't show up
+    let tag = uuid::Uuid::new_v4().simple().to_string();
+    for name in ["carol", "alice", "bob"] {
+        app.signup_user(&format!("{}-{}@example.com", name, tag), false)
+            .await;
+    }
+
+    app.signup_and_login(&[Role::admin()]).await;
+
+    let response = app
+        .get_admin_users(&[("email", &tag.to_uppercase()), ("limit", "2")])
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let page = response
+        .json::<AdminUsersResponse>()
+        .await
+        .expect("Could not deserialize response body to AdminUsersResponse");
+    let emails: Vec<String> = page.users.into_iter().map(|user| user.email).collect();
+
+    assert_eq!(page.total, 3);
+    assert_eq!(
+        emails,
+        [
+            format!("alice-{}@example.com", tag),
+            format!("bob-{}@example.com", tag)
+        ]
+    );
+
+    let response = app
+        .get_admin_users(&[("email", &tag), ("offset", "2"), ("limit", "2")])
+        .await;
+    let page = response
+        .json::<AdminUsersResponse>()
+        .await
+        .expect("Could not deserialize response body to AdminUsersResponse");
+
+    assert_eq!(page.users.len(), 1);
+    assert_eq!(page.users[0].email, format!("carol-{}@example.com", tag));
+}
+
+#[api_test]
+async fn should_get_user() {
+    let random_email = get_random_email();
+    let id = app.signup_user(&random_email, false).await.to_string();
+
+    app.signup_and_login(&[Role::admin()]).await;
+
+    let response = app.get_admin_user(&id).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let user = response
+        .json::<AdminUserResponse>()
+        .await
+        .expect("Could not deserialize response body to AdminUserResponse");
+
+    assert_eq!(user.id, id);
+    assert_eq!(user.email, random_email);
+    assert!(user.roles.is_empty());
+    assert!(user.permissions.is_empty());
+    assert!(!user.disabled);
+}
+
+#[api_test]
+async fn should_return_404_if_user_not_found() {
+    app.signup_and_login(&[Role::admin()]).await;
+
+    for id in [uuid::Uuid::new_v4().to_string(), "invalid".to_owned()] {
+        let response = app.get_admin_user(&id).await;
+
+        assert_eq!(response.status().as_u16(), 404);
+        assert_eq!(error_message(response).await, "User not found".to_owned());
+    }
+}
+
+#[api_test]
+async fn should_disable_and_enable_user() {
+    let random_email = get_random_email();
+    let id = app.signup_user(&random_email, false).await.to_string();
+
+    let response = app.login_user(&random_email).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    let token = auth_cookie.value().to_owned();
+
+    app.signup_and_login(&[Role::admin()]).await;
+
+    let response = app.post_admin_disable_user(&id).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.login_user(&random_email).await;
+
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(error_message(response).await, "Account disabled".to_owned());
+
+    let response = app.post_admin_enable_user(&id).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.login_user(&random_email).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Sessions from before the account was disabled stay revoked
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_toggle_requires_2fa() {
+    let random_email = get_random_email();
+    let id = app.signup_user(&random_email, false).await.to_string();
+
+    app.signup_and_login(&[Role::admin()]).await;
+
+    let response = app
+        .put_admin_requires_2fa(&id, &serde_json::json!({ "requires2FA": true }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let user = response
+        .json::<AdminUserResponse>()
+        .await
+        .expect("Could not deserialize response body to AdminUserResponse");
+
+    assert!(user.requires_2fa);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.login_user(&random_email).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+}
+
+#[api_test]
+async fn should_force_password_reset() {
+    let TestUser {
+        email: random_email,
+        id,
+        ..
+    } = app.signup_and_login(&[]).await;
+
+    let response = app
+        .post_api_key(&serde_json::json!({ "name": "Deploy script" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let api_key = response
+        .json::<ApiKeyResponse>()
+        .await
+        .expect("Could not deserialize response body to ApiKeyResponse")
+        .api_key
+        .expect("No API key found");
+
+    app.signup_and_login(&[Role::admin()]).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_admin_password_reset(&id.to_string()).await;
+
+    assert_eq!(response.status().as_u16(), 202);
+
+    // The old password no longer works
+    let response = app.login_user(&random_email).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Nor do the user's API keys
+    let response = app.post_verify_api_key(&api_key).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_delete_user() {
+    let random_email = get_random_email();
+    let id = app.signup_user(&random_email, false).await.to_string();
+
+    app.signup_and_login(&[Role::admin()]).await;
+
+    let response = app.delete_admin_user(&id).await;
+
+    assert_eq!(response.status().as_u16(), 204);
+
+    let email = Email::parse(Secret::new(random_email)).unwrap();
+
+    assert_eq!(
+        app.user_store.read().await.get_user(&email).await,
+        Err(UserStoreError::UserNotFound)
+    );
+
+    let response = app.get_admin_user(&id).await;
+
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[api_test]
+async fn should_grant_and_revoke_grants() {
+    let random_email = get_random_email();
+    let id = app.signup_user(&random_email, false).await.to_string();
+
+    app.signup_and_login(&[Role::admin()]).await;
+
+    let response = app
+        .post_admin_role(&id, &serde_json::json!({ "role": "editor" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_admin_permission(&id, &serde_json::json!({ "permission": "reports:read" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let user = response
+        .json::<AdminUserResponse>()
+        .await
+        .expect("Could not deserialize response body to AdminUserResponse");
+
+    assert_eq!(user.roles, ["editor"]);
+    assert_eq!(user.permissions, ["reports:read"]);
+
+    // Tokens issued from now on carry the grants
+    let response = app.login_user(&random_email).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response
+            .json::<VerifyTokenResponse>()
+            .await
+            .expect("Could not deserialize response body to VerifyTokenResponse"),
+        VerifyTokenResponse {
+            subject_type: SubjectType::User,
+            roles: vec!["editor".to_owned()],
+            permissions: vec!["reports:read".to_owned()],
+            client_id: None,
+            scope: None,
+        }
+    );
+
+    app.signup_and_login(&[Role::admin()]).await;
+
+    let response = app.delete_admin_permission(&id, "reports:read").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let user = response
+        .json::<AdminUserResponse>()
+        .await
+        .expect("Could not deserialize response body to AdminUserResponse");
+
+    assert!(user.permissions.is_empty());
+
+    // Tokens carrying the revoked permission are no longer accepted
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.delete_admin_role(&id, "editor").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_400_if_grant_is_malformed() {
+    let random_email = get_random_email();
+    let id = app.signup_user(&random_email, false).await.to_string();
+
+    app.signup_and_login(&[Role::admin()]).await;
+
+    let response = app
+        .post_admin_role(&id, &serde_json::json!({ "role": "Not A Role" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app
+        .post_admin_permission(&id, &serde_json::json!({ "permission": "" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_deny_admin_api_once_admin_role_is_revoked() {
+    let id = app.signup_and_login(&[Role::admin()]).await.id;
+
+    let response = app.delete_admin_role(&id.to_string(), Role::ADMIN).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.get_admin_users(&[]).await;
+
+    assert_eq!(response.status().as_u16(), 403);
+}
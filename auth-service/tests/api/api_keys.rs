@@ -0,0 +1,229 @@
+use auth_service::{
+    domain::{ApiKey, ApiKeySecret, OAuthScope},
+    routes::{ApiKeyResponse, ApiKeysResponse, VerifyApiKeyResponse},
+    ErrorResponse,
+};
+use chrono::{Duration, Utc};
+use secrecy::ExposeSecret;
+
+use crate::helpers::TestApp;
+use test_helpers::api_test;
+
+async fn create_api_key(app: &TestApp, body: &serde_json::Value) -> ApiKeyResponse {
+    let response = app.post_api_key(body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    response
+        .json::<ApiKeyResponse>()
+        .await
+        .expect("Could not deserialize response body to ApiKeyResponse")
+}
+
+async fn get_api_keys(app: &TestApp) -> Vec<ApiKeyResponse> {
+    let response = app.get_api_keys().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<ApiKeysResponse>()
+        .await
+        .expect("Could not deserialize response body to ApiKeysResponse")
+        .api_keys
+}
+
+#[api_test]
+async fn should_create_api_key_shown_only_once() {
+    let user_id = app.signup_and_login(&[]).await.id;
+
+    let created = create_api_key(
+        &app,
+        &serde_json::json!({
+            "name": "Deploy script",
+            "scope": "reports:read reports:write",
+            "expiresInDays": 30
+        }),
+    )
+    .await;
+
+    let api_key = created.api_key.clone().expect("No API key found");
+    assert!(api_key.starts_with("apikey_"));
+    assert!(api_key.starts_with(&created.hint));
+    assert_eq!(created.scope, "reports:read reports:write");
+    assert!(created.expires_at.is_some());
+
+    let keys = get_api_keys(&app).await;
+
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0].id, created.id);
+    assert_eq!(keys[0].name, "Deploy script");
+    assert_eq!(keys[0].api_key, None);
+
+    let response = app.post_verify_api_key(&api_key).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response
+            .json::<VerifyApiKeyResponse>()
+            .await
+            .expect("Could not deserialize response body to VerifyApiKeyResponse"),
+        VerifyApiKeyResponse {
+            user_id: user_id.to_string(),
+            key_id: created.id,
+            scope: "reports:read reports:write".to_owned(),
+        }
+    );
+}
+
+#[api_test]
+async fn should_rename_and_revoke_api_key() {
+    app.signup_and_login(&[]).await;
+
+    let created = create_api_key(&app, &serde_json::json!({ "name": "Deploy script" })).await;
+    let api_key = created.api_key.expect("No API key found");
+
+    // Keys without an expiry last until they're revoked
+    assert_eq!(created.expires_at, None);
+
+    let response = app
+        .patch_api_key(&created.id, &serde_json::json!({ "name": "Backups" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response
+            .json::<ApiKeyResponse>()
+            .await
+            .expect("Could not deserialize response body to ApiKeyResponse")
+            .name,
+        "Backups"
+    );
+
+    let response = app.delete_api_key(&created.id).await;
+
+    assert_eq!(response.status().as_u16(), 204);
+    assert!(get_api_keys(&app).await.is_empty());
+
+    let response = app.post_verify_api_key(&api_key).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.delete_api_key(&created.id).await;
+
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[api_test]
+async fn should_not_let_users_manage_each_others_api_keys() {
+    app.signup_and_login(&[]).await;
+    let created = create_api_key(&app, &serde_json::json!({ "name": "Deploy script" })).await;
+
+    // Logging in as someone else replaces the auth cookie
+    app.signup_and_login(&[]).await;
+
+    assert!(get_api_keys(&app).await.is_empty());
+
+    let response = app
+        .patch_api_key(&created.id, &serde_json::json!({ "name": "Mine now" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 404);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "API key not found"
+    );
+
+    let response = app.delete_api_key(&created.id).await;
+
+    assert_eq!(response.status().as_u16(), 404);
+
+    let response = app
+        .post_verify_api_key(&created.api_key.expect("No API key found"))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_reject_expired_and_malformed_api_keys() {
+    let user_id = app.signup_and_login(&[]).await.id;
+
+    let secret = ApiKeySecret::default();
+    let mut key = ApiKey::new(
+        user_id,
+        "Old script".to_owned(),
+        OAuthScope::default(),
+        None,
+        &secret,
+    )
+    .unwrap();
+    key.expires_at = Some(Utc::now() - Duration::seconds(1));
+    app.api_key_store
+        .write()
+        .await
+        .add_key(key)
+        .await
+        .expect("Failed to add API key");
+
+    let unknown = ApiKeySecret::default();
+    let test_cases = [
+        secret.as_ref().expose_secret().as_str(),
+        unknown.as_ref().expose_secret().as_str(),
+        "apikey_short",
+        "not-an-api-key",
+    ];
+
+    for api_key in test_cases {
+        let response = app.post_verify_api_key(api_key).await;
+
+        assert_eq!(response.status().as_u16(), 401);
+    }
+}
+
+#[api_test]
+async fn should_return_400_if_api_key_request_is_invalid() {
+    app.signup_and_login(&[]).await;
+
+    let test_cases = [
+        serde_json::json!({ "name": "" }),
+        serde_json::json!({ "name": "Deploy script", "scope": "bad\"scope" }),
+        serde_json::json!({ "name": "Deploy script", "expiresInDays": 0 }),
+        serde_json::json!({ "name": "Deploy script", "expiresInDays": 366 }),
+    ];
+
+    for body in test_cases {
+        let response = app.post_api_key(&body).await;
+
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "Failed for input: {}",
+            body
+        );
+    }
+}
+
+#[api_test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let response = app
+        .post_api_key(&serde_json::json!({ "name": "Deploy script" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app.get_api_keys().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = reqwest::Client::new()
+        .post(&format!("{}/verify-api-key", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    assert_eq!(response.status().as_u16(), 400);
+}
@@ -0,0 +1,312 @@
+use auth_service::ErrorResponse;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp, TestUser};
+use test_helpers::api_test;
+
+async fn mount_email_server(app: &TestApp, expected_emails: u64) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(expected_emails)
+        .mount(&app.email_server)
+        .await;
+}
+
+// Pulls the token out of the last confirmation link sent to `recipient`
+async fn change_email_token(app: &TestApp, recipient: &str) -> String {
+    let requests = app
+        .email_server
+        .received_requests()
+        .await
+        .expect("Request recording is disabled");
+    let body: serde_json::Value = requests
+        .iter()
+        .rev()
+        .filter_map(|request| request.body_json::<serde_json::Value>().ok())
+        .find(|body| body["To"] == recipient && body["Subject"] == "Confirm your new email")
+        .expect("No email was sent to the new email");
+    let text = body["TextBody"].as_str().expect("Email has no text body");
+    let start = text.find("token=").expect("No token in email") + "token=".len();
+
+    text[start..].trim().to_owned()
+}
+
+// Signs up and logs in a user, then asks to move them to a new email
+async fn request_change(app: &TestApp) -> (String, String, String) {
+    let TestUser {
+        email, auth_token, ..
+    } = app.signup_and_login(&[]).await;
+    let new_email = get_random_email();
+
+    let response = app
+        .post_change_email(&serde_json::json!({
+            "newEmail": new_email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 202);
+
+    (email, new_email, auth_token)
+}
+
+#[api_test]
+async fn should_send_confirmation_to_new_email_and_notice_to_old() {
+    mount_email_server(&app, 2).await;
+
+    let (email, new_email, _) = request_change(&app).await;
+
+    let requests = app
+        .email_server
+        .received_requests()
+        .await
+        .expect("Request recording is disabled");
+    let recipients: Vec<String> = requests
+        .iter()
+        .filter_map(|request| request.body_json::<serde_json::Value>().ok())
+        .map(|body| body["To"].as_str().unwrap_or_default().to_owned())
+        .collect();
+
+    assert!(recipients.contains(&email));
+    assert!(recipients.contains(&new_email));
+
+    // Nothing changes until the new email is confirmed
+    let response = app.login_user(&email).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_move_account_to_new_email_once_confirmed() {
+    mount_email_server(&app, 2).await;
+
+    let (email, new_email, auth_token) = request_change(&app).await;
+
+    let token = change_email_token(&app, &new_email).await;
+
+    let response = app.get_change_email_confirm(&token).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.login_user(&email).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.login_user(&new_email).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The link only works once
+    let response = app.get_change_email_confirm(&token).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Tokens issued to the old email are revoked, even if it's registered again
+    app.signup_user(&email, false).await;
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": auth_token }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_void_password_reset_links_sent_to_old_email() {
+    mount_email_server(&app, 3).await;
+
+    let (email, new_email, _) = request_change(&app).await;
+
+    let response = app
+        .post_password_reset_request(&serde_json::json!({ "email": email }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 202);
+
+    let reset_token = app.password_reset_token(3).await;
+
+    let response = app
+        .get_change_email_confirm(&change_email_token(&app, &new_email).await)
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_password_reset_confirm(&serde_json::json!({
+            "token": reset_token,
+            "password": "newpassword123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_409_if_new_email_taken() {
+    let email = get_random_email();
+    let other_email = get_random_email();
+
+    app.signup_user(&other_email, false).await;
+    app.signup_user(&email, false).await;
+
+    let response = app.login_user(&email).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_change_email(&serde_json::json!({
+            "newEmail": other_email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 409);
+}
+
+#[api_test]
+async fn should_return_409_if_new_email_taken_before_confirmation() {
+    mount_email_server(&app, 2).await;
+
+    let (email, new_email, auth_token) = request_change(&app).await;
+
+    let token = change_email_token(&app, &new_email).await;
+
+    app.signup_user(&new_email, false).await;
+
+    let response = app.get_change_email_confirm(&token).await;
+
+    assert_eq!(response.status().as_u16(), 409);
+
+    // The account stays where it was, still logged in
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": auth_token }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.login_user(&email).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_not_accept_link_again_after_changing_back() {
+    mount_email_server(&app, 4).await;
+
+    let (email, new_email, _) = request_change(&app).await;
+
+    let token = change_email_token(&app, &new_email).await;
+
+    let response = app.get_change_email_confirm(&token).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Move back to the original email
+    let response = app.login_user(&new_email).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_change_email(&serde_json::json!({
+            "newEmail": email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 202);
+
+    let response = app
+        .get_change_email_confirm(&change_email_token(&app, &email).await)
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The first link names the original email again, but has been used
+    let response = app.get_change_email_confirm(&token).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.login_user(&email).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_401_if_password_incorrect() {
+    let email = get_random_email();
+
+    app.signup_user(&email, false).await;
+
+    let response = app.login_user(&email).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_change_email(&serde_json::json!({
+            "newEmail": get_random_email(),
+            "password": "wrongpassword",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Incorrect credentials".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_return_400_if_invalid_input() {
+    let email = get_random_email();
+
+    app.signup_user(&email, false).await;
+
+    let response = app.login_user(&email).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let test_cases = [
+        serde_json::json!({
+            "newEmail": "invalid",
+            "password": "password123",
+        }),
+        serde_json::json!({
+            "newEmail": email,
+            "password": "password123",
+        }),
+    ];
+
+    for test_case in test_cases {
+        let response = app.post_change_email(&test_case).await;
+
+        assert_eq!(response.status().as_u16(), 400);
+    }
+}
+
+#[api_test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let response = app
+        .post_change_email(&serde_json::json!({
+            "newEmail": get_random_email(),
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_return_401_if_confirmation_token_invalid() {
+    let response = app.get_change_email_confirm("invalid").await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
@@ -0,0 +1,236 @@
+use auth_service::{
+    domain::{ClientSecret, OAuthClient, OAuthClientSecret, OAuthError, OAuthScope, SubjectType},
+    routes::{ClientTokenResponse, VerifyTokenResponse},
+    OAuthErrorResponse,
+};
+use secrecy::ExposeSecret;
+
+use crate::helpers::TestApp;
+use test_helpers::api_test;
+
+// Registers a machine client allowed to read and write reports, returning its ID and secret
+async fn register_machine_client(app: &TestApp) -> (String, String) {
+    let mut client = OAuthClient::new_machine(
+        "Report Jobs".to_owned(),
+        OAuthScope::parse("reports:read reports:write").unwrap(),
+    )
+    .expect("Failed to create client");
+    let client_secret = ClientSecret::default();
+    client.secret = Some(OAuthClientSecret::new(&client_secret));
+
+    app.oauth_client_store
+        .write()
+        .await
+        .add_client(client.clone())
+        .await
+        .expect("Failed to add client");
+
+    (
+        client.id.to_string(),
+        client_secret.as_ref().expose_secret().to_owned(),
+    )
+}
+
+async fn assert_oauth_error(response: reqwest::Response, status: u16, error: OAuthError) {
+    assert_eq!(response.status().as_u16(), status);
+    assert_eq!(
+        response
+            .json::<OAuthErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to OAuthErrorResponse")
+            .error,
+        error.code()
+    );
+}
+
+#[api_test]
+async fn should_issue_token_for_the_client_itself() {
+    let (client_id, client_secret) = register_machine_client(&app).await;
+
+    let response = app
+        .post_oauth_token_with_basic_auth(
+            &client_id,
+            &client_secret,
+            &[
+                ("grant_type", "client_credentials"),
+                ("scope", "reports:read"),
+            ],
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response
+            .headers()
+            .get("cache-control")
+            .and_then(|value| value.to_str().ok()),
+        Some("no-store")
+    );
+
+    let body = response
+        .json::<serde_json::Value>()
+        .await
+        .expect("Could not deserialize response body");
+
+    // Clients can just authenticate again, so they get no refresh token
+    assert!(body.get("refresh_token").is_none());
+
+    let tokens = serde_json::from_value::<ClientTokenResponse>(body)
+        .expect("Could not deserialize response body to ClientTokenResponse");
+
+    assert_eq!(tokens.token_type, "Bearer");
+    assert_eq!(tokens.scope, "reports:read");
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": tokens.access_token }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response
+            .json::<VerifyTokenResponse>()
+            .await
+            .expect("Could not deserialize response body to VerifyTokenResponse"),
+        VerifyTokenResponse {
+            subject_type: SubjectType::Client,
+            roles: Vec::new(),
+            permissions: Vec::new(),
+            client_id: Some(client_id),
+            scope: Some("reports:read".to_owned()),
+        }
+    );
+}
+
+#[api_test]
+async fn should_accept_secret_in_form_and_grant_full_scope_by_default() {
+    let (client_id, client_secret) = register_machine_client(&app).await;
+
+    let response = app
+        .post_oauth_token(&[
+            ("grant_type", "client_credentials"),
+            ("client_id", &client_id),
+            ("client_secret", &client_secret),
+        ])
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let tokens = response
+        .json::<ClientTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to ClientTokenResponse");
+
+    assert_eq!(tokens.scope, "reports:read reports:write");
+}
+
+#[api_test]
+async fn should_return_400_if_scope_exceeds_what_client_is_allowed() {
+    let (client_id, client_secret) = register_machine_client(&app).await;
+
+    let response = app
+        .post_oauth_token_with_basic_auth(
+            &client_id,
+            &client_secret,
+            &[
+                ("grant_type", "client_credentials"),
+                ("scope", "reports:read users:delete"),
+            ],
+        )
+        .await;
+
+    assert_oauth_error(response, 400, OAuthError::InvalidScope).await;
+}
+
+#[api_test]
+async fn should_return_401_if_client_cannot_authenticate() {
+    let (client_id, client_secret) = register_machine_client(&app).await;
+
+    let public_client = OAuthClient::new(
+        "Example App".to_owned(),
+        vec!["https://app.example.com/callback".to_owned()],
+    )
+    .unwrap();
+    app.oauth_client_store
+        .write()
+        .await
+        .add_client(public_client.clone())
+        .await
+        .unwrap();
+    let public_client_id = public_client.id.to_string();
+
+    let wrong_secret = ClientSecret::default();
+    let wrong_secret = wrong_secret.as_ref().expose_secret();
+
+    let test_cases = [
+        (client_id.as_str(), wrong_secret.as_str()),
+        (client_id.as_str(), "too-short"),
+        (
+            "5f0c1f0e-3a4b-4c8d-9e2f-1a2b3c4d5e6f",
+            client_secret.as_str(),
+        ),
+        // Public clients have no secret to authenticate with
+        (public_client_id.as_str(), client_secret.as_str()),
+    ];
+
+    for (id, secret) in test_cases {
+        let response = app
+            .post_oauth_token_with_basic_auth(id, secret, &[("grant_type", "client_credentials")])
+            .await;
+
+        assert_oauth_error(response, 401, OAuthError::InvalidClient).await;
+    }
+
+    let response = app
+        .post_oauth_token(&[
+            ("grant_type", "client_credentials"),
+            ("client_id", &client_id),
+        ])
+        .await;
+
+    assert_oauth_error(response, 401, OAuthError::InvalidClient).await;
+}
+
+#[api_test]
+async fn should_not_let_machine_clients_act_for_users() {
+    let (client_id, client_secret) = register_machine_client(&app).await;
+
+    let response = app
+        .get_oauth_authorize(&[
+            ("response_type", "code"),
+            ("client_id", &client_id),
+            (
+                "code_challenge",
+                "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM",
+            ),
+            ("code_challenge_method", "S256"),
+        ])
+        .await;
+
+    assert_oauth_error(response, 400, OAuthError::UnauthorizedClient).await;
+
+    let response = app
+        .post_oauth_token(&[
+            ("grant_type", "refresh_token"),
+            ("client_id", &client_id),
+            ("refresh_token", &client_secret),
+        ])
+        .await;
+
+    assert_oauth_error(response, 400, OAuthError::UnauthorizedClient).await;
+
+    // Nor is their token any good where a user's is expected
+    let tokens = app
+        .post_oauth_token_with_basic_auth(
+            &client_id,
+            &client_secret,
+            &[("grant_type", "client_credentials")],
+        )
+        .await
+        .json::<ClientTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to ClientTokenResponse");
+
+    let response = app.get_userinfo(&tokens.access_token).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
@@ -1,14 +1,29 @@
-use std::{str::FromStr, sync::Arc};
+use std::{str::FromStr, sync::Arc, time::Duration};
 
 use auth_service::{
-    app_state::{AppState, BannedTokenStoreType, TwoFACodeStoreType},
-    domain::Email,
+    app_state::{
+        ApiKeyStoreType, AppState, BannedTokenStoreType, OAuthClientStoreType,
+        OAuthConsentStoreType, RoleStoreType, TwoFACodeStoreType, UserStoreType,
+    },
+    domain::{Email, Role, UserId},
     get_postgres_pool, get_redis_client,
     services::{
-        data_stores::{PostgresUserStore, RedisBannedTokenStore, RedisTwoFACodeStore},
+        data_stores::{
+            HashMapFailedAttemptStore, HashMapRateLimitStore, PostgresApiKeyStore,
+            PostgresAuditEventStore, PostgresOAuthClientStore, PostgresOAuthConsentStore,
+            PostgresRecoveryCodeStore, PostgresRefreshTokenStore, PostgresRoleStore,
+            PostgresSessionStore, PostgresUserStore, PostgresWebAuthnCredentialStore,
+            RedisAuthorizationCodeStore, RedisBannedTokenStore, RedisPasswordResetTokenStore,
+            RedisTwoFACodeStore, RedisWebAuthnChallengeStore,
+        },
         postmark_email_client::PostmarkEmailClient,
     },
-    utils::constants::{test, DATABASE_URL, REDIS_HOST_NAME},
+    utils::{
+        constants::{
+            test, DATABASE_URL, JWT_COOKIE_NAME, REDIS_HOST_NAME, REFRESH_TOKEN_COOKIE_NAME,
+        },
+        rate_limit::{RateLimitConfig, RateLimiter},
+    },
     Application,
 };
 use reqwest::{cookie::Jar, Client};
@@ -19,13 +34,29 @@ use sqlx::{
 };
 use tokio::sync::RwLock;
 use uuid::Uuid;
-use wiremock::MockServer;
+use wiremock::{MockServer, Request};
+
+// The password every test user signs up with
+pub const TEST_PASSWORD: &str = "password123";
+
+/// A user signed up and logged in by `TestApp::signup_and_login`, whose cookies the app's client now holds.
+pub struct TestUser {
+    pub email: String,
+    pub id: UserId,
+    pub auth_token: String,
+    pub refresh_token: String,
+}
 
 pub struct TestApp {
     pub address: String,
     pub cookie_jar: Arc<Jar>,
+    pub user_store: UserStoreType,
     pub banned_token_store: BannedTokenStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
+    pub role_store: RoleStoreType,
+    pub oauth_client_store: OAuthClientStoreType,
+    pub oauth_consent_store: OAuthConsentStoreType,
+    pub api_key_store: ApiKeyStoreType,
     pub http_client: reqwest::Client,
     pub email_server: MockServer,
     pub db_name: String,
@@ -33,16 +64,61 @@ pub struct TestApp {
 }
 
 impl TestApp {
+    // Most tests log in right after signing up, so verification is opt-in
     pub async fn new() -> Self {
+        Self::build(false, RateLimitConfig::default()).await
+    }
+
+    pub async fn with_email_verification() -> Self {
+        Self::build(true, RateLimitConfig::default()).await
+    }
+
+    // Unlimited by default, so only the rate limiting tests have to budget their requests
+    pub async fn with_rate_limits(rate_limits: &str) -> Self {
+        let rate_limits = RateLimitConfig::parse(rate_limits).expect("Invalid rate limits");
+        Self::build(false, rate_limits).await
+    }
+
+    async fn build(require_email_verification: bool, rate_limits: RateLimitConfig) -> Self {
         let db_name = Uuid::new_v4().to_string();
         let pg_pool = configure_postgresql(&db_name).await;
         let redis_connection = Arc::new(RwLock::new(configure_redis()));
 
-        let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
+        let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool.clone())));
         let banned_token_store = Arc::new(RwLock::new(RedisBannedTokenStore::new(
             redis_connection.clone(),
         )));
-        let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_connection)));
+        let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(
+            redis_connection.clone(),
+        )));
+        let password_reset_token_store = Arc::new(RwLock::new(RedisPasswordResetTokenStore::new(
+            redis_connection.clone(),
+        )));
+        // In memory rather than Redis, as every test connects from 127.0.0.1 and would share the IP counters
+        let failed_attempt_store = Arc::new(RwLock::new(HashMapFailedAttemptStore::default()));
+        let webauthn_challenge_store = Arc::new(RwLock::new(RedisWebAuthnChallengeStore::new(
+            redis_connection.clone(),
+        )));
+        let authorization_code_store = Arc::new(RwLock::new(RedisAuthorizationCodeStore::new(
+            redis_connection,
+        )));
+        let recovery_code_store =
+            Arc::new(RwLock::new(PostgresRecoveryCodeStore::new(pg_pool.clone())));
+        let webauthn_credential_store = Arc::new(RwLock::new(
+            PostgresWebAuthnCredentialStore::new(pg_pool.clone()),
+        ));
+        let role_store = Arc::new(RwLock::new(PostgresRoleStore::new(pg_pool.clone())));
+        let oauth_client_store =
+            Arc::new(RwLock::new(PostgresOAuthClientStore::new(pg_pool.clone())));
+        let oauth_consent_store =
+            Arc::new(RwLock::new(PostgresOAuthConsentStore::new(pg_pool.clone())));
+        let api_key_store = Arc::new(RwLock::new(PostgresApiKeyStore::new(pg_pool.clone())));
+        let audit_event_store =
+            Arc::new(RwLock::new(PostgresAuditEventStore::new(pg_pool.clone())));
+        let session_store = Arc::new(RwLock::new(PostgresSessionStore::new(pg_pool.clone())));
+        let refresh_token_store = Arc::new(RwLock::new(PostgresRefreshTokenStore::new(pg_pool)));
+        let rate_limit_store = Arc::new(RwLock::new(HashMapRateLimitStore::default()));
+        let rate_limiter = RateLimiter::new(rate_limit_store, rate_limits);
 
         // Setup a mock email server
         let email_server = MockServer::start().await;
@@ -50,10 +126,25 @@ impl TestApp {
         let email_client = Arc::new(configure_postmark_email_client(base_url));
 
         let app_state = AppState::new(
-            user_store,
+            user_store.clone(),
             banned_token_store.clone(),
             two_fa_code_store.clone(),
+            refresh_token_store,
+            session_store,
+            password_reset_token_store,
+            recovery_code_store,
+            failed_attempt_store,
+            webauthn_credential_store,
+            webauthn_challenge_store,
+            role_store.clone(),
+            oauth_client_store.clone(),
+            oauth_consent_store.clone(),
+            authorization_code_store,
+            api_key_store.clone(),
+            audit_event_store,
+            rate_limiter,
             email_client,
+            require_email_verification,
         );
 
         let app = Application::build(app_state, test::APP_ADDRESS)
@@ -74,8 +165,13 @@ impl TestApp {
         Self {
             address,
             cookie_jar,
+            user_store,
             banned_token_store,
             two_fa_code_store,
+            role_store,
+            oauth_client_store,
+            oauth_consent_store,
+            api_key_store,
             http_client,
             email_server,
             db_name,
@@ -83,6 +179,64 @@ impl TestApp {
         }
     }
 
+    // Signs up a user with `TEST_PASSWORD`, returning their ID
+    pub async fn signup_user(&self, email: &str, requires_2fa: bool) -> UserId {
+        let signup_body = serde_json::json!({
+            "email": email,
+            "password": TEST_PASSWORD,
+            "requires2FA": requires_2fa
+        });
+
+        let response = self.post_signup(&signup_body).await;
+
+        assert_eq!(response.status().as_u16(), 201);
+
+        let email = Email::parse(Secret::new(email.to_owned())).unwrap();
+        self.user_store
+            .read()
+            .await
+            .get_user(&email)
+            .await
+            .expect("Failed to get user")
+            .id
+    }
+
+    pub async fn login_user(&self, email: &str) -> reqwest::Response {
+        self.post_login(&serde_json::json!({
+            "email": email,
+            "password": TEST_PASSWORD,
+        }))
+        .await
+    }
+
+    // Signs up a new user without 2FA, grants them `roles` so their tokens carry them, and logs them in
+    pub async fn signup_and_login(&self, roles: &[Role]) -> TestUser {
+        let email = get_random_email();
+        let id = self.signup_user(&email, false).await;
+
+        for role in roles {
+            self.role_store
+                .write()
+                .await
+                .grant_role(&id, role)
+                .await
+                .expect("Failed to grant role");
+        }
+
+        let response = self.login_user(&email).await;
+
+        assert_eq!(response.status().as_u16(), 200);
+
+        let (auth_token, refresh_token) = login_tokens(&response);
+
+        TestUser {
+            email,
+            id,
+            auth_token,
+            refresh_token,
+        }
+    }
+
     pub async fn get_root(&self) -> reqwest::Response {
         self.http_client
             .get(&format!("{}/", &self.address))
@@ -91,6 +245,44 @@ impl TestApp {
             .expect("Failed to execute request")
     }
 
+    // What a browser asks before a cross-origin request from `origin`
+    pub async fn options_preflight(
+        &self,
+        path: &str,
+        origin: &str,
+        method: &str,
+    ) -> reqwest::Response {
+        self.http_client
+            .request(
+                reqwest::Method::OPTIONS,
+                &format!("{}{}", &self.address, path),
+            )
+            .header("Origin", origin)
+            .header("Access-Control-Request-Method", method)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_jwks(&self) -> reqwest::Response {
+        self.http_client
+            .get(&format!("{}/.well-known/jwks.json", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_openid_configuration(&self) -> reqwest::Response {
+        self.http_client
+            .get(&format!(
+                "{}/.well-known/openid-configuration",
+                &self.address
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
     pub async fn post_login<Body>(&self, body: Body) -> reqwest::Response
     where
         Body: serde::Serialize,
@@ -111,6 +303,505 @@ impl TestApp {
             .expect("Failed to execute request")
     }
 
+    pub async fn post_logout_all(&self) -> reqwest::Response {
+        self.http_client
+            .post(&format!("{}/logout-all", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_sessions(&self) -> reqwest::Response {
+        self.http_client
+            .get(&format!("{}/sessions", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn delete_session(&self, id: &str) -> reqwest::Response {
+        self.http_client
+            .delete(&format!("{}/sessions/{}", &self.address, id))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_api_keys(&self) -> reqwest::Response {
+        self.http_client
+            .get(&format!("{}/api-keys", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_api_key<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(&format!("{}/api-keys", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn patch_api_key<Body>(&self, id: &str, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .patch(&format!("{}/api-keys/{}", &self.address, id))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn delete_api_key(&self, id: &str) -> reqwest::Response {
+        self.http_client
+            .delete(&format!("{}/api-keys/{}", &self.address, id))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    // Authenticates with the API key only, as scripts do
+    pub async fn post_verify_api_key(&self, api_key: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(&format!("{}/verify-api-key", &self.address))
+            .bearer_auth(api_key)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_refresh(&self) -> reqwest::Response {
+        self.http_client
+            .post(&format!("{}/refresh", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_password_reset_request<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(&format!("{}/password-reset/request", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_password_reset_confirm<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(&format!("{}/password-reset/confirm", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_change_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(&format!("{}/change-password", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_change_email<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(&format!("{}/change-email", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_change_email_confirm(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .get(&format!("{}/change-email/confirm", &self.address))
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn delete_account<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .delete(&format!("{}/account", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_account_export(&self) -> reqwest::Response {
+        self.http_client
+            .get(&format!("{}/account/export", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_admin_users(&self, query: &[(&str, &str)]) -> reqwest::Response {
+        self.http_client
+            .get(&format!("{}/admin/users", &self.address))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_admin_user(&self, id: &str) -> reqwest::Response {
+        self.http_client
+            .get(&format!("{}/admin/users/{}", &self.address, id))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn delete_admin_user(&self, id: &str) -> reqwest::Response {
+        self.http_client
+            .delete(&format!("{}/admin/users/{}", &self.address, id))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_admin_disable_user(&self, id: &str) -> reqwest::Response {
+        self.http_client
+            .post(&format!("{}/admin/users/{}/disable", &self.address, id))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_admin_enable_user(&self, id: &str) -> reqwest::Response {
+        self.http_client
+            .post(&format!("{}/admin/users/{}/enable", &self.address, id))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn put_admin_requires_2fa<Body>(&self, id: &str, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .put(&format!(
+                "{}/admin/users/{}/requires-2fa",
+                &self.address, id
+            ))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_admin_password_reset(&self, id: &str) -> reqwest::Response {
+        self.http_client
+            .post(&format!(
+                "{}/admin/users/{}/password-reset",
+                &self.address, id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_admin_role<Body>(&self, id: &str, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(&format!("{}/admin/users/{}/roles", &self.address, id))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn delete_admin_role(&self, id: &str, role: &str) -> reqwest::Response {
+        self.http_client
+            .delete(&format!(
+                "{}/admin/users/{}/roles/{}",
+                &self.address, id, role
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_admin_permission<Body>(&self, id: &str, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(&format!("{}/admin/users/{}/permissions", &self.address, id))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn delete_admin_permission(&self, id: &str, permission: &str) -> reqwest::Response {
+        self.http_client
+            .delete(&format!(
+                "{}/admin/users/{}/permissions/{}",
+                &self.address, id, permission
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_admin_oauth_client<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(&format!("{}/admin/oauth/clients", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_admin_oauth_clients(&self) -> reqwest::Response {
+        self.http_client
+            .get(&format!("{}/admin/oauth/clients", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_admin_oauth_client(&self, id: &str) -> reqwest::Response {
+        self.http_client
+            .get(&format!("{}/admin/oauth/clients/{}", &self.address, id))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_admin_oauth_client_secret(&self, id: &str) -> reqwest::Response {
+        self.http_client
+            .post(&format!(
+                "{}/admin/oauth/clients/{}/secret",
+                &self.address, id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn delete_admin_oauth_client_secret(&self, id: &str) -> reqwest::Response {
+        self.http_client
+            .delete(&format!(
+                "{}/admin/oauth/clients/{}/secret",
+                &self.address, id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    // Doesn't follow redirects, so tests can check where the user is sent
+    pub async fn get_oauth_authorize(&self, query: &[(&str, &str)]) -> reqwest::Response {
+        reqwest::Client::builder()
+            .cookie_provider(self.cookie_jar.clone())
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap()
+            .get(&format!("{}/oauth/authorize", &self.address))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_oauth_consent<Body>(
+        &self,
+        query: &[(&str, &str)],
+        body: &Body,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(&format!("{}/oauth/authorize", &self.address))
+            .query(query)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_oauth_token(&self, form: &[(&str, &str)]) -> reqwest::Response {
+        self.http_client
+            .post(&format!("{}/oauth/token", &self.address))
+            .form(form)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    // Authenticates with an `Authorization: Basic` header, as machine clients usually do
+    pub async fn post_oauth_token_with_basic_auth(
+        &self,
+        client_id: &str,
+        client_secret: &str,
+        form: &[(&str, &str)],
+    ) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(&format!("{}/oauth/token", &self.address))
+            .basic_auth(client_id, Some(client_secret))
+            .form(form)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_oauth_introspect(
+        &self,
+        client_id: &str,
+        client_secret: &str,
+        form: &[(&str, &str)],
+    ) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(&format!("{}/oauth/introspect", &self.address))
+            .basic_auth(client_id, Some(client_secret))
+            .form(form)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_oauth_revoke(&self, form: &[(&str, &str)]) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(&format!("{}/oauth/revoke", &self.address))
+            .form(form)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_oauth_revoke_with_basic_auth(
+        &self,
+        client_id: &str,
+        client_secret: &str,
+        form: &[(&str, &str)],
+    ) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(&format!("{}/oauth/revoke", &self.address))
+            .basic_auth(client_id, Some(client_secret))
+            .form(form)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    // Authenticates with the bearer token only, as OAuth clients do
+    pub async fn get_userinfo(&self, token: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(&format!("{}/userinfo", &self.address))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_recovery_codes(&self) -> reqwest::Response {
+        self.http_client
+            .post(&format!("{}/recovery-codes", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_totp_enroll(&self) -> reqwest::Response {
+        self.http_client
+            .post(&format!("{}/totp/enroll", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_totp_confirm<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(&format!("{}/totp/confirm", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_webauthn_register_start(&self) -> reqwest::Response {
+        self.http_client
+            .post(&format!("{}/webauthn/register/start", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_webauthn_register_finish<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(&format!("{}/webauthn/register/finish", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_webauthn_login_start<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(&format!("{}/webauthn/login/start", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_webauthn_login_finish<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(&format!("{}/webauthn/login/finish", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
     pub async fn post_verify_2fa<Body>(&self, body: &Body) -> reqwest::Response
     where
         Body: serde::Serialize,
@@ -135,6 +826,27 @@ impl TestApp {
             .expect("Failed to execute request")
     }
 
+    pub async fn get_verify_email(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .get(&format!("{}/verify-email", &self.address))
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_resend_verification<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(&format!("{}/resend-verification", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
     pub async fn post_signup<Body>(&self, body: &Body) -> reqwest::Response
     where
         Body: serde::Serialize,
@@ -147,6 +859,35 @@ impl TestApp {
             .expect("Failed to execute request")
     }
 
+    // Some emails are sent in the background, so may not have arrived when the response does
+    pub async fn wait_for_emails(&self, count: usize) -> Vec<Request> {
+        for _ in 0..100 {
+            let requests = self
+                .email_server
+                .received_requests()
+                .await
+                .expect("Request recording is disabled");
+            if requests.len() >= count {
+                return requests;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("Expected {} emails to have been sent", count);
+    }
+
+    // Pulls the token out of the link in the password reset email, which is the `count`th email sent
+    pub async fn password_reset_token(&self, count: usize) -> String {
+        let requests = self.wait_for_emails(count).await;
+        let body: serde_json::Value = requests[count - 1]
+            .body_json()
+            .expect("Email request body is not JSON");
+        let text = body["TextBody"].as_str().expect("Email has no text body");
+        let start =
+            text.find("reset_token=").expect("No reset token in email") + "reset_token=".len();
+
+        text[start..start + 64].to_owned()
+    }
+
     pub async fn clean_up(&mut self) {
         if self.clean_up_called {
             return;
@@ -165,6 +906,20 @@ impl Drop for TestApp {
     }
 }
 
+// The auth and refresh tokens a successful login set as cookies
+pub fn login_tokens(response: &reqwest::Response) -> (String, String) {
+    let cookie = |name: &str| {
+        response
+            .cookies()
+            .find(|cookie| cookie.name() == name)
+            .unwrap_or_else(|| panic!("No {} cookie found", name))
+            .value()
+            .to_owned()
+    };
+
+    (cookie(JWT_COOKIE_NAME), cookie(REFRESH_TOKEN_COOKIE_NAME))
+}
+
 pub fn get_random_email() -> String {
     format!("{}@example.com", Uuid::new_v4())
 }
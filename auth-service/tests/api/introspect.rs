@@ -0,0 +1,151 @@
+use auth_service::{
+    domain::{ClientSecret, OAuthClient, OAuthClientSecret, OAuthError, OAuthScope},
+    routes::ClientTokenResponse,
+    OAuthErrorResponse,
+};
+use secrecy::ExposeSecret;
+
+use crate::helpers::TestApp;
+use test_helpers::api_test;
+
+// Registers a machine client allowed to read reports, returning its ID and secret
+async fn register_machine_client(app: &TestApp) -> (String, String) {
+    let mut client = OAuthClient::new_machine(
+        "Report API".to_owned(),
+        OAuthScope::parse("reports:read").unwrap(),
+    )
+    .expect("Failed to create client");
+    let client_secret = ClientSecret::default();
+    client.secret = Some(OAuthClientSecret::new(&client_secret));
+
+    app.oauth_client_store
+        .write()
+        .await
+        .add_client(client.clone())
+        .await
+        .expect("Failed to add client");
+
+    (
+        client.id.to_string(),
+        client_secret.as_ref().expose_secret().to_owned(),
+    )
+}
+
+async fn client_token(app: &TestApp, client_id: &str, client_secret: &str) -> String {
+    app.post_oauth_token_with_basic_auth(
+        client_id,
+        client_secret,
+        &[("grant_type", "client_credentials")],
+    )
+    .await
+    .json::<ClientTokenResponse>()
+    .await
+    .expect("Could not deserialize response body to ClientTokenResponse")
+    .access_token
+}
+
+async fn introspect(
+    app: &TestApp,
+    client_id: &str,
+    client_secret: &str,
+    token: &str,
+) -> serde_json::Value {
+    let response = app
+        .post_oauth_introspect(client_id, client_secret, &[("token", token)])
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response
+            .headers()
+            .get("cache-control")
+            .and_then(|value| value.to_str().ok()),
+        Some("no-store")
+    );
+
+    response
+        .json::<serde_json::Value>()
+        .await
+        .expect("Could not deserialize response body")
+}
+
+#[api_test]
+async fn should_describe_active_tokens() {
+    let (client_id, client_secret) = register_machine_client(&app).await;
+    let token = client_token(&app, &client_id, &client_secret).await;
+
+    let body = introspect(&app, &client_id, &client_secret, &token).await;
+
+    assert_eq!(body["active"], true);
+    assert_eq!(body["token_type"], "Bearer");
+    assert_eq!(body["sub"], client_id.as_str());
+    assert_eq!(body["sub_type"], "client");
+    assert_eq!(body["client_id"], client_id.as_str());
+    assert_eq!(body["scope"], "reports:read");
+    assert!(body["exp"].as_u64().is_some());
+    assert!(body["iat"].as_u64().is_some());
+    assert!(body["jti"].as_str().is_some());
+}
+
+#[api_test]
+async fn should_describe_users_tokens() {
+    let (client_id, client_secret) = register_machine_client(&app).await;
+
+    let token = app.signup_and_login(&[]).await.auth_token;
+
+    let body = introspect(&app, &client_id, &client_secret, &token).await;
+
+    assert_eq!(body["active"], true);
+    assert!(body.get("sub_type").is_none());
+    assert!(body.get("client_id").is_none());
+}
+
+#[api_test]
+async fn should_report_invalid_tokens_as_inactive() {
+    let (client_id, client_secret) = register_machine_client(&app).await;
+    let token = client_token(&app, &client_id, &client_secret).await;
+
+    let response = app
+        .post_oauth_revoke_with_basic_auth(&client_id, &client_secret, &[("token", &token)])
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let refresh_token = ClientSecret::default();
+    let test_cases = [
+        "invalid",
+        token.as_str(),
+        refresh_token.as_ref().expose_secret().as_str(),
+    ];
+
+    for token in test_cases {
+        let body = introspect(&app, &client_id, &client_secret, token).await;
+
+        // Nothing more is given away about inactive tokens
+        assert_eq!(body, serde_json::json!({ "active": false }));
+    }
+}
+
+#[api_test]
+async fn should_return_401_if_client_cannot_authenticate() {
+    let (client_id, client_secret) = register_machine_client(&app).await;
+    let token = client_token(&app, &client_id, &client_secret).await;
+
+    let wrong_secret = ClientSecret::default();
+    let response = app
+        .post_oauth_introspect(
+            &client_id,
+            wrong_secret.as_ref().expose_secret(),
+            &[("token", &token)],
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response
+            .json::<OAuthErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to OAuthErrorResponse")
+            .error,
+        OAuthError::InvalidClient.code()
+    );
+}
@@ -0,0 +1,27 @@
+use crate::helpers::TestApp;
+use auth_service::utils::constants::JWT_KEYRING;
+use jsonwebtoken::{decode_header, jwk::JwkSet};
+use test_helpers::api_test;
+
+#[api_test]
+async fn should_return_200_with_signing_keys() {
+    let response = app.get_jwks().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let jwks = response
+        .json::<JwkSet>()
+        .await
+        .expect("Could not deserialize response body to JwkSet");
+
+    assert_eq!(jwks, JWT_KEYRING.read().unwrap().jwks());
+}
+
+#[api_test]
+async fn should_issue_tokens_with_kid_header() {
+    let auth_token = app.signup_and_login(&[]).await.auth_token;
+
+    let header = decode_header(&auth_token).expect("Failed to decode token header");
+
+    assert_eq!(header, JWT_KEYRING.read().unwrap().signing_key().header());
+}
@@ -1,7 +1,11 @@
-use crate::helpers::{get_random_email, TestApp};
+use crate::helpers::{get_random_email, TestApp, TestUser};
 use auth_service::{
-    domain::Email, routes::TwoFactorAuthResponse, utils::constants::JWT_COOKIE_NAME,
+    domain::Email,
+    routes::TwoFactorAuthResponse,
+    utils::constants::{DEFAULT_MAX_FAILED_LOGIN_ATTEMPTS, JWT_COOKIE_NAME},
+    ErrorResponse,
 };
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use secrecy::{ExposeSecret, Secret};
 use test_helpers::api_test;
 use wiremock::{
@@ -92,6 +96,21 @@ async fn should_return_200_if_valid_credentials_and_2fa_disabled() {
     assert!(!auth_cookie.value().is_empty());
 }
 
+#[api_test]
+async fn should_name_user_by_id_rather_than_email_in_token() {
+    let TestUser { id, auth_token, .. } = app.signup_and_login(&[]).await;
+
+    let payload = auth_token.split('.').nth(1).expect("Token has no payload");
+    let claims: serde_json::Value = serde_json::from_slice(
+        &URL_SAFE_NO_PAD
+            .decode(payload)
+            .expect("Token payload is not base64"),
+    )
+    .expect("Token payload is not JSON");
+
+    assert_eq!(claims["sub"], id.to_string());
+}
+
 #[api_test]
 async fn should_return_401_if_incorrect_credentials() {
     let random_email = get_random_email();
@@ -136,6 +155,70 @@ async fn should_return_401_if_incorrect_credentials() {
     );
 }
 
+#[api_test]
+async fn should_return_429_after_too_many_failed_attempts() {
+    let random_email = get_random_email();
+
+    app.signup_user(&random_email, false).await;
+
+    let wrong_login_body = serde_json::json!({
+        "email": random_email,
+        "password": "wrongpassword",
+    });
+
+    for _ in 0..DEFAULT_MAX_FAILED_LOGIN_ATTEMPTS {
+        let response = app.post_login(&wrong_login_body).await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    // Even the right password is refused while the account is locked
+    let response = app.login_user(&random_email).await;
+
+    assert_eq!(response.status().as_u16(), 429);
+
+    let retry_after: u64 = response
+        .headers()
+        .get("Retry-After")
+        .expect("No Retry-After header found")
+        .to_str()
+        .unwrap()
+        .parse()
+        .expect("Retry-After is not a number of seconds");
+    assert!(retry_after > 0);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Too many attempts".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_not_lock_out_other_accounts() {
+    let locked_email = get_random_email();
+    let other_email = get_random_email();
+
+    for email in [&locked_email, &other_email] {
+        app.signup_user(email, false).await;
+    }
+
+    let wrong_login_body = serde_json::json!({
+        "email": locked_email,
+        "password": "wrongpassword",
+    });
+
+    for _ in 0..DEFAULT_MAX_FAILED_LOGIN_ATTEMPTS {
+        app.post_login(&wrong_login_body).await;
+    }
+
+    let response = app.login_user(&other_email).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
 #[api_test]
 async fn should_return_400_if_invalid_input() {
     let random_email = get_random_email();
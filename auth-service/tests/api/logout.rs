@@ -1,4 +1,10 @@
-use auth_service::{utils::constants::JWT_COOKIE_NAME, ErrorResponse};
+use auth_service::{
+    utils::{
+        auth::validate_token,
+        constants::{JWT_AUDIENCE, JWT_COOKIE_NAME},
+    },
+    ErrorResponse,
+};
 use reqwest::Url;
 use secrecy::Secret;
 
@@ -36,6 +42,14 @@ async fn should_return_200_if_valid_jwt_cookie() {
     assert!(!auth_cookie.value().is_empty());
 
     let token = Secret::new(auth_cookie.value().to_owned());
+    let (claims, _) = validate_token(
+        &token,
+        &JWT_AUDIENCE,
+        app.banned_token_store.clone(),
+        app.user_store.clone(),
+    )
+    .await
+    .expect("Failed to validate token");
 
     let response = app.post_logout().await;
 
@@ -50,7 +64,7 @@ async fn should_return_200_if_valid_jwt_cookie() {
 
     let banned_token_store = app.banned_token_store.read().await;
     let contains_token = banned_token_store
-        .is_banned(&token)
+        .is_banned(claims.jti())
         .await
         .expect("Failed to check if token is banned");
 
@@ -0,0 +1,114 @@
+use auth_service::{
+    utils::constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+    ErrorResponse,
+};
+use reqwest::Url;
+
+use crate::helpers::{login_tokens, TestApp, TestUser};
+use test_helpers::api_test;
+
+#[api_test]
+async fn should_return_200_and_revoke_every_session() {
+    // Two sessions, as if logged in on two devices
+    let TestUser {
+        email,
+        auth_token: first_token,
+        refresh_token: first_refresh_token,
+        ..
+    } = app.signup_and_login(&[]).await;
+
+    let response = app.login_user(&email).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let (second_token, _) = login_tokens(&response);
+
+    let response = app.post_logout_all().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    assert!(auth_cookie.value().is_empty());
+
+    for token in [first_token, second_token] {
+        let verify_token_body = serde_json::json!({
+            "token": token,
+        });
+
+        let response = app.post_verify_token(&verify_token_body).await;
+
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    // The other device can't get a new auth token either
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Lax; Secure; Path=/",
+            REFRESH_TOKEN_COOKIE_NAME, first_refresh_token
+        ),
+        &Url::parse("http://127.0.0.1").expect("failed to parse URL"),
+    );
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Logging in again starts a fresh session
+    let response = app.login_user(&email).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let (token, _) = login_tokens(&response);
+
+    let verify_token_body = serde_json::json!({
+        "token": token,
+    });
+
+    let response = app.post_verify_token(&verify_token_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let response = app.post_logout_all().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Missing auth token".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_return_401_if_invalid_token() {
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}=invalid; HttpOnly; SameSite=Lax; Secure; Path=/",
+            JWT_COOKIE_NAME
+        ),
+        &Url::parse("http://127.0.0.1").expect("failed to parse URL"),
+    );
+
+    let response = app.post_logout_all().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Invalid auth token".to_owned()
+    );
+}
@@ -1,7 +1,28 @@
+mod account;
+mod admin;
+mod api_keys;
+mod change_email;
+mod change_password;
+mod client_credentials;
 mod helpers;
+mod introspect;
+mod jwks;
 mod login;
 mod logout;
+mod logout_all;
+mod oauth;
+mod oauth_clients;
+mod oidc;
+mod password_reset;
+mod rate_limit;
+mod recovery_codes;
+mod refresh;
+mod revoke;
 mod root;
+mod sessions;
 mod signup;
+mod totp;
 mod verify_2fa;
+mod verify_email;
 mod verify_token;
+mod webauthn;
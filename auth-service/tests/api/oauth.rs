@@ -0,0 +1,521 @@
+use auth_service::{
+    domain::{OAuthClient, OAuthError, SubjectType},
+    routes::{ConsentResponse, SessionsResponse, TokenResponse, VerifyTokenResponse},
+    utils::constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+    OAuthErrorResponse,
+};
+use reqwest::Url;
+
+use crate::helpers::TestApp;
+use test_helpers::api_test;
+
+const REDIRECT_URI: &str = "https://app.example.com/callback";
+
+// The example from RFC 7636, appendix B
+const CODE_VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+const CODE_CHALLENGE: &str = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+
+async fn register_client(app: &TestApp) -> OAuthClient {
+    let client = OAuthClient::new("Example App".to_owned(), vec![REDIRECT_URI.to_owned()])
+        .expect("Failed to create client");
+
+    app.oauth_client_store
+        .write()
+        .await
+        .add_client(client.clone())
+        .await
+        .expect("Failed to add client");
+
+    client
+}
+
+fn authorize_query(client_id: &str) -> Vec<(&str, &str)> {
+    vec![
+        ("response_type", "code"),
+        ("client_id", client_id),
+        ("redirect_uri", REDIRECT_URI),
+        ("scope", "profile read"),
+        ("state", "xyz"),
+        ("code_challenge", CODE_CHALLENGE),
+        ("code_challenge_method", "S256"),
+    ]
+}
+
+fn location(response: &reqwest::Response) -> Url {
+    assert_eq!(response.status().as_u16(), 303);
+
+    let location = response
+        .headers()
+        .get("location")
+        .expect("No location header found")
+        .to_str()
+        .unwrap();
+
+    Url::parse("http://localhost")
+        .unwrap()
+        .join(location)
+        .expect("Invalid location")
+}
+
+fn query_param(url: &Url, name: &str) -> Option<String> {
+    url.query_pairs()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+}
+
+// Approves the client on the consent page and returns the code it's sent back with
+async fn approve(app: &TestApp, client_id: &str) -> String {
+    let response = app
+        .post_oauth_consent(
+            &authorize_query(client_id),
+            &serde_json::json!({ "approve": true }),
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let redirect_to = response
+        .json::<ConsentResponse>()
+        .await
+        .expect("Could not deserialize response body to ConsentResponse")
+        .redirect_to;
+    let redirect_to = Url::parse(&redirect_to).expect("Invalid redirect");
+
+    assert!(redirect_to.as_str().starts_with(REDIRECT_URI));
+    assert_eq!(query_param(&redirect_to, "state").as_deref(), Some("xyz"));
+
+    query_param(&redirect_to, "code").expect("No code found")
+}
+
+async fn exchange_code(app: &TestApp, client_id: &str, code: &str) -> reqwest::Response {
+    app.post_oauth_token(&[
+        ("grant_type", "authorization_code"),
+        ("client_id", client_id),
+        ("code", code),
+        ("redirect_uri", REDIRECT_URI),
+        ("code_verifier", CODE_VERIFIER),
+    ])
+    .await
+}
+
+async fn refresh(app: &TestApp, client_id: &str, refresh_token: &str) -> reqwest::Response {
+    app.post_oauth_token(&[
+        ("grant_type", "refresh_token"),
+        ("client_id", client_id),
+        ("refresh_token", refresh_token),
+    ])
+    .await
+}
+
+async fn tokens(response: reqwest::Response) -> TokenResponse {
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response
+            .headers()
+            .get("cache-control")
+            .and_then(|value| value.to_str().ok()),
+        Some("no-store")
+    );
+
+    response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse")
+}
+
+async fn error_code(response: reqwest::Response) -> String {
+    response
+        .json::<OAuthErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to OAuthErrorResponse")
+        .error
+}
+
+#[api_test]
+async fn should_send_logged_out_users_to_login_page() {
+    let client = register_client(&app).await;
+    let client_id = client.id.to_string();
+
+    let response = app.get_oauth_authorize(&authorize_query(&client_id)).await;
+
+    let location = location(&response);
+    assert_eq!(location.path(), "/");
+
+    // The login page comes back with the whole request
+    let authorize = query_param(&location, "authorize").expect("No authorize param found");
+    assert!(authorize.contains(&format!("client_id={}", client_id)));
+    assert_eq!(query_param(&location, "client"), None);
+}
+
+#[api_test]
+async fn should_ask_for_consent_then_issue_tokens() {
+    let client = register_client(&app).await;
+    let client_id = client.id.to_string();
+    app.signup_and_login(&[]).await;
+
+    let response = app.get_oauth_authorize(&authorize_query(&client_id)).await;
+
+    let location = location(&response);
+    assert_eq!(location.path(), "/");
+    assert_eq!(
+        query_param(&location, "client").as_deref(),
+        Some("Example App")
+    );
+
+    let code = approve(&app, &client_id).await;
+
+    let tokens = tokens(exchange_code(&app, &client_id, &code).await).await;
+
+    assert_eq!(tokens.token_type, "Bearer");
+    assert_eq!(tokens.scope, "profile read");
+    assert!(!tokens.refresh_token.is_empty());
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": tokens.access_token }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response
+            .json::<VerifyTokenResponse>()
+            .await
+            .expect("Could not deserialize response body to VerifyTokenResponse"),
+        VerifyTokenResponse {
+            subject_type: SubjectType::User,
+            roles: Vec::new(),
+            permissions: Vec::new(),
+            client_id: Some(client_id.clone()),
+            scope: Some("profile read".to_owned()),
+        }
+    );
+
+    // The client's login shows up among the user's sessions
+    let response = app.get_sessions().await;
+    let sessions = response
+        .json::<SessionsResponse>()
+        .await
+        .expect("Could not deserialize response body to SessionsResponse")
+        .sessions;
+    assert!(sessions
+        .iter()
+        .any(|session| session.oauth_client_id.as_deref() == Some(client_id.as_str())));
+}
+
+#[api_test]
+async fn should_skip_consent_once_given() {
+    let client = register_client(&app).await;
+    let client_id = client.id.to_string();
+    app.signup_and_login(&[]).await;
+
+    approve(&app, &client_id).await;
+
+    let response = app.get_oauth_authorize(&authorize_query(&client_id)).await;
+
+    let location = location(&response);
+    assert!(location.as_str().starts_with(REDIRECT_URI));
+    assert!(query_param(&location, "code").is_some());
+    assert_eq!(query_param(&location, "state").as_deref(), Some("xyz"));
+}
+
+#[api_test]
+async fn should_ask_for_consent_without_scope_if_never_given() {
+    let client = register_client(&app).await;
+    let client_id = client.id.to_string();
+    app.signup_and_login(&[]).await;
+
+    // No scope is within any scope, but the user still hasn't approved this client
+    let query: Vec<_> = authorize_query(&client_id)
+        .into_iter()
+        .filter(|(name, _)| *name != "scope")
+        .collect();
+
+    let response = app.get_oauth_authorize(&query).await;
+
+    let location = location(&response);
+    assert_eq!(location.path(), "/");
+    assert_eq!(
+        query_param(&location, "client").as_deref(),
+        Some("Example App")
+    );
+    assert_eq!(query_param(&location, "code"), None);
+}
+
+#[api_test]
+async fn should_send_access_denied_if_user_denies() {
+    let client = register_client(&app).await;
+    let client_id = client.id.to_string();
+    app.signup_and_login(&[]).await;
+
+    let response = app
+        .post_oauth_consent(
+            &authorize_query(&client_id),
+            &serde_json::json!({ "approve": false }),
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let redirect_to = response
+        .json::<ConsentResponse>()
+        .await
+        .expect("Could not deserialize response body to ConsentResponse")
+        .redirect_to;
+    let redirect_to = Url::parse(&redirect_to).expect("Invalid redirect");
+
+    assert_eq!(
+        query_param(&redirect_to, "error").as_deref(),
+        Some(OAuthError::AccessDenied.code())
+    );
+    assert_eq!(query_param(&redirect_to, "code"), None);
+}
+
+#[api_test]
+async fn should_return_400_if_client_or_redirect_uri_is_unknown() {
+    let client = register_client(&app).await;
+    let client_id = client.id.to_string();
+    let other_client_id = OAuthClient::new("Other".to_owned(), vec![REDIRECT_URI.to_owned()])
+        .unwrap()
+        .id
+        .to_string();
+
+    // Errors can't be sent to a redirect URI that isn't known to be the client's
+    let mut unknown_client = authorize_query(&other_client_id);
+    let mut unknown_redirect_uri = authorize_query(&client_id);
+    unknown_redirect_uri[2] = ("redirect_uri", "https://evil.example.com/callback");
+    unknown_client.retain(|(name, _)| *name != "state");
+
+    for query in [unknown_client, unknown_redirect_uri] {
+        let response = app.get_oauth_authorize(&query).await;
+
+        assert_eq!(response.status().as_u16(), 400);
+        assert_eq!(error_code(response).await, "invalid_request");
+    }
+}
+
+#[api_test]
+async fn should_redirect_with_error_if_request_is_invalid() {
+    let client = register_client(&app).await;
+    let client_id = client.id.to_string();
+
+    let test_cases = [
+        ("response_type", "token", "unsupported_response_type"),
+        ("code_challenge_method", "plain", "invalid_request"),
+        ("code_challenge", "too-short", "invalid_request"),
+        ("scope", "quote\"d", "invalid_scope"),
+    ];
+
+    for (name, value, error) in test_cases {
+        let mut query = authorize_query(&client_id);
+        for param in query.iter_mut() {
+            if param.0 == name {
+                param.1 = value;
+            }
+        }
+
+        let response = app.get_oauth_authorize(&query).await;
+
+        let location = location(&response);
+        assert!(location.as_str().starts_with(REDIRECT_URI));
+        assert_eq!(query_param(&location, "error").as_deref(), Some(error));
+        assert_eq!(query_param(&location, "state").as_deref(), Some("xyz"));
+    }
+}
+
+#[api_test]
+async fn should_reject_code_with_wrong_verifier_or_reused() {
+    let client = register_client(&app).await;
+    let client_id = client.id.to_string();
+    app.signup_and_login(&[]).await;
+
+    let code = approve(&app, &client_id).await;
+
+    let response = app
+        .post_oauth_token(&[
+            ("grant_type", "authorization_code"),
+            ("client_id", &client_id),
+            ("code", &code),
+            ("redirect_uri", REDIRECT_URI),
+            ("code_verifier", &"a".repeat(43)),
+        ])
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(error_code(response).await, "invalid_grant");
+
+    // A failed exchange uses up the code
+    let response = exchange_code(&app, &client_id, &code).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(error_code(response).await, "invalid_grant");
+
+    let code = approve(&app, &client_id).await;
+    tokens(exchange_code(&app, &client_id, &code).await).await;
+
+    let response = exchange_code(&app, &client_id, &code).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(error_code(response).await, "invalid_grant");
+}
+
+#[api_test]
+async fn should_rotate_refresh_tokens_and_detect_reuse() {
+    let client = register_client(&app).await;
+    let client_id = client.id.to_string();
+    app.signup_and_login(&[]).await;
+
+    let code = approve(&app, &client_id).await;
+    let first = tokens(exchange_code(&app, &client_id, &code).await).await;
+
+    // Another client can't redeem the token, nor use it up for the client it belongs to
+    let other_client = register_client(&app).await;
+    let response = refresh(&app, &other_client.id.to_string(), &first.refresh_token).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(error_code(response).await, "invalid_grant");
+
+    let second = tokens(refresh(&app, &client_id, &first.refresh_token).await).await;
+
+    assert_ne!(second.refresh_token, first.refresh_token);
+    assert_eq!(second.scope, "profile read");
+
+    // Presenting the rotated token again revokes the session
+    let response = refresh(&app, &client_id, &first.refresh_token).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(error_code(response).await, "invalid_grant");
+
+    let response = refresh(&app, &client_id, &second.refresh_token).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": second.access_token }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_log_session_out_when_refresh_token_is_revoked() {
+    let client = register_client(&app).await;
+    let client_id = client.id.to_string();
+    app.signup_and_login(&[]).await;
+
+    let code = approve(&app, &client_id).await;
+    let tokens = tokens(exchange_code(&app, &client_id, &code).await).await;
+
+    // Only the client the token was issued to can revoke it
+    let other_client = register_client(&app).await;
+    let response = app
+        .post_oauth_revoke(&[
+            ("token", &tokens.refresh_token),
+            ("client_id", &other_client.id.to_string()),
+        ])
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(error_code(response).await, "unauthorized_client");
+
+    let response = app
+        .post_oauth_revoke(&[
+            ("token", &tokens.refresh_token),
+            ("token_type_hint", "refresh_token"),
+            ("client_id", &client_id),
+        ])
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = refresh(&app, &client_id, &tokens.refresh_token).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(error_code(response).await, "invalid_grant");
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": tokens.access_token }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_not_accept_oauth_tokens_as_cookies() {
+    let client = register_client(&app).await;
+    let client_id = client.id.to_string();
+    app.signup_and_login(&[]).await;
+
+    let code = approve(&app, &client_id).await;
+    let tokens = tokens(exchange_code(&app, &client_id, &code).await).await;
+
+    let url = Url::parse("http://127.0.0.1").expect("failed to parse URL");
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Lax; Secure; Path=/",
+            JWT_COOKIE_NAME, tokens.access_token
+        ),
+        &url,
+    );
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Lax; Secure; Path=/",
+            REFRESH_TOKEN_COOKIE_NAME, tokens.refresh_token
+        ),
+        &url,
+    );
+
+    let response = app.get_sessions().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Sending it to the wrong endpoint doesn't use it up
+    let response = refresh(&app, &client_id, &tokens.refresh_token).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_not_accept_cookie_refresh_tokens() {
+    let client = register_client(&app).await;
+    let client_id = client.id.to_string();
+    let user = app.signup_and_login(&[]).await;
+
+    let response = refresh(&app, &client_id, &user.refresh_token).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(error_code(response).await, "invalid_grant");
+
+    // The session it belongs to isn't logged out
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": user.auth_token }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_error_for_unsupported_grant_type() {
+    let client = register_client(&app).await;
+
+    let response = app
+        .post_oauth_token(&[
+            ("grant_type", "password"),
+            ("client_id", &client.id.to_string()),
+        ])
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(error_code(response).await, "unsupported_grant_type");
+
+    let response = app.post_oauth_token(&[]).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(error_code(response).await, "invalid_request");
+}
@@ -0,0 +1,266 @@
+use auth_service::{
+    domain::Role,
+    routes::{
+        ClientTokenResponse, OAuthClientResponse, OAuthClientSecretResponse, OAuthClientsResponse,
+    },
+    ErrorResponse,
+};
+
+use crate::helpers::TestApp;
+use test_helpers::api_test;
+
+async fn create_client(app: &TestApp, name: &str) -> OAuthClientResponse {
+    let response = app
+        .post_admin_oauth_client(&serde_json::json!({
+            "name": name,
+            "redirectUris": ["https://app.example.com/callback", "http://127.0.0.1:8765/callback"],
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    response
+        .json::<OAuthClientResponse>()
+        .await
+        .expect("Could not deserialize response body to OAuthClientResponse")
+}
+
+#[api_test]
+async fn should_create_list_and_get_clients() {
+    app.signup_and_login(&[Role::admin()]).await;
+
+    let first = create_client(&app, "First").await;
+    let second = create_client(&app, "Second").await;
+
+    assert_eq!(first.name, "First");
+    assert_eq!(first.redirect_uris.len(), 2);
+
+    let response = app.get_admin_oauth_clients().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let clients = response
+        .json::<OAuthClientsResponse>()
+        .await
+        .expect("Could not deserialize response body to OAuthClientsResponse")
+        .clients;
+
+    assert_eq!(clients, vec![first, second]);
+
+    let response = app.get_admin_oauth_client(&clients[1].id).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response
+            .json::<OAuthClientResponse>()
+            .await
+            .expect("Could not deserialize response body to OAuthClientResponse"),
+        clients[1]
+    );
+}
+
+async fn create_machine_client(app: &TestApp) -> OAuthClientResponse {
+    let response = app
+        .post_admin_oauth_client(&serde_json::json!({
+            "name": "Report Jobs",
+            "type": "machine",
+            "scope": "reports:read",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    response
+        .json::<OAuthClientResponse>()
+        .await
+        .expect("Could not deserialize response body to OAuthClientResponse")
+}
+
+async fn client_credentials(app: &TestApp, client_id: &str, client_secret: &str) -> u16 {
+    app.post_oauth_token_with_basic_auth(
+        client_id,
+        client_secret,
+        &[("grant_type", "client_credentials")],
+    )
+    .await
+    .status()
+    .as_u16()
+}
+
+#[api_test]
+async fn should_create_machine_client_and_show_its_secret_once() {
+    app.signup_and_login(&[Role::admin()]).await;
+
+    let client = create_machine_client(&app).await;
+
+    assert_eq!(client.kind, "machine");
+    assert_eq!(client.scope.as_deref(), Some("reports:read"));
+    assert!(client.redirect_uris.is_empty());
+    assert!(client.secret_created_at.is_some());
+
+    let client_secret = client.client_secret.clone().expect("No client secret");
+    assert_eq!(
+        client_credentials(&app, &client.id, &client_secret).await,
+        200
+    );
+
+    let response = app.get_admin_oauth_client(&client.id).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let fetched = response
+        .json::<OAuthClientResponse>()
+        .await
+        .expect("Could not deserialize response body to OAuthClientResponse");
+
+    assert_eq!(fetched.client_secret, None);
+    assert_eq!(fetched.secret_created_at, client.secret_created_at);
+}
+
+#[api_test]
+async fn should_rotate_client_secret() {
+    app.signup_and_login(&[Role::admin()]).await;
+
+    let client = create_machine_client(&app).await;
+    let old_secret = client.client_secret.clone().expect("No client secret");
+
+    let token = app
+        .post_oauth_token_with_basic_auth(
+            &client.id,
+            &old_secret,
+            &[("grant_type", "client_credentials")],
+        )
+        .await
+        .json::<ClientTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to ClientTokenResponse")
+        .access_token;
+
+    let response = app.post_admin_oauth_client_secret(&client.id).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let new_secret = response
+        .json::<OAuthClientSecretResponse>()
+        .await
+        .expect("Could not deserialize response body to OAuthClientSecretResponse")
+        .client_secret;
+
+    assert_eq!(client_credentials(&app, &client.id, &old_secret).await, 401);
+    assert_eq!(client_credentials(&app, &client.id, &new_secret).await, 200);
+
+    // Tokens issued with the old secret go with it
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_revoke_client_secret() {
+    app.signup_and_login(&[Role::admin()]).await;
+
+    let client = create_machine_client(&app).await;
+    let client_secret = client.client_secret.clone().expect("No client secret");
+
+    let response = app.delete_admin_oauth_client_secret(&client.id).await;
+
+    assert_eq!(response.status().as_u16(), 204);
+    assert_eq!(
+        client_credentials(&app, &client.id, &client_secret).await,
+        401
+    );
+
+    let fetched = app
+        .get_admin_oauth_client(&client.id)
+        .await
+        .json::<OAuthClientResponse>()
+        .await
+        .expect("Could not deserialize response body to OAuthClientResponse");
+
+    assert_eq!(fetched.secret_created_at, None);
+}
+
+#[api_test]
+async fn should_return_400_if_client_has_no_secret_to_manage() {
+    app.signup_and_login(&[Role::admin()]).await;
+
+    let client = create_client(&app, "App").await;
+
+    let response = app.post_admin_oauth_client_secret(&client.id).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app.delete_admin_oauth_client_secret(&client.id).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_return_404_if_client_not_found() {
+    app.signup_and_login(&[Role::admin()]).await;
+
+    for id in ["not-a-uuid", "5f0c1f0e-3a4b-4c8d-9e2f-1a2b3c4d5e6f"] {
+        let response = app.get_admin_oauth_client(id).await;
+
+        assert_eq!(response.status().as_u16(), 404);
+
+        let response = app.post_admin_oauth_client_secret(id).await;
+
+        assert_eq!(response.status().as_u16(), 404);
+    }
+}
+
+#[api_test]
+async fn should_return_400_if_client_is_invalid() {
+    app.signup_and_login(&[Role::admin()]).await;
+
+    let test_cases = [
+        serde_json::json!({ "name": "", "redirectUris": ["https://app.example.com/callback"] }),
+        serde_json::json!({ "name": "App", "redirectUris": [] }),
+        serde_json::json!({ "name": "App", "redirectUris": ["http://app.example.com/callback"] }),
+        serde_json::json!({ "name": "App", "redirectUris": ["https://app.example.com/#fragment"] }),
+        serde_json::json!({ "name": "App", "type": "robot" }),
+        serde_json::json!({ "name": "", "type": "machine" }),
+        serde_json::json!({ "name": "Jobs", "type": "machine", "redirectUris": ["https://app.example.com/callback"] }),
+        serde_json::json!({ "name": "Jobs", "type": "machine", "scope": "quote\"d" }),
+    ];
+
+    for test_case in test_cases {
+        let response = app.post_admin_oauth_client(&test_case).await;
+
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "Failed for input: {:?}",
+            test_case
+        );
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            "Invalid credentials"
+        );
+    }
+}
+
+#[api_test]
+async fn should_return_403_if_not_admin() {
+    app.signup_and_login(&[]).await;
+
+    let response = app
+        .post_admin_oauth_client(&serde_json::json!({
+            "name": "App",
+            "redirectUris": ["https://app.example.com/callback"],
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = app.get_admin_oauth_clients().await;
+
+    assert_eq!(response.status().as_u16(), 403);
+}
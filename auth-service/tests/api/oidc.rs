@@ -0,0 +1,260 @@
+use auth_service::{
+    domain::{Email, OAuthClient, UserInfo},
+    routes::{ConsentResponse, OpenIdConfiguration, TokenResponse, TwoFactorAuthResponse},
+    utils::constants::{JWT_ISSUER, JWT_KEYRING},
+};
+use jsonwebtoken::Validation;
+use reqwest::Url;
+use secrecy::{ExposeSecret, Secret};
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+use test_helpers::api_test;
+
+const REDIRECT_URI: &str = "https://app.example.com/callback";
+
+// The example from RFC 7636, appendix B
+const CODE_VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+const CODE_CHALLENGE: &str = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+
+const NONCE: &str = "n-0S6_WzA2Mj";
+
+async fn register_client(app: &TestApp) -> String {
+    let client = OAuthClient::new("Example App".to_owned(), vec![REDIRECT_URI.to_owned()])
+        .expect("Failed to create client");
+
+    app.oauth_client_store
+        .write()
+        .await
+        .add_client(client.clone())
+        .await
+        .expect("Failed to add client");
+
+    client.id.to_string()
+}
+
+// Runs the authorization code flow for `scope` and returns the tokens the client ends up with
+async fn authorize(app: &TestApp, client_id: &str, scope: &str) -> TokenResponse {
+    let query = [
+        ("response_type", "code"),
+        ("client_id", client_id),
+        ("redirect_uri", REDIRECT_URI),
+        ("scope", scope),
+        ("nonce", NONCE),
+        ("code_challenge", CODE_CHALLENGE),
+        ("code_challenge_method", "S256"),
+    ];
+
+    let response = app
+        .post_oauth_consent(&query, &serde_json::json!({ "approve": true }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let redirect_to = response
+        .json::<ConsentResponse>()
+        .await
+        .expect("Could not deserialize response body to ConsentResponse")
+        .redirect_to;
+    let code = Url::parse(&redirect_to)
+        .expect("Invalid redirect")
+        .query_pairs()
+        .find(|(name, _)| name == "code")
+        .map(|(_, value)| value.into_owned())
+        .expect("No code found");
+
+    let response = app
+        .post_oauth_token(&[
+            ("grant_type", "authorization_code"),
+            ("client_id", client_id),
+            ("code", &code),
+            ("redirect_uri", REDIRECT_URI),
+            ("code_verifier", CODE_VERIFIER),
+        ])
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse")
+}
+
+// Verifies the ID token the way the client would, and returns its claims
+fn id_token_claims(id_token: &str, client_id: &str) -> serde_json::Value {
+    let mut validation = Validation::default();
+    validation.set_issuer(&[JWT_ISSUER.as_str()]);
+    validation.set_audience(&[client_id]);
+
+    JWT_KEYRING
+        .read()
+        .unwrap()
+        .decode::<serde_json::Value>(id_token, validation)
+        .expect("Invalid ID token")
+}
+
+#[api_test]
+async fn should_publish_openid_configuration() {
+    let response = app.get_openid_configuration().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let configuration = response
+        .json::<OpenIdConfiguration>()
+        .await
+        .expect("Could not deserialize response body to OpenIdConfiguration");
+
+    assert_eq!(configuration.issuer, *JWT_ISSUER);
+    assert!(configuration
+        .authorization_endpoint
+        .ends_with("/oauth/authorize"));
+    assert!(configuration.token_endpoint.ends_with("/oauth/token"));
+    assert!(configuration.userinfo_endpoint.ends_with("/userinfo"));
+    assert!(configuration.jwks_uri.ends_with("/.well-known/jwks.json"));
+    assert_eq!(
+        configuration.scopes_supported,
+        ["openid", "email", "profile"]
+    );
+    assert_eq!(configuration.code_challenge_methods_supported, ["S256"]);
+}
+
+#[api_test]
+async fn should_issue_id_token_for_openid_scope() {
+    let client_id = register_client(&app).await;
+    let email = app.signup_and_login(&[]).await.email;
+
+    let tokens = authorize(&app, &client_id, "openid email").await;
+
+    let id_token = tokens.id_token.expect("No ID token found");
+    let claims = id_token_claims(&id_token, &client_id);
+
+    assert_eq!(claims["nonce"], NONCE);
+    assert_eq!(claims["amr"], serde_json::json!(["pwd"]));
+    assert!(claims["auth_time"].as_i64().unwrap() <= claims["iat"].as_i64().unwrap());
+    assert_eq!(claims["email"], email.as_str());
+
+    // Refreshed ID tokens are about the same login, but not for the same request
+    let response = app
+        .post_oauth_token(&[
+            ("grant_type", "refresh_token"),
+            ("client_id", &client_id),
+            ("refresh_token", &tokens.refresh_token),
+        ])
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let refreshed = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse")
+        .id_token
+        .expect("No ID token found");
+    let refreshed_claims = id_token_claims(&refreshed, &client_id);
+
+    assert_eq!(refreshed_claims["sub"], claims["sub"]);
+    assert_eq!(refreshed_claims["auth_time"], claims["auth_time"]);
+    assert!(refreshed_claims.get("nonce").is_none());
+}
+
+#[api_test]
+async fn should_not_issue_id_token_without_openid_scope() {
+    let client_id = register_client(&app).await;
+    app.signup_and_login(&[]).await;
+
+    let tokens = authorize(&app, &client_id, "email").await;
+
+    assert_eq!(tokens.id_token, None);
+
+    // Nor may the client ask who the user is
+    let response = app.get_userinfo(&tokens.access_token).await;
+
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[api_test]
+async fn should_report_2fa_in_amr() {
+    let client_id = register_client(&app).await;
+    let email = get_random_email();
+    app.signup_user(&email, true).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.login_user(&email).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+    let (_, two_fa_code) = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&Email::parse(Secret::new(email.clone())).unwrap())
+        .await
+        .unwrap();
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": two_fa_code.as_ref().expose_secret(),
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let tokens = authorize(&app, &client_id, "openid").await;
+    let claims = id_token_claims(&tokens.id_token.expect("No ID token found"), &client_id);
+
+    assert_eq!(claims["amr"], serde_json::json!(["pwd", "otp", "mfa"]));
+}
+
+#[api_test]
+async fn should_return_user_info_the_scope_grants() {
+    let client_id = register_client(&app).await;
+    let email = app.signup_and_login(&[]).await.email;
+
+    let tokens = authorize(&app, &client_id, "openid email").await;
+
+    let response = app.get_userinfo(&tokens.access_token).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let user_info = response
+        .json::<UserInfo>()
+        .await
+        .expect("Could not deserialize response body to UserInfo");
+
+    let claims = id_token_claims(&tokens.id_token.expect("No ID token found"), &client_id);
+    assert_eq!(user_info.sub, claims["sub"]);
+    assert_eq!(user_info.email, Some(email));
+    assert!(user_info.email_verified.is_some());
+    assert_eq!(user_info.preferred_username, None);
+}
+
+#[api_test]
+async fn should_reject_userinfo_without_client_token() {
+    // The user's own auth token isn't issued to any client
+    let auth_token = app.signup_and_login(&[]).await.auth_token;
+
+    let response = app.get_userinfo(&auth_token).await;
+
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = app.get_userinfo("invalid").await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
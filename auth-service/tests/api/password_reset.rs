@@ -0,0 +1,282 @@
+use auth_service::{routes::ApiKeyResponse, ErrorResponse};
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp, TestUser};
+use test_helpers::api_test;
+
+async fn request_reset_token(app: &TestApp, email: &str) -> String {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let emails_sent = app
+        .email_server
+        .received_requests()
+        .await
+        .expect("Request recording is disabled")
+        .len();
+
+    let response = app
+        .post_password_reset_request(&serde_json::json!({ "email": email }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 202);
+
+    app.password_reset_token(emails_sent + 1).await
+}
+
+#[api_test]
+async fn should_return_202_and_send_email_if_user_exists() {
+    let random_email = get_random_email();
+
+    app.signup_user(&random_email, false).await;
+
+    let token = request_reset_token(&app, &random_email).await;
+
+    assert_eq!(token.len(), 64);
+}
+
+#[api_test]
+async fn should_return_202_without_sending_email_if_user_unknown() {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_password_reset_request(&serde_json::json!({ "email": get_random_email() }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 202);
+}
+
+#[api_test]
+async fn should_return_400_if_invalid_email() {
+    let response = app
+        .post_password_reset_request(&serde_json::json!({ "email": "invalid" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Invalid credentials".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_return_200_and_change_password_if_valid_token() {
+    let random_email = get_random_email();
+
+    app.signup_user(&random_email, false).await;
+
+    let token = request_reset_token(&app, &random_email).await;
+
+    let response = app
+        .post_password_reset_confirm(&serde_json::json!({
+            "token": token,
+            "password": "newpassword123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "newpassword123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_invalidate_existing_sessions_after_reset() {
+    let TestUser {
+        email: random_email,
+        auth_token: old_token,
+        ..
+    } = app.signup_and_login(&[]).await;
+
+    let token = request_reset_token(&app, &random_email).await;
+
+    let response = app
+        .post_password_reset_confirm(&serde_json::json!({
+            "token": token,
+            "password": "newpassword123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": old_token }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    // The refresh token issued at login is revoked as well
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_revoke_api_keys_after_reset() {
+    let random_email = app.signup_and_login(&[]).await.email;
+
+    let response = app
+        .post_api_key(&serde_json::json!({ "name": "Deploy script" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let api_key = response
+        .json::<ApiKeyResponse>()
+        .await
+        .expect("Could not deserialize response body to ApiKeyResponse")
+        .api_key
+        .expect("No API key found");
+
+    let token = request_reset_token(&app, &random_email).await;
+
+    let response = app
+        .post_password_reset_confirm(&serde_json::json!({
+            "token": token,
+            "password": "newpassword123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_verify_api_key(&api_key).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_401_if_token_reused() {
+    let random_email = get_random_email();
+
+    app.signup_user(&random_email, false).await;
+
+    let token = request_reset_token(&app, &random_email).await;
+
+    let confirm_body = serde_json::json!({
+        "token": token,
+        "password": "newpassword123",
+    });
+
+    let response = app.post_password_reset_confirm(&confirm_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_password_reset_confirm(&confirm_body).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Invalid auth token".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_return_401_if_invalid_token() {
+    let test_cases = ["invalid".to_owned(), "a".repeat(64)];
+
+    for test_case in test_cases.iter() {
+        let response = app
+            .post_password_reset_confirm(&serde_json::json!({
+                "token": test_case,
+                "password": "newpassword123",
+            }))
+            .await;
+
+        assert_eq!(
+            response.status().as_u16(),
+            401,
+            "Failed for input: {:?}",
+            test_case
+        );
+    }
+}
+
+#[api_test]
+async fn should_return_400_and_keep_token_if_invalid_password() {
+    let random_email = get_random_email();
+
+    app.signup_user(&random_email, false).await;
+
+    let token = request_reset_token(&app, &random_email).await;
+
+    let response = app
+        .post_password_reset_confirm(&serde_json::json!({
+            "token": token,
+            "password": "short",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    // The rejected attempt did not use up the token
+    let response = app
+        .post_password_reset_confirm(&serde_json::json!({
+            "token": token,
+            "password": "newpassword123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_422_if_malformed_input() {
+    let test_cases = [
+        serde_json::json!({ "token": "a".repeat(64) }),
+        serde_json::json!({ "password": "newpassword123" }),
+        serde_json::json!({}),
+    ];
+
+    for test_case in test_cases.iter() {
+        let response = app.post_password_reset_confirm(test_case).await;
+
+        assert_eq!(
+            response.status().as_u16(),
+            422,
+            "Failed for input: {:?}",
+            test_case
+        );
+    }
+
+    let response = app
+        .post_password_reset_request(&serde_json::json!({}))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 422);
+}
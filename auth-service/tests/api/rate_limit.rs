@@ -0,0 +1,104 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::ErrorResponse;
+use test_helpers::api_test;
+
+#[tokio::test]
+async fn should_return_429_once_ip_budget_is_spent() {
+    let mut app = TestApp::with_rate_limits("/login=ip:2/60").await;
+
+    let login_body = serde_json::json!({
+        "email": get_random_email(),
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(response.headers()["ratelimit-limit"], "2");
+    assert_eq!(response.headers()["ratelimit-remaining"], "1");
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(response.headers()["ratelimit-remaining"], "0");
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 429);
+    assert_eq!(response.headers()["ratelimit-remaining"], "0");
+    assert_eq!(response.headers()["retry-after"], "30");
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Too many requests".to_owned()
+    );
+
+    // Routes without rules of their own aren't limited
+    let response = app.post_logout().await;
+    assert!(response.headers().get("ratelimit-limit").is_none());
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_share_budget_across_ids_of_a_route() {
+    let mut app = TestApp::with_rate_limits("/sessions/:id=ip:1/60").await;
+
+    let response = app.delete_session("first").await;
+    assert_ne!(response.status().as_u16(), 429);
+    assert_eq!(response.headers()["ratelimit-remaining"], "0");
+
+    let response = app.delete_session("second").await;
+    assert_eq!(response.status().as_u16(), 429);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_limit_each_email_separately() {
+    let mut app = TestApp::with_rate_limits("*=email:1/60").await;
+
+    let first_email = get_random_email();
+    let second_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": first_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    // Differs only in case, so it draws from the same budget
+    let signup_body = serde_json::json!({
+        "email": first_email.to_uppercase(),
+        "password": "password123",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 429);
+
+    let signup_body = serde_json::json!({
+        "email": second_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.clean_up().await;
+}
+
+#[api_test]
+async fn should_not_limit_by_default_in_tests() {
+    let login_body = serde_json::json!({
+        "email": get_random_email(),
+        "password": "password123",
+    });
+
+    for _ in 0..5 {
+        let response = app.post_login(&login_body).await;
+        assert!(response.headers().get("ratelimit-limit").is_none());
+    }
+}
@@ -0,0 +1,188 @@
+use auth_service::{
+    domain::Email,
+    routes::{RecoveryCodeLoginResponse, RecoveryCodesResponse, TwoFactorAuthResponse},
+    ErrorResponse,
+};
+use secrecy::{ExposeSecret, Secret};
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+use test_helpers::api_test;
+
+async fn signup_with_2fa(app: &TestApp) -> String {
+    let random_email = get_random_email();
+
+    app.signup_user(&random_email, true).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    random_email
+}
+
+// Gets past the password step, returning the login attempt ID needed by verify-2fa
+async fn login(app: &TestApp, email: &str) -> String {
+    let response = app.login_user(email).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id
+}
+
+async fn generate_codes(app: &TestApp) -> Vec<String> {
+    let response = app.post_recovery_codes().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<RecoveryCodesResponse>()
+        .await
+        .expect("Could not deserialize response body to RecoveryCodesResponse")
+        .recovery_codes
+}
+
+// Finishes a login with the emailed code so the user has a JWT cookie
+async fn complete_login(app: &TestApp, email: &str) {
+    let login_attempt_id = login(app, email).await;
+    let code_tuple = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&Email::parse(Secret::new(email.to_owned())).unwrap())
+        .await
+        .unwrap();
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": code_tuple.1.as_ref().expose_secret(),
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_10_unique_codes() {
+    let random_email = signup_with_2fa(&app).await;
+    complete_login(&app, &random_email).await;
+
+    let mut codes = generate_codes(&app).await;
+
+    assert_eq!(codes.len(), 10);
+    codes.sort();
+    codes.dedup();
+    assert_eq!(codes.len(), 10);
+}
+
+#[api_test]
+async fn should_log_in_with_recovery_code_and_burn_it() {
+    let random_email = signup_with_2fa(&app).await;
+    complete_login(&app, &random_email).await;
+
+    let codes = generate_codes(&app).await;
+
+    let login_attempt_id = login(&app, &random_email).await;
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": codes[0],
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response
+            .json::<RecoveryCodeLoginResponse>()
+            .await
+            .expect("Could not deserialize response body to RecoveryCodeLoginResponse")
+            .recovery_codes_remaining,
+        9
+    );
+
+    let login_attempt_id = login(&app, &random_email).await;
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": codes[0],
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Incorrect credentials".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_accept_codes_without_dash_and_in_uppercase() {
+    let random_email = signup_with_2fa(&app).await;
+    complete_login(&app, &random_email).await;
+
+    let codes = generate_codes(&app).await;
+
+    let login_attempt_id = login(&app, &random_email).await;
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": codes[0].replace('-', "").to_uppercase(),
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_invalidate_old_codes_when_regenerated() {
+    let random_email = signup_with_2fa(&app).await;
+    complete_login(&app, &random_email).await;
+
+    let old_codes = generate_codes(&app).await;
+    generate_codes(&app).await;
+
+    let login_attempt_id = login(&app, &random_email).await;
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": old_codes[0],
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let response = app.post_recovery_codes().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Missing auth token".to_owned()
+    );
+}
@@ -0,0 +1,145 @@
+use auth_service::{
+    domain::{ClientSecret, OAuthClient, OAuthClientSecret, OAuthError, OAuthScope},
+    routes::ClientTokenResponse,
+    OAuthErrorResponse,
+};
+use secrecy::ExposeSecret;
+
+use crate::helpers::TestApp;
+use test_helpers::api_test;
+
+// Registers a machine client allowed to read reports, returning its ID and secret
+async fn register_machine_client(app: &TestApp) -> (String, String) {
+    let mut client = OAuthClient::new_machine(
+        "Report Jobs".to_owned(),
+        OAuthScope::parse("reports:read").unwrap(),
+    )
+    .expect("Failed to create client");
+    let client_secret = ClientSecret::default();
+    client.secret = Some(OAuthClientSecret::new(&client_secret));
+
+    app.oauth_client_store
+        .write()
+        .await
+        .add_client(client.clone())
+        .await
+        .expect("Failed to add client");
+
+    (
+        client.id.to_string(),
+        client_secret.as_ref().expose_secret().to_owned(),
+    )
+}
+
+async fn client_token(app: &TestApp, client_id: &str, client_secret: &str) -> String {
+    app.post_oauth_token_with_basic_auth(
+        client_id,
+        client_secret,
+        &[("grant_type", "client_credentials")],
+    )
+    .await
+    .json::<ClientTokenResponse>()
+    .await
+    .expect("Could not deserialize response body to ClientTokenResponse")
+    .access_token
+}
+
+async fn assert_oauth_error(response: reqwest::Response, status: u16, error: OAuthError) {
+    assert_eq!(response.status().as_u16(), status);
+    assert_eq!(
+        response
+            .json::<OAuthErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to OAuthErrorResponse")
+            .error,
+        error.code()
+    );
+}
+
+#[api_test]
+async fn should_revoke_access_token() {
+    let (client_id, client_secret) = register_machine_client(&app).await;
+    let token = client_token(&app, &client_id, &client_secret).await;
+
+    let response = app
+        .post_oauth_revoke(&[
+            ("token", &token),
+            ("token_type_hint", "access_token"),
+            ("client_id", &client_id),
+            ("client_secret", &client_secret),
+        ])
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Revoking it again is no error, nor is revoking something that was never a token
+    for token in [token.as_str(), "invalid"] {
+        let response = app
+            .post_oauth_revoke_with_basic_auth(&client_id, &client_secret, &[("token", token)])
+            .await;
+
+        assert_eq!(response.status().as_u16(), 200);
+    }
+}
+
+#[api_test]
+async fn should_not_revoke_tokens_of_other_clients() {
+    let (client_id, client_secret) = register_machine_client(&app).await;
+    let token = client_token(&app, &client_id, &client_secret).await;
+
+    let (other_client_id, other_client_secret) = register_machine_client(&app).await;
+
+    let response = app
+        .post_oauth_revoke_with_basic_auth(
+            &other_client_id,
+            &other_client_secret,
+            &[("token", &token)],
+        )
+        .await;
+
+    assert_oauth_error(response, 400, OAuthError::UnauthorizedClient).await;
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_error_if_client_or_token_is_missing() {
+    let (client_id, client_secret) = register_machine_client(&app).await;
+    let token = client_token(&app, &client_id, &client_secret).await;
+
+    let wrong_secret = ClientSecret::default();
+    let response = app
+        .post_oauth_revoke_with_basic_auth(
+            &client_id,
+            wrong_secret.as_ref().expose_secret(),
+            &[("token", &token)],
+        )
+        .await;
+
+    assert_oauth_error(response, 401, OAuthError::InvalidClient).await;
+
+    let response = app.post_oauth_revoke(&[("token", &token)]).await;
+
+    assert_oauth_error(
+        response,
+        400,
+        OAuthError::InvalidRequest("Missing client_id"),
+    )
+    .await;
+
+    let response = app
+        .post_oauth_revoke_with_basic_auth(&client_id, &client_secret, &[])
+        .await;
+
+    assert_oauth_error(response, 400, OAuthError::InvalidRequest("Missing token")).await;
+}
@@ -8,3 +8,28 @@ async fn root_returns_auth_ui() {
     assert_eq!(response.status().as_u16(), 200);
     assert_eq!(response.headers().get("content-type").unwrap(), "text/html");
 }
+
+#[api_test]
+async fn should_allow_app_to_call_every_method_cross_origin() {
+    for (path, method) in [
+        ("/login", "POST"),
+        ("/sessions/id", "DELETE"),
+        ("/api-keys/id", "PATCH"),
+        ("/admin/users/id/requires-2fa", "PUT"),
+    ] {
+        let response = app
+            .options_preflight(path, "http://localhost:8000", method)
+            .await;
+
+        assert_eq!(response.status().as_u16(), 200);
+
+        let allowed_methods = response
+            .headers()
+            .get("access-control-allow-methods")
+            .expect("No allowed methods")
+            .to_str()
+            .unwrap();
+
+        assert!(allowed_methods.contains(method), "{} not allowed", method);
+    }
+}
@@ -0,0 +1,145 @@
+use auth_service::{routes::SessionsResponse, utils::constants::JWT_COOKIE_NAME, ErrorResponse};
+
+use crate::helpers::{login_tokens, TestApp, TestUser};
+use test_helpers::api_test;
+
+async fn get_sessions(app: &TestApp) -> SessionsResponse {
+    let response = app.get_sessions().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<SessionsResponse>()
+        .await
+        .expect("Could not deserialize response body to SessionsResponse")
+}
+
+async fn verify_token(app: &TestApp, token: &str) -> u16 {
+    let verify_token_body = serde_json::json!({
+        "token": token,
+    });
+
+    app.post_verify_token(&verify_token_body)
+        .await
+        .status()
+        .as_u16()
+}
+
+#[api_test]
+async fn should_list_every_session_of_the_user() {
+    // Log in twice, as if from two devices. The second login is the current one.
+    let email = app.signup_and_login(&[]).await.email;
+    let response = app.login_user(&email).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let sessions = get_sessions(&app).await.sessions;
+
+    assert_eq!(sessions.len(), 2);
+    assert_eq!(sessions.iter().filter(|session| session.current).count(), 1);
+    for session in &sessions {
+        assert_eq!(session.ip_address, "127.0.0.1");
+        assert!(!session.created_at.is_empty());
+        assert!(!session.last_seen_at.is_empty());
+    }
+
+    // Another user's sessions aren't listed
+    app.signup_and_login(&[]).await;
+
+    let sessions = get_sessions(&app).await.sessions;
+
+    assert_eq!(sessions.len(), 1);
+    assert!(sessions[0].current);
+}
+
+#[api_test]
+async fn should_revoke_another_session() {
+    let TestUser {
+        email,
+        auth_token: other_token,
+        ..
+    } = app.signup_and_login(&[]).await;
+
+    let response = app.login_user(&email).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let (token, _) = login_tokens(&response);
+
+    let sessions = get_sessions(&app).await.sessions;
+    let other_session = sessions
+        .iter()
+        .find(|session| !session.current)
+        .expect("No other session found");
+
+    let response = app.delete_session(&other_session.id).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Only the revoked session is logged out
+    assert_eq!(verify_token(&app, &other_token).await, 401);
+    assert_eq!(verify_token(&app, &token).await, 200);
+
+    let sessions = get_sessions(&app).await.sessions;
+
+    assert_eq!(sessions.len(), 1);
+    assert!(sessions[0].current);
+}
+
+#[api_test]
+async fn should_log_out_when_revoking_the_current_session() {
+    let token = app.signup_and_login(&[]).await.auth_token;
+
+    let sessions = get_sessions(&app).await.sessions;
+
+    let response = app.delete_session(&sessions[0].id).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    assert!(auth_cookie.value().is_empty());
+
+    assert_eq!(verify_token(&app, &token).await, 401);
+}
+
+#[api_test]
+async fn should_return_404_for_another_users_session() {
+    app.signup_and_login(&[]).await;
+    let session_id = get_sessions(&app).await.sessions[0].id.clone();
+
+    app.signup_and_login(&[]).await;
+
+    let session_ids = [session_id.as_str(), "not-a-session-id"];
+
+    for session_id in session_ids {
+        let response = app.delete_session(session_id).await;
+
+        assert_eq!(response.status().as_u16(), 404);
+
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            "Session not found".to_owned()
+        );
+    }
+}
+
+#[api_test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let response = app.get_sessions().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app
+        .delete_session("8a2c1c8e-2b3c-4a55-9f0e-5d6f1f3b0a11")
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
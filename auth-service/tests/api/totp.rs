@@ -0,0 +1,260 @@
+use auth_service::{
+    routes::{EnrollTotpResponse, TwoFactorAuthResponse},
+    ErrorResponse,
+};
+use totp_rs::{Algorithm, Secret, TOTP};
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::TestApp;
+use test_helpers::api_test;
+
+async fn enroll(app: &TestApp) -> EnrollTotpResponse {
+    let response = app.post_totp_enroll().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<EnrollTotpResponse>()
+        .await
+        .expect("Could not deserialize response body to EnrollTotpResponse")
+}
+
+fn build_totp(secret: &str) -> TOTP {
+    let secret = Secret::Encoded(secret.to_owned())
+        .to_bytes()
+        .expect("Invalid TOTP secret");
+
+    TOTP::new(Algorithm::SHA1, 6, 1, 30, secret, None, "".to_owned()).expect("Failed to build TOTP")
+}
+
+// What the user's authenticator app would show right now
+fn current_code(secret: &str) -> String {
+    build_totp(secret)
+        .generate_current()
+        .expect("Failed to generate TOTP code")
+}
+
+// What the app will show next, which is still accepted to allow for clock drift. Each code can
+// only be used once, so this gives a second code to test with without waiting for the next step
+fn next_code(secret: &str) -> String {
+    let totp = build_totp(secret);
+    let now = totp.next_step_current().expect("Failed to get next step");
+
+    totp.generate(now)
+}
+
+async fn login_with_totp(app: &TestApp, email: &str, code: &str) -> u16 {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.login_user(email).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    app.post_verify_2fa(&serde_json::json!({
+        "email": email,
+        "loginAttemptId": login_attempt_id,
+        "2FACode": code,
+    }))
+    .await
+    .status()
+    .as_u16()
+}
+
+#[api_test]
+async fn should_return_200_with_otpauth_uri_and_qr_code() {
+    app.signup_and_login(&[]).await;
+
+    let enrolment = enroll(&app).await;
+
+    assert!(enrolment.otpauth_uri.starts_with("otpauth://totp/"));
+    assert!(enrolment
+        .otpauth_uri
+        .contains(&format!("secret={}", enrolment.secret)));
+    assert!(enrolment.qr_code.contains("<svg"));
+}
+
+#[api_test]
+async fn should_enable_totp_after_confirming_valid_code() {
+    app.signup_and_login(&[]).await;
+
+    let enrolment = enroll(&app).await;
+
+    let response = app
+        .post_totp_confirm(&serde_json::json!({ "code": current_code(&enrolment.secret) }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Enrolling again would silently replace the active secret
+    let response = app.post_totp_enroll().await;
+
+    assert_eq!(response.status().as_u16(), 409);
+}
+
+#[api_test]
+async fn should_accept_totp_code_in_verify_2fa() {
+    let random_email = app.signup_and_login(&[]).await.email;
+
+    let enrolment = enroll(&app).await;
+
+    let response = app
+        .post_totp_confirm(&serde_json::json!({ "code": current_code(&enrolment.secret) }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The emailed code is still sent as a fallback
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": next_code(&enrolment.secret),
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_401_if_totp_code_reused() {
+    let random_email = app.signup_and_login(&[]).await.email;
+
+    let enrolment = enroll(&app).await;
+    let confirmation_code = current_code(&enrolment.secret);
+
+    let response = app
+        .post_totp_confirm(&serde_json::json!({ "code": confirmation_code }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The code that confirmed enrolment has been used up
+    assert_eq!(
+        login_with_totp(&app, &random_email, &confirmation_code).await,
+        401
+    );
+
+    let code = next_code(&enrolment.secret);
+
+    assert_eq!(login_with_totp(&app, &random_email, &code).await, 200);
+
+    // Someone who saw the code can't log in with it while it's still current
+    assert_eq!(login_with_totp(&app, &random_email, &code).await, 401);
+}
+
+#[api_test]
+async fn should_return_401_if_incorrect_code() {
+    app.signup_and_login(&[]).await;
+
+    let enrolment = enroll(&app).await;
+
+    // Enrolling again replaces the pending secret, so codes for the first one stop working
+    let other_secret = enroll(&app).await.secret;
+    assert_ne!(enrolment.secret, other_secret);
+
+    let response = app
+        .post_totp_confirm(&serde_json::json!({ "code": current_code(&enrolment.secret) }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Incorrect credentials".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_return_400_if_not_enrolled() {
+    app.signup_and_login(&[]).await;
+
+    let response = app
+        .post_totp_confirm(&serde_json::json!({ "code": "123456" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_return_400_if_invalid_code() {
+    app.signup_and_login(&[]).await;
+
+    enroll(&app).await;
+
+    for code in ["", "12345", "abcdef"] {
+        let response = app
+            .post_totp_confirm(&serde_json::json!({ "code": code }))
+            .await;
+
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "Failed for input: {:?}",
+            code
+        );
+    }
+}
+
+#[api_test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let response = app.post_totp_enroll().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Missing auth token".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_return_422_if_malformed_input() {
+    app.signup_and_login(&[]).await;
+
+    let response = app.post_totp_confirm(&serde_json::json!({})).await;
+
+    assert_eq!(response.status().as_u16(), 422);
+}
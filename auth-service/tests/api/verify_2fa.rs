@@ -1,7 +1,7 @@
 use auth_service::{
     domain::{Email, LoginAttemptId, TwoFACode},
     routes::TwoFactorAuthResponse,
-    utils::constants::JWT_COOKIE_NAME,
+    utils::constants::{DEFAULT_MAX_FAILED_2FA_ATTEMPTS, JWT_COOKIE_NAME},
     ErrorResponse,
 };
 use secrecy::{ExposeSecret, Secret};
@@ -400,3 +400,65 @@ async fn should_return_422_if_malformed_input() {
         );
     }
 }
+
+#[api_test]
+async fn should_invalidate_login_attempt_after_too_many_wrong_codes() {
+    let random_email = get_random_email();
+
+    app.signup_user(&random_email, true).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.login_user(&random_email).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    let code_tuple = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&Email::parse(Secret::new(random_email.clone())).unwrap())
+        .await
+        .unwrap();
+
+    let two_fa_code = code_tuple.1.as_ref().expose_secret().to_owned();
+    let wrong_two_fa_code = if two_fa_code == "123456" {
+        "654321"
+    } else {
+        "123456"
+    };
+
+    for _ in 0..DEFAULT_MAX_FAILED_2FA_ATTEMPTS {
+        let response = app
+            .post_verify_2fa(&serde_json::json!({
+                "email": random_email,
+                "loginAttemptId": login_attempt_id,
+                "2FACode": wrong_two_fa_code,
+            }))
+            .await;
+
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    // The right code no longer helps, the user has to start over with their password
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": two_fa_code,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
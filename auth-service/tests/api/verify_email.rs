@@ -0,0 +1,193 @@
+use auth_service::ErrorResponse;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+use test_helpers::api_test;
+
+// Pulls the token out of the link in the verification email, which is the `count`th email sent
+async fn verification_token(app: &TestApp, count: usize) -> String {
+    let requests = app.wait_for_emails(count).await;
+    let body: serde_json::Value = requests[count - 1]
+        .body_json()
+        .expect("Email request body is not JSON");
+    let text = body["TextBody"].as_str().expect("Email has no text body");
+    let start = text.find("token=").expect("No token in email") + "token=".len();
+
+    text[start..].trim().to_owned()
+}
+
+async fn mount_email_server(app: &TestApp, expected_emails: u64) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(expected_emails)
+        .mount(&app.email_server)
+        .await;
+}
+
+#[tokio::test]
+async fn should_return_403_until_email_verified() {
+    let mut app = TestApp::with_email_verification().await;
+    let random_email = get_random_email();
+
+    mount_email_server(&app, 1).await;
+
+    app.signup_user(&random_email, false).await;
+
+    let response = app.login_user(&random_email).await;
+
+    assert_eq!(response.status().as_u16(), 403);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Email not verified".to_owned()
+    );
+
+    let token = verification_token(&app, 1).await;
+
+    let response = app.get_verify_email(&token).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.login_user(&random_email).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Following the link again is harmless
+    let response = app.get_verify_email(&token).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_not_403_if_unverified_and_wrong_password() {
+    let mut app = TestApp::with_email_verification().await;
+    let random_email = get_random_email();
+
+    mount_email_server(&app, 1).await;
+
+    app.signup_user(&random_email, false).await;
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "wrongpassword",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_resend_verification_only_to_unverified_accounts() {
+    let mut app = TestApp::with_email_verification().await;
+    let random_email = get_random_email();
+
+    // One email at signup and one from the first resend
+    mount_email_server(&app, 2).await;
+
+    app.signup_user(&random_email, false).await;
+
+    let resend_body = serde_json::json!({ "email": random_email });
+
+    let response = app.post_resend_verification(&resend_body).await;
+
+    assert_eq!(response.status().as_u16(), 202);
+
+    let token = verification_token(&app, 2).await;
+
+    let response = app.get_verify_email(&token).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Already verified, so nothing is sent but the answer is the same
+    let response = app.post_resend_verification(&resend_body).await;
+
+    assert_eq!(response.status().as_u16(), 202);
+
+    // Unknown accounts get the same answer too
+    let response = app
+        .post_resend_verification(&serde_json::json!({ "email": get_random_email() }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 202);
+
+    app.clean_up().await;
+}
+
+#[api_test]
+async fn should_not_require_verification_if_disabled() {
+    let random_email = get_random_email();
+
+    mount_email_server(&app, 0).await;
+
+    app.signup_user(&random_email, false).await;
+
+    let response = app.login_user(&random_email).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_401_if_invalid_token() {
+    let test_cases = ["", "invalid_token", "a.b.c"];
+
+    for test_case in test_cases.iter() {
+        let response = app.get_verify_email(test_case).await;
+
+        assert_eq!(
+            response.status().as_u16(),
+            401,
+            "Failed for input: {:?}",
+            test_case
+        );
+
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            "Invalid auth token".to_owned()
+        );
+    }
+}
+
+#[api_test]
+async fn should_return_400_if_token_missing() {
+    let response = app
+        .http_client
+        .get(&format!("{}/verify-email", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_return_400_if_resend_with_invalid_email() {
+    let response = app
+        .post_resend_verification(&serde_json::json!({ "email": "invalid" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_return_422_if_resend_with_malformed_input() {
+    let response = app.post_resend_verification(&serde_json::json!({})).await;
+
+    assert_eq!(response.status().as_u16(), 422);
+}
@@ -1,4 +1,7 @@
-use auth_service::{utils::constants::JWT_COOKIE_NAME, ErrorResponse};
+use auth_service::{
+    utils::constants::{JWT_AUDIENCE, JWT_COOKIE_NAME},
+    ErrorResponse,
+};
 
 use crate::helpers::{get_random_email, TestApp};
 use test_helpers::api_test;
@@ -44,6 +47,29 @@ async fn should_return_200_valid_token() {
     assert_eq!(response.status().as_u16(), 200);
 }
 
+#[api_test]
+async fn should_return_401_if_token_for_other_audience() {
+    let token = app.signup_and_login(&[]).await.auth_token;
+
+    let verify_token_body = serde_json::json!({
+        "token": &token,
+        "audience": JWT_AUDIENCE.as_str(),
+    });
+
+    let response = app.post_verify_token(&verify_token_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let verify_token_body = serde_json::json!({
+        "token": &token,
+        "audience": "other-service",
+    });
+
+    let response = app.post_verify_token(&verify_token_body).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
 #[api_test]
 async fn should_return_401_invalid_token() {
     let test_cases = ["", "invalid_token"];
@@ -0,0 +1,346 @@
+use auth_service::{
+    routes::{WebAuthnLoginStartResponse, WebAuthnRegisterStartResponse},
+    utils::constants::{
+        AUTH_SERVICE_URL, DEFAULT_MAX_FAILED_LOGIN_ATTEMPTS, JWT_COOKIE_NAME, WEBAUTHN_RP_ID,
+    },
+    ErrorResponse,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD as BASE64_URL, Engine};
+use ciborium::value::Value;
+use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+use sha2::{Digest, Sha256};
+
+use crate::helpers::{get_random_email, TestApp};
+use test_helpers::api_test;
+
+// User present and user verified
+const FLAGS_UP_UV: u8 = 0x05;
+const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+/// Stands in for a platform authenticator, producing what navigator.credentials would hand back.
+struct SoftwareAuthenticator {
+    credential_id: Vec<u8>,
+    key: SigningKey,
+    sign_count: u32,
+}
+
+impl SoftwareAuthenticator {
+    fn new() -> Self {
+        Self {
+            credential_id: uuid::Uuid::new_v4().as_bytes().to_vec(),
+            key: SigningKey::random(&mut rand::thread_rng()),
+            sign_count: 0,
+        }
+    }
+
+    fn id(&self) -> String {
+        BASE64_URL.encode(&self.credential_id)
+    }
+
+    fn authenticator_data(&self, flags: u8) -> Vec<u8> {
+        let mut data = Sha256::digest(WEBAUTHN_RP_ID.as_bytes()).to_vec();
+        data.push(flags);
+        data.extend_from_slice(&self.sign_count.to_be_bytes());
+        data
+    }
+
+    fn client_data(ceremony_type: &str, challenge: &str) -> Vec<u8> {
+        serde_json::to_vec(&serde_json::json!({
+            "type": ceremony_type,
+            "challenge": challenge,
+            "origin": AUTH_SERVICE_URL.as_str(),
+        }))
+        .unwrap()
+    }
+
+    fn create(&self, challenge: &str) -> serde_json::Value {
+        let point = self.key.verifying_key().to_encoded_point(false);
+        let cose_key = Value::Map(vec![
+            (Value::from(1), Value::from(2)),
+            (Value::from(3), Value::from(-7)),
+            (Value::from(-1), Value::from(1)),
+            (Value::from(-2), Value::Bytes(point.x().unwrap().to_vec())),
+            (Value::from(-3), Value::Bytes(point.y().unwrap().to_vec())),
+        ]);
+
+        let mut auth_data = self.authenticator_data(FLAGS_UP_UV | FLAG_ATTESTED_CREDENTIAL_DATA);
+        auth_data.extend_from_slice(&[0; 16]);
+        auth_data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+        auth_data.extend_from_slice(&self.credential_id);
+        ciborium::ser::into_writer(&cose_key, &mut auth_data).unwrap();
+
+        let attestation = Value::Map(vec![
+            (Value::from("fmt"), Value::from("none")),
+            (Value::from("attStmt"), Value::Map(vec![])),
+            (Value::from("authData"), Value::Bytes(auth_data)),
+        ]);
+        let mut attestation_object = Vec::new();
+        ciborium::ser::into_writer(&attestation, &mut attestation_object).unwrap();
+
+        serde_json::json!({
+            "id": self.id(),
+            "response": {
+                "clientDataJSON": BASE64_URL.encode(Self::client_data("webauthn.create", challenge)),
+                "attestationObject": BASE64_URL.encode(attestation_object),
+            }
+        })
+    }
+
+    fn get(&mut self, challenge: &str) -> serde_json::Value {
+        self.sign_count += 1;
+
+        let auth_data = self.authenticator_data(FLAGS_UP_UV);
+        let client_data = Self::client_data("webauthn.get", challenge);
+
+        let mut signed_data = auth_data.clone();
+        signed_data.extend_from_slice(&Sha256::digest(&client_data));
+        let signature: Signature = self.key.sign(&signed_data);
+
+        serde_json::json!({
+            "id": self.id(),
+            "response": {
+                "clientDataJSON": BASE64_URL.encode(client_data),
+                "authenticatorData": BASE64_URL.encode(auth_data),
+                "signature": BASE64_URL.encode(signature.to_der().as_bytes()),
+            }
+        })
+    }
+}
+
+async fn register_start(app: &TestApp) -> WebAuthnRegisterStartResponse {
+    let response = app.post_webauthn_register_start().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<WebAuthnRegisterStartResponse>()
+        .await
+        .expect("Could not deserialize response body to WebAuthnRegisterStartResponse")
+}
+
+async fn register(app: &TestApp, authenticator: &SoftwareAuthenticator) {
+    let options = register_start(app).await;
+
+    let response = app
+        .post_webauthn_register_finish(&authenticator.create(&options.public_key.challenge))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+async fn login_start(app: &TestApp, email: &str) -> WebAuthnLoginStartResponse {
+    let response = app
+        .post_webauthn_login_start(&serde_json::json!({ "email": email }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<WebAuthnLoginStartResponse>()
+        .await
+        .expect("Could not deserialize response body to WebAuthnLoginStartResponse")
+}
+
+#[api_test]
+async fn should_register_passkey_and_log_in_with_it() {
+    let random_email = app.signup_and_login(&[]).await.email;
+
+    let mut authenticator = SoftwareAuthenticator::new();
+    register(&app, &authenticator).await;
+
+    let options = login_start(&app, &random_email).await;
+
+    assert_eq!(options.public_key.allow_credentials.len(), 1);
+    assert_eq!(
+        options.public_key.allow_credentials[0].id,
+        authenticator.id()
+    );
+
+    let response = app
+        .post_webauthn_login_finish(&authenticator.get(&options.public_key.challenge))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    assert!(!auth_cookie.value().is_empty());
+}
+
+#[api_test]
+async fn should_exclude_registered_passkeys_from_new_registrations() {
+    app.signup_and_login(&[]).await;
+
+    let authenticator = SoftwareAuthenticator::new();
+    register(&app, &authenticator).await;
+
+    let options = register_start(&app).await;
+
+    assert_eq!(options.public_key.exclude_credentials.len(), 1);
+    assert_eq!(
+        options.public_key.exclude_credentials[0].id,
+        authenticator.id()
+    );
+
+    let response = app
+        .post_webauthn_register_finish(&authenticator.create(&options.public_key.challenge))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 409);
+}
+
+#[api_test]
+async fn should_return_401_if_challenge_is_reused() {
+    let random_email = app.signup_and_login(&[]).await.email;
+
+    let mut authenticator = SoftwareAuthenticator::new();
+    register(&app, &authenticator).await;
+
+    let options = login_start(&app, &random_email).await;
+
+    let response = app
+        .post_webauthn_login_finish(&authenticator.get(&options.public_key.challenge))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_webauthn_login_finish(&authenticator.get(&options.public_key.challenge))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_401_if_signed_by_another_authenticator() {
+    let random_email = app.signup_and_login(&[]).await.email;
+
+    let authenticator = SoftwareAuthenticator::new();
+    register(&app, &authenticator).await;
+
+    let options = login_start(&app, &random_email).await;
+
+    // Same credential ID but a different private key
+    let mut impostor = SoftwareAuthenticator::new();
+    impostor.credential_id = authenticator.credential_id.clone();
+
+    let response = app
+        .post_webauthn_login_finish(&impostor.get(&options.public_key.challenge))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Incorrect credentials".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_return_401_if_sign_count_goes_backwards() {
+    let random_email = app.signup_and_login(&[]).await.email;
+
+    let mut authenticator = SoftwareAuthenticator::new();
+    register(&app, &authenticator).await;
+
+    let options = login_start(&app, &random_email).await;
+    authenticator.sign_count = 10;
+    let response = app
+        .post_webauthn_login_finish(&authenticator.get(&options.public_key.challenge))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    // A cloned authenticator would still be on an older counter
+    let options = login_start(&app, &random_email).await;
+    authenticator.sign_count = 3;
+    let response = app
+        .post_webauthn_login_finish(&authenticator.get(&options.public_key.challenge))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_429_after_too_many_failed_attempts() {
+    let random_email = app.signup_and_login(&[]).await.email;
+
+    let mut authenticator = SoftwareAuthenticator::new();
+    register(&app, &authenticator).await;
+
+    let mut impostor = SoftwareAuthenticator::new();
+    impostor.credential_id = authenticator.credential_id.clone();
+
+    for _ in 0..DEFAULT_MAX_FAILED_LOGIN_ATTEMPTS {
+        let options = login_start(&app, &random_email).await;
+        let response = app
+            .post_webauthn_login_finish(&impostor.get(&options.public_key.challenge))
+            .await;
+
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    // Even the right passkey is refused while the account is locked
+    let options = login_start(&app, &random_email).await;
+    let response = app
+        .post_webauthn_login_finish(&authenticator.get(&options.public_key.challenge))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 429);
+
+    // The lockout is the account's, so its password is refused too
+    let response = app.login_user(&random_email).await;
+
+    assert_eq!(response.status().as_u16(), 429);
+}
+
+#[api_test]
+async fn should_offer_no_passkeys_rather_than_reveal_the_account() {
+    let random_email = app.signup_and_login(&[]).await.email;
+
+    // An account without passkeys looks just like one that doesn't exist
+    for email in [random_email, get_random_email()] {
+        let options = login_start(&app, &email).await;
+
+        assert!(options.public_key.allow_credentials.is_empty());
+        assert!(!options.public_key.challenge.is_empty());
+    }
+}
+
+#[api_test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let response = app.post_webauthn_register_start().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_return_400_if_invalid_input() {
+    app.signup_and_login(&[]).await;
+
+    let response = app
+        .post_webauthn_register_finish(&serde_json::json!({
+            "id": "not base64!",
+            "response": {
+                "clientDataJSON": "",
+                "attestationObject": "",
+            }
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_return_422_if_malformed_input() {
+    let response = app.post_webauthn_login_finish(&serde_json::json!({})).await;
+
+    assert_eq!(response.status().as_u16(), 422);
+}